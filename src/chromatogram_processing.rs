@@ -0,0 +1,1024 @@
+//! # processing of chromatogram traces
+
+//! The `chromatogram_processing` module provides functions that process the intensities of chromatograms, independent of how they were extracted.
+
+//! ## Overview
+
+//! The functions operate on the intensity array of a single trace and return a new array of the same length, leaving the input untouched.
+//! The data points are assumed to be (nearly) evenly spaced in retention time, so filter windows are expressed in points.
+
+//!## Features
+
+//!- **Smoothing**: Smooth traces with a moving average, a Savitzky–Golay filter of configurable polynomial order or a Gaussian kernel.
+//!  All filters also smooth the first and last points of a trace, using the part of the window that lies inside the trace.
+//!- **Baseline Correction**: Estimate the baseline of a trace, e.g. the drift of a TIC along the gradient, with asymmetric least squares, a rolling minimum or SNIP, and subtract it.
+//!- **Signal-to-Noise**: Compute the signal-to-noise ratio of a peak against a chosen or automatically found noise region, with peak-to-peak, RMS or MAD noise.
+
+#![warn(clippy::all)]
+
+use std::fmt;
+
+/// The number of reweighting iterations of the asymmetric least squares baseline.
+const ALS_ITERATIONS: usize = 10;
+
+/// The factor that scales the median absolute deviation to the standard deviation of normally distributed noise.
+const MAD_SCALE: f64 = 1.4826;
+
+/// The width of the region excluded around a peak when searching for a noise region, in half widths at half maximum on either side.
+const PEAK_EXCLUSION_WIDTHS: usize = 3;
+
+/// The filter used to smooth chromatograms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SmoothingMethod {
+    /// The unweighted mean of the window.
+    #[default]
+    MovingAverage,
+    /// A least-squares polynomial fitted to the window, which preserves the height and width of narrow peaks.
+    SavitzkyGolay,
+    /// The mean of the window weighted by a Gaussian kernel with a standard deviation of half the window.
+    Gaussian,
+}
+
+impl SmoothingMethod {
+    /// Returns the name of the method, e.g. `Savitzky-Golay`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            SmoothingMethod::MovingAverage => "moving average",
+            SmoothingMethod::SavitzkyGolay => "Savitzky-Golay",
+            SmoothingMethod::Gaussian => "Gaussian",
+        }
+    }
+}
+
+/// The parameters of chromatogram smoothing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SmoothingParameters {
+    /// The smoothing filter.
+    pub method: SmoothingMethod,
+    /// The number of points on either side of the current point that are included in the window, 0 for no smoothing.
+    pub half_window: u8,
+    /// The order of the polynomial fitted by the Savitzky–Golay filter. It is lowered if the window has too few points.
+    pub polynomial_order: u8,
+}
+
+impl Default for SmoothingParameters {
+    fn default() -> Self {
+        Self {
+            method: SmoothingMethod::MovingAverage,
+            half_window: 0,
+            polynomial_order: 2,
+        }
+    }
+}
+
+impl SmoothingParameters {
+    /// Returns a moving average over `half_window` points on either side.
+    pub fn moving_average(half_window: u8) -> Self {
+        Self {
+            half_window,
+            ..Default::default()
+        }
+    }
+}
+
+impl fmt::Display for SmoothingParameters {
+    /// Describes the smoothing, e.g. `Savitzky-Golay, 3 points per side, order 2`, or `none`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.half_window == 0 {
+            return write!(f, "none");
+        }
+        write!(
+            f,
+            "{}, {} {} per side",
+            self.method.name(),
+            self.half_window,
+            if self.half_window == 1 {
+                "point"
+            } else {
+                "points"
+            }
+        )?;
+        if self.method == SmoothingMethod::SavitzkyGolay {
+            write!(f, ", order {}", self.polynomial_order)?;
+        }
+        Ok(())
+    }
+}
+
+/// Smooths the intensities of a chromatogram.
+///
+/// # Parameters
+/// - `intensities: &[f64]` - The intensities of the trace, in retention time order.
+/// - `parameters: &SmoothingParameters` - The filter and its window.
+///
+/// # Returns
+/// - `Vec<f64>` - The smoothed intensities, one for every input point.
+///
+/// # Functionality
+/// Near the ends of the trace, the window is truncated: the moving average and the Gaussian kernel are renormalized to the points inside the trace,
+/// and the Savitzky–Golay polynomial is fitted to the full-size window closest to the point and evaluated at the point.
+pub fn smooth(intensities: &[f64], parameters: &SmoothingParameters) -> Vec<f64> {
+    let half_window = parameters.half_window as usize;
+    if half_window == 0 || intensities.len() < 2 {
+        return intensities.to_vec();
+    }
+    match parameters.method {
+        SmoothingMethod::MovingAverage => {
+            weighted_mean(intensities, &vec![1.0; 2 * half_window + 1])
+        }
+        SmoothingMethod::Gaussian => {
+            let sigma = half_window as f64 / 2.0;
+            let kernel: Vec<f64> = (0..=2 * half_window)
+                .map(|i| {
+                    let distance = i as f64 - half_window as f64;
+                    (-0.5 * (distance / sigma).powi(2)).exp()
+                })
+                .collect();
+            weighted_mean(intensities, &kernel)
+        }
+        SmoothingMethod::SavitzkyGolay => savitzky_golay(
+            intensities,
+            half_window,
+            parameters.polynomial_order as usize,
+        ),
+    }
+}
+
+/// Convolves the intensities with a symmetric kernel, renormalizing the kernel where it extends beyond the trace.
+fn weighted_mean(intensities: &[f64], kernel: &[f64]) -> Vec<f64> {
+    let half_window = kernel.len() / 2;
+    (0..intensities.len())
+        .map(|i| {
+            let first = i.saturating_sub(half_window);
+            let last = (i + half_window).min(intensities.len() - 1);
+            let (sum, weight) = (first..=last).fold((0.0, 0.0), |(sum, weight), j| {
+                let w = kernel[j + half_window - i];
+                (sum + w * intensities[j], weight + w)
+            });
+            sum / weight
+        })
+        .collect()
+}
+
+/// Applies a Savitzky–Golay filter, fitting the polynomial to the full-size window closest to every point.
+fn savitzky_golay(intensities: &[f64], half_window: usize, order: usize) -> Vec<f64> {
+    let window = (2 * half_window + 1).min(intensities.len());
+    let order = order.min(window - 1);
+    // the coefficients only depend on the position of the point within its window
+    let coefficients: Vec<Vec<f64>> = (0..window)
+        .map(|position| savitzky_golay_coefficients(window, position, order))
+        .collect();
+    (0..intensities.len())
+        .map(|i| {
+            let first = i.saturating_sub(window / 2).min(intensities.len() - window);
+            coefficients[i - first]
+                .iter()
+                .zip(&intensities[first..first + window])
+                .map(|(c, y)| c * y)
+                .sum()
+        })
+        .collect()
+}
+
+/// Computes the weights of a window of `window` points that evaluate the least-squares polynomial of the given order at `position`.
+///
+/// The weights are the row of the pseudo-inverse of the Vandermonde matrix of the window that belongs to the constant term,
+/// with the window centered on `position`. They are found by solving the normal equations.
+fn savitzky_golay_coefficients(window: usize, position: usize, order: usize) -> Vec<f64> {
+    let terms = order + 1;
+    // scale the offsets to [-1, 1] to keep the normal equations well conditioned
+    let scale = (window - 1).max(1) as f64;
+    let powers: Vec<Vec<f64>> = (0..window)
+        .map(|j| {
+            let x = (j as f64 - position as f64) / scale;
+            (0..terms).map(|k| x.powi(k as i32)).collect()
+        })
+        .collect();
+
+    // normal matrix (A^T A), solved for the first unit vector
+    let mut matrix: Vec<Vec<f64>> = (0..terms)
+        .map(|r| {
+            let mut row: Vec<f64> = (0..terms)
+                .map(|c| powers.iter().map(|p| p[r] * p[c]).sum())
+                .collect();
+            row.push(if r == 0 { 1.0 } else { 0.0 });
+            row
+        })
+        .collect();
+    let solution = solve(&mut matrix);
+
+    powers
+        .iter()
+        .map(|p| p.iter().zip(&solution).map(|(a, z)| a * z).sum())
+        .collect()
+}
+
+/// Solves a linear system given as an augmented matrix by Gaussian elimination with partial pivoting.
+fn solve(matrix: &mut [Vec<f64>]) -> Vec<f64> {
+    let n = matrix.len();
+    for column in 0..n {
+        let pivot = (column..n)
+            .max_by(|&a, &b| matrix[a][column].abs().total_cmp(&matrix[b][column].abs()))
+            .unwrap_or(column);
+        matrix.swap(column, pivot);
+        for row in column + 1..n {
+            let factor = matrix[row][column] / matrix[column][column];
+            for c in column..=n {
+                matrix[row][c] -= factor * matrix[column][c];
+            }
+        }
+    }
+    let mut solution = vec![0.0; n];
+    for row in (0..n).rev() {
+        let sum: f64 = (row + 1..n).map(|c| matrix[row][c] * solution[c]).sum();
+        solution[row] = (matrix[row][n] - sum) / matrix[row][row];
+    }
+    solution
+}
+
+/// The method used to estimate the baseline of chromatograms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BaselineMethod {
+    /// Asymmetric least squares: a smooth curve that is pulled below the peaks by weighting points above it less.
+    #[default]
+    AsymmetricLeastSquares,
+    /// The minimum of a moving window, opened with the maximum of the same window and smoothed with a moving average.
+    RollingMinimum,
+    /// Statistics-sensitive non-linear iterative peak clipping, which clips peaks narrower than the window.
+    Snip,
+}
+
+impl BaselineMethod {
+    /// Returns the name of the method, e.g. `SNIP`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            BaselineMethod::AsymmetricLeastSquares => "asymmetric least squares",
+            BaselineMethod::RollingMinimum => "rolling minimum",
+            BaselineMethod::Snip => "SNIP",
+        }
+    }
+}
+
+/// The parameters of the baseline estimation of chromatograms.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BaselineParameters {
+    /// The estimation method.
+    pub method: BaselineMethod,
+    /// The smoothness (lambda) of the asymmetric least squares baseline. Larger values give a stiffer baseline.
+    pub smoothness: f64,
+    /// The weight of points above the asymmetric least squares baseline, between 0 and 0.5.
+    pub asymmetry: f64,
+    /// The number of points on either side of the rolling minimum window, or the number of SNIP iterations.
+    /// It should be larger than the half width of the widest peak.
+    pub half_window: usize,
+}
+
+impl Default for BaselineParameters {
+    fn default() -> Self {
+        Self {
+            method: BaselineMethod::AsymmetricLeastSquares,
+            smoothness: 1e5,
+            asymmetry: 0.01,
+            half_window: 20,
+        }
+    }
+}
+
+impl fmt::Display for BaselineParameters {
+    /// Describes the baseline estimation, e.g. `SNIP, 20 iterations`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.method {
+            BaselineMethod::AsymmetricLeastSquares => write!(
+                f,
+                "{}, lambda {:e}, asymmetry {}",
+                self.method.name(),
+                self.smoothness,
+                self.asymmetry
+            ),
+            BaselineMethod::RollingMinimum => write!(
+                f,
+                "{}, {} points per side",
+                self.method.name(),
+                self.half_window
+            ),
+            BaselineMethod::Snip => {
+                write!(f, "{}, {} iterations", self.method.name(), self.half_window)
+            }
+        }
+    }
+}
+
+/// Estimates the baseline of a chromatogram.
+///
+/// # Parameters
+/// - `intensities: &[f64]` - The intensities of the trace, in retention time order.
+/// - `parameters: &BaselineParameters` - The method and its parameters.
+///
+/// # Returns
+/// - `Vec<f64>` - The baseline, one value for every input point.
+///
+/// # Functionality
+/// - Asymmetric least squares (Eilers and Boelens) minimizes the weighted squared distance to the trace plus `smoothness` times the squared second differences of the baseline.
+///   Points above the baseline get the weight `asymmetry`, points below it `1 - asymmetry`, and the weights are updated `ALS_ITERATIONS` times.
+/// - The rolling minimum takes the minimum of the window around every point, then the maximum of the minima (a morphological opening, so drifting
+///   baselines are not lowered by the slope), averages it over the same window and caps it at the trace.
+/// - SNIP works on the log-log-square root transformed intensities, replacing every point by the mean of its neighbours `k` points away if that is lower, for `k` up to `half_window`.
+pub fn estimate_baseline(intensities: &[f64], parameters: &BaselineParameters) -> Vec<f64> {
+    if intensities.len() < 3 {
+        let minimum = intensities.iter().cloned().fold(f64::INFINITY, f64::min);
+        return vec![minimum; intensities.len()];
+    }
+    match parameters.method {
+        BaselineMethod::AsymmetricLeastSquares => {
+            asymmetric_least_squares(intensities, parameters.smoothness, parameters.asymmetry)
+        }
+        BaselineMethod::RollingMinimum => rolling_minimum(intensities, parameters.half_window),
+        BaselineMethod::Snip => snip(intensities, parameters.half_window),
+    }
+}
+
+/// Subtracts a baseline from the intensities of a chromatogram.
+///
+/// The corrected intensities are not clipped at zero, so the noise around the baseline keeps its distribution.
+pub fn subtract_baseline(intensities: &[f64], baseline: &[f64]) -> Vec<f64> {
+    intensities
+        .iter()
+        .zip(baseline)
+        .map(|(intensity, baseline)| intensity - baseline)
+        .collect()
+}
+
+/// Fits the asymmetric least squares baseline by solving the banded normal equations of every iteration.
+fn asymmetric_least_squares(intensities: &[f64], smoothness: f64, asymmetry: f64) -> Vec<f64> {
+    let n = intensities.len();
+    // the diagonal, first and second off-diagonal of the penalty matrix D'D of the second differences
+    let mut penalty = [vec![0.0; n], vec![0.0; n], vec![0.0; n]];
+    for row in 0..n - 2 {
+        let coefficients = [1.0, -2.0, 1.0];
+        for a in 0..3 {
+            for b in a..3 {
+                penalty[b - a][row + a] += coefficients[a] * coefficients[b];
+            }
+        }
+    }
+
+    let mut weights = vec![1.0; n];
+    let mut baseline = intensities.to_vec();
+    for _ in 0..ALS_ITERATIONS {
+        let diagonal: Vec<f64> = (0..n)
+            .map(|i| weights[i] + smoothness * penalty[0][i])
+            .collect();
+        let first: Vec<f64> = penalty[1].iter().map(|p| smoothness * p).collect();
+        let second: Vec<f64> = penalty[2].iter().map(|p| smoothness * p).collect();
+        let rhs: Vec<f64> = (0..n).map(|i| weights[i] * intensities[i]).collect();
+        baseline = solve_pentadiagonal(&diagonal, &first, &second, &rhs);
+        for i in 0..n {
+            weights[i] = if intensities[i] > baseline[i] {
+                asymmetry
+            } else {
+                1.0 - asymmetry
+            };
+        }
+    }
+    baseline
+}
+
+/// Solves a symmetric positive definite pentadiagonal system by a banded Cholesky decomposition.
+///
+/// `first[i]` and `second[i]` are the entries of row `i` at the columns `i + 1` and `i + 2`.
+fn solve_pentadiagonal(diagonal: &[f64], first: &[f64], second: &[f64], rhs: &[f64]) -> Vec<f64> {
+    let n = diagonal.len();
+    // the diagonal and the two sub-diagonals of the Cholesky factor, l1[i] and l2[i] at the columns i - 1 and i - 2
+    let (mut l0, mut l1, mut l2) = (vec![0.0; n], vec![0.0; n], vec![0.0; n]);
+    for i in 0..n {
+        if i >= 2 {
+            l2[i] = second[i - 2] / l0[i - 2];
+        }
+        if i >= 1 {
+            let overlap = if i >= 2 { l2[i] * l1[i - 1] } else { 0.0 };
+            l1[i] = (first[i - 1] - overlap) / l0[i - 1];
+        }
+        l0[i] = (diagonal[i] - l1[i].powi(2) - l2[i].powi(2)).sqrt();
+    }
+
+    let mut forward = vec![0.0; n];
+    for i in 0..n {
+        let mut sum = rhs[i];
+        if i >= 1 {
+            sum -= l1[i] * forward[i - 1];
+        }
+        if i >= 2 {
+            sum -= l2[i] * forward[i - 2];
+        }
+        forward[i] = sum / l0[i];
+    }
+    let mut solution = vec![0.0; n];
+    for i in (0..n).rev() {
+        let mut sum = forward[i];
+        if i + 1 < n {
+            sum -= l1[i + 1] * solution[i + 1];
+        }
+        if i + 2 < n {
+            sum -= l2[i + 2] * solution[i + 2];
+        }
+        solution[i] = sum / l0[i];
+    }
+    solution
+}
+
+/// Estimates the baseline as the moving average of the opening (rolling maximum of the rolling minimum), capped at the trace.
+fn rolling_minimum(intensities: &[f64], half_window: usize) -> Vec<f64> {
+    let n = intensities.len();
+    let window = |i: usize| i.saturating_sub(half_window)..=(i + half_window).min(n - 1);
+    let minima: Vec<f64> = (0..n)
+        .map(|i| {
+            intensities[window(i)]
+                .iter()
+                .cloned()
+                .fold(f64::INFINITY, f64::min)
+        })
+        .collect();
+    let opening: Vec<f64> = (0..n)
+        .map(|i| {
+            minima[window(i)]
+                .iter()
+                .cloned()
+                .fold(f64::NEG_INFINITY, f64::max)
+        })
+        .collect();
+    (0..n)
+        .map(|i| {
+            let range = window(i);
+            let count = range.clone().count() as f64;
+            let mean = opening[range].iter().sum::<f64>() / count;
+            mean.min(intensities[i])
+        })
+        .collect()
+}
+
+/// Estimates the baseline by SNIP clipping of the log-log-square root transformed intensities.
+fn snip(intensities: &[f64], iterations: usize) -> Vec<f64> {
+    let n = intensities.len();
+    let forward = |y: f64| ((y.max(0.0) + 1.0).sqrt() + 1.0).ln().ln_1p();
+    let inverse = |v: f64| ((v.exp_m1()).exp() - 1.0).powi(2) - 1.0;
+
+    let mut values: Vec<f64> = intensities.iter().map(|&y| forward(y)).collect();
+    for k in 1..=iterations.min((n - 1) / 2) {
+        let previous = values.clone();
+        for i in k..n - k {
+            values[i] = previous[i].min((previous[i - k] + previous[i + k]) / 2.0);
+        }
+    }
+    values
+        .into_iter()
+        .zip(intensities)
+        .map(|(v, &y)| inverse(v).min(y))
+        .collect()
+}
+
+/// The measure of the noise used for signal-to-noise ratios.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NoiseMethod {
+    /// The difference between the highest and lowest point of the noise region, with S/N = 2H/h as in the European and US Pharmacopoeia.
+    PeakToPeak,
+    /// The root mean square deviation from the mean of the noise region.
+    #[default]
+    Rms,
+    /// The median absolute deviation from the median of the noise region, scaled to the standard deviation of normal noise and robust to spikes.
+    Mad,
+}
+
+impl NoiseMethod {
+    /// Returns the name of the method, e.g. `RMS`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            NoiseMethod::PeakToPeak => "peak-to-peak",
+            NoiseMethod::Rms => "RMS",
+            NoiseMethod::Mad => "MAD",
+        }
+    }
+}
+
+/// The parameters of the signal-to-noise estimation of chromatographic peaks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SignalToNoiseParameters {
+    /// The noise measure.
+    pub method: NoiseMethod,
+    /// The retention time range of the noise region, or `None` to find a region of typical noise away from the peak.
+    pub noise_region: Option<(f64, f64)>,
+    /// The number of points of an automatically found noise region.
+    pub noise_window: usize,
+}
+
+impl Default for SignalToNoiseParameters {
+    fn default() -> Self {
+        Self {
+            method: NoiseMethod::Rms,
+            noise_region: None,
+            noise_window: 30,
+        }
+    }
+}
+
+impl fmt::Display for SignalToNoiseParameters {
+    /// Describes the estimation, e.g. `RMS noise, automatic region of 30 points`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.noise_region {
+            Some((start, end)) => write!(
+                f,
+                "{} noise, region {:.3}-{:.3}",
+                self.method.name(),
+                start,
+                end
+            ),
+            None => write!(
+                f,
+                "{} noise, automatic region of {} points",
+                self.method.name(),
+                self.noise_window
+            ),
+        }
+    }
+}
+
+/// The signal-to-noise ratio of a chromatographic peak.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SignalToNoise {
+    /// The index of the apex of the peak.
+    pub apex: usize,
+    /// The height of the apex above the level of the noise region.
+    pub signal: f64,
+    /// The noise of the noise region.
+    pub noise: f64,
+    /// The signal-to-noise ratio.
+    pub ratio: f64,
+    /// The first and last index of the noise region.
+    pub noise_region: (usize, usize),
+}
+
+/// Computes the signal-to-noise ratio of the peak at a retention time.
+///
+/// # Parameters
+/// - `retention_time: &[f64]` - The retention times of the trace, in ascending order.
+/// - `intensities: &[f64]` - The intensities of the trace, one for every retention time.
+/// - `peak_retention_time: f64` - A retention time on the peak, e.g. where the user clicked.
+/// - `parameters: &SignalToNoiseParameters` - The noise measure and noise region.
+///
+/// # Returns
+/// - `Option<SignalToNoise>` - The signal-to-noise ratio, or `None` if the trace is empty, the noise region holds fewer than 2 points
+///   or it has no noise, e.g. the exact zeros left by subtracting a rolling minimum or SNIP baseline.
+///
+/// # Functionality
+/// - The apex is found by climbing from the point closest to `peak_retention_time` to the nearest local maximum.
+/// - Without a chosen noise region, the windows of `noise_window` points that stay `PEAK_EXCLUSION_WIDTHS` half widths at half maximum
+///   away from the apex are compared and the one with the median noise is used. The quietest window would give the best-case noise
+///   and overestimate the ratio, which matters for detection limits. Windows without noise are skipped.
+/// - The signal is the height of the apex above the mean of the noise region, or its median for MAD.
+/// - The ratio is 2H/h for peak-to-peak noise and H/h for RMS and MAD noise.
+pub fn signal_to_noise(
+    retention_time: &[f64],
+    intensities: &[f64],
+    peak_retention_time: f64,
+    parameters: &SignalToNoiseParameters,
+) -> Option<SignalToNoise> {
+    let length = retention_time.len().min(intensities.len());
+    if length == 0 {
+        return None;
+    }
+    let closest = (0..length).min_by(|&a, &b| {
+        (retention_time[a] - peak_retention_time)
+            .abs()
+            .total_cmp(&(retention_time[b] - peak_retention_time).abs())
+    })?;
+    let apex = climb_to_apex(&intensities[..length], closest);
+
+    let (first, last) = match parameters.noise_region {
+        Some((start, end)) => {
+            let first = retention_time[..length].partition_point(|&rt| rt < start.min(end));
+            let last = retention_time[..length].partition_point(|&rt| rt <= start.max(end));
+            (first, last.checked_sub(1)?)
+        }
+        None => typical_region(
+            &intensities[..length],
+            apex,
+            parameters.noise_window,
+            parameters.method,
+        )?,
+    };
+    if last <= first {
+        return None;
+    }
+
+    let (level, noise) = noise(&intensities[first..=last], parameters.method);
+    if noise <= 0.0 || !noise.is_finite() {
+        return None;
+    }
+    let signal = intensities[apex] - level;
+    let ratio = match parameters.method {
+        NoiseMethod::PeakToPeak => 2.0 * signal / noise,
+        NoiseMethod::Rms | NoiseMethod::Mad => signal / noise,
+    };
+    Some(SignalToNoise {
+        apex,
+        signal,
+        noise,
+        ratio,
+        noise_region: (first, last),
+    })
+}
+
+/// Returns the index of the local maximum reached by moving from `start` towards higher neighbours.
+fn climb_to_apex(intensities: &[f64], start: usize) -> usize {
+    let mut apex = start;
+    loop {
+        let left = apex
+            .checked_sub(1)
+            .filter(|&i| intensities[i] > intensities[apex]);
+        let right =
+            Some(apex + 1).filter(|&i| i < intensities.len() && intensities[i] > intensities[apex]);
+        apex = match (left, right) {
+            (Some(l), Some(r)) if intensities[l] > intensities[r] => l,
+            (_, Some(r)) => r,
+            (Some(l), None) => l,
+            (None, None) => return apex,
+        };
+    }
+}
+
+/// Returns the first and last index of the window of `window` points with the median noise of the windows that do not overlap the peak at `apex`.
+///
+/// The peak extends `PEAK_EXCLUSION_WIDTHS` times its half width at half maximum, measured from the trace minimum, on either side of the apex.
+/// Windows without noise are not considered, so `None` is returned if no window has noise.
+fn typical_region(
+    intensities: &[f64],
+    apex: usize,
+    window: usize,
+    method: NoiseMethod,
+) -> Option<(usize, usize)> {
+    let window = window.max(3);
+    let minimum = intensities.iter().cloned().fold(f64::INFINITY, f64::min);
+    let half_maximum = minimum + (intensities[apex] - minimum) / 2.0;
+    let left = (0..apex)
+        .rev()
+        .find(|&i| intensities[i] < half_maximum)
+        .unwrap_or(0);
+    let right = (apex + 1..intensities.len())
+        .find(|&i| intensities[i] < half_maximum)
+        .unwrap_or(intensities.len() - 1);
+    let excluded_start = apex.saturating_sub(PEAK_EXCLUSION_WIDTHS * (apex - left).max(1));
+    let excluded_end = apex + PEAK_EXCLUSION_WIDTHS * (right - apex).max(1);
+
+    let mut candidates: Vec<(usize, f64)> = (0..intensities.len().saturating_sub(window - 1))
+        .filter(|&start| start + window - 1 < excluded_start || start > excluded_end)
+        .map(|start| {
+            let (_, noise) = noise(&intensities[start..start + window], method);
+            (start, noise)
+        })
+        .filter(|&(_, noise)| noise > 0.0 && noise.is_finite())
+        .collect();
+    if candidates.is_empty() {
+        return None;
+    }
+    candidates.sort_by(|a, b| a.1.total_cmp(&b.1));
+    let (start, _) = candidates[candidates.len() / 2];
+    Some((start, start + window - 1))
+}
+
+/// Returns the level and the noise of a noise region.
+///
+/// The level is the mean of the region, or its median for MAD noise.
+fn noise(values: &[f64], method: NoiseMethod) -> (f64, f64) {
+    match method {
+        NoiseMethod::PeakToPeak | NoiseMethod::Rms => {
+            let mean = values.iter().sum::<f64>() / values.len() as f64;
+            let noise = match method {
+                NoiseMethod::PeakToPeak => {
+                    let maximum = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+                    let minimum = values.iter().cloned().fold(f64::INFINITY, f64::min);
+                    maximum - minimum
+                }
+                _ => (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len() as f64)
+                    .sqrt(),
+            };
+            (mean, noise)
+        }
+        NoiseMethod::Mad => {
+            let level = median(values.to_vec());
+            let deviations = values.iter().map(|v| (v - level).abs()).collect();
+            (level, MAD_SCALE * median(deviations))
+        }
+    }
+}
+
+/// Returns the median of a non-empty set of values.
+fn median(mut values: Vec<f64>) -> f64 {
+    values.sort_by(f64::total_cmp);
+    let middle = values.len() / 2;
+    if values.len() % 2 == 0 {
+        (values[middle - 1] + values[middle]) / 2.0
+    } else {
+        values[middle]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-9, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_moving_average() {
+        let smoothed = smooth(
+            &[1.0, 4.0, 1.0, 4.0, 1.0],
+            &SmoothingParameters::moving_average(1),
+        );
+        // the ends are averaged over the points inside the trace
+        assert_close(&smoothed, &[2.5, 2.0, 3.0, 2.0, 2.5]);
+        assert_eq!(
+            smooth(&[1.0, 2.0], &SmoothingParameters::default()),
+            vec![1.0, 2.0]
+        );
+    }
+
+    #[test]
+    fn test_savitzky_golay() {
+        // a quadratic is reproduced exactly, also at the ends
+        let quadratic: Vec<f64> = (0..9).map(|i| (i * i) as f64 - 3.0 * i as f64).collect();
+        let parameters = SmoothingParameters {
+            method: SmoothingMethod::SavitzkyGolay,
+            half_window: 2,
+            polynomial_order: 2,
+        };
+        assert_close(&smooth(&quadratic, &parameters), &quadratic);
+
+        // the classic 5-point quadratic coefficients
+        assert_close(
+            &savitzky_golay_coefficients(5, 2, 2),
+            &[
+                -3.0 / 35.0,
+                12.0 / 35.0,
+                17.0 / 35.0,
+                12.0 / 35.0,
+                -3.0 / 35.0,
+            ],
+        );
+
+        // a narrow peak keeps more of its height than with a moving average
+        let peak = [0.0, 0.0, 1.0, 5.0, 10.0, 5.0, 1.0, 0.0, 0.0];
+        let savitzky_golay = smooth(&peak, &parameters);
+        let moving_average = smooth(&peak, &SmoothingParameters::moving_average(2));
+        assert!(savitzky_golay[4] > moving_average[4]);
+
+        // the order is lowered for traces shorter than the window
+        let short = smooth(
+            &[1.0, 2.0],
+            &SmoothingParameters {
+                half_window: 5,
+                ..parameters
+            },
+        );
+        assert_close(&short, &[1.0, 2.0]);
+    }
+
+    #[test]
+    fn test_gaussian() {
+        let parameters = SmoothingParameters {
+            method: SmoothingMethod::Gaussian,
+            half_window: 3,
+            ..Default::default()
+        };
+        let constant = smooth(&[2.0; 6], &parameters);
+        assert_close(&constant, &[2.0; 6]);
+
+        let peak = [0.0, 0.0, 0.0, 9.0, 0.0, 0.0, 0.0];
+        let smoothed = smooth(&peak, &parameters);
+        assert!(smoothed[3] < 9.0 && smoothed[3] > smoothed[2]);
+        assert!((smoothed[2] - smoothed[4]).abs() < 1e-12);
+    }
+
+    /// A linear drift with a Gaussian peak at the center.
+    fn drifting_peak() -> (Vec<f64>, Vec<f64>) {
+        let drift: Vec<f64> = (0..200).map(|i| 100.0 + 2.0 * i as f64).collect();
+        let trace = drift
+            .iter()
+            .enumerate()
+            .map(|(i, d)| d + 1000.0 * (-0.5 * ((i as f64 - 100.0) / 4.0).powi(2)).exp())
+            .collect();
+        (drift, trace)
+    }
+
+    #[test]
+    fn test_baseline() {
+        let (drift, trace) = drifting_peak();
+        for method in [
+            BaselineMethod::AsymmetricLeastSquares,
+            BaselineMethod::RollingMinimum,
+            BaselineMethod::Snip,
+        ] {
+            let parameters = BaselineParameters {
+                method,
+                ..Default::default()
+            };
+            let baseline = estimate_baseline(&trace, &parameters);
+            assert_eq!(baseline.len(), trace.len());
+            // the baseline follows the drift under the peak and away from it
+            for i in [30, 100, 170] {
+                assert!(
+                    (baseline[i] - drift[i]).abs() < 0.1 * drift[i],
+                    "{:?} at {}: {} != {}",
+                    method,
+                    i,
+                    baseline[i],
+                    drift[i]
+                );
+            }
+            let corrected = subtract_baseline(&trace, &baseline);
+            assert!((corrected[100] - 1000.0).abs() < 50.0, "{:?}", method);
+        }
+
+        assert_eq!(
+            estimate_baseline(&[3.0, 1.0], &BaselineParameters::default()),
+            vec![1.0, 1.0]
+        );
+    }
+
+    /// A Gaussian peak of height 1000 on alternating noise of amplitude 1, sampled every 0.01 minutes.
+    fn noisy_peak() -> (Vec<f64>, Vec<f64>) {
+        let retention_time = (0..200).map(|i| i as f64 * 0.01).collect();
+        let trace = (0..200)
+            .map(|i| {
+                let noise = if i % 2 == 0 { 1.0 } else { -1.0 };
+                noise + 1000.0 * (-0.5 * ((i as f64 - 100.0) / 4.0).powi(2)).exp()
+            })
+            .collect();
+        (retention_time, trace)
+    }
+
+    #[test]
+    fn test_signal_to_noise() {
+        let (retention_time, trace) = noisy_peak();
+        // the apex is found from a point on the flank of the peak
+        for (method, noise, ratio) in [
+            (NoiseMethod::PeakToPeak, 2.0, 1000.0),
+            (NoiseMethod::Rms, 1.0, 1000.0),
+            (NoiseMethod::Mad, MAD_SCALE, 1000.0 / MAD_SCALE),
+        ] {
+            let parameters = SignalToNoiseParameters {
+                method,
+                noise_region: Some((0.0, 0.595)),
+                ..Default::default()
+            };
+            let result = signal_to_noise(&retention_time, &trace, 0.97, &parameters).unwrap();
+            assert_eq!(result.apex, 100);
+            assert_eq!(result.noise_region, (0, 59));
+            assert!((result.noise - noise).abs() < 1e-9, "{:?}", method);
+            assert!((result.ratio - ratio).abs() < 0.01 * ratio, "{:?}", method);
+
+            // the automatic noise region stays away from the peak
+            let parameters = SignalToNoiseParameters {
+                method,
+                ..Default::default()
+            };
+            let result = signal_to_noise(&retention_time, &trace, 0.97, &parameters).unwrap();
+            let (first, last) = result.noise_region;
+            assert_eq!(last - first + 1, 30);
+            assert!(last < 85 || first > 115, "{:?}", result.noise_region);
+            assert!((result.ratio - ratio).abs() < 0.05 * ratio, "{:?}", method);
+        }
+
+        // the limits of a chosen noise region may be swapped, and a region that includes the peak raises the noise
+        let parameters = SignalToNoiseParameters {
+            noise_region: Some((0.5, 0.0)),
+            ..Default::default()
+        };
+        let result = signal_to_noise(&retention_time, &trace, 1.0, &parameters).unwrap();
+        assert_eq!(result.noise_region, (0, 50));
+        assert!((result.noise - 1.0).abs() < 1e-2);
+        let parameters = SignalToNoiseParameters {
+            noise_region: Some((0.8, 1.2)),
+            ..Default::default()
+        };
+        let result = signal_to_noise(&retention_time, &trace, 1.0, &parameters).unwrap();
+        assert!(result.ratio < 10.0);
+
+        // empty traces and regions without points have no ratio
+        let parameters = SignalToNoiseParameters {
+            noise_region: Some((5.0, 6.0)),
+            ..Default::default()
+        };
+        assert!(signal_to_noise(&retention_time, &trace, 1.0, &parameters).is_none());
+        assert!(signal_to_noise(&[], &[], 1.0, &SignalToNoiseParameters::default()).is_none());
+    }
+
+    #[test]
+    fn test_signal_to_noise_typical_region() {
+        // a short quiet stretch does not set the noise of a trace that is mostly noisier
+        let (retention_time, mut trace) = noisy_peak();
+        for value in trace.iter_mut().take(40) {
+            *value *= 0.1;
+        }
+        let result = signal_to_noise(
+            &retention_time,
+            &trace,
+            1.0,
+            &SignalToNoiseParameters::default(),
+        )
+        .unwrap();
+        assert!((result.noise - 1.0).abs() < 0.1, "{:?}", result);
+        assert!(result.noise_region.0 >= 40, "{:?}", result.noise_region);
+    }
+
+    #[test]
+    fn test_signal_to_noise_without_noise() {
+        // the exact zeros left by subtracting a rolling minimum or SNIP baseline
+        let retention_time: Vec<f64> = (0..200).map(|i| i as f64 * 0.01).collect();
+        let (_, drifting) = drifting_peak();
+        let parameters = BaselineParameters {
+            method: BaselineMethod::RollingMinimum,
+            ..Default::default()
+        };
+        let corrected = subtract_baseline(&drifting, &estimate_baseline(&drifting, &parameters));
+        let flat = vec![0.0; 200];
+        for method in [NoiseMethod::PeakToPeak, NoiseMethod::Rms, NoiseMethod::Mad] {
+            let parameters = SignalToNoiseParameters {
+                method,
+                ..Default::default()
+            };
+            assert!(signal_to_noise(&retention_time, &flat, 1.0, &parameters).is_none());
+            // windows of the corrected trace without noise are skipped
+            let result = signal_to_noise(&retention_time, &corrected, 1.0, &parameters);
+            assert!(
+                result.map_or(true, |r| r.ratio.is_finite() && r.noise > 0.0),
+                "{:?}: {:?}",
+                method,
+                result
+            );
+        }
+
+        // a chosen region where most values are equal has no MAD noise
+        let mut trace = vec![5.0; 20];
+        trace[3] = 6.0;
+        trace[10] = 100.0;
+        let retention_time: Vec<f64> = (0..20).map(|i| i as f64).collect();
+        let parameters = SignalToNoiseParameters {
+            method: NoiseMethod::Mad,
+            noise_region: Some((0.0, 6.0)),
+            ..Default::default()
+        };
+        assert!(signal_to_noise(&retention_time, &trace, 10.0, &parameters).is_none());
+        let parameters = SignalToNoiseParameters {
+            method: NoiseMethod::Rms,
+            ..parameters
+        };
+        let result = signal_to_noise(&retention_time, &trace, 10.0, &parameters).unwrap();
+        assert!(result.ratio.is_finite());
+    }
+
+    #[test]
+    fn test_solve_pentadiagonal() {
+        // the tridiagonal 2, -1 matrix, padded with a zero second off-diagonal
+        let solution = solve_pentadiagonal(
+            &[2.0, 2.0, 2.0],
+            &[-1.0, -1.0, 0.0],
+            &[0.0, 0.0, 0.0],
+            &[1.0, 0.0, 1.0],
+        );
+        assert_close(&solution, &[1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            BaselineParameters {
+                method: BaselineMethod::Snip,
+                ..Default::default()
+            }
+            .to_string(),
+            "SNIP, 20 iterations"
+        );
+        assert_eq!(
+            SignalToNoiseParameters::default().to_string(),
+            "RMS noise, automatic region of 30 points"
+        );
+        assert_eq!(SmoothingParameters::default().to_string(), "none");
+        assert_eq!(
+            SmoothingParameters::moving_average(3).to_string(),
+            "moving average, 3 points per side"
+        );
+        let parameters = SmoothingParameters {
+            method: SmoothingMethod::SavitzkyGolay,
+            half_window: 4,
+            polynomial_order: 3,
+        };
+        assert_eq!(
+            parameters.to_string(),
+            "Savitzky-Golay, 4 points per side, order 3"
+        );
+    }
+}
@@ -0,0 +1,501 @@
+//! # headless command-line interface
+
+//! The `cli` module runs the chromatogram extraction of Chromascope from the command line, without opening a window.
+
+//! ## Overview
+
+//! When Chromascope is started with arguments, `main` hands them to `run`, which parses them, extracts the chromatogram with the same `MzData`
+//! methods and smoothing as the GUI, and writes it with the `export` module. The returned exit code tells scripts what went wrong:
+
+//! | Code | Meaning |
+//! |------|---------|
+//! | 0 | The chromatogram was written. |
+//! | 1 | The chromatogram is empty, e.g. the file holds no scans of the requested polarity or no peak matches the m/z. |
+//! | 2 | The arguments are invalid. |
+//! | 3 | The mzML file cannot be opened or read. |
+//! | 4 | The output cannot be written. |
+
+//!## Features
+
+//!- **Chromatograms**: Extract the TIC, BPC or XIC of either polarity, with the mass tolerance and smoothing of the GUI.
+//!- **Output Formats**: Write CSV, TSV or JSON, chosen from the extension of the output file or with `--format`, to a file or to the standard output.
+
+#![warn(clippy::all)]
+
+use chromascope::chromatogram_processing::{SmoothingMethod, SmoothingParameters};
+use chromascope::{export, parser};
+use log::info;
+use mzdata::spectrum::ScanPolarity;
+use std::path::{Path, PathBuf};
+
+/// The exit code of a successful run.
+pub const EXIT_SUCCESS: i32 = 0;
+/// The exit code when the extracted chromatogram is empty.
+pub const EXIT_NO_DATA: i32 = 1;
+/// The exit code of invalid arguments.
+pub const EXIT_USAGE: i32 = 2;
+/// The exit code when the mzML file cannot be opened or read.
+pub const EXIT_INPUT: i32 = 3;
+/// The exit code when the output cannot be written.
+pub const EXIT_OUTPUT: i32 = 4;
+
+/// The default mass tolerance of XICs in ppm, the same as in the GUI.
+const DEFAULT_PPM: f64 = 10.0;
+
+const USAGE: &str = "\
+Usage: chromascope <tic|bpc|xic> [options] <file.mzML>
+
+Extracts a chromatogram without opening a window. Without arguments, the GUI is started.
+
+Options:
+  --polarity <pos|neg>   Polarity of the scans [default: pos]
+  --mz <m/z>             m/z of the XIC (required for xic)
+  --ppm <ppm>            Mass tolerance of the XIC in ppm [default: 10]
+  --smooth <n>           Smooth over n points on either side [default: 0]
+  --smooth-method <ma|sg|gauss>
+                         Moving average, Savitzky-Golay or Gaussian smoothing [default: ma]
+  --sg-order <n>         Polynomial order of the Savitzky-Golay filter [default: 2]
+  --format <csv|tsv|json>
+                         Output format [default: from the output extension, else csv]
+  -o, --output <path>    Output file [default: standard output]
+  -h, --help             Print this help
+  -V, --version          Print the version
+
+Exit codes: 0 success, 1 empty chromatogram, 2 invalid arguments, 3 unreadable input, 4 unwritable output.";
+
+/// The chromatogram extracted by the command line interface.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Chromatogram {
+    /// The total ion chromatogram.
+    Tic,
+    /// The base peak chromatogram.
+    Bpc,
+    /// The extracted ion chromatogram of an m/z.
+    Xic(f64),
+}
+
+/// The format of the command line output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Delimited text, as written by the GUI export.
+    Delimited(export::Delimiter),
+    /// A JSON document.
+    Json,
+}
+
+/// The options of a chromatogram extraction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    /// The chromatogram to be extracted.
+    pub chromatogram: Chromatogram,
+    /// The polarity of the scans.
+    pub polarity: ScanPolarity,
+    /// The mass tolerance of the XIC in ppm.
+    pub ppm: f64,
+    /// The smoothing filter and its window.
+    pub smoothing: SmoothingParameters,
+    /// The mzML file to be read.
+    pub input: PathBuf,
+    /// The output file, or `None` for the standard output.
+    pub output: Option<PathBuf>,
+    /// The output format.
+    pub format: OutputFormat,
+}
+
+/// What the command line asks for.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Extract a chromatogram.
+    Extract(Options),
+    /// Print the usage.
+    Help,
+    /// Print the version.
+    Version,
+}
+
+/// An error of a command line run, which determines the exit code.
+#[derive(Debug)]
+pub enum CliError {
+    /// The arguments are invalid.
+    Usage(String),
+    /// The extracted chromatogram is empty.
+    NoData(String),
+    /// The mzML file cannot be opened or read.
+    Input(parser::ParserError),
+    /// The output cannot be written.
+    Output(anyhow::Error),
+}
+
+impl CliError {
+    /// Returns the exit code of the error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CliError::Usage(_) => EXIT_USAGE,
+            CliError::NoData(_) => EXIT_NO_DATA,
+            CliError::Input(_) => EXIT_INPUT,
+            CliError::Output(_) => EXIT_OUTPUT,
+        }
+    }
+}
+
+impl From<parser::ParserError> for CliError {
+    /// A file without matching scans yields no data, every other parser error is an input error.
+    fn from(error: parser::ParserError) -> Self {
+        match error {
+            parser::ParserError::NoMatchingSpectra { .. } => CliError::NoData(error.to_string()),
+            error => CliError::Input(error),
+        }
+    }
+}
+
+impl std::fmt::Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::Usage(message) => write!(f, "{}", message),
+            CliError::NoData(message) => write!(f, "{}", message),
+            CliError::Input(e) => write!(f, "Cannot read the input: {}", e),
+            CliError::Output(e) => write!(f, "Cannot write the output: {}", e),
+        }
+    }
+}
+
+/// Parses a numeric option value.
+fn parse_value<T: std::str::FromStr>(option: &str, value: &str) -> Result<T, CliError> {
+    value
+        .parse()
+        .map_err(|_| CliError::Usage(format!("Invalid value for {}: {}", option, value)))
+}
+
+/// Parses the command line arguments, without the program name.
+///
+/// # Parameters
+/// - `args: &[String]` - The arguments. Options take their value as the next argument or after `=`, e.g. `--mz 722.43` or `--mz=722.43`.
+///
+/// # Returns
+/// - `Result<Command, CliError>` - The command, or a usage error describing the first invalid argument.
+///
+/// # Functionality
+/// The first argument that is not an option selects the chromatogram (`tic`, `bpc` or `xic`) and the second one is the mzML file.
+/// `--mz` is required for, and only allowed with, `xic`. Without `--format`, the format follows the extension of the output file:
+/// JSON for `.json`, TSV for `.tsv` and `.txt` and CSV otherwise.
+pub fn parse_args(args: &[String]) -> Result<Command, CliError> {
+    let mut positional = Vec::new();
+    let mut polarity = ScanPolarity::Positive;
+    let mut mz = None;
+    let mut ppm = DEFAULT_PPM;
+    let mut smoothing = SmoothingParameters::default();
+    let mut output = None;
+    let mut format = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with('-') || arg == "-" {
+            positional.push(arg.clone());
+            continue;
+        }
+        let (option, inline_value) = match arg.split_once('=') {
+            Some((option, value)) => (option, Some(value.to_string())),
+            None => (arg.as_str(), None),
+        };
+        match option {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-V" | "--version" => return Ok(Command::Version),
+            _ => {}
+        }
+        let value = match inline_value.or_else(|| args.next().cloned()) {
+            Some(value) => value,
+            None => return Err(CliError::Usage(format!("Missing value for {}", option))),
+        };
+        match option {
+            "--polarity" => {
+                polarity = match value.to_lowercase().as_str() {
+                    "pos" | "positive" | "+" => ScanPolarity::Positive,
+                    "neg" | "negative" | "-" => ScanPolarity::Negative,
+                    _ => {
+                        return Err(CliError::Usage(format!(
+                            "Invalid polarity: {} (expected pos or neg)",
+                            value
+                        )))
+                    }
+                }
+            }
+            "--mz" => mz = Some(parse_value::<f64>(option, &value)?),
+            "--ppm" => ppm = parse_value(option, &value)?,
+            "--smooth" => smoothing.half_window = parse_value(option, &value)?,
+            "--smooth-method" => {
+                smoothing.method = match value.to_lowercase().as_str() {
+                    "ma" | "moving-average" => SmoothingMethod::MovingAverage,
+                    "sg" | "savitzky-golay" => SmoothingMethod::SavitzkyGolay,
+                    "gauss" | "gaussian" => SmoothingMethod::Gaussian,
+                    _ => {
+                        return Err(CliError::Usage(format!(
+                            "Invalid smoothing method: {} (expected ma, sg or gauss)",
+                            value
+                        )))
+                    }
+                }
+            }
+            "--sg-order" => smoothing.polynomial_order = parse_value(option, &value)?,
+            "-o" | "--output" => output = Some(PathBuf::from(value)),
+            "--format" => {
+                format = Some(match value.to_lowercase().as_str() {
+                    "csv" => OutputFormat::Delimited(export::Delimiter::Comma),
+                    "tsv" => OutputFormat::Delimited(export::Delimiter::Tab),
+                    "json" => OutputFormat::Json,
+                    _ => {
+                        return Err(CliError::Usage(format!(
+                            "Invalid format: {} (expected csv, tsv or json)",
+                            value
+                        )))
+                    }
+                })
+            }
+            _ => return Err(CliError::Usage(format!("Unknown option: {}", option))),
+        }
+    }
+
+    let mut positional = positional.into_iter();
+    let chromatogram = match (positional.next().as_deref(), mz) {
+        (Some("tic"), None) => Chromatogram::Tic,
+        (Some("bpc"), None) => Chromatogram::Bpc,
+        (Some("xic"), Some(mz)) => Chromatogram::Xic(mz),
+        (Some("xic"), None) => return Err(CliError::Usage("xic requires --mz".to_string())),
+        (Some("tic" | "bpc"), Some(_)) => {
+            return Err(CliError::Usage("--mz only applies to xic".to_string()))
+        }
+        (Some(other), _) => {
+            return Err(CliError::Usage(format!(
+                "Unknown chromatogram: {} (expected tic, bpc or xic)",
+                other
+            )))
+        }
+        (None, _) => return Err(CliError::Usage("Missing chromatogram type".to_string())),
+    };
+    let input = positional
+        .next()
+        .map(PathBuf::from)
+        .ok_or_else(|| CliError::Usage("Missing mzML file".to_string()))?;
+    if let Some(extra) = positional.next() {
+        return Err(CliError::Usage(format!("Unexpected argument: {}", extra)));
+    }
+    if ppm <= 0.0 {
+        return Err(CliError::Usage("--ppm must be positive".to_string()));
+    }
+
+    let format = format.unwrap_or_else(|| match &output {
+        Some(path) => format_from_path(path),
+        None => OutputFormat::Delimited(export::Delimiter::Comma),
+    });
+    Ok(Command::Extract(Options {
+        chromatogram,
+        polarity,
+        ppm,
+        smoothing,
+        input,
+        output,
+        format,
+    }))
+}
+
+/// Returns the output format matching the extension of a file.
+fn format_from_path(path: &Path) -> OutputFormat {
+    let file_name = path.to_string_lossy();
+    if path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"))
+    {
+        OutputFormat::Json
+    } else {
+        OutputFormat::Delimited(export::Delimiter::from_file_name(&file_name))
+    }
+}
+
+/// Extracts the chromatogram and writes it.
+///
+/// # Parameters
+/// - `options: &Options` - The options of the extraction.
+///
+/// # Returns
+/// - `Result<(), CliError>` - An error if the file cannot be read, the chromatogram is empty or the output cannot be written.
+///
+/// # Functionality
+/// The chromatogram is extracted with `MzData::get_tic`, `get_bpic` or `get_xic` and smoothed with `Chromatogram::smoothed`, as in the GUI.
+/// The output starts with the metadata of the chromatogram (file, plot type, polarity, MS level, m/z, mass tolerance, smoothing and retention time unit)
+/// and holds the raw and smoothed trace.
+pub fn extract(options: &Options) -> Result<(), CliError> {
+    let mut data = parser::MzData::default();
+    data.open_msfile(&options.input)?;
+    let raw = match options.chromatogram {
+        Chromatogram::Tic => data.get_tic(options.polarity),
+        Chromatogram::Bpc => data.get_bpic(options.polarity),
+        Chromatogram::Xic(mz) => data.get_xic(mz, options.polarity, options.ppm),
+    }?;
+    if raw.is_empty() {
+        return Err(CliError::NoData(format!(
+            "The {} of {} is empty for {:?} polarity",
+            raw.name(),
+            options.input.display(),
+            options.polarity
+        )));
+    }
+    let smoothed = raw.smoothed(&options.smoothing);
+
+    let metadata = smoothed.metadata();
+    let traces = [export::Trace::new(raw.name(), &raw, &smoothed)];
+
+    let write = |writer: &mut dyn std::io::Write| -> anyhow::Result<()> {
+        let mut writer = std::io::BufWriter::new(writer);
+        match options.format {
+            OutputFormat::Delimited(delimiter) => export::write_chromatogram(
+                &mut writer,
+                &metadata,
+                &traces,
+                export::TraceLayout::Aligned,
+                delimiter,
+            )?,
+            OutputFormat::Json => export::write_chromatogram_json(&mut writer, &metadata, &traces)?,
+        }
+        std::io::Write::flush(&mut writer)?;
+        Ok(())
+    };
+    match &options.output {
+        Some(path) => {
+            let mut file = std::fs::File::create(path)
+                .map_err(|e| CliError::Output(anyhow::anyhow!("{}: {}", path.display(), e)))?;
+            write(&mut file).map_err(CliError::Output)?;
+            info!("Chromatogram written to {:?}", path);
+        }
+        None => write(&mut std::io::stdout().lock()).map_err(CliError::Output)?,
+    }
+    Ok(())
+}
+
+/// Runs the command line interface and returns the exit code.
+///
+/// # Parameters
+/// - `args: &[String]` - The command line arguments, without the program name.
+///
+/// # Returns
+/// - `i32` - The exit code, see the module documentation. Errors are printed to the standard error.
+pub fn run(args: &[String]) -> i32 {
+    let result = parse_args(args).and_then(|command| match command {
+        Command::Help => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        Command::Version => {
+            println!("chromascope {}", env!("CARGO_PKG_VERSION"));
+            Ok(())
+        }
+        Command::Extract(options) => extract(&options),
+    });
+    match result {
+        Ok(()) => EXIT_SUCCESS,
+        Err(e) => {
+            info!("Command line run failed: {}", e);
+            eprintln!("Error: {}", e);
+            if let CliError::Usage(_) = e {
+                eprintln!("\n{}", USAGE);
+            }
+            e.exit_code()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let command = parse_args(&args(
+            "xic --polarity neg --mz=722.43 --ppm 5 --smooth 3 --smooth-method sg --sg-order 3 run.mzML -o out.json",
+        ))
+        .unwrap();
+        assert_eq!(
+            command,
+            Command::Extract(Options {
+                chromatogram: Chromatogram::Xic(722.43),
+                polarity: ScanPolarity::Negative,
+                ppm: 5.0,
+                smoothing: SmoothingParameters {
+                    method: SmoothingMethod::SavitzkyGolay,
+                    half_window: 3,
+                    polynomial_order: 3,
+                },
+                input: PathBuf::from("run.mzML"),
+                output: Some(PathBuf::from("out.json")),
+                format: OutputFormat::Json,
+            })
+        );
+
+        let Command::Extract(options) = parse_args(&args("tic run.mzML")).unwrap() else {
+            panic!("expected an extraction");
+        };
+        assert_eq!(options.chromatogram, Chromatogram::Tic);
+        assert_eq!(options.polarity, ScanPolarity::Positive);
+        assert_eq!(
+            options.format,
+            OutputFormat::Delimited(export::Delimiter::Comma)
+        );
+        assert_eq!(parse_args(&args("bpc --help")).unwrap(), Command::Help);
+    }
+
+    #[test]
+    fn test_parse_args_errors() {
+        for line in [
+            "xic run.mzML",
+            "tic --mz 100 run.mzML",
+            "sic run.mzML",
+            "tic",
+            "tic run.mzML other.mzML",
+            "tic --smooth -1 run.mzML",
+            "tic --smooth-method median run.mzML",
+            "tic --polarity up run.mzML",
+            "tic run.mzML -o",
+            "tic --unknown 1 run.mzML",
+        ] {
+            let error = parse_args(&args(line)).unwrap_err();
+            assert_eq!(error.exit_code(), EXIT_USAGE, "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_extract() {
+        let output = std::env::temp_dir().join("chromascope_cli_test.csv");
+        let options = Options {
+            chromatogram: Chromatogram::Tic,
+            polarity: ScanPolarity::Positive,
+            ppm: DEFAULT_PPM,
+            smoothing: SmoothingParameters::moving_average(1),
+            input: PathBuf::from("test_file/data_dependent_02.mzML"),
+            output: Some(output.clone()),
+            format: OutputFormat::Delimited(export::Delimiter::Comma),
+        };
+        extract(&options).unwrap();
+        let text = std::fs::read_to_string(&output).unwrap();
+        std::fs::remove_file(&output).unwrap();
+        assert!(text.contains("# plot type: TIC\n"));
+        // one row per MS1 scan after the comment lines and the header
+        assert_eq!(
+            text.lines().filter(|line| !line.starts_with('#')).count(),
+            54
+        );
+
+        let negative = Options {
+            polarity: ScanPolarity::Negative,
+            ..options.clone()
+        };
+        assert_eq!(extract(&negative).unwrap_err().exit_code(), EXIT_NO_DATA);
+
+        let missing = Options {
+            input: PathBuf::from("test_file/missing.mzML"),
+            ..options
+        };
+        assert_eq!(extract(&missing).unwrap_err().exit_code(), EXIT_INPUT);
+    }
+}
@@ -0,0 +1,608 @@
+//! # elemental composition generation from accurate mass
+
+//! The `composition` module provides functionality for finding the molecular formulas that explain an accurately measured m/z.
+
+//! ## Overview
+
+//! `generate` enumerates the formulas within the element ranges whose ion m/z lies within a ppm tolerance of the measured m/z, keeps those that
+//! pass the RDBE range and the nitrogen rule, and ranks them by how well their theoretical isotope pattern fits the observed spectrum.
+//! The element counts are capped at `Element::max_count`, which bounds the time the enumeration takes, and a running enumeration can be
+//! cancelled through a shared flag when its result is no longer needed.
+
+//!## Features
+
+//!- **Formula Enumeration**: Find all formulas of C, H, N, O, P, S, Cl and Br within element ranges and a mass tolerance.
+//!- **Chemical Constraints**: Filter formulas by ring and double bond equivalents (RDBE) and the nitrogen rule.
+//!- **Isotope Pattern Fit**: Compute the isotope pattern of a formula and score it against the observed isotopic peaks.
+
+#![warn(clippy::all)]
+
+use crate::deconvolution::PROTON_MASS;
+use log::debug;
+use std::cmp::Ordering;
+use std::sync::atomic::{self, AtomicBool};
+
+/// The number of isotopic peaks (including the monoisotopic peak) compared with the observed spectrum.
+const ISOTOPE_PEAKS: usize = 4;
+
+/// Represents a chemical element with its isotopes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Element {
+    /// The symbol of the element.
+    pub symbol: &'static str,
+    /// The mass of the most abundant (lightest) isotope, in Da.
+    pub mass: f64,
+    /// The nominal mass of the most abundant isotope.
+    pub nominal_mass: u32,
+    /// The valence used to compute the RDBE.
+    pub valence: u32,
+    /// The highest count of the element searched by `generate`.
+    pub max_count: u32,
+    /// The (mass difference to the lightest isotope, abundance) of every isotope.
+    pub isotopes: &'static [(f64, f64)],
+}
+
+/// The elements considered by the composition generator.
+pub const ELEMENTS: [Element; 8] = [
+    Element {
+        symbol: "C",
+        mass: 12.0,
+        nominal_mass: 12,
+        valence: 4,
+        max_count: 150,
+        isotopes: &[(0.0, 0.9893), (1.003355, 0.0107)],
+    },
+    Element {
+        symbol: "H",
+        mass: 1.007825,
+        nominal_mass: 1,
+        valence: 1,
+        max_count: 300,
+        isotopes: &[(0.0, 0.999885), (1.006277, 0.000115)],
+    },
+    Element {
+        symbol: "N",
+        mass: 14.003074,
+        nominal_mass: 14,
+        valence: 3,
+        max_count: 30,
+        isotopes: &[(0.0, 0.99636), (0.997035, 0.00364)],
+    },
+    Element {
+        symbol: "O",
+        mass: 15.994915,
+        nominal_mass: 16,
+        valence: 2,
+        max_count: 60,
+        isotopes: &[(0.0, 0.99757), (1.004217, 0.00038), (2.004246, 0.00205)],
+    },
+    Element {
+        symbol: "P",
+        mass: 30.973762,
+        nominal_mass: 31,
+        valence: 3,
+        max_count: 6,
+        isotopes: &[(0.0, 1.0)],
+    },
+    Element {
+        symbol: "S",
+        mass: 31.972071,
+        nominal_mass: 32,
+        valence: 2,
+        max_count: 8,
+        isotopes: &[
+            (0.0, 0.9499),
+            (0.999388, 0.0075),
+            (1.995796, 0.0425),
+            (3.99501, 0.0001),
+        ],
+    },
+    Element {
+        symbol: "Cl",
+        mass: 34.968853,
+        nominal_mass: 35,
+        valence: 1,
+        max_count: 8,
+        isotopes: &[(0.0, 0.7576), (1.99705, 0.2424)],
+    },
+    Element {
+        symbol: "Br",
+        mass: 78.918338,
+        nominal_mass: 79,
+        valence: 1,
+        max_count: 6,
+        isotopes: &[(0.0, 0.5069), (1.997953, 0.4931)],
+    },
+];
+
+/// The index of nitrogen in `ELEMENTS`.
+const NITROGEN: usize = 2;
+/// The index of hydrogen in `ELEMENTS`.
+const HYDROGEN: usize = 1;
+
+/// The parameters of the composition generator.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CompositionParameters {
+    /// The m/z tolerance (in ppm) between the measured and the theoretical m/z.
+    pub tolerance: f64,
+    /// The charge of the ion. Positive ions are protonated and negative ions deprotonated molecules. With a charge of 0 the m/z is taken as the neutral mass.
+    pub charge: i32,
+    /// The minimum and maximum count of every element, in the order of `ELEMENTS`. Counts above `Element::max_count` are not searched.
+    pub ranges: [(u32, u32); 8],
+    /// The lowest RDBE of a candidate.
+    pub min_rdbe: f64,
+    /// The highest RDBE of a candidate.
+    pub max_rdbe: f64,
+    /// Whether candidates must obey the nitrogen rule.
+    pub nitrogen_rule: bool,
+    /// The m/z tolerance (in ppm) used to find the isotopic peaks in the observed spectrum.
+    pub isotope_tolerance: f64,
+    /// The maximum number of returned candidates.
+    pub max_candidates: usize,
+}
+
+impl Default for CompositionParameters {
+    fn default() -> Self {
+        Self {
+            tolerance: 5.0,
+            charge: 1,
+            ranges: [
+                (0, 50),
+                (0, 100),
+                (0, 10),
+                (0, 15),
+                (0, 2),
+                (0, 3),
+                (0, 0),
+                (0, 0),
+            ],
+            min_rdbe: -0.5,
+            max_rdbe: 40.0,
+            nitrogen_rule: true,
+            isotope_tolerance: 20.0,
+            max_candidates: 50,
+        }
+    }
+}
+
+/// Represents a molecular formula explaining a measured m/z.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    /// The count of every element, in the order of `ELEMENTS`.
+    pub counts: [u32; 8],
+    /// The monoisotopic neutral mass of the formula.
+    pub mass: f64,
+    /// The theoretical m/z of the ion.
+    pub mz: f64,
+    /// The difference between the theoretical and the measured m/z, in ppm.
+    pub error: f64,
+    /// The ring and double bond equivalents of the formula.
+    pub rdbe: f64,
+    /// The fit of the theoretical isotope pattern to the observed one, from 0 (no fit) to 1 (perfect fit).
+    pub isotope_score: f64,
+}
+
+impl Candidate {
+    /// Returns the formula in Hill notation, e.g. `C8H10N4O2`.
+    pub fn formula(&self) -> String {
+        let mut order: Vec<usize> = (0..ELEMENTS.len()).collect();
+        order.sort_by_key(|&i| ELEMENTS[i].symbol);
+        if self.counts[0] > 0 {
+            // carbon first, then hydrogen, then all other elements alphabetically
+            order.retain(|&i| i != 0 && i != HYDROGEN);
+            order.splice(0..0, [0, HYDROGEN]);
+        }
+        order
+            .into_iter()
+            .filter(|&i| self.counts[i] > 0)
+            .map(|i| match self.counts[i] {
+                1 => ELEMENTS[i].symbol.to_string(),
+                count => format!("{}{}", ELEMENTS[i].symbol, count),
+            })
+            .collect()
+    }
+}
+
+/// Returns the ring and double bond equivalents of a formula.
+pub fn rdbe(counts: &[u32; 8]) -> f64 {
+    1.0 + counts
+        .iter()
+        .zip(ELEMENTS.iter())
+        .map(|(&count, element)| count as f64 * (element.valence as f64 - 2.0) / 2.0)
+        .sum::<f64>()
+}
+
+/// Checks the nitrogen rule: a molecule with an even nominal mass has an even number of nitrogen atoms, and vice versa.
+pub fn obeys_nitrogen_rule(counts: &[u32; 8]) -> bool {
+    let nominal_mass: u32 = counts
+        .iter()
+        .zip(ELEMENTS.iter())
+        .map(|(&count, element)| count * element.nominal_mass)
+        .sum();
+    nominal_mass % 2 == counts[NITROGEN] % 2
+}
+
+/// Computes the isotope pattern of a formula at nominal mass resolution.
+///
+/// # Parameters
+/// - `counts: &[u32; 8]` - The count of every element, in the order of `ELEMENTS`.
+/// - `peaks: usize` - The number of isotopic peaks, starting with the monoisotopic peak.
+///
+/// # Returns
+/// - `Vec<(f64, f64)>` - The (mass difference to the monoisotopic peak, abundance relative to the monoisotopic peak) of every isotopic peak.
+pub fn isotope_pattern(counts: &[u32; 8], peaks: usize) -> Vec<(f64, f64)> {
+    // (abundance, abundance-weighted mass difference) of every nominal mass
+    let mut pattern = vec![(0.0_f64, 0.0_f64); peaks];
+    pattern[0].0 = 1.0;
+    for (&count, element) in counts.iter().zip(ELEMENTS.iter()) {
+        for _ in 0..count {
+            let mut next = vec![(0.0, 0.0); peaks];
+            for (k, &(abundance, weighted_shift)) in pattern.iter().enumerate() {
+                for &(shift, isotope_abundance) in element.isotopes {
+                    let target = k + shift.round() as usize;
+                    if target < peaks {
+                        next[target].0 += abundance * isotope_abundance;
+                        next[target].1 += (weighted_shift + abundance * shift) * isotope_abundance;
+                    }
+                }
+            }
+            pattern = next;
+        }
+    }
+    let monoisotopic = pattern[0].0;
+    pattern
+        .into_iter()
+        .map(|(abundance, weighted_shift)| {
+            if abundance > 0.0 {
+                (weighted_shift / abundance, abundance / monoisotopic)
+            } else {
+                (0.0, 0.0)
+            }
+        })
+        .collect()
+}
+
+/// Scores the fit of the isotope pattern of a formula to the isotopic peaks of the observed spectrum.
+///
+/// The observed intensities of the isotopic peaks, relative to the monoisotopic peak, are compared with the theoretical abundances.
+/// Isotopic peaks that are not observed count as an intensity of 0.
+///
+/// # Returns
+/// - `f64` - `1 - Σ|observed - theoretical| / Σ(observed + theoretical)` over the isotopic peaks after the monoisotopic one.
+fn isotope_score(
+    counts: &[u32; 8],
+    monoisotopic_mz: f64,
+    charge: u32,
+    mz: &[f64],
+    intensity: &[f32],
+    tolerance: f64,
+) -> f64 {
+    let most_intense = |target: f64| {
+        let max_error = target * tolerance * 1e-6;
+        let start = mz.partition_point(|&m| m < target - max_error);
+        (start..mz.len().min(intensity.len()))
+            .take_while(|&i| mz[i] <= target + max_error)
+            .map(|i| intensity[i] as f64)
+            .fold(0.0, f64::max)
+    };
+    let monoisotopic_intensity = most_intense(monoisotopic_mz);
+    if monoisotopic_intensity <= 0.0 {
+        return 0.0;
+    }
+    let (difference, total) = isotope_pattern(counts, ISOTOPE_PEAKS)
+        .into_iter()
+        .skip(1)
+        .map(|(shift, abundance)| {
+            let observed = if abundance > 0.0 {
+                most_intense(monoisotopic_mz + shift / charge as f64) / monoisotopic_intensity
+            } else {
+                0.0
+            };
+            ((observed - abundance).abs(), observed + abundance)
+        })
+        .fold((0.0, 0.0), |sums, (difference, total)| {
+            (sums.0 + difference, sums.1 + total)
+        });
+    if total > 0.0 {
+        1.0 - difference / total
+    } else {
+        1.0
+    }
+}
+
+/// Generates the molecular formulas explaining a measured m/z.
+///
+/// # Parameters
+/// - `observed_mz: f64` - The measured m/z of the monoisotopic peak.
+/// - `mz: &[f64]` - The sorted m/z values of the observed centroid spectrum, used for the isotope pattern fit.
+/// - `intensity: &[f32]` - The intensities of the observed centroid spectrum.
+/// - `parameters: &CompositionParameters` - The tolerance, charge, element ranges and constraints of the search. The element ranges are capped
+///   at the `max_count` of their element.
+/// - `cancel: &AtomicBool` - A flag that stops the enumeration when it is set, e.g. by another thread once the result is no longer needed.
+///
+/// # Returns
+/// - `Vec<Candidate>` - At most `parameters.max_candidates` candidates, ranked by decreasing isotope pattern fit and increasing absolute mass error.
+///   If the enumeration was cancelled, only the candidates found until then are returned.
+pub fn generate(
+    observed_mz: f64,
+    mz: &[f64],
+    intensity: &[f32],
+    parameters: &CompositionParameters,
+    cancel: &AtomicBool,
+) -> Vec<Candidate> {
+    let mut ranges = parameters.ranges;
+    for (range, element) in ranges.iter_mut().zip(ELEMENTS.iter()) {
+        range.1 = range.1.min(element.max_count);
+    }
+    let parameters = &CompositionParameters {
+        ranges,
+        ..*parameters
+    };
+    let charge = parameters.charge.unsigned_abs().max(1);
+    let neutral_mass = observed_mz * charge as f64 - parameters.charge as f64 * PROTON_MASS;
+    let ion_mz = |mass: f64| (mass + parameters.charge as f64 * PROTON_MASS) / charge as f64;
+    let max_error = neutral_mass.abs() * parameters.tolerance * 1e-6 + 1e-9;
+
+    // heavy elements first, hydrogen is solved from the remaining mass
+    let mut order: Vec<usize> = (0..ELEMENTS.len()).filter(|&i| i != HYDROGEN).collect();
+    order.sort_by(|&a, &b| {
+        ELEMENTS[b]
+            .mass
+            .partial_cmp(&ELEMENTS[a].mass)
+            .unwrap_or(Ordering::Equal)
+    });
+    // the lightest and heaviest mass that the elements from a level on can add
+    let mut min_remaining = vec![parameters.ranges[HYDROGEN].0 as f64 * ELEMENTS[HYDROGEN].mass];
+    let mut max_remaining = vec![parameters.ranges[HYDROGEN].1 as f64 * ELEMENTS[HYDROGEN].mass];
+    for &i in order.iter().rev() {
+        let last = *min_remaining.last().unwrap();
+        min_remaining.push(last + parameters.ranges[i].0 as f64 * ELEMENTS[i].mass);
+        let last = *max_remaining.last().unwrap();
+        max_remaining.push(last + parameters.ranges[i].1 as f64 * ELEMENTS[i].mass);
+    }
+    min_remaining.reverse();
+    max_remaining.reverse();
+
+    let search = Search {
+        order: &order,
+        min_remaining: &min_remaining,
+        max_remaining: &max_remaining,
+        target: neutral_mass,
+        max_error,
+        parameters,
+        cancel,
+    };
+    let mut candidates = Vec::new();
+    let mut counts = [0_u32; 8];
+    enumerate(&search, 0, 0.0, &mut counts, &mut |counts| {
+        let mass: f64 = counts
+            .iter()
+            .zip(ELEMENTS.iter())
+            .map(|(&count, element)| count as f64 * element.mass)
+            .sum();
+        let rdbe = rdbe(counts);
+        if rdbe < parameters.min_rdbe
+            || rdbe > parameters.max_rdbe
+            || (parameters.nitrogen_rule && !obeys_nitrogen_rule(counts))
+        {
+            return;
+        }
+        let theoretical_mz = ion_mz(mass);
+        candidates.push(Candidate {
+            counts: *counts,
+            mass,
+            mz: theoretical_mz,
+            error: (theoretical_mz - observed_mz) / observed_mz * 1e6,
+            rdbe,
+            isotope_score: isotope_score(
+                counts,
+                observed_mz,
+                charge,
+                mz,
+                intensity,
+                parameters.isotope_tolerance,
+            ),
+        });
+    });
+    if cancel.load(atomic::Ordering::Relaxed) {
+        debug!(
+            "Cancelled the formula enumeration for m/z {:.4}",
+            observed_mz
+        );
+    }
+
+    candidates.sort_by(|a, b| {
+        b.isotope_score
+            .partial_cmp(&a.isotope_score)
+            .unwrap_or(Ordering::Equal)
+            .then(
+                a.error
+                    .abs()
+                    .partial_cmp(&b.error.abs())
+                    .unwrap_or(Ordering::Equal),
+            )
+    });
+    candidates.truncate(parameters.max_candidates);
+    candidates
+}
+
+/// The fixed state of a formula enumeration, shared by all levels of `enumerate`.
+struct Search<'a> {
+    /// The elements in the order they are assigned, hydrogen excluded
+    order: &'a [usize],
+    /// The lightest mass that the elements from a level of `order` on, hydrogen included, must add
+    min_remaining: &'a [f64],
+    /// The heaviest mass that the elements from a level of `order` on, hydrogen included, can add
+    max_remaining: &'a [f64],
+    /// The neutral mass to be explained
+    target: f64,
+    /// The largest accepted mass error, in Da
+    max_error: f64,
+    /// The element ranges of the search
+    parameters: &'a CompositionParameters,
+    /// The flag that stops the enumeration
+    cancel: &'a AtomicBool,
+}
+
+/// Recursively enumerates the element counts whose mass lies within `max_error` of `target`.
+///
+/// The elements of `order` are assigned one level at a time, and the hydrogen count is computed from the remaining mass.
+/// Counts whose mass cannot reach the target even with the largest counts of the remaining elements are skipped, and counts that
+/// exceed it even with their smallest counts end the level. The enumeration returns early once `cancel` is set.
+fn enumerate(
+    search: &Search,
+    level: usize,
+    mass: f64,
+    counts: &mut [u32; 8],
+    found: &mut dyn FnMut(&[u32; 8]),
+) {
+    if search.cancel.load(atomic::Ordering::Relaxed) {
+        return;
+    }
+    if level == search.order.len() {
+        let hydrogen = &ELEMENTS[HYDROGEN];
+        let (min, max) = search.parameters.ranges[HYDROGEN];
+        let count = ((search.target - mass) / hydrogen.mass).round();
+        if count < min as f64 || count > max as f64 {
+            return;
+        }
+        if (mass + count * hydrogen.mass - search.target).abs() <= search.max_error {
+            counts[HYDROGEN] = count as u32;
+            found(counts);
+        }
+        return;
+    }
+    let element = search.order[level];
+    let (min, max) = search.parameters.ranges[element];
+    for count in min..=max {
+        let partial = mass + count as f64 * ELEMENTS[element].mass;
+        if partial + search.min_remaining[level + 1] > search.target + search.max_error {
+            break;
+        }
+        if partial + search.max_remaining[level + 1] < search.target - search.max_error {
+            continue;
+        }
+        counts[element] = count;
+        enumerate(search, level + 1, partial, counts, found);
+    }
+    counts[element] = 0;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The element counts of caffeine, C8H10N4O2.
+    const CAFFEINE: [u32; 8] = [8, 10, 4, 2, 0, 0, 0, 0];
+
+    #[test]
+    fn test_formula_and_constraints() {
+        let candidate = Candidate {
+            counts: CAFFEINE,
+            mass: 0.0,
+            mz: 0.0,
+            error: 0.0,
+            rdbe: 0.0,
+            isotope_score: 0.0,
+        };
+        assert_eq!(candidate.formula(), "C8H10N4O2");
+        let candidate = Candidate {
+            counts: [0, 1, 0, 0, 0, 0, 1, 0],
+            ..candidate
+        };
+        assert_eq!(candidate.formula(), "ClH");
+
+        assert_eq!(rdbe(&CAFFEINE), 6.0);
+        assert!(obeys_nitrogen_rule(&CAFFEINE));
+        assert!(!obeys_nitrogen_rule(&[8, 11, 4, 2, 0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn test_isotope_pattern() {
+        let pattern = isotope_pattern(&CAFFEINE, 3);
+        assert_eq!(pattern[0], (0.0, 1.0));
+        // mostly 8 carbons with 1.07 % 13C
+        assert!((pattern[1].1 - 0.1).abs() < 0.01);
+        assert!((pattern[1].0 - 1.003).abs() < 0.01);
+        assert!(pattern[2].1 < pattern[1].1);
+
+        let chlorine = isotope_pattern(&[0, 1, 0, 0, 0, 0, 1, 0], 3);
+        assert!((chlorine[2].1 - 0.2424 / 0.7576).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_generate() {
+        let observed_mz = 195.087652;
+        let (mz, intensity): (Vec<f64>, Vec<f32>) = isotope_pattern(&CAFFEINE, ISOTOPE_PEAKS)
+            .into_iter()
+            .map(|(shift, abundance)| (observed_mz + shift, (abundance * 1000.0) as f32))
+            .unzip();
+
+        let parameters = CompositionParameters::default();
+        let candidates = generate(
+            observed_mz,
+            &mz,
+            &intensity,
+            &parameters,
+            &AtomicBool::new(false),
+        );
+        assert!(!candidates.is_empty());
+        assert_eq!(candidates[0].formula(), "C8H10N4O2");
+        assert!(candidates[0].error.abs() < 1.0);
+        assert!(candidates[0].isotope_score > 0.99);
+        assert!(candidates
+            .iter()
+            .all(|candidate| candidate.error.abs() <= parameters.tolerance));
+
+        let parameters = CompositionParameters {
+            ranges: [
+                (0, 50),
+                (0, 100),
+                (0, 0),
+                (0, 15),
+                (0, 2),
+                (0, 3),
+                (0, 0),
+                (0, 0),
+            ],
+            ..Default::default()
+        };
+        assert!(generate(
+            observed_mz,
+            &mz,
+            &intensity,
+            &parameters,
+            &AtomicBool::new(false)
+        )
+        .iter()
+        .all(|candidate| candidate.counts[NITROGEN] == 0));
+
+        let parameters = CompositionParameters {
+            ranges: [(0, u32::MAX); 8],
+            ..Default::default()
+        };
+        assert!(generate(
+            observed_mz,
+            &mz,
+            &intensity,
+            &parameters,
+            &AtomicBool::new(false)
+        )
+        .iter()
+        .all(|candidate| candidate
+            .counts
+            .iter()
+            .zip(ELEMENTS.iter())
+            .all(|(&count, element)| count <= element.max_count)));
+
+        let parameters = CompositionParameters::default();
+        assert!(generate(
+            observed_mz,
+            &mz,
+            &intensity,
+            &parameters,
+            &AtomicBool::new(true)
+        )
+        .is_empty());
+    }
+}
@@ -0,0 +1,369 @@
+//! # isotope envelope detection, charge state assignment and charge deconvolution
+
+//! The `deconvolution` module provides functionality for recognizing the isotope envelopes of centroid mass spectra, assigning their charge states
+//! and transforming spectra of multiply charged ions into neutral mass spectra.
+
+//! ## Overview
+
+//! Isotopic peaks of an ion with charge `z` are spaced by `ISOTOPE_SPACING / z` in m/z. `detect_envelopes` groups the peaks of a spectrum into such series,
+//! starting from the most intense peaks, and reports the charge and monoisotopic m/z of every envelope.
+//! Large molecules such as intact proteins form a ladder of charge states instead, whose isotopes are usually not resolved. `deconvolve` finds these
+//! ladders and sums the intensity of all their charge states at the neutral mass of the molecule.
+
+//!## Features
+
+//!- **Envelope Detection**: Group centroid peaks into isotope series within a ppm tolerance.
+//!- **Charge Assignment**: Assign the charge state that explains the most peaks of an envelope.
+//!- **Charge Deconvolution**: Compute the zero-charge (neutral mass) spectrum of multiply charged ions within an m/z and charge range.
+
+#![warn(clippy::all)]
+
+use std::cmp::Ordering;
+
+/// The mass difference between the first two isotopes of a typical organic molecule (¹³C - ¹²C), in Da.
+pub const ISOTOPE_SPACING: f64 = 1.003355;
+
+/// The mass of a proton, in Da.
+pub const PROTON_MASS: f64 = 1.007276;
+
+/// The parameters of the isotope envelope detection.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EnvelopeParameters {
+    /// The m/z tolerance (in ppm) between an expected and an observed isotopic peak.
+    pub tolerance: f64,
+    /// The highest charge state considered.
+    pub max_charge: u32,
+    /// The minimum number of peaks of an envelope.
+    pub min_peaks: usize,
+}
+
+impl Default for EnvelopeParameters {
+    fn default() -> Self {
+        Self {
+            tolerance: 10.0,
+            max_charge: 10,
+            min_peaks: 3,
+        }
+    }
+}
+
+/// The parameters of the charge deconvolution.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeconvolutionParameters {
+    /// The m/z range of the peaks that are deconvolved. All peaks are used if `None`.
+    pub mz_range: Option<(f64, f64)>,
+    /// The lowest and highest charge states considered.
+    pub charge_range: (u32, u32),
+    /// The m/z tolerance (in ppm) between an expected and an observed charge state.
+    pub tolerance: f64,
+    /// The minimum number of consecutive charge states that must be observed for a neutral mass.
+    pub min_charge_states: usize,
+    /// The width (in Da) of the bins of the neutral mass spectrum.
+    pub mass_bin: f64,
+}
+
+impl Default for DeconvolutionParameters {
+    fn default() -> Self {
+        Self {
+            mz_range: None,
+            charge_range: (5, 60),
+            tolerance: 20.0,
+            min_charge_states: 3,
+            mass_bin: 1.0,
+        }
+    }
+}
+
+/// Represents an isotope envelope found in a spectrum.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IsotopeEnvelope {
+    /// The charge state of the envelope.
+    pub charge: u32,
+    /// The m/z of the lowest isotopic peak of the envelope, taken as the monoisotopic peak.
+    pub monoisotopic_mz: f64,
+    /// The indices of the peaks of the envelope, by increasing m/z.
+    pub peaks: Vec<usize>,
+    /// The intensity of the most intense peak of the envelope.
+    pub max_intensity: f32,
+}
+
+/// Finds the unassigned peak closest to `target` within the tolerance.
+///
+/// `order` holds the peak indices sorted by m/z.
+fn find_peak(
+    mz: &[f64],
+    order: &[usize],
+    assigned: &[bool],
+    target: f64,
+    tolerance: f64,
+) -> Option<usize> {
+    let max_error = target * tolerance * 1e-6;
+    let start = order.partition_point(|&i| mz[i] < target - max_error);
+    order[start..]
+        .iter()
+        .take_while(|&&i| mz[i] <= target + max_error)
+        .filter(|&&i| !assigned[i])
+        .min_by(|&&a, &&b| {
+            (mz[a] - target)
+                .abs()
+                .partial_cmp(&(mz[b] - target).abs())
+                .unwrap_or(Ordering::Equal)
+        })
+        .copied()
+}
+
+/// Follows the isotope series of a seed peak at a charge state, in both directions.
+///
+/// # Returns
+/// - `Vec<usize>` - The indices of the peaks of the series, by increasing m/z.
+fn follow_series(
+    mz: &[f64],
+    order: &[usize],
+    assigned: &[bool],
+    seed: usize,
+    charge: u32,
+    tolerance: f64,
+) -> Vec<usize> {
+    let step = ISOTOPE_SPACING / charge as f64;
+    let mut lower = Vec::new();
+    let mut current = seed;
+    while let Some(peak) = find_peak(mz, order, assigned, mz[current] - step, tolerance) {
+        lower.push(peak);
+        current = peak;
+    }
+    let mut upper = Vec::new();
+    current = seed;
+    while let Some(peak) = find_peak(mz, order, assigned, mz[current] + step, tolerance) {
+        upper.push(peak);
+        current = peak;
+    }
+    lower.reverse();
+    lower.push(seed);
+    lower.extend(upper);
+    lower
+}
+
+/// Detects the isotope envelopes of a centroid spectrum and assigns their charge states.
+///
+/// Peaks are visited by decreasing intensity. For every peak that does not belong to an envelope yet, the isotope series is followed
+/// at every charge state up to `parameters.max_charge`, and the charge explaining the most peaks is kept (the lower charge on a tie).
+/// Series with fewer than `parameters.min_peaks` peaks are discarded.
+///
+/// # Parameters
+/// - `mz: &[f64]` - The m/z values of the centroid spectrum.
+/// - `intensity: &[f32]` - The intensities of the centroid spectrum.
+/// - `parameters: &EnvelopeParameters` - The tolerance, charge range and minimum size of the envelopes.
+///
+/// # Returns
+/// - `Vec<IsotopeEnvelope>` - The detected envelopes, by decreasing intensity of their most intense peak.
+pub fn detect_envelopes(
+    mz: &[f64],
+    intensity: &[f32],
+    parameters: &EnvelopeParameters,
+) -> Vec<IsotopeEnvelope> {
+    let n = mz.len().min(intensity.len());
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|&a, &b| mz[a].partial_cmp(&mz[b]).unwrap_or(Ordering::Equal));
+    let mut by_intensity: Vec<usize> = (0..n).filter(|&i| intensity[i] > 0.0).collect();
+    by_intensity.sort_by(|&a, &b| {
+        intensity[b]
+            .partial_cmp(&intensity[a])
+            .unwrap_or(Ordering::Equal)
+    });
+
+    let mut assigned = vec![false; n];
+    let mut envelopes = Vec::new();
+    for seed in by_intensity {
+        if assigned[seed] {
+            continue;
+        }
+        let best = (1..=parameters.max_charge)
+            .map(|charge| {
+                (
+                    charge,
+                    follow_series(mz, &order, &assigned, seed, charge, parameters.tolerance),
+                )
+            })
+            .fold(None::<(u32, Vec<usize>)>, |best, candidate| match best {
+                Some(best) if best.1.len() >= candidate.1.len() => Some(best),
+                _ => Some(candidate),
+            });
+        let Some((charge, peaks)) = best else {
+            continue;
+        };
+        if peaks.len() < parameters.min_peaks.max(2) {
+            continue;
+        }
+        for &peak in &peaks {
+            assigned[peak] = true;
+        }
+        envelopes.push(IsotopeEnvelope {
+            charge,
+            monoisotopic_mz: mz[peaks[0]],
+            max_intensity: intensity[seed],
+            peaks,
+        });
+    }
+    envelopes
+}
+
+/// Checks whether a peak lies within the tolerance of `target`.
+///
+/// `order` holds the indices of the peaks sorted by m/z.
+fn contains_peak(mz: &[f64], order: &[usize], target: f64, tolerance: f64) -> bool {
+    let max_error = target * tolerance * 1e-6;
+    let start = order.partition_point(|&i| mz[i] < target - max_error);
+    order
+        .get(start)
+        .is_some_and(|&i| mz[i] <= target + max_error)
+}
+
+/// Deconvolves a centroid spectrum of multiply charged ions into a neutral mass spectrum.
+///
+/// Every peak within `parameters.mz_range` is interpreted at every charge state of `parameters.charge_range`. The resulting neutral mass
+/// is kept if the peaks of at least `parameters.min_charge_states` consecutive charge states, including the peak itself, are observed.
+/// The intensities of the kept peaks are summed into bins of `parameters.mass_bin` Da, so all charge states of a molecule add up at its neutral mass.
+/// Requiring consecutive charge states suppresses most artifacts, but a weaker artifact may remain at half the neutral mass of a long charge ladder.
+///
+/// # Parameters
+/// - `mz: &[f64]` - The m/z values of the centroid spectrum.
+/// - `intensity: &[f32]` - The intensities of the centroid spectrum.
+/// - `parameters: &DeconvolutionParameters` - The m/z range, charge range, tolerance and mass bin width of the deconvolution.
+///
+/// # Returns
+/// - `(Vec<f64>, Vec<f32>)` - The neutral masses (the intensity-weighted mean mass of every bin) and intensities, by increasing mass.
+pub fn deconvolve(
+    mz: &[f64],
+    intensity: &[f32],
+    parameters: &DeconvolutionParameters,
+) -> (Vec<f64>, Vec<f32>) {
+    let (min_charge, max_charge) = parameters.charge_range;
+    let mut order: Vec<usize> = (0..mz.len().min(intensity.len()))
+        .filter(|&i| {
+            intensity[i] > 0.0
+                && parameters
+                    .mz_range
+                    .map_or(true, |(min, max)| min <= mz[i] && mz[i] <= max)
+        })
+        .collect();
+    order.sort_by(|&a, &b| mz[a].partial_cmp(&mz[b]).unwrap_or(Ordering::Equal));
+
+    let expected_mz = |mass: f64, charge: u32| (mass + charge as f64 * PROTON_MASS) / charge as f64;
+    let mut bins: std::collections::BTreeMap<i64, (f64, f64)> = std::collections::BTreeMap::new();
+    for &i in &order {
+        for charge in min_charge.max(1)..=max_charge {
+            let mass = charge as f64 * (mz[i] - PROTON_MASS);
+            let lower = (min_charge.max(1)..charge)
+                .rev()
+                .take_while(|&z| {
+                    contains_peak(mz, &order, expected_mz(mass, z), parameters.tolerance)
+                })
+                .count();
+            let upper = (charge + 1..=max_charge)
+                .take_while(|&z| {
+                    contains_peak(mz, &order, expected_mz(mass, z), parameters.tolerance)
+                })
+                .count();
+            if lower + upper + 1 < parameters.min_charge_states {
+                continue;
+            }
+            let bin = bins
+                .entry((mass / parameters.mass_bin).round() as i64)
+                .or_insert((0.0, 0.0));
+            bin.0 += mass * intensity[i] as f64;
+            bin.1 += intensity[i] as f64;
+        }
+    }
+    bins.into_values()
+        .map(|(weighted_mass, total)| (weighted_mass / total, total as f32))
+        .unzip()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the m/z values and intensities of an isotope envelope.
+    fn envelope(monoisotopic_mz: f64, charge: u32, intensities: &[f32]) -> (Vec<f64>, Vec<f32>) {
+        (
+            (0..intensities.len())
+                .map(|k| monoisotopic_mz + k as f64 * ISOTOPE_SPACING / charge as f64)
+                .collect(),
+            intensities.to_vec(),
+        )
+    }
+
+    #[test]
+    fn test_detect_envelopes() {
+        let (mut mz, mut intensity) = envelope(400.2, 1, &[100.0, 60.0, 20.0]);
+        let (mz2, intensity2) = envelope(600.3, 2, &[50.0, 80.0, 40.0, 10.0]);
+        mz.extend(mz2);
+        intensity.extend(intensity2);
+        // an isolated peak is not an envelope
+        mz.push(700.0);
+        intensity.push(500.0);
+
+        let envelopes = detect_envelopes(&mz, &intensity, &EnvelopeParameters::default());
+        assert_eq!(envelopes.len(), 2);
+        assert_eq!(envelopes[0].charge, 1);
+        assert_eq!(envelopes[0].monoisotopic_mz, 400.2);
+        assert_eq!(envelopes[0].peaks, vec![0, 1, 2]);
+        assert_eq!(envelopes[1].charge, 2);
+        assert_eq!(envelopes[1].monoisotopic_mz, 600.3);
+        assert_eq!(envelopes[1].peaks.len(), 4);
+        assert_eq!(envelopes[1].max_intensity, 80.0);
+    }
+
+    #[test]
+    fn test_detect_envelopes_min_peaks() {
+        let (mz, intensity) = envelope(500.0, 3, &[100.0, 50.0]);
+        assert!(detect_envelopes(&mz, &intensity, &EnvelopeParameters::default()).is_empty());
+
+        let parameters = EnvelopeParameters {
+            min_peaks: 2,
+            ..Default::default()
+        };
+        let envelopes = detect_envelopes(&mz, &intensity, &parameters);
+        assert_eq!(envelopes.len(), 1);
+        assert_eq!(envelopes[0].charge, 3);
+    }
+
+    #[test]
+    fn test_deconvolve() {
+        let mass = 20000.0;
+        let mut mz: Vec<f64> = (10..=20)
+            .map(|z| (mass + z as f64 * PROTON_MASS) / z as f64)
+            .collect();
+        let mut intensity: Vec<f32> = (10..=20).map(|z| 100.0 + z as f32).collect();
+        // unrelated peaks do not form a charge ladder
+        mz.extend([987.6, 1234.5]);
+        intensity.extend([1000.0, 1000.0]);
+
+        let (masses, intensities) =
+            deconvolve(&mz, &intensity, &DeconvolutionParameters::default());
+        let base_peak = (0..masses.len())
+            .max_by(|&a, &b| intensities[a].partial_cmp(&intensities[b]).unwrap())
+            .unwrap();
+        assert!((masses[base_peak] - mass).abs() < 0.01);
+        assert_eq!(intensities[base_peak], intensity[..11].iter().sum::<f32>());
+
+        // the m/z range excludes the low charge states
+        let parameters = DeconvolutionParameters {
+            mz_range: Some((0.0, 1500.0)),
+            ..Default::default()
+        };
+        let (masses, intensities) = deconvolve(&mz, &intensity, &parameters);
+        let i = masses.iter().position(|m| (m - mass).abs() < 0.01).unwrap();
+        assert_eq!(
+            intensities[i],
+            (14..=20).map(|z| 100.0 + z as f32).sum::<f32>()
+        );
+
+        // too few charge states
+        let parameters = DeconvolutionParameters {
+            min_charge_states: 12,
+            ..Default::default()
+        };
+        assert!(deconvolve(&mz, &intensity, &parameters).0.is_empty());
+    }
+}
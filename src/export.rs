@@ -0,0 +1,578 @@
+//! # export of plotted data to text files
+
+//! The `export` module provides functions that write the data shown in Chromascope to files that other tools can read.
+
+//! ## Overview
+
+//! The functions write to any `std::io::Write` implementation, so they are independent of the GUI and of the file system.
+//! Delimited text files start with comment lines (`# key: value`) describing where the data comes from.
+
+//!## Features
+
+//!- **Chromatogram Export**: Write the raw and smoothed chromatogram traces as delimited text, with the traces as aligned columns or in long format, or as JSON.
+//!- **Spectrum Export**: Write a mass spectrum as delimited text, or as an MSP or MGF entry with its precursor and retention time.
+
+#![warn(clippy::all)]
+
+use crate::parser::{Chromatogram, Spectrum};
+use anyhow::Result;
+use std::io::Write;
+
+/// The field delimiter of an exported text file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Delimiter {
+    /// Comma-separated values (CSV).
+    #[default]
+    Comma,
+    /// Tab-separated values (TSV).
+    Tab,
+}
+
+impl Delimiter {
+    /// Returns the delimiter matching the extension of a file name: tabs for `.tsv` and `.txt` files, commas otherwise.
+    pub fn from_file_name(file_name: &str) -> Self {
+        let extension = std::path::Path::new(file_name)
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+        match extension.as_deref() {
+            Some("tsv") | Some("txt") => Delimiter::Tab,
+            _ => Delimiter::Comma,
+        }
+    }
+
+    /// Returns the delimiter character.
+    pub fn as_char(&self) -> char {
+        match self {
+            Delimiter::Comma => ',',
+            Delimiter::Tab => '\t',
+        }
+    }
+}
+
+/// How several traces are arranged in an exported file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TraceLayout {
+    /// One row per retention time, with the raw and smoothed intensity of every trace in its own columns.
+    #[default]
+    Aligned,
+    /// One row per trace and retention time, with the trace name in the first column.
+    Long,
+}
+
+/// The file format of an exported mass spectrum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpectrumFormat {
+    /// Delimited text with an m/z and an intensity column.
+    Delimited(Delimiter),
+    /// An entry of an MSP (NIST) spectral library.
+    Msp,
+    /// An entry of a Mascot Generic Format (MGF) file.
+    Mgf,
+}
+
+impl SpectrumFormat {
+    /// Returns the format matching the extension of a file name: MSP for `.msp`, MGF for `.mgf` and delimited text otherwise.
+    pub fn from_file_name(file_name: &str) -> Self {
+        let extension = std::path::Path::new(file_name)
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+        match extension.as_deref() {
+            Some("msp") => SpectrumFormat::Msp,
+            Some("mgf") => SpectrumFormat::Mgf,
+            _ => SpectrumFormat::Delimited(Delimiter::from_file_name(file_name)),
+        }
+    }
+}
+
+/// Represents a chromatogram trace with its raw and smoothed data points.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Trace {
+    /// The name of the trace, used in the column names or in the trace column.
+    pub name: String,
+    /// The raw (retention time, intensity) data points, sorted by retention time.
+    pub raw: Vec<[f64; 2]>,
+    /// The smoothed (retention time, intensity) data points, at the same retention times as `raw`.
+    pub smoothed: Vec<[f64; 2]>,
+}
+
+impl Trace {
+    /// Creates a trace from a raw chromatogram and its smoothed copy.
+    pub fn new(name: String, raw: &Chromatogram, smoothed: &Chromatogram) -> Self {
+        Self {
+            name,
+            raw: raw.points(),
+            smoothed: smoothed.points(),
+        }
+    }
+}
+
+/// Quotes a field if it contains the delimiter, a quote or a line break.
+fn escape_field(field: &str, delimiter: Delimiter) -> String {
+    if field.contains(delimiter.as_char()) || field.contains('"') || field.contains('\n') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Writes the metadata of an exported file as `# key: value` comment lines.
+fn write_metadata(writer: &mut impl Write, metadata: &[(String, String)]) -> Result<()> {
+    for (key, value) in metadata {
+        writeln!(writer, "# {}: {}", key, value)?;
+    }
+    Ok(())
+}
+
+/// Writes a row of fields separated by the delimiter.
+fn write_row(writer: &mut impl Write, fields: &[String], delimiter: Delimiter) -> Result<()> {
+    let row: Vec<String> = fields
+        .iter()
+        .map(|field| escape_field(field, delimiter))
+        .collect();
+    writeln!(writer, "{}", row.join(&delimiter.as_char().to_string()))?;
+    Ok(())
+}
+
+/// Writes chromatogram traces as delimited text.
+///
+/// # Parameters
+/// - `writer: &mut impl Write` - The destination of the text.
+/// - `metadata: &[(String, String)]` - The (key, value) pairs written as comment lines before the data, e.g. the file, plot type and polarity.
+/// - `traces: &[Trace]` - The traces to be written.
+/// - `layout: TraceLayout` - Whether the traces are written as aligned columns or in long format.
+/// - `delimiter: Delimiter` - The field delimiter.
+///
+/// # Functionality
+/// - `TraceLayout::Aligned`: The retention times of all traces are merged. Every row holds a retention time and the raw and smoothed intensity of every trace,
+///   and traces without a data point at that retention time get empty fields.
+/// - `TraceLayout::Long`: Every row holds the trace name, the retention time and the raw and smoothed intensity of one data point.
+///
+/// # Errors
+/// Returns an error if the text cannot be written.
+pub fn write_chromatogram(
+    writer: &mut impl Write,
+    metadata: &[(String, String)],
+    traces: &[Trace],
+    layout: TraceLayout,
+    delimiter: Delimiter,
+) -> Result<()> {
+    write_metadata(writer, metadata)?;
+    match layout {
+        TraceLayout::Aligned => {
+            let mut header = vec!["retention_time".to_string()];
+            for trace in traces {
+                header.push(format!("{} raw", trace.name));
+                header.push(format!("{} smoothed", trace.name));
+            }
+            write_row(writer, &header, delimiter)?;
+
+            let mut retention_times: Vec<f64> = traces
+                .iter()
+                .flat_map(|trace| trace.raw.iter().map(|point| point[0]))
+                .collect();
+            retention_times.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+            retention_times.dedup();
+
+            // the position of the next data point of every trace
+            let mut positions = vec![0_usize; traces.len()];
+            for rt in retention_times {
+                let mut row = vec![rt.to_string()];
+                for (trace, position) in traces.iter().zip(positions.iter_mut()) {
+                    match trace.raw.get(*position) {
+                        Some(point) if point[0] == rt => {
+                            row.push(point[1].to_string());
+                            row.push(
+                                trace
+                                    .smoothed
+                                    .get(*position)
+                                    .map(|point| point[1].to_string())
+                                    .unwrap_or_default(),
+                            );
+                            *position += 1;
+                        }
+                        _ => row.extend([String::new(), String::new()]),
+                    }
+                }
+                write_row(writer, &row, delimiter)?;
+            }
+        }
+        TraceLayout::Long => {
+            write_row(
+                writer,
+                &[
+                    "trace".to_string(),
+                    "retention_time".to_string(),
+                    "intensity_raw".to_string(),
+                    "intensity_smoothed".to_string(),
+                ],
+                delimiter,
+            )?;
+            for trace in traces {
+                for (i, point) in trace.raw.iter().enumerate() {
+                    write_row(
+                        writer,
+                        &[
+                            trace.name.clone(),
+                            point[0].to_string(),
+                            point[1].to_string(),
+                            trace
+                                .smoothed
+                                .get(i)
+                                .map(|point| point[1].to_string())
+                                .unwrap_or_default(),
+                        ],
+                        delimiter,
+                    )?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Writes chromatogram traces as a JSON document.
+///
+/// # Parameters
+/// - `writer: &mut impl Write` - The destination of the text.
+/// - `metadata: &[(String, String)]` - The (key, value) pairs describing the traces, e.g. the file, plot type and polarity.
+/// - `traces: &[Trace]` - The traces to be written.
+///
+/// # Functionality
+/// The document is an object with a `metadata` object holding the (key, value) pairs and a `traces` array. Every trace is an object with its `name`,
+/// its `retention_time` values and its `raw` and `smoothed` intensities, which are arrays of the same length.
+///
+/// # Errors
+/// Returns an error if the text cannot be written.
+pub fn write_chromatogram_json(
+    writer: &mut impl Write,
+    metadata: &[(String, String)],
+    traces: &[Trace],
+) -> Result<()> {
+    let metadata: serde_json::Map<String, serde_json::Value> = metadata
+        .iter()
+        .map(|(key, value)| (key.clone(), serde_json::Value::from(value.as_str())))
+        .collect();
+    let traces: Vec<serde_json::Value> = traces
+        .iter()
+        .map(|trace| {
+            serde_json::json!({
+                "name": trace.name,
+                "retention_time": trace.raw.iter().map(|point| point[0]).collect::<Vec<f64>>(),
+                "raw": trace.raw.iter().map(|point| point[1]).collect::<Vec<f64>>(),
+                "smoothed": trace.smoothed.iter().map(|point| point[1]).collect::<Vec<f64>>(),
+            })
+        })
+        .collect();
+    serde_json::to_writer_pretty(
+        &mut *writer,
+        &serde_json::json!({ "metadata": metadata, "traces": traces }),
+    )?;
+    writeln!(writer)?;
+    Ok(())
+}
+
+/// Formats a charge state as in MGF files, e.g. `2+` or `1-`.
+fn format_charge(charge: i32) -> String {
+    format!(
+        "{}{}",
+        charge.unsigned_abs(),
+        if charge < 0 { '-' } else { '+' }
+    )
+}
+
+/// Writes a mass spectrum in the chosen format.
+///
+/// # Parameters
+/// - `writer: &mut impl Write` - The destination of the text.
+/// - `spectrum: &Spectrum` - The spectrum to be written.
+/// - `format: SpectrumFormat` - The file format.
+///
+/// # Functionality
+/// - `SpectrumFormat::Delimited`: Comment lines with the label, scan, retention time and precursor of the spectrum, followed by an `mz` and an `intensity` column.
+/// - `SpectrumFormat::Msp`: A `Name:` line with the label, `PrecursorMZ:`, `Charge:`, `RetentionTime:` (in minutes) and `Comment:` lines when known, `Num Peaks:` and the peak list, followed by an empty line.
+/// - `SpectrumFormat::Mgf`: A `BEGIN IONS`/`END IONS` block with `TITLE`, `PEPMASS`, `CHARGE`, `RTINSECONDS` and `SCANS` when known, and the peak list.
+///
+/// The retention time is converted from the `time_unit` of the spectrum to the unit of the MSP and MGF fields.
+///
+/// # Errors
+/// Returns an error if the text cannot be written.
+pub fn write_spectrum(
+    writer: &mut impl Write,
+    spectrum: &Spectrum,
+    format: SpectrumFormat,
+) -> Result<()> {
+    let peaks = spectrum.mz.iter().zip(spectrum.intensity.iter());
+    match format {
+        SpectrumFormat::Delimited(delimiter) => {
+            let mut metadata = vec![("spectrum".to_string(), spectrum.label.clone())];
+            if let Some(index) = spectrum.index {
+                metadata.push(("scan".to_string(), index.to_string()));
+            }
+            if let Some(retention_time) = spectrum.retention_time {
+                metadata.push(("retention time".to_string(), retention_time.to_string()));
+            }
+            if let Some(precursor_mz) = spectrum.precursor_mz {
+                metadata.push(("precursor m/z".to_string(), precursor_mz.to_string()));
+            }
+            if let Some(charge) = spectrum.precursor_charge {
+                metadata.push(("precursor charge".to_string(), format_charge(charge)));
+            }
+            metadata.push((
+                "representation".to_string(),
+                if spectrum.profile {
+                    "profile"
+                } else {
+                    "centroid"
+                }
+                .to_string(),
+            ));
+            write_metadata(writer, &metadata)?;
+            write_row(
+                writer,
+                &["mz".to_string(), "intensity".to_string()],
+                delimiter,
+            )?;
+            for (mz, intensity) in peaks {
+                write_row(writer, &[mz.to_string(), intensity.to_string()], delimiter)?;
+            }
+        }
+        SpectrumFormat::Msp => {
+            writeln!(writer, "Name: {}", spectrum.label)?;
+            if let Some(precursor_mz) = spectrum.precursor_mz {
+                writeln!(writer, "PrecursorMZ: {}", precursor_mz)?;
+            }
+            if let Some(charge) = spectrum.precursor_charge {
+                writeln!(writer, "Charge: {}", format_charge(charge))?;
+            }
+            if let Some(retention_time) = spectrum.retention_time {
+                writeln!(
+                    writer,
+                    "RetentionTime: {}",
+                    spectrum.time_unit.to_minutes(retention_time)
+                )?;
+            }
+            if let Some(index) = spectrum.index {
+                writeln!(writer, "Comment: scan={}", index)?;
+            }
+            writeln!(writer, "Num Peaks: {}", spectrum.mz.len())?;
+            for (mz, intensity) in peaks {
+                writeln!(writer, "{} {}", mz, intensity)?;
+            }
+            writeln!(writer)?;
+        }
+        SpectrumFormat::Mgf => {
+            writeln!(writer, "BEGIN IONS")?;
+            writeln!(writer, "TITLE={}", spectrum.label)?;
+            if let Some(precursor_mz) = spectrum.precursor_mz {
+                writeln!(writer, "PEPMASS={}", precursor_mz)?;
+            }
+            if let Some(charge) = spectrum.precursor_charge {
+                writeln!(writer, "CHARGE={}", format_charge(charge))?;
+            }
+            if let Some(retention_time) = spectrum.retention_time {
+                writeln!(
+                    writer,
+                    "RTINSECONDS={}",
+                    spectrum.time_unit.to_seconds(retention_time)
+                )?;
+            }
+            if let Some(index) = spectrum.index {
+                writeln!(writer, "SCANS={}", index)?;
+            }
+            for (mz, intensity) in peaks {
+                writeln!(writer, "{} {}", mz, intensity)?;
+            }
+            writeln!(writer, "END IONS")?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::TimeUnit;
+
+    fn traces() -> Vec<Trace> {
+        vec![
+            Trace {
+                name: "MS1".to_string(),
+                raw: vec![[1.0, 10.0], [2.0, 20.0]],
+                smoothed: vec![[1.0, 15.0], [2.0, 15.0]],
+            },
+            Trace {
+                name: "MS2, fragment".to_string(),
+                raw: vec![[1.5, 5.0], [2.0, 7.0]],
+                smoothed: vec![[1.5, 5.0], [2.0, 7.0]],
+            },
+        ]
+    }
+
+    #[test]
+    fn test_delimiter() {
+        assert_eq!(Delimiter::from_file_name("out.tsv"), Delimiter::Tab);
+        assert_eq!(Delimiter::from_file_name("out.CSV"), Delimiter::Comma);
+        assert_eq!(Delimiter::from_file_name("out"), Delimiter::Comma);
+    }
+
+    #[test]
+    fn test_write_chromatogram_aligned() {
+        let mut output = Vec::new();
+        let metadata = vec![("plot type".to_string(), "XIC".to_string())];
+        write_chromatogram(
+            &mut output,
+            &metadata,
+            &traces(),
+            TraceLayout::Aligned,
+            Delimiter::Comma,
+        )
+        .unwrap();
+        let text = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "# plot type: XIC");
+        assert_eq!(
+            lines[1],
+            "retention_time,MS1 raw,MS1 smoothed,\"MS2, fragment raw\",\"MS2, fragment smoothed\""
+        );
+        assert_eq!(lines[2], "1,10,15,,");
+        assert_eq!(lines[3], "1.5,,,5,5");
+        assert_eq!(lines[4], "2,20,15,7,7");
+        assert_eq!(lines.len(), 5);
+    }
+
+    #[test]
+    fn test_write_chromatogram_long() {
+        let mut output = Vec::new();
+        write_chromatogram(
+            &mut output,
+            &[],
+            &traces(),
+            TraceLayout::Long,
+            Delimiter::Tab,
+        )
+        .unwrap();
+        let text = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(
+            lines[0],
+            "trace\tretention_time\tintensity_raw\tintensity_smoothed"
+        );
+        assert_eq!(lines[1], "MS1\t1\t10\t15");
+        assert_eq!(lines[3], "MS2, fragment\t1.5\t5\t5");
+        assert_eq!(lines.len(), 5);
+    }
+
+    fn spectrum() -> Spectrum {
+        Spectrum {
+            label: "run.mzML scan 7".to_string(),
+            index: Some(7),
+            retention_time: Some(1.5),
+            precursor_mz: Some(445.12),
+            precursor_charge: Some(2),
+            mz: vec![100.5, 200.25],
+            intensity: vec![10.0, 20.0],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_spectrum_format() {
+        assert_eq!(SpectrumFormat::from_file_name("a.MSP"), SpectrumFormat::Msp);
+        assert_eq!(SpectrumFormat::from_file_name("a.mgf"), SpectrumFormat::Mgf);
+        assert_eq!(
+            SpectrumFormat::from_file_name("a.tsv"),
+            SpectrumFormat::Delimited(Delimiter::Tab)
+        );
+    }
+
+    #[test]
+    fn test_write_spectrum_delimited() {
+        let mut output = Vec::new();
+        write_spectrum(
+            &mut output,
+            &spectrum(),
+            SpectrumFormat::Delimited(Delimiter::Comma),
+        )
+        .unwrap();
+        let text = String::from_utf8(output).unwrap();
+        assert!(text.starts_with("# spectrum: run.mzML scan 7\n"));
+        assert!(text.contains("# precursor charge: 2+\n"));
+        assert!(text.ends_with("mz,intensity\n100.5,10\n200.25,20\n"));
+    }
+
+    #[test]
+    fn test_write_spectrum_msp() {
+        let mut output = Vec::new();
+        write_spectrum(&mut output, &spectrum(), SpectrumFormat::Msp).unwrap();
+        // the entry can be read back as a library spectrum
+        let entries = crate::library::parse_msp(output.as_slice()).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "run.mzML scan 7");
+        assert_eq!(entries[0].precursor_mz, Some(445.12));
+        assert_eq!(entries[0].mz, vec![100.5, 200.25]);
+        assert_eq!(entries[0].intensity, vec![10.0, 20.0]);
+    }
+
+    #[test]
+    fn test_write_spectrum_mgf() {
+        let mut output = Vec::new();
+        write_spectrum(&mut output, &spectrum(), SpectrumFormat::Mgf).unwrap();
+        let text = String::from_utf8(output).unwrap();
+        assert_eq!(
+            text,
+            "BEGIN IONS\nTITLE=run.mzML scan 7\nPEPMASS=445.12\nCHARGE=2+\nRTINSECONDS=90\nSCANS=7\n100.5 10\n200.25 20\nEND IONS\n"
+        );
+    }
+
+    #[test]
+    fn test_write_spectrum_time_unit() {
+        // the same retention time as `spectrum()`, in seconds
+        let seconds = Spectrum {
+            retention_time: Some(90.0),
+            time_unit: TimeUnit::Second,
+            ..spectrum()
+        };
+        for spectrum in [seconds, spectrum()] {
+            let mut output = Vec::new();
+            write_spectrum(&mut output, &spectrum, SpectrumFormat::Msp).unwrap();
+            assert!(String::from_utf8(output)
+                .unwrap()
+                .contains("RetentionTime: 1.5\n"));
+            let mut output = Vec::new();
+            write_spectrum(&mut output, &spectrum, SpectrumFormat::Mgf).unwrap();
+            assert!(String::from_utf8(output)
+                .unwrap()
+                .contains("RTINSECONDS=90\n"));
+        }
+    }
+
+    #[test]
+    fn test_write_chromatogram_json() {
+        let mut output = Vec::new();
+        write_chromatogram_json(
+            &mut output,
+            &[("plot type".to_string(), "TIC".to_string())],
+            &[Trace {
+                name: "TIC".to_string(),
+                raw: vec![[1.0, 10.0], [2.0, 30.0]],
+                smoothed: vec![[1.0, 10.0], [2.0, 20.0]],
+            }],
+        )
+        .unwrap();
+        let document: serde_json::Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(document["metadata"]["plot type"], "TIC");
+        assert_eq!(document["traces"][0]["name"], "TIC");
+        assert_eq!(
+            document["traces"][0]["retention_time"],
+            serde_json::json!([1.0, 2.0])
+        );
+        assert_eq!(
+            document["traces"][0]["raw"],
+            serde_json::json!([10.0, 30.0])
+        );
+        assert_eq!(
+            document["traces"][0]["smoothed"],
+            serde_json::json!([10.0, 20.0])
+        );
+    }
+}
@@ -0,0 +1,823 @@
+//! # rendering of plots to image files
+
+//! The `figure` module renders chromatograms and mass spectra to PNG and SVG images for reports and papers.
+
+//! ## Overview
+
+//! A `Figure` describes a plot independently of the GUI: its title, axis labels, axis ranges, the data series and the text annotations.
+//! The same drawing code lays the figure out on a vector canvas, written as SVG, and on a raster canvas, encoded as PNG.
+//! Sizes are given in points (1/72 inch), so a figure looks the same in both formats and the PNG resolution only depends on the chosen DPI.
+//!
+//! Figures are drawn here rather than captured from the GUI: `egui_plot` only paints into a window through the GPU, so it cannot render
+//! at a chosen size and DPI, and it has no vector output. A plotting crate would bring a second set of styles and, on Linux, a system font
+//! stack. The raster canvas only draws lines and text; text is rasterized with `ab_glyph`, which egui already uses for its own text, from the
+//! font embedded in egui, and the image is encoded by the `png` crate.
+
+//!## Features
+
+//!- **SVG Export**: Write a figure as a scalable vector image with text elements that remain editable.
+//!- **PNG Export**: Rasterize a figure with anti-aliased lines and text at a chosen DPI, which is stored in the PNG file.
+//!- **Axes**: Axis ticks are placed at round values, and the tick labels of the y-axis can be formatted by the caller, e.g. for a logarithmic scale.
+
+#![warn(clippy::all)]
+
+use ab_glyph::{Font, FontArc, PxScale, ScaleFont};
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+
+/// The margin to the left of the plot area, in points, holding the y tick labels and the y-axis label.
+const MARGIN_LEFT: f32 = 58.0;
+/// The margin to the right of the plot area, in points.
+const MARGIN_RIGHT: f32 = 12.0;
+/// The margin above the plot area, in points, holding the title.
+const MARGIN_TOP: f32 = 24.0;
+/// The margin below the plot area, in points, holding the x tick labels and the x-axis label.
+const MARGIN_BOTTOM: f32 = 36.0;
+/// The length of the tick marks, in points.
+const TICK_LENGTH: f32 = 3.0;
+/// The number of ticks aimed for on each axis.
+const TICK_COUNT: usize = 6;
+/// The font sizes of the title, the axis labels, the tick labels and the annotations, in points.
+const TITLE_SIZE: f32 = 10.0;
+const LABEL_SIZE: f32 = 9.0;
+const TICK_LABEL_SIZE: f32 = 8.0;
+const ANNOTATION_SIZE: f32 = 7.0;
+/// The points per inch of the figure coordinates.
+const POINTS_PER_INCH: f32 = 72.0;
+/// The name of the egui default font used to rasterize text.
+const FONT_NAME: &str = "Ubuntu-Light";
+/// The meters per inch, used to store the resolution of PNG images in pixels per meter.
+const METERS_PER_INCH: f64 = 0.0254;
+
+/// The size and resolution of an exported image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImageParameters {
+    /// The width of the image in inches.
+    pub width: f32,
+    /// The height of the image in inches.
+    pub height: f32,
+    /// The resolution of PNG images in dots per inch.
+    pub dpi: u32,
+    /// Whether the image is drawn with light text on a dark background, e.g. for white or yellow lines.
+    pub dark_background: bool,
+}
+
+impl Default for ImageParameters {
+    fn default() -> Self {
+        Self {
+            width: 6.0,
+            height: 4.0,
+            dpi: 300,
+            dark_background: false,
+        }
+    }
+}
+
+impl ImageParameters {
+    /// Returns the width and height of the image in points.
+    fn size(&self) -> (f32, f32) {
+        (self.width * POINTS_PER_INCH, self.height * POINTS_PER_INCH)
+    }
+
+    /// Returns the background and foreground (axes and text) colors.
+    fn colors(&self) -> ([u8; 3], [u8; 3]) {
+        if self.dark_background {
+            ([27, 27, 27], [220, 220, 220])
+        } else {
+            ([255, 255, 255], [0, 0, 0])
+        }
+    }
+}
+
+/// How a data series is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeriesStyle {
+    /// The points are connected by a line, as for chromatograms and profile spectra.
+    Line,
+    /// Every point is drawn as a vertical stick from zero, as for centroid spectra.
+    Sticks,
+}
+
+/// A data series of a figure.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Series {
+    /// The name shown in the legend, if any.
+    pub name: Option<String>,
+    /// The x and y values of the points.
+    pub points: Vec<[f64; 2]>,
+    /// The RGB color of the series.
+    pub color: [u8; 3],
+    /// The line width in points.
+    pub width: f32,
+    /// How the series is drawn.
+    pub style: SeriesStyle,
+}
+
+/// A text placed above a data point, such as a peak label. Lines are separated by `\n`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    /// The x and y values of the data point.
+    pub position: [f64; 2],
+    /// The text of the annotation.
+    pub text: String,
+    /// The RGB color of the text.
+    pub color: [u8; 3],
+}
+
+/// A plot to be rendered as an image.
+#[derive(Debug, Clone, Default)]
+pub struct Figure {
+    /// The title shown above the plot.
+    pub title: String,
+    /// The label of the x-axis.
+    pub x_label: String,
+    /// The label of the y-axis.
+    pub y_label: String,
+    /// The visible range of the x-axis.
+    pub x_range: (f64, f64),
+    /// The visible range of the y-axis.
+    pub y_range: (f64, f64),
+    /// Formats the tick labels of the y-axis. By default, the tick values are shown.
+    pub y_tick_format: Option<fn(f64) -> String>,
+    /// The data series, drawn in order.
+    pub series: Vec<Series>,
+    /// The text annotations, drawn above the series.
+    pub annotations: Vec<Annotation>,
+}
+
+impl Figure {
+    /// Returns the x-axis distance covered by one point of the plot area of an image of the given size.
+    ///
+    /// This converts distances on screen, such as the minimum spacing of peak labels, to data units.
+    pub fn x_per_point(&self, parameters: &ImageParameters) -> f64 {
+        let (width, _) = parameters.size();
+        let plot_width = (width - MARGIN_LEFT - MARGIN_RIGHT).max(1.0);
+        (self.x_range.1 - self.x_range.0) / plot_width as f64
+    }
+}
+
+/// The point of a text that is placed at the given position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Anchor {
+    /// The center of the top edge.
+    Top,
+    /// The center of the bottom edge.
+    Bottom,
+    /// The middle of the left edge.
+    Left,
+    /// The middle of the right edge.
+    Right,
+    /// The center of the text.
+    Center,
+}
+
+/// A surface the figure is drawn on, in points with the origin at the top left.
+trait Canvas {
+    /// Fills the whole canvas with a color.
+    fn background(&mut self, color: [u8; 3]);
+    /// Draws a polyline. If `clip` is set, the line is clipped to the plot area.
+    fn polyline(&mut self, points: &[[f32; 2]], color: [u8; 3], width: f32, clip: bool);
+    /// Draws a single line of text. Vertical text reads from bottom to top.
+    fn text(
+        &mut self,
+        position: [f32; 2],
+        text: &str,
+        size: f32,
+        color: [u8; 3],
+        anchor: Anchor,
+        vertical: bool,
+    );
+}
+
+/// Returns round tick values covering a range, with a step of 1, 2 or 5 times a power of ten.
+///
+/// # Parameters
+/// - `range: (f64, f64)`: The minimum and maximum of the axis.
+/// - `count: usize`: The number of ticks aimed for.
+///
+/// # Returns
+/// - `(Vec<f64>, f64)`: The tick values within the range, and the step between them. No ticks are returned for an empty or invalid range.
+pub fn nice_ticks(range: (f64, f64), count: usize) -> (Vec<f64>, f64) {
+    let span = range.1 - range.0;
+    if !span.is_finite() || span <= 0.0 || count == 0 {
+        return (Vec::new(), 0.0);
+    }
+    let rough_step = span / count as f64;
+    let magnitude = 10f64.powf(rough_step.log10().floor());
+    let step = [1.0, 2.0, 5.0, 10.0]
+        .iter()
+        .map(|factor| factor * magnitude)
+        .find(|step| *step >= rough_step)
+        .unwrap_or(10.0 * magnitude);
+    let first = (range.0 / step).ceil() as i64;
+    let last = (range.1 / step).floor() as i64;
+    let ticks = (first..=last).map(|i| i as f64 * step).collect();
+    (ticks, step)
+}
+
+/// Formats a tick value with as many decimals as the tick step requires, or in scientific notation for large values.
+fn format_tick(value: f64, step: f64, max_abs: f64) -> String {
+    if value == 0.0 {
+        "0".to_string()
+    } else if max_abs >= 1e5 {
+        format!("{:.1e}", value)
+    } else {
+        let decimals = (-step.log10().floor()).max(0.0) as usize;
+        format!("{:.*}", decimals, value)
+    }
+}
+
+/// Draws a figure on a canvas of the given size in points.
+fn draw(figure: &Figure, canvas: &mut impl Canvas, size: (f32, f32), colors: ([u8; 3], [u8; 3])) {
+    let (background, foreground) = colors;
+    let (width, height) = size;
+    let (left, top) = (MARGIN_LEFT, MARGIN_TOP);
+    let right = (width - MARGIN_RIGHT).max(left + 1.0);
+    let bottom = (height - MARGIN_BOTTOM).max(top + 1.0);
+    let (x_min, x_max) = figure.x_range;
+    let (y_min, y_max) = figure.y_range;
+    let to_canvas = |x: f64, y: f64| -> [f32; 2] {
+        [
+            left + ((x - x_min) / (x_max - x_min)) as f32 * (right - left),
+            bottom - ((y - y_min) / (y_max - y_min)) as f32 * (bottom - top),
+        ]
+    };
+
+    canvas.background(background);
+
+    for series in &figure.series {
+        match series.style {
+            SeriesStyle::Line => {
+                let points: Vec<[f32; 2]> = series
+                    .points
+                    .iter()
+                    .map(|p| to_canvas(p[0], p[1]))
+                    .collect();
+                canvas.polyline(&points, series.color, series.width, true);
+            }
+            SeriesStyle::Sticks => {
+                let base = 0f64.clamp(y_min, y_max);
+                for p in &series.points {
+                    if p[0] < x_min || p[0] > x_max {
+                        continue;
+                    }
+                    canvas.polyline(
+                        &[to_canvas(p[0], base), to_canvas(p[0], p[1])],
+                        series.color,
+                        series.width,
+                        true,
+                    );
+                }
+            }
+        }
+    }
+
+    for annotation in &figure.annotations {
+        let [x, y] = annotation.position;
+        if x < x_min || x > x_max || y < y_min || y > y_max {
+            continue;
+        }
+        let [px, py] = to_canvas(x, y);
+        let line_height = ANNOTATION_SIZE * 1.2;
+        for (i, line) in annotation.text.lines().rev().enumerate() {
+            canvas.text(
+                [px, py - 2.0 - i as f32 * line_height],
+                line,
+                ANNOTATION_SIZE,
+                annotation.color,
+                Anchor::Bottom,
+                false,
+            );
+        }
+    }
+
+    // axes frame
+    canvas.polyline(
+        &[
+            [left, top],
+            [right, top],
+            [right, bottom],
+            [left, bottom],
+            [left, top],
+        ],
+        foreground,
+        0.8,
+        false,
+    );
+
+    let (x_ticks, x_step) = nice_ticks(figure.x_range, TICK_COUNT);
+    let x_max_abs = x_min.abs().max(x_max.abs());
+    for tick in x_ticks {
+        let [px, _] = to_canvas(tick, y_min);
+        canvas.polyline(
+            &[[px, bottom], [px, bottom + TICK_LENGTH]],
+            foreground,
+            0.8,
+            false,
+        );
+        canvas.text(
+            [px, bottom + TICK_LENGTH + 1.5],
+            &format_tick(tick, x_step, x_max_abs),
+            TICK_LABEL_SIZE,
+            foreground,
+            Anchor::Top,
+            false,
+        );
+    }
+    let (y_ticks, y_step) = nice_ticks(figure.y_range, TICK_COUNT);
+    let y_max_abs = y_min.abs().max(y_max.abs());
+    for tick in y_ticks {
+        let [_, py] = to_canvas(x_min, tick);
+        canvas.polyline(
+            &[[left - TICK_LENGTH, py], [left, py]],
+            foreground,
+            0.8,
+            false,
+        );
+        let label = match figure.y_tick_format {
+            Some(format) => format(tick),
+            None => format_tick(tick, y_step, y_max_abs),
+        };
+        canvas.text(
+            [left - TICK_LENGTH - 2.0, py],
+            &label,
+            TICK_LABEL_SIZE,
+            foreground,
+            Anchor::Right,
+            false,
+        );
+    }
+
+    canvas.text(
+        [(left + right) / 2.0, height - 4.0],
+        &figure.x_label,
+        LABEL_SIZE,
+        foreground,
+        Anchor::Bottom,
+        false,
+    );
+    canvas.text(
+        [LABEL_SIZE * 0.8, (top + bottom) / 2.0],
+        &figure.y_label,
+        LABEL_SIZE,
+        foreground,
+        Anchor::Center,
+        true,
+    );
+    canvas.text(
+        [(left + right) / 2.0, top - 6.0],
+        &figure.title,
+        TITLE_SIZE,
+        foreground,
+        Anchor::Bottom,
+        false,
+    );
+
+    // legend in the top right corner of the plot area, when several series are named
+    let named: Vec<&Series> = figure.series.iter().filter(|s| s.name.is_some()).collect();
+    if named.len() > 1 {
+        let line_height = ANNOTATION_SIZE * 1.4;
+        for (i, series) in named.iter().enumerate() {
+            let name = series.name.as_deref().unwrap_or_default();
+            // the text width is estimated, as the vector canvas cannot measure text
+            let text_width = name.chars().count() as f32 * ANNOTATION_SIZE * 0.55;
+            let y = top + 8.0 + i as f32 * line_height;
+            let text_x = right - 6.0 - text_width;
+            canvas.polyline(
+                &[[text_x - 16.0, y], [text_x - 4.0, y]],
+                series.color,
+                1.5,
+                false,
+            );
+            canvas.text(
+                [text_x, y],
+                name,
+                ANNOTATION_SIZE,
+                foreground,
+                Anchor::Left,
+                false,
+            );
+        }
+    }
+}
+
+/// Escapes the characters of a text that have a special meaning in XML.
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Formats an RGB color as a CSS hex color.
+fn css_color(color: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+/// A canvas that collects SVG elements.
+struct SvgCanvas {
+    size: (f32, f32),
+    body: String,
+}
+
+impl Canvas for SvgCanvas {
+    fn background(&mut self, color: [u8; 3]) {
+        let _ = writeln!(
+            self.body,
+            r#"<rect width="{}" height="{}" fill="{}"/>"#,
+            self.size.0,
+            self.size.1,
+            css_color(color)
+        );
+    }
+
+    fn polyline(&mut self, points: &[[f32; 2]], color: [u8; 3], width: f32, clip: bool) {
+        if points.len() < 2 {
+            return;
+        }
+        let coordinates = points
+            .iter()
+            .map(|[x, y]| format!("{:.2},{:.2}", x, y))
+            .collect::<Vec<String>>()
+            .join(" ");
+        let _ = writeln!(
+            self.body,
+            r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="{}" stroke-linejoin="round"{}/>"#,
+            coordinates,
+            css_color(color),
+            width,
+            if clip {
+                r#" clip-path="url(#plot-area)""#
+            } else {
+                ""
+            }
+        );
+    }
+
+    fn text(
+        &mut self,
+        position: [f32; 2],
+        text: &str,
+        size: f32,
+        color: [u8; 3],
+        anchor: Anchor,
+        vertical: bool,
+    ) {
+        if text.is_empty() {
+            return;
+        }
+        let (text_anchor, baseline) = match anchor {
+            Anchor::Top => ("middle", "hanging"),
+            Anchor::Bottom => ("middle", "text-after-edge"),
+            Anchor::Left => ("start", "central"),
+            Anchor::Right => ("end", "central"),
+            Anchor::Center => ("middle", "central"),
+        };
+        let [x, y] = position;
+        let transform = if vertical {
+            format!(r#" transform="rotate(-90 {:.2} {:.2})""#, x, y)
+        } else {
+            String::new()
+        };
+        let _ = writeln!(
+            self.body,
+            r#"<text x="{:.2}" y="{:.2}" font-family="sans-serif" font-size="{}" fill="{}" text-anchor="{}" dominant-baseline="{}"{}>{}</text>"#,
+            x,
+            y,
+            size,
+            css_color(color),
+            text_anchor,
+            baseline,
+            transform,
+            escape_xml(text)
+        );
+    }
+}
+
+/// Renders a figure as an SVG document.
+///
+/// # Parameters
+/// - `figure: &Figure`: The figure to be rendered.
+/// - `parameters: &ImageParameters`: The size of the image in inches and its colors. The DPI is not used, as the image is scalable.
+///
+/// # Returns
+/// - `String`: The SVG document.
+pub fn render_svg(figure: &Figure, parameters: &ImageParameters) -> String {
+    let size = parameters.size();
+    let mut canvas = SvgCanvas {
+        size,
+        body: String::new(),
+    };
+    draw(figure, &mut canvas, size, parameters.colors());
+
+    let (width, height) = size;
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}in" height="{}in" viewBox="0 0 {} {}">"#,
+        parameters.width, parameters.height, width, height
+    );
+    let _ = writeln!(
+        svg,
+        r#"<defs><clipPath id="plot-area"><rect x="{}" y="{}" width="{}" height="{}"/></clipPath></defs>"#,
+        MARGIN_LEFT,
+        MARGIN_TOP,
+        (width - MARGIN_LEFT - MARGIN_RIGHT).max(1.0),
+        (height - MARGIN_TOP - MARGIN_BOTTOM).max(1.0)
+    );
+    svg.push_str(&canvas.body);
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// A canvas that rasterizes the figure into an RGB image.
+struct RasterCanvas {
+    image: image::RgbImage,
+    /// The pixels per point.
+    scale: f32,
+    /// The plot area in pixels: left, top, right, bottom.
+    clip: [f32; 4],
+    font: FontArc,
+}
+
+impl RasterCanvas {
+    /// Blends a color into a pixel with the given coverage.
+    fn blend(&mut self, x: i64, y: i64, color: [u8; 3], coverage: f32) {
+        if x < 0 || y < 0 || x >= self.image.width() as i64 || y >= self.image.height() as i64 {
+            return;
+        }
+        let coverage = coverage.clamp(0.0, 1.0);
+        let pixel = self.image.get_pixel_mut(x as u32, y as u32);
+        for (channel, target) in pixel.0.iter_mut().zip(color) {
+            *channel =
+                (*channel as f32 * (1.0 - coverage) + target as f32 * coverage).round() as u8;
+        }
+    }
+
+    /// Draws an anti-aliased segment between two points in pixels.
+    fn segment(&mut self, a: [f32; 2], b: [f32; 2], color: [u8; 3], half_width: f32, clip: bool) {
+        let bounds = if clip {
+            self.clip
+        } else {
+            [
+                0.0,
+                0.0,
+                self.image.width() as f32,
+                self.image.height() as f32,
+            ]
+        };
+        let reach = half_width + 1.0;
+        let x0 = (a[0].min(b[0]) - reach).max(bounds[0]).floor() as i64;
+        let x1 = (a[0].max(b[0]) + reach).min(bounds[2]).ceil() as i64;
+        let y0 = (a[1].min(b[1]) - reach).max(bounds[1]).floor() as i64;
+        let y1 = (a[1].max(b[1]) + reach).min(bounds[3]).ceil() as i64;
+        let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+        let length_squared = dx * dx + dy * dy;
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+                let t = if length_squared > 0.0 {
+                    (((px - a[0]) * dx + (py - a[1]) * dy) / length_squared).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                let distance = ((px - a[0] - t * dx).powi(2) + (py - a[1] - t * dy).powi(2)).sqrt();
+                let coverage = half_width + 0.5 - distance;
+                if coverage > 0.0 {
+                    self.blend(x, y, color, coverage);
+                }
+            }
+        }
+    }
+}
+
+impl Canvas for RasterCanvas {
+    fn background(&mut self, color: [u8; 3]) {
+        for pixel in self.image.pixels_mut() {
+            pixel.0 = color;
+        }
+    }
+
+    fn polyline(&mut self, points: &[[f32; 2]], color: [u8; 3], width: f32, clip: bool) {
+        // lines stay visible at low resolution
+        let half_width = (width * self.scale / 2.0).max(0.5);
+        for pair in points.windows(2) {
+            let a = [pair[0][0] * self.scale, pair[0][1] * self.scale];
+            let b = [pair[1][0] * self.scale, pair[1][1] * self.scale];
+            if a.iter().chain(b.iter()).all(|v| v.is_finite()) {
+                self.segment(a, b, color, half_width, clip);
+            }
+        }
+    }
+
+    fn text(
+        &mut self,
+        position: [f32; 2],
+        text: &str,
+        size: f32,
+        color: [u8; 3],
+        anchor: Anchor,
+        vertical: bool,
+    ) {
+        if text.is_empty() {
+            return;
+        }
+        let font = self.font.as_scaled(PxScale::from(size * self.scale));
+
+        // lay out the glyphs along the baseline and collect their coverage
+        let ascent = font.ascent();
+        let text_height = (ascent - font.descent()).ceil().max(1.0);
+        let mut caret = 0.0;
+        let mut previous = None;
+        let mut glyphs = Vec::new();
+        for character in text.chars() {
+            let id = font.glyph_id(character);
+            if let Some(previous) = previous {
+                caret += font.kern(previous, id);
+            }
+            glyphs.push(id.with_scale_and_position(font.scale(), ab_glyph::point(caret, ascent)));
+            caret += font.h_advance(id);
+            previous = Some(id);
+        }
+        let text_width = caret.ceil().max(1.0);
+        let (w, h) = (text_width as usize, text_height as usize);
+        let mut coverage = vec![0f32; w * h];
+        for glyph in glyphs {
+            if let Some(outline) = self.font.outline_glyph(glyph) {
+                let bounds = outline.px_bounds();
+                outline.draw(|gx, gy, c| {
+                    let x = bounds.min.x as i64 + gx as i64;
+                    let y = bounds.min.y as i64 + gy as i64;
+                    if x >= 0 && y >= 0 && (x as usize) < w && (y as usize) < h {
+                        let cell = &mut coverage[y as usize * w + x as usize];
+                        *cell = (*cell + c).min(1.0);
+                    }
+                });
+            }
+        }
+
+        // place the text box, rotated by 90 degrees for vertical text
+        let (box_width, box_height) = if vertical {
+            (text_height, text_width)
+        } else {
+            (text_width, text_height)
+        };
+        let (x, y) = (position[0] * self.scale, position[1] * self.scale);
+        let (left, top) = match anchor {
+            Anchor::Top => (x - box_width / 2.0, y),
+            Anchor::Bottom => (x - box_width / 2.0, y - box_height),
+            Anchor::Left => (x, y - box_height / 2.0),
+            Anchor::Right => (x - box_width, y - box_height / 2.0),
+            Anchor::Center => (x - box_width / 2.0, y - box_height / 2.0),
+        };
+        let (left, top) = (left.round() as i64, top.round() as i64);
+        for v in 0..h {
+            for u in 0..w {
+                let c = coverage[v * w + u];
+                if c <= 0.0 {
+                    continue;
+                }
+                let (px, py) = if vertical {
+                    (left + v as i64, top + (w - 1 - u) as i64)
+                } else {
+                    (left + u as i64, top + v as i64)
+                };
+                self.blend(px, py, color, c);
+            }
+        }
+    }
+}
+
+/// Returns the font used to rasterize text, the default proportional font of egui.
+fn default_font() -> Result<FontArc> {
+    let definitions = egui::FontDefinitions::default();
+    let data = definitions
+        .font_data
+        .get(FONT_NAME)
+        .with_context(|| format!("Font {} not available", FONT_NAME))?;
+    Ok(FontArc::try_from_vec(data.font.to_vec())?)
+}
+
+/// Renders a figure as a PNG image.
+///
+/// # Parameters
+/// - `figure: &Figure`: The figure to be rendered.
+/// - `parameters: &ImageParameters`: The size of the image in inches, its resolution and its colors.
+///
+/// # Returns
+/// - `Result<Vec<u8>>`: The PNG file, `width × dpi` by `height × dpi` pixels, with the DPI stored as its physical pixel dimensions.
+///
+/// # Errors
+/// Returns an error if the image is empty, the font cannot be loaded or the image cannot be encoded.
+pub fn render_png(figure: &Figure, parameters: &ImageParameters) -> Result<Vec<u8>> {
+    let size = parameters.size();
+    let scale = parameters.dpi as f32 / POINTS_PER_INCH;
+    let (width, height) = (
+        (size.0 * scale).round() as u32,
+        (size.1 * scale).round() as u32,
+    );
+    if width == 0 || height == 0 {
+        anyhow::bail!("The image has no pixels");
+    }
+    let mut canvas = RasterCanvas {
+        image: image::RgbImage::new(width, height),
+        scale,
+        clip: [
+            MARGIN_LEFT * scale,
+            MARGIN_TOP * scale,
+            (size.0 - MARGIN_RIGHT) * scale,
+            (size.1 - MARGIN_BOTTOM) * scale,
+        ],
+        font: default_font()?,
+    };
+    draw(figure, &mut canvas, size, parameters.colors());
+
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let pixels_per_meter = (parameters.dpi as f64 / METERS_PER_INCH).round() as u32;
+    encoder.set_pixel_dims(Some(png::PixelDimensions {
+        xppu: pixels_per_meter,
+        yppu: pixels_per_meter,
+        unit: png::Unit::Meter,
+    }));
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(canvas.image.as_raw())?;
+    writer.finish()?;
+    Ok(png)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn figure() -> Figure {
+        Figure {
+            title: "TIC <test>".to_string(),
+            x_label: "Retention time (min)".to_string(),
+            y_label: "Intensity".to_string(),
+            x_range: (0.0, 10.0),
+            y_range: (0.0, 100.0),
+            series: vec![Series {
+                name: Some("TIC".to_string()),
+                points: vec![[0.0, 0.0], [5.0, 100.0], [10.0, 0.0]],
+                color: [255, 0, 0],
+                width: 2.0,
+                style: SeriesStyle::Line,
+            }],
+            annotations: vec![Annotation {
+                position: [5.0, 100.0],
+                text: "5.00".to_string(),
+                color: [0, 0, 255],
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_nice_ticks() {
+        let (ticks, step) = nice_ticks((0.3, 10.2), 6);
+        assert_eq!(step, 2.0);
+        assert_eq!(ticks, vec![2.0, 4.0, 6.0, 8.0, 10.0]);
+        assert!(nice_ticks((1.0, 1.0), 6).0.is_empty());
+        assert_eq!(format_tick(0.25, 0.05, 1.0), "0.25");
+        assert_eq!(format_tick(2e6, 1e6, 5e6), "2.0e6");
+    }
+
+    #[test]
+    fn test_render_svg() {
+        let svg = render_svg(&figure(), &ImageParameters::default());
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.contains(r#"width="6in" height="4in" viewBox="0 0 432 288""#));
+        assert!(svg.contains("TIC &lt;test&gt;"));
+        assert!(svg.contains("<polyline points=\"58.00,252.00 "));
+        assert!(svg.trim_end().ends_with("</svg>"));
+    }
+
+    #[test]
+    fn test_render_png() {
+        let parameters = ImageParameters {
+            width: 4.0,
+            height: 3.0,
+            dpi: 150,
+            dark_background: false,
+        };
+        let png = render_png(&figure(), &parameters).unwrap();
+        let reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+        let dimensions = reader.info().pixel_dims.unwrap();
+        assert_eq!((dimensions.xppu, dimensions.yppu), (5906, 5906));
+        assert_eq!(dimensions.unit, png::Unit::Meter);
+
+        let image = image::load_from_memory(&png).unwrap().to_rgb8();
+        assert_eq!((image.width(), image.height()), (600, 450));
+        // the apex of the trace is red, the corner is the background
+        let scale = 150.0 / 72.0;
+        let apex = image.get_pixel(
+            ((MARGIN_LEFT + (288.0 - MARGIN_LEFT - MARGIN_RIGHT) / 2.0) * scale) as u32,
+            (MARGIN_TOP * scale) as u32 + 2,
+        );
+        assert_eq!(apex.0, [255, 0, 0]);
+        assert_eq!(image.get_pixel(1, 1).0, [255, 255, 255]);
+    }
+}
@@ -25,17 +25,51 @@
 //!#### Fields
 
 //! - `file_path`: An optional string representing the path to the selected MzML file.
-//! - `plot_type`: The type of plot to be generated (TIC, BPC, or XIC).
+//! - `plot_type`: The type of plot to be generated (TIC, BPC, XIC or DIA).
 //! - `polarity`: The scan polarity for the mass spectrometry data.
 //! - `mass_input`: A string representation of the mass input provided by the user.
 //! - `mass_tolerance_input`: A string representation of the mass tolerance input provided by the user.
 //! - `mass`: The mass value parsed from the mass_input.
 //! - `mass_tolerance`: The mass tolerance value parsed from the mass_tolerance_input.
+//! - `fragment_mz_input`: A string representation of the DIA fragment m/z values provided by the user.
+//! - `fragment_mz`: The fragment m/z values parsed from the fragment_mz_input.
+//! - `precursor_color`: Whether the precursor coverage map is colored by charge or by intensity.
+//! - `mobility_min_input`, `mobility_max_input`: String representations of the ion mobility range provided by the user.
+//! - `mobility_range`: The ion mobility range parsed from the inputs, used to filter the XIC.
+//! - `library_search`: The similarity method, tolerances and number of hits of library searches.
+//! - `spectrum_layout`: Whether the mass spectra are overlaid or stacked.
+//! - `peak_label_count`: The number of most intense visible peaks labelled in every spectrum.
+//! - `peak_label_spacing`: The minimum distance between two labelled peaks.
+//! - `normalization`: Whether spectra are shown in absolute intensity, in % of the base peak or in % of a reference peak.
+//! - `reference_mz_input`, `reference_mz`, `reference_tolerance`: The reference peak used for normalization.
+//! - `log_intensity`: Whether the intensity axis of the mass spectra is logarithmic.
+//! - `centroid_profile`, `centroid_parameters`: Whether and how profile spectra are centroided before they are displayed.
+//! - `show_envelopes`, `envelope_parameters`: Whether and how isotope envelopes are detected and labelled with their charge and monoisotopic m/z.
+//! - `spectrum_filter`: The intensity threshold, top-N per m/z window and m/z crop applied to the displayed spectra.
+//! - `crop_min_input`, `crop_max_input`: String representations of the m/z crop provided by the user.
+//! - `average_rt_min_input`, `average_rt_max_input`: String representations of the retention time range of the averaged spectrum.
+//! - `deconvolution_parameters`: The m/z range, charge range, tolerance and mass bin width of the charge deconvolution.
+//! - `deconvolution_mz_min_input`, `deconvolution_mz_max_input`: String representations of the m/z range of the charge deconvolution.
+//! - `peptide_input`: The peptide sequence, with modifications, whose fragment ions are annotated in the displayed spectrum.
+//! - `peptide_annotation`: The ion types, charge states, tolerance and fixed modifications of the fragment ion annotation.
+//! - `composition`: The tolerance, charge, element ranges and constraints of the elemental composition generator.
+//! - `trace_layout`: Whether exported chromatogram traces are written as aligned columns or in long format.
+//! - `export_displayed_spectrum`: Whether the mass spectrum is exported as displayed (centroided, filtered and scaled) or as read from the file.
+//! - `image_parameters`: The size, resolution and background of exported plot images.
+//! - `baseline`: The method and parameters of the chromatogram baseline estimation.
+//! - `show_baseline`: Whether the estimated baseline is drawn on top of the chromatogram.
+//! - `subtract_baseline`: Whether the baseline corrected chromatogram is plotted instead of the smoothed one.
+//! - `signal_to_noise`: The noise measure and noise region of the signal-to-noise estimation.
+//! - `peak_retention_time`: The retention time of the peak whose signal-to-noise ratio is shown, selected with Shift+click.
+//! - `manual_noise_region`: Whether the noise region is chosen by the user instead of found automatically.
+//! - `noise_rt_min_input`, `noise_rt_max_input`: String representations of the chosen noise region.
+//! - `mirror_source`: The spectrum compared with the pinned spectrum (displayed scan, other file or library hit).
+//! - `comparison_index`: The scan index of the comparison file.
 //! - `line_type`: The type of line to be used in the plot (solid, dashed, dotted).
 //! - `line_color`: The color of the line in the plot.
-//! - `smoothing`: The level of smoothing to be applied to the plot data.
+//! - `smoothing`: The filter (moving average, Savitzky–Golay or Gaussian), window and polynomial order of the smoothing applied to the plot data.
 //! - `line_width`: The width of the line in the plot.
-//! - `retention_time_ms_spectrum`: An optional retention time for the mass spectrum.
+//! - `retention_time_ms_spectrum`: An optional retention time for the mass spectrum, in minutes.
 
 //!### `MzViewerApp`
 
@@ -43,27 +77,99 @@
 
 //!#### Fields
 
-//! - `parsed_ms_data`: An instance of `parser::MzData` that holds the parsed mass spectrometry data.
-//! - `plot_data`: An optional vector of plot data points.
+//! - `parsed_ms_data`: An instance of `parser::MzData` that holds the opened mzML file.
+//! - `mass_spectra`: The mass spectra of the spectrum panel. The last one is the most recently retrieved spectrum, the others are pinned.
+//! - `current_spectrum_pinned`: Whether the most recently retrieved spectrum is pinned as well.
+//! - `precursors`: The MS2 precursors of the file shown in the precursor coverage map.
+//! - `heatmap`: The binned retention time × m/z intensity map of the MS1 data.
+//! - `mobilogram`: The ion mobilogram of the XIC m/z.
+//! - `mobility_heatmap`: The binned ion mobility × m/z intensity map of the selected frame.
+//! - `library_matches`: The best library hit of every MS2 spectrum of the file.
+//! - `library_search_receiver`, `library_search_progress`: The channel delivering the result of the library search of all MS2 spectra running on a worker thread, and its progress.
+//! - `chromatograms`: The raw chromatograms of the chromatogram plot, the TIC, BPC or (precursor) XIC followed by the DIA fragment traces.
+//! - `plot_data`: The smoothed (and optionally baseline corrected) copies of `chromatograms`, in the same order.
+//! - `baselines`: The estimated baselines of `plot_data`, drawn as an overlay.
+//! - `signal_to_noise`: The signal-to-noise ratio of the selected peak of the main chromatogram.
+//! - `heatmap_texture`: The texture of the rendered retention time × m/z heatmap.
+//! - `heatmap_failed`: Whether the heatmap of the current file and polarity could not be computed, so it is not retried every frame.
+//! - `heatmap_bounds`: The plot bounds of the heatmap at the last frame, used to re-bin the visible region.
+//! - `chromatogram_bounds`, `spectrum_bounds`: The plot bounds of the chromatogram and the mass spectrum at the last frame, used as the ranges of exported images.
+//! - `selected_spectrum_index`: The index of the spectrum shown in the mass spectrum plot.
+//! - `mobility_heatmap_texture`: The texture of the rendered ion mobility × m/z heatmap of the selected frame.
+//! - `mobility_heatmap_index`: The spectrum index the mobility heatmap was last computed for.
+//! - `libraries`: The loaded MSP spectral libraries.
+//! - `library_hits`: The ranked library hits of the displayed spectrum.
+//! - `selected_library_hit`: The library hit shown in the mirror plot.
+//! - `library_window_open`: A boolean indicating if the library search window is open.
+//! - `mirror_reference`: The pinned spectrum drawn on top of the mirror plot.
+//! - `comparison_data`: An instance of `parser::MzData` holding another mzML file to compare scans with.
+//! - `comparison_spectrum`: The scan of `comparison_data` compared with the pinned spectrum.
+//! - `deconvolved_spectrum`: The neutral mass spectrum of the last deconvolved spectrum.
+//! - `displayed_spectra`: The spectra of the spectrum panel as displayed, kept until a spectrum or the display settings change.
+//! - `spectrum_envelopes`: The isotope envelopes detected in the displayed spectra, kept until a spectrum, its display settings or the detection parameters change.
+//! - `composition_mz`: The m/z of the peak whose elemental composition is generated.
+//! - `composition_candidates`: The ranked candidate formulas of the selected peak.
+//! - `composition_receiver`: The channel delivering the candidate formulas being generated on a worker thread.
+//! - `composition_cancel`: The flag that stops the worker thread generating candidate formulas.
+//! - `composition_window_open`: A boolean indicating if the elemental composition window is open.
 //! - `user_input`: An instance of `UserInput` that holds user-defined parameters.
 //! - `invalid_file`: An enum indicating the validity of the selected file.
 //! - `state_changed`: An enum indicating whether the application state has changed.
 //! - `options_window_open`: A boolean indicating if the options window is open.
 //! - `checkbox_bool`: A boolean for managing checkbox states.
+//! - `status`: The message shown in the status bar, e.g. why a file could not be opened or a chromatogram is empty.
 
 //!#### Methods
 
 //! - `new()`: Creates a new instance of `MzViewerApp` with default values.
-//! - `process_plot_data()`: Processes the plot data based on user inputs and returns the prepared data for plotting.
+//! - `process_plot_data()`: Extracts the chromatograms selected by the user inputs and smooths them for plotting.
 //! - `plot_chromatogram()`: Renders the chromatogram plot based on the processed data.
 //! - `determine_rt_clicked()`: Determines the retention time clicked on the plot.
 //! - `find_closest_spectrum()`: Finds the closest spectrum index based on the clicked retention time.
-//! - `plot_mass_spectrum()`: Renders the mass spectrum plot based on the parsed mass spectrum data.
+//! - `update_signal_to_noise()`: Computes the signal-to-noise ratio of the selected peak of the displayed chromatogram.
+//! - `add_signal_to_noise_options()`: Adds the options for the noise measure and noise region, and the signal-to-noise result.
+//! - `plot_precursor_map()`: Renders the precursor coverage map of the MS2 scans and opens the spectrum of a clicked precursor.
+//! - `find_clicked_precursor()`: Finds the MS2 spectrum index of the precursor closest to the clicked position.
+//! - `plot_heatmap()`: Renders the binned retention time × m/z heatmap of the MS1 data.
+//! - `select_heatmap_region()`: Opens the mass spectrum and XIC of a clicked heatmap region.
+//! - `plot_ion_mobility()`: Renders the mobilogram of the XIC m/z and the mobility × m/z heatmap of the selected frame.
+//! - `show_mass_spectrum()`: Retrieves the mass spectrum at an index and records it as the selected spectrum.
+//! - `current_spectrum()`, `set_current_spectrum()`, `pin_current_spectrum()`, `is_pinned()`: Keep the displayed spectrum and the pinned spectra of the spectrum panel.
+//! - `plot_mass_spectrum()`: Renders the displayed and pinned mass spectra, overlaid or stacked.
+//! - `display_spectrum()`: Centroids a profile spectrum if requested, filters it and applies the selected intensity scale before it is plotted.
+//! - `update_displayed_spectra()`, `displayed_spectrum()`: Keep the displayed copies of the spectra of the spectrum panel and prepare them again only when a spectrum or the display settings change.
+//! - `spectrum_plot()`: Creates a mass spectrum plot with the axis labels of the selected intensity scale.
+//! - `add_mass_spectrum_options()`: Adds the options for pinning spectra and choosing the spectrum layout.
+//! - `add_spectrum_filter_options()`: Adds the options for the intensity threshold, top-N per m/z window and m/z crop of the spectra.
+//! - `add_intensity_scale_options()`: Adds the options for normalizing spectra and using a logarithmic intensity axis.
+//! - `plot_deconvolution()`: Renders the controls for averaging scans and deconvolving the displayed spectrum, and the neutral mass spectrum.
+//! - `fragment_annotations()`: Matches the fragment ions of the typed peptide to the peaks of a displayed spectrum.
+//! - `show_peptide_annotation()`: Renders the peptide sequence, the annotation settings and the fragment ion coverage table.
+//! - `open_composition_window()`: Selects a clicked peak and generates its candidate formulas.
+//! - `generate_compositions()`: Starts generating the candidate formulas of the selected peak from the displayed spectrum on a worker thread.
+//! - `receive_compositions()`: Stores the candidate formulas once the worker thread has generated them.
+//! - `cancel_compositions()`: Stops the worker thread generating candidate formulas and discards its result.
+//! - `update_composition_window()`: Updates the elemental composition window with the constraints and the ranked candidate formulas.
+//! - `plot_spectrum_comparison()`: Renders the mirror plot of a pinned spectrum against another scan, another file or a library entry.
+//! - `add_library_options()`: Adds the options for loading and removing spectral libraries.
+//! - `search_displayed_spectrum()`: Searches the displayed mass spectrum against the loaded libraries.
+//! - `search_ms2_spectra()`: Starts searching all MS2 spectra of the file against the loaded libraries on a worker thread.
+//! - `receive_library_matches()`: Stores the best hits of the MS2 spectra once the worker thread has searched them.
+//! - `cancel_library_search()`: Stops the worker thread searching the MS2 spectra and discards its result.
+//! - `update_library_window()`: Updates the library search window with the search settings, ranked hits and mirror plot.
+//! - `add_export_options()`: Adds the options for exporting the displayed data.
+//! - `export_chromatogram()`: Writes the raw and smoothed chromatogram traces to a CSV or TSV file.
+//! - `export_spectrum()`: Writes the displayed mass spectrum to a CSV, TSV, MSP or MGF file.
+//! - `chromatogram_figure()`, `spectrum_figure()`: Describe the displayed chromatogram and mass spectra as figures for image export.
+//! - `export_image()`: Writes a figure to a PNG or SVG file.
 //! - `update_data_selection_panel()`: Updates the data selection panel in the GUI.
 //! - `add_display_options()`: Adds options for adjusting display settings such as smoothing, line width, and color.
 //! - `handle_file_selection()`: Handles the file selection process and updates the file path and validity.
+//! - `open_file()`: Opens a file passed on the command line, e.g. by a file association.
 //! - `update_file_path_and_validity()`: Updates the file path and checks the validity of the selected file.
 //! - `update_file_information_panel()`: Updates the file information panel in the GUI.
+//! - `report_error()`, `report_warning()`: Log a message and show it in the status bar.
+//! - `update_status_bar()`: Updates the status bar at the bottom of the GUI.
 
 //!## Enums
 
@@ -81,6 +187,14 @@
 //! - `Changed`: Indicates that the state has changed.
 //! - `Unchanged`: Indicates that the state has not changed.
 
+//!### `StatusLevel`
+
+//! An enum representing the severity of a status bar message.
+
+//! - `Info`: Indicates a successful operation.
+//! - `Warning`: Indicates that an operation succeeded without a result, e.g. an XIC without peaks.
+//! - `Error`: Indicates that an operation failed.
+
 //!## Usage
 
 //! To use this module, integrate it into your Rust application that requires visualization of mass spectrometry data.
@@ -89,13 +203,32 @@
 #![warn(clippy::all)]
 
 use crate::{
-    parser,
-    plotting_parameters::{self, LineColor, LineType, PlotType},
+    figure,
+    plotting_parameters::{
+        self, LineColor, LineType, MirrorSource, PlotType, PrecursorColor, SpectrumLayout,
+    },
+};
+use chromascope::{
+    chromatogram_processing::{
+        BaselineMethod, BaselineParameters, NoiseMethod, SignalToNoise, SignalToNoiseParameters,
+        SmoothingMethod, SmoothingParameters,
+    },
+    composition, deconvolution, export,
+    library::{self, SimilarityMethod},
+    parser, peptide, spectrum_processing,
 };
 
+use anyhow::Result;
 use mzdata::spectrum::ScanPolarity;
-use std::ops::Div;
-use std::path::PathBuf;
+use std::{
+    path::Path,
+    sync::{
+        atomic::{self, AtomicBool},
+        mpsc, Arc,
+    },
+    thread,
+    time::Duration,
+};
 
 use eframe::egui;
 use egui::{Color32, Context, Ui};
@@ -104,6 +237,32 @@ use log::{debug, error, info, warn};
 use std::cmp::Ordering;
 
 const FILE_FORMAT: &str = "mzML";
+/// The number of intensity levels used to color the precursor coverage map.
+const PRECURSOR_INTENSITY_LEVELS: usize = 10;
+/// The maximum distance (in points) between a click and a precursor for its MS2 spectrum to be opened.
+const PRECURSOR_CLICK_RADIUS: f32 = 8.0;
+/// The number of retention time bins of the heatmap.
+const HEATMAP_RT_BINS: usize = 512;
+/// The number of m/z bins of the heatmap.
+const HEATMAP_MZ_BINS: usize = 512;
+/// The number of ion mobility bins of the mobility heatmap.
+const MOBILITY_BINS: usize = 256;
+/// The mass tolerance (in ppm) used for the XIC of a clicked heatmap region if the user has not set one.
+const DEFAULT_MASS_TOLERANCE: f64 = 10.0;
+/// The precursor tolerance (in ppm) of a library search when the precursor filter is switched on.
+const DEFAULT_PRECURSOR_TOLERANCE: f64 = 10.0;
+/// The number of peaks labelled in every spectrum by default.
+const DEFAULT_PEAK_LABEL_COUNT: usize = 10;
+/// The minimum distance (in points) between two labelled peaks by default.
+const DEFAULT_PEAK_LABEL_SPACING: f32 = 40.0;
+/// The maximum distance (in points) between a click and the peak it selects in the mass spectrum
+const PEAK_CLICK_RADIUS: f32 = 8.0;
+/// The color of the annotated N-terminal (a, b, c) fragment ions
+const N_TERMINAL_ION_COLOR: Color32 = Color32::from_rgb(70, 130, 240);
+/// The color of the annotated C-terminal (y, z) fragment ions
+const C_TERMINAL_ION_COLOR: Color32 = Color32::from_rgb(230, 80, 60);
+/// The file extension of MSP spectral libraries.
+const LIBRARY_FORMAT: &str = "msp";
 
 #[derive(PartialEq, Default)]
 pub struct UserInput {
@@ -121,16 +280,102 @@ pub struct UserInput {
     pub mass: f64,
     /// The mass tolerance value parsed from `mass_tolerance_input`
     pub mass_tolerance: f64,
+    /// The comma separated DIA fragment m/z values provided by the user
+    pub fragment_mz_input: String,
+    /// The fragment m/z values parsed from `fragment_mz_input`
+    pub fragment_mz: Vec<f64>,
+    /// Whether the precursor coverage map is colored by charge or by intensity
+    pub precursor_color: PrecursorColor,
+    /// The lower ion mobility bound input value provided by the user
+    pub mobility_min_input: String,
+    /// The upper ion mobility bound input value provided by the user
+    pub mobility_max_input: String,
+    /// The ion mobility range parsed from `mobility_min_input` and `mobility_max_input`. The XIC is not filtered by ion mobility if `None`
+    pub mobility_range: Option<(f64, f64)>,
+    /// The parameters of library searches
+    pub library_search: library::SearchParameters,
+    /// Whether the mass spectra are overlaid in one plot or stacked in separate plots
+    pub spectrum_layout: SpectrumLayout,
+    /// The number of most intense visible peaks labelled with their m/z in every spectrum
+    pub peak_label_count: usize,
+    /// The minimum distance (in points) between two labelled peaks
+    pub peak_label_spacing: f32,
+    /// The intensity scale of the mass spectra
+    pub normalization: spectrum_processing::Normalization,
+    /// The reference peak m/z input value provided by the user
+    pub reference_mz_input: String,
+    /// The reference peak m/z parsed from `reference_mz_input`
+    pub reference_mz: f64,
+    /// The tolerance (in ppm) used to find the reference peak
+    pub reference_tolerance: f64,
+    /// Whether the intensity axis of the mass spectra is logarithmic
+    pub log_intensity: bool,
+    /// Whether profile spectra are centroided before they are displayed
+    pub centroid_profile: bool,
+    /// The peak picking parameters used to centroid profile spectra
+    pub centroid_parameters: spectrum_processing::CentroidParameters,
+    /// Whether isotope envelopes are labelled with their charge state and monoisotopic m/z
+    pub show_envelopes: bool,
+    /// The parameters of the isotope envelope detection
+    pub envelope_parameters: deconvolution::EnvelopeParameters,
+    /// The display-time filters of the mass spectra
+    pub spectrum_filter: spectrum_processing::SpectrumFilter,
+    /// The lower m/z bound of the spectrum crop provided by the user
+    pub crop_min_input: String,
+    /// The upper m/z bound of the spectrum crop provided by the user
+    pub crop_max_input: String,
+    /// The lower retention time bound of the averaged spectrum provided by the user
+    pub average_rt_min_input: String,
+    /// The upper retention time bound of the averaged spectrum provided by the user
+    pub average_rt_max_input: String,
+    /// The parameters of the charge deconvolution
+    pub deconvolution_parameters: deconvolution::DeconvolutionParameters,
+    /// The lower m/z bound of the charge deconvolution provided by the user
+    pub deconvolution_mz_min_input: String,
+    /// The upper m/z bound of the charge deconvolution provided by the user
+    pub deconvolution_mz_max_input: String,
+    /// The peptide sequence provided by the user, with modifications in brackets
+    pub peptide_input: String,
+    /// The parameters of the fragment ion annotation
+    pub peptide_annotation: peptide::AnnotationParameters,
+    /// The parameters of the elemental composition generator
+    pub composition: composition::CompositionParameters,
+    /// Whether exported chromatogram traces are written as aligned columns or in long format
+    pub trace_layout: export::TraceLayout,
+    /// Whether the mass spectrum is exported as displayed instead of as read from the file
+    pub export_displayed_spectrum: bool,
+    /// The size, resolution and background of exported plot images
+    pub image_parameters: figure::ImageParameters,
+    /// The method and parameters of the chromatogram baseline estimation
+    pub baseline: BaselineParameters,
+    /// Whether the estimated baseline is drawn on top of the chromatogram
+    pub show_baseline: bool,
+    /// Whether the baseline corrected chromatogram is plotted
+    pub subtract_baseline: bool,
+    /// The noise measure and noise region of the signal-to-noise estimation
+    pub signal_to_noise: SignalToNoiseParameters,
+    /// The retention time of the peak whose signal-to-noise ratio is shown
+    pub peak_retention_time: Option<f64>,
+    /// Whether the noise region is chosen by the user instead of found automatically
+    pub manual_noise_region: bool,
+    /// The lower retention time bound of the noise region provided by the user
+    pub noise_rt_min_input: String,
+    /// The upper retention time bound of the noise region provided by the user
+    pub noise_rt_max_input: String,
+    /// The spectrum compared with the pinned spectrum in the mirror plot
+    pub mirror_source: MirrorSource,
+    /// The scan index of the comparison file to be shown in the mirror plot
+    pub comparison_index: usize,
     /// The type of line to be used in the plot
     pub line_type: LineType,
     /// The color of the line to be used in the plot
     pub line_color: LineColor,
-    /// The amount of smoothing to be applied to the plot
-    pub smoothing: u8,
+    /// The smoothing filter and window to be applied to the plot
+    pub smoothing: SmoothingParameters,
     /// The width of the line to be used in the plot
     pub line_width: f32,
     /// The retention time of a given scan. Needed for mass spectrum extraction when the user triple clicks the chromatogram
-    pub retention_time_ms_spectrum: Option<f32>,
+    pub retention_time_ms_spectrum: Option<f64>,
 }
 
 #[derive(Default)]
@@ -146,12 +391,239 @@ enum StateChange {
     Unchanged,
 }
 
+/// The severity of a status bar message.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StatusLevel {
+    Info,
+    Warning,
+    Error,
+}
+
+impl StatusLevel {
+    /// Returns the color the message is drawn in.
+    fn color(&self) -> Color32 {
+        match self {
+            StatusLevel::Info => Color32::from_rgb(90, 170, 90),
+            StatusLevel::Warning => Color32::from_rgb(220, 160, 40),
+            StatusLevel::Error => Color32::from_rgb(220, 70, 70),
+        }
+    }
+}
+
+/// A message shown in the status bar.
+struct StatusMessage {
+    /// The severity of the message
+    level: StatusLevel,
+    /// The text of the message
+    text: String,
+}
+
+/// The identity of a spectrum of the spectrum panel, used to tell whether its displayed copy is still valid.
+#[derive(Clone, PartialEq)]
+struct SpectrumKey {
+    /// The label of the spectrum
+    label: String,
+    /// The index of the spectrum in its file, if it was read from one
+    index: Option<usize>,
+    /// Whether the spectrum is pinned
+    pinned: bool,
+}
+
+impl SpectrumKey {
+    /// Creates the key of a spectrum of the spectrum panel.
+    fn new(spectrum: &parser::Spectrum, pinned: bool) -> Self {
+        SpectrumKey {
+            label: spectrum.label.clone(),
+            index: spectrum.index,
+            pinned,
+        }
+    }
+}
+
+/// The settings of the user that change how a spectrum is displayed.
+#[derive(Clone, Copy, PartialEq)]
+struct DisplayParameters {
+    /// Whether profile spectra are centroided
+    centroid_profile: bool,
+    /// The peak picking parameters used to centroid profile spectra
+    centroid_parameters: spectrum_processing::CentroidParameters,
+    /// The display-time filters
+    spectrum_filter: spectrum_processing::SpectrumFilter,
+    /// The intensity scale
+    normalization: spectrum_processing::Normalization,
+    /// The m/z of the reference peak of the reference peak normalization
+    reference_mz: f64,
+    /// The m/z tolerance of the reference peak
+    reference_tolerance: f64,
+    /// Whether the intensities are shown on a logarithmic scale
+    log_intensity: bool,
+}
+
+impl DisplayParameters {
+    /// Collects the display settings of the user input.
+    fn new(user_input: &UserInput) -> Self {
+        DisplayParameters {
+            centroid_profile: user_input.centroid_profile,
+            centroid_parameters: user_input.centroid_parameters,
+            spectrum_filter: user_input.spectrum_filter,
+            normalization: user_input.normalization,
+            reference_mz: user_input.reference_mz,
+            reference_tolerance: user_input.reference_tolerance,
+            log_intensity: user_input.log_intensity,
+        }
+    }
+}
+
+/// A spectrum of the spectrum panel as displayed, kept until the spectrum or the display settings change.
+struct DisplayedSpectrum {
+    /// The identity of the spectrum it was prepared from
+    key: SpectrumKey,
+    /// The display settings it was prepared with
+    parameters: DisplayParameters,
+    /// The centroided, filtered and scaled spectrum, or `None` if its base or reference peak is missing
+    spectrum: Option<parser::Spectrum>,
+}
+
+/// The isotope envelopes detected in a displayed spectrum, kept until the spectrum, its display settings or the detection parameters change.
+#[derive(Default)]
+struct SpectrumEnvelopes {
+    /// The identity of the spectrum the envelopes were detected in
+    spectrum: Option<SpectrumKey>,
+    /// The display settings of the spectrum the envelopes were detected in
+    display: Option<DisplayParameters>,
+    /// The parameters the envelopes were detected with
+    parameters: deconvolution::EnvelopeParameters,
+    /// The (m/z, intensity) of the centroid peaks the envelopes were detected in
+    peaks: Vec<[f64; 2]>,
+    /// The detected envelopes. Their peak indices refer to `peaks`.
+    envelopes: Vec<deconvolution::IsotopeEnvelope>,
+}
+
+impl SpectrumEnvelopes {
+    /// Detects the isotope envelopes of a displayed spectrum, unless they were already detected in the same spectrum with the same display settings and parameters.
+    ///
+    /// Profile spectra are centroided with the default parameters before the envelopes are detected.
+    ///
+    /// # Parameters
+    /// - `displayed: &DisplayedSpectrum`: The displayed spectrum. Nothing is detected if it could not be displayed.
+    /// - `parameters: &deconvolution::EnvelopeParameters`: The parameters of the envelope detection.
+    fn update(
+        &mut self,
+        displayed: &DisplayedSpectrum,
+        parameters: &deconvolution::EnvelopeParameters,
+    ) {
+        if self.parameters == *parameters
+            && self.spectrum.as_ref() == Some(&displayed.key)
+            && self.display == Some(displayed.parameters)
+        {
+            return;
+        }
+        let Some(spectrum) = &displayed.spectrum else {
+            return;
+        };
+        let (mz, intensity) = if spectrum.profile {
+            spectrum_processing::centroid(
+                &spectrum.mz,
+                &spectrum.intensity,
+                &spectrum_processing::CentroidParameters::default(),
+            )
+        } else {
+            (spectrum.mz.clone(), spectrum.intensity.clone())
+        };
+        self.envelopes = deconvolution::detect_envelopes(&mz, &intensity, parameters);
+        debug!(
+            "Detected {} isotope envelopes in {}",
+            self.envelopes.len(),
+            spectrum.label
+        );
+        self.peaks = mz
+            .iter()
+            .zip(intensity.iter())
+            .map(|(&mz, &intensity)| [mz, intensity as f64])
+            .collect();
+        self.spectrum = Some(displayed.key.clone());
+        self.display = Some(displayed.parameters);
+        self.parameters = *parameters;
+    }
+}
+
 #[derive(Default)]
 pub struct MzViewerApp {
-    /// The parsed mass spectrometry data
+    /// The opened mass spectrometry file
     parsed_ms_data: parser::MzData,
-    /// The plot data, prepared by the `process_plot_data` method
-    plot_data: Option<Vec<[f64; 2]>>,
+    /// The mass spectra shown in the spectrum panel. The last one is the most recently retrieved spectrum, the others are pinned
+    mass_spectra: Vec<parser::Spectrum>,
+    /// Whether the last entry of `mass_spectra` is pinned as well, so that it is kept when another spectrum is retrieved
+    current_spectrum_pinned: bool,
+    /// The MS2 precursors of the file, read when the precursor coverage map is first shown
+    precursors: Option<Vec<parser::PrecursorPoint>>,
+    /// The binned retention time × m/z intensity map of the MS1 data
+    heatmap: Option<parser::Heatmap>,
+    /// The mobilogram of the XIC m/z, read when the ion mobility view is first shown after the plot data changed
+    mobilogram: Option<parser::Mobilogram>,
+    /// The binned ion mobility × m/z intensity map of the frame of the selected spectrum
+    mobility_heatmap: Option<parser::MobilityHeatmap>,
+    /// The best library hit of every MS2 spectrum, produced by a library search of the whole file
+    library_matches: Option<Vec<parser::SpectrumMatch>>,
+    /// The channel delivering the result of the library search of all MS2 spectra running on a worker thread
+    library_search_receiver:
+        Option<mpsc::Receiver<parser::ParserResult<Vec<parser::SpectrumMatch>>>>,
+    /// The progress of the library search of `library_search_receiver`, also used to cancel it
+    library_search_progress: Option<Arc<parser::Progress>>,
+    /// The raw chromatograms extracted by the `process_plot_data` method, the main trace followed by the DIA fragment traces
+    chromatograms: Vec<parser::Chromatogram>,
+    /// The smoothed copies of `chromatograms`, baseline corrected if requested, prepared by the `process_plot_data` method
+    plot_data: Vec<parser::Chromatogram>,
+    /// The estimated baselines of `plot_data`, if the baseline overlay is shown
+    baselines: Vec<Vec<f64>>,
+    /// The signal-to-noise ratio of the selected peak of the main chromatogram, updated by `update_signal_to_noise`
+    signal_to_noise: Option<SignalToNoise>,
+    /// The texture of the rendered heatmap, created from `heatmap`
+    heatmap_texture: Option<egui::TextureHandle>,
+    /// Whether the heatmap of the current file and polarity could not be computed, so it is not retried every frame
+    heatmap_failed: bool,
+    /// The plot bounds of the heatmap at the last frame, used to re-bin the visible region
+    heatmap_bounds: Option<egui_plot::PlotBounds>,
+    /// The plot bounds of the chromatogram at the last frame
+    chromatogram_bounds: Option<egui_plot::PlotBounds>,
+    /// The plot bounds of the mass spectrum at the last frame
+    spectrum_bounds: Option<egui_plot::PlotBounds>,
+    /// The index of the spectrum shown in the mass spectrum plot
+    selected_spectrum_index: Option<usize>,
+    /// The texture of the rendered mobility heatmap, created from `mobility_heatmap`
+    mobility_heatmap_texture: Option<egui::TextureHandle>,
+    /// The spectrum index the mobility heatmap was last computed for
+    mobility_heatmap_index: Option<usize>,
+    /// The loaded spectral libraries
+    libraries: Vec<library::SpectralLibrary>,
+    /// The ranked library hits of the displayed spectrum
+    library_hits: Vec<library::LibraryHit>,
+    /// The position in `library_hits` of the hit shown in the mirror plot
+    selected_library_hit: Option<usize>,
+    /// Whether the library search window is open
+    library_window_open: bool,
+    /// The position in `mass_spectra` of the pinned spectrum drawn on top of the mirror plot
+    mirror_reference: Option<usize>,
+    /// The data of another mzML file whose scans can be compared with the pinned spectrum
+    comparison_data: parser::MzData,
+    /// The scan of `comparison_data` at `user_input.comparison_index`
+    comparison_spectrum: Option<parser::Spectrum>,
+    /// The neutral mass spectrum computed by the charge deconvolution
+    deconvolved_spectrum: Option<parser::Spectrum>,
+    /// The displayed copies of `mass_spectra`, by position, updated by `update_displayed_spectra()`
+    displayed_spectra: Vec<DisplayedSpectrum>,
+    /// The isotope envelopes of the displayed spectra, by position in `mass_spectra`
+    spectrum_envelopes: Vec<SpectrumEnvelopes>,
+    /// The m/z of the peak whose elemental composition is generated
+    composition_mz: Option<f64>,
+    /// The ranked candidate formulas of the peak at `composition_mz`
+    composition_candidates: Vec<composition::Candidate>,
+    /// The channel delivering the m/z and candidate formulas of the peak whose formulas are being generated on a worker thread
+    composition_receiver: Option<mpsc::Receiver<(f64, Vec<composition::Candidate>)>>,
+    /// The flag that stops the worker thread of `composition_receiver`
+    composition_cancel: Option<Arc<AtomicBool>>,
+    /// Whether the elemental composition window is open
+    composition_window_open: bool,
     /// The user input parameters
     user_input: UserInput,
     /// The validity of the input file. Only MzML files can be read in.
@@ -162,6 +634,8 @@ pub struct MzViewerApp {
     options_window_open: bool,
     /// A boolean value for a checkbox/file selector
     checkbox_bool: bool,
+    /// The message shown in the status bar
+    status: Option<StatusMessage>,
 }
 
 impl MzViewerApp {
@@ -173,12 +647,18 @@ impl MzViewerApp {
     /// # Returns
     /// A new instance of the `MzViewerApp` struct with the following default values:
     /// - `user_input.line_width`: 1.0
+    /// - `user_input.peak_label_count`: `DEFAULT_PEAK_LABEL_COUNT`
+    /// - `user_input.peak_label_spacing`: `DEFAULT_PEAK_LABEL_SPACING`
+    /// - `user_input.reference_tolerance`: `DEFAULT_MASS_TOLERANCE`
     /// - All other fields in `user_input` are set to their default values.
     /// - All other fields in the `MzViewerApp` struct are set to their default values.
     pub fn new(_cc: &eframe::CreationContext<'_>) -> Self {
         Self {
             user_input: UserInput {
                 line_width: 1.0,
+                peak_label_count: DEFAULT_PEAK_LABEL_COUNT,
+                peak_label_spacing: DEFAULT_PEAK_LABEL_SPACING,
+                reference_tolerance: DEFAULT_MASS_TOLERANCE,
                 ..Default::default()
             },
             ..Default::default()
@@ -186,23 +666,57 @@ impl MzViewerApp {
     }
     /// Resets the internal state of the instance.
     ///
-    /// This function clears the parsed measurement data and sets the plot data to `None`.
+    /// This function clears the parsed measurement data and the chromatograms.
+    /// Pinned mass spectra are kept, so that spectra of different files can be compared.
     pub fn reset_state(&mut self) {
+        if !self.current_spectrum_pinned {
+            self.mass_spectra.pop();
+        }
+        // only pinned spectra are left
+        self.current_spectrum_pinned = true;
         self.parsed_ms_data = parser::MzData::default();
-        self.plot_data = None;
+        self.precursors = None;
+        self.heatmap = None;
+        self.mobilogram = None;
+        self.mobility_heatmap = None;
+        self.library_matches = None;
+        self.cancel_library_search();
+        self.chromatograms.clear();
+        self.plot_data.clear();
+        self.baselines.clear();
+        self.signal_to_noise = None;
+        self.heatmap_texture = None;
+        self.heatmap_failed = false;
+        self.heatmap_bounds = None;
+        self.chromatogram_bounds = None;
+        self.spectrum_bounds = None;
+        self.selected_spectrum_index = None;
+        self.mobility_heatmap_texture = None;
+        self.mobility_heatmap_index = None;
+        self.library_hits.clear();
+        self.selected_library_hit = None;
+        self.deconvolved_spectrum = None;
+        self.displayed_spectra.clear();
+        self.spectrum_envelopes.clear();
+        self.composition_mz = None;
+        self.composition_candidates.clear();
+        self.cancel_compositions();
     }
 
     /// Processes the plot data based on the user's input.
     ///
-    /// This function is responsible for retrieving the appropriate plot data (TIC, BPC, or XIC) from the `parsed_ms_data` object,
-    /// preparing the data for plotting, and optionally smoothing the data if requested by the user.
+    /// This function is responsible for extracting the appropriate chromatograms (TIC, BPC, XIC or DIA) from the `parsed_ms_data` object
+    /// and smoothing them if requested by the user. For DIA, the first chromatogram is the MS1 XIC of the precursor, followed by the fragment traces.
+    /// The raw chromatograms are stored in `chromatograms` and the smoothed ones in `plot_data`.
+    /// If requested, the baselines of the smoothed chromatograms are estimated for the overlay, or subtracted from `plot_data`.
     ///
     /// # Parameters
     /// - `&mut self`: A mutable reference to the current instance of the struct that contains the `parsed_ms_data` and `user_input` fields.
     ///
-    /// # Returns
-    /// - `Option<Vec<[f64; 2]>>`: An optional vector of 2-element arrays of `f64` values, representing the processed plot data. If there was an error during the processing, `None` is returned.
-    fn process_plot_data(&mut self) -> Option<Vec<[f64; 2]>> {
+    /// # Errors
+    /// If the chromatograms cannot be extracted, the error is shown in the status bar and no chromatogram is plotted.
+    /// An XIC without peaks and DIA fragment traces that cannot be extracted are reported as warnings.
+    fn process_plot_data(&mut self) {
         info!("Starting to process plot data");
 
         // Log user inputs
@@ -215,36 +729,164 @@ impl MzViewerApp {
         self.user_input.smoothing
     );
 
-        let result = match self.user_input.plot_type {
-            PlotType::Tic => self.parsed_ms_data.get_tic(self.user_input.polarity),
-            PlotType::Bpc => self.parsed_ms_data.get_bpic(self.user_input.polarity),
-            PlotType::Xic => self.parsed_ms_data.get_xic(
-                self.user_input.mass,
-                self.user_input.polarity,
-                self.user_input.mass_tolerance,
-            ),
+        self.mobilogram = None;
+
+        let user_input = &self.user_input;
+        let data = &mut self.parsed_ms_data;
+        let result = match user_input.plot_type {
+            PlotType::Tic => data.get_tic(user_input.polarity).map(|tic| vec![tic]),
+            PlotType::Bpc => data.get_bpic(user_input.polarity).map(|bpc| vec![bpc]),
+            PlotType::Xic => match user_input.mobility_range {
+                Some(mobility_range) => data.get_mobility_filtered_xic(
+                    user_input.mass,
+                    user_input.polarity,
+                    user_input.mass_tolerance,
+                    mobility_range,
+                ),
+                None => data.get_xic(
+                    user_input.mass,
+                    user_input.polarity,
+                    user_input.mass_tolerance,
+                ),
+            }
+            .map(|xic| vec![xic]),
+            PlotType::Dia => data
+                .get_xic(
+                    user_input.mass,
+                    user_input.polarity,
+                    user_input.mass_tolerance,
+                )
+                .map(|xic| vec![xic]),
+        };
+        let fragment_result = match (&result, &user_input.plot_type) {
+            (Ok(_), PlotType::Dia) => Some(data.get_dia_fragment_xics(
+                user_input.mass,
+                &user_input.fragment_mz,
+                user_input.polarity,
+                user_input.mass_tolerance,
+            )),
+            _ => None,
         };
 
-        if result.is_err() {
-            error!("Failed to get plot data for the specified plot type");
+        self.status = None;
+        match result {
+            Ok(chromatograms) => self.chromatograms = chromatograms,
+            Err(e) => {
+                self.chromatograms.clear();
+                self.report_error(format!("Failed to extract the chromatogram. {}", e));
+            }
         }
-
-        let prepared_data = self.parsed_ms_data.prepare_for_plot();
-        if prepared_data.is_err() {
-            error!("Failed to prepare data for plotting");
+        match fragment_result {
+            Some(Ok(fragments)) => self.chromatograms.extend(fragments),
+            Some(Err(e)) => self.report_warning(format!("Only the precursor XIC is shown. {}", e)),
+            None => {}
         }
-        if self
-            .parsed_ms_data
-            .smooth_data(prepared_data, self.user_input.smoothing)
-            .is_err()
+        if let Some(xic) = self
+            .chromatograms
+            .first()
+            .filter(|xic| xic.is_empty() && xic.chromatogram_type == parser::ChromatogramType::Xic)
         {
-            error!("Failed to smooth data");
-            return None;
+            let message = format!(
+                "No peak of m/z {} within {} ppm was found. Increase the mass tolerance or check the m/z and polarity.",
+                xic.mz.unwrap_or_default(),
+                xic.mass_tolerance.unwrap_or_default()
+            );
+            self.report_warning(message);
+        }
+        self.plot_data = self
+            .chromatograms
+            .iter()
+            .map(|chromatogram| chromatogram.smoothed(&self.user_input.smoothing))
+            .collect();
+        self.baselines.clear();
+        if self.user_input.subtract_baseline {
+            self.plot_data = self
+                .plot_data
+                .iter()
+                .map(|chromatogram| chromatogram.baseline_corrected(&self.user_input.baseline))
+                .collect();
+        } else if self.user_input.show_baseline {
+            self.baselines = self
+                .plot_data
+                .iter()
+                .map(|chromatogram| chromatogram.baseline(&self.user_input.baseline))
+                .collect();
+        }
+        self.update_signal_to_noise();
+        info!("Finished processing plot data");
+    }
+
+    /// Computes the signal-to-noise ratio of the selected peak of the displayed chromatogram.
+    ///
+    /// The ratio is computed on the main trace of `plot_data`, so it reflects the smoothing and baseline correction of the plot.
+    /// Nothing is computed while the user has chosen to select the noise region but has not selected one.
+    /// If a peak is selected but the noise region contains fewer than two points or no noise, a warning is shown in the status bar.
+    fn update_signal_to_noise(&mut self) {
+        self.signal_to_noise = None;
+        let (Some(retention_time), Some(chromatogram)) =
+            (self.user_input.peak_retention_time, self.plot_data.first())
+        else {
+            return;
         };
+        if self.user_input.manual_noise_region
+            && self.user_input.signal_to_noise.noise_region.is_none()
+        {
+            debug!("No noise region selected, the signal-to-noise ratio is not computed");
+            return;
+        }
+        self.signal_to_noise =
+            chromatogram.signal_to_noise(retention_time, &self.user_input.signal_to_noise);
+        match &self.signal_to_noise {
+            Some(result) => info!(
+                "S/N of the peak at {:.3}: {:.1} ({})",
+                chromatogram.retention_time[result.apex],
+                result.ratio,
+                self.user_input.signal_to_noise
+            ),
+            None => self.report_warning(
+                "The signal-to-noise ratio cannot be computed: the noise region contains fewer than two points or no noise. \
+                 Choose a wider noise region, use the automatic one or turn off baseline subtraction."
+                    .to_string(),
+            ),
+        }
+    }
 
-        let plot_data = &self.parsed_ms_data.plot_data;
-        info!("Finished processing plot data");
-        plot_data.clone()
+    /// Returns the color of a chromatogram of the chromatogram plot.
+    ///
+    /// The main trace is drawn in the line color, the DIA fragment traces in the colors of `plotting_parameters::trace_color`.
+    fn trace_color(&self, position: usize) -> Color32 {
+        match position {
+            0 => self.user_input.line_color.to_egui(),
+            _ => plotting_parameters::trace_color(position - 1),
+        }
+    }
+
+    /// Returns the (retention time, intensity) points of the estimated baseline of a chromatogram of the chromatogram plot, if it is shown.
+    fn baseline_points(&self, position: usize) -> Option<Vec<[f64; 2]>> {
+        let baseline = self.baselines.get(position)?;
+        let chromatogram = self.plot_data.get(position)?;
+        Some(
+            chromatogram
+                .retention_time
+                .iter()
+                .zip(baseline)
+                .map(|(&rt, &intensity)| [rt, intensity])
+                .collect(),
+        )
+    }
+
+    /// Returns the legend name of a chromatogram of the chromatogram plot.
+    ///
+    /// The main trace of a DIA plot is named after the precursor, all other chromatograms after their type and m/z.
+    fn trace_name(&self, position: usize, chromatogram: &parser::Chromatogram) -> String {
+        if position == 0 && self.user_input.plot_type == PlotType::Dia {
+            format!(
+                "MS1 precursor m/z = {:.4}",
+                chromatogram.mz.unwrap_or_default()
+            )
+        } else {
+            chromatogram.name()
+        }
     }
 
     /// Plots the chromatogram (TIC, BPC, or XIC) based on the user's input.
@@ -263,43 +905,76 @@ impl MzViewerApp {
             // Only re-process the data if the state has changed
             if self.state_changed == StateChange::Changed {
                 info!("State has changed, starting to plot chromatogram");
-                self.plot_data = self.process_plot_data();
+                self.process_plot_data();
                 self.state_changed = StateChange::Unchanged;
             }
         }
 
         let mut plot_bounds = None;
+        let mut peak_clicked = None;
+        let shift_pressed = ui.input(|input| input.modifiers.shift);
 
         let response = egui_plot::Plot::new("chromatogram")
             .width(ui.available_width() * 0.99)
             .height(ui.available_height() * 0.6)
+            .legend(egui_plot::Legend::default())
             .show(ui, |plot_ui| {
-                if let Some(data) = &self.plot_data {
-                    plot_ui.line(
-                        Line::new(PlotPoints::from(data.clone()))
-                            .width(self.user_input.line_width)
-                            .style(self.user_input.line_type.to_egui())
-                            .color(self.user_input.line_color.to_egui()), //.name(format!("{:?}", self.user_input.plot_type)),
-                    );
-                } else {
+                if self.plot_data.is_empty() {
                     warn!("No plot data available");
                 }
+                for (i, chromatogram) in self.plot_data.iter().enumerate() {
+                    let mut line = Line::new(PlotPoints::from(chromatogram.points()))
+                        .width(self.user_input.line_width)
+                        .style(self.user_input.line_type.to_egui())
+                        .color(self.trace_color(i)); //.name(format!("{:?}", self.user_input.plot_type)),
+                    if i > 0 || self.user_input.plot_type == PlotType::Dia {
+                        line = line.name(self.trace_name(i, chromatogram));
+                    }
+                    plot_ui.line(line);
+                    if let Some(baseline) = self.baseline_points(i) {
+                        plot_ui.line(
+                            Line::new(PlotPoints::from(baseline))
+                                .width(self.user_input.line_width)
+                                .style(egui_plot::LineStyle::dashed_dense())
+                                .color(self.trace_color(i).gamma_multiply(0.7))
+                                .name("Baseline"),
+                        );
+                    }
+                }
+                if let (Some(result), Some(chromatogram)) =
+                    (&self.signal_to_noise, self.plot_data.first())
+                {
+                    add_signal_to_noise_annotation(
+                        plot_ui,
+                        chromatogram,
+                        result,
+                        self.trace_color(0),
+                    );
+                }
+                if shift_pressed && plot_ui.response().clicked() {
+                    peak_clicked = plot_ui.pointer_coordinate().map(|point| point.x);
+                }
                 plot_bounds = Some(plot_ui.plot_bounds());
             })
             .response;
+        self.chromatogram_bounds = plot_bounds;
+
+        if let Some(retention_time) = peak_clicked {
+            info!("Peak selected for S/N at {:.3}", retention_time);
+            self.user_input.peak_retention_time = Some(retention_time);
+            self.update_signal_to_noise();
+        }
 
         if response.triple_clicked() {
-            // this was added because when triple clicked on XIC the extracted mz spectrum was not accurate (gave different result compared to BIC and TIC)
-            if self.user_input.plot_type != plotting_parameters::PlotType::Xic {
-                let rt_clicked = self.determine_rt_clicked(&response, plot_bounds);
-                info!("Triple click detected on plot at {:?}", &rt_clicked);
-
-                if let Some(index) = self.find_closest_spectrum(rt_clicked) {
-                    info!("Found closest spectrum at index: {}", index);
-                    self.parsed_ms_data.get_mass_spectrum_by_index(index);
-                } else {
-                    warn!("No close spectrum found for the clicked retention time");
-                }
+            // every chromatogram records the scan index of its data points, so the spectrum of a clicked XIC or DIA point is the scan it was extracted from
+            let rt_clicked = self.determine_rt_clicked(&response, plot_bounds);
+            info!("Triple click detected on plot at {:?}", &rt_clicked);
+
+            if let Some(index) = self.find_closest_spectrum(rt_clicked) {
+                info!("Found closest spectrum at index: {}", index);
+                self.show_mass_spectrum(index);
+            } else {
+                warn!("No close spectrum found for the clicked retention time");
             }
         }
 
@@ -316,12 +991,12 @@ impl MzViewerApp {
     /// - `plot_bounds: Option<egui_plot::PlotBounds>`: An optional reference to the plot's bounds, which are used to calculate the retention time.
     ///
     /// # Returns
-    /// - `Option<f32>`: The calculated retention time at the clicked location, or `None` if the plot position or bounds are not available.
+    /// - `Option<f64>`: The calculated retention time at the clicked location, or `None` if the plot position or bounds are not available.
     fn determine_rt_clicked(
         &mut self,
         response: &egui::Response,
         plot_bounds: Option<egui_plot::PlotBounds>,
-    ) -> Option<f32> {
+    ) -> Option<f64> {
         if let Some(plot_position) = response.interact_pointer_pos() {
             if let Some(bounds) = plot_bounds {
                 let plot_width = response.rect.width();
@@ -334,13 +1009,13 @@ impl MzViewerApp {
 
                 let converted_rt = min_x + relative_x as f64 * (max_x - min_x);
 
-                self.user_input.retention_time_ms_spectrum = Some(converted_rt as f32);
+                self.user_input.retention_time_ms_spectrum = Some(converted_rt);
                 info!(
                     "Retention time clicked: {:?}",
                     self.user_input.retention_time_ms_spectrum
                 );
 
-                return Some(converted_rt as f32);
+                return Some(converted_rt);
             } else {
                 warn!("Plot bounds are None");
             }
@@ -352,72 +1027,341 @@ impl MzViewerApp {
 
     /// Finds the index of the mass spectrum closest to the given retention time.
     ///
-    /// This function searches the `retention_time` array in the `parsed_ms_data` object to find the mass spectrum with the closest retention time to the given value.
-    /// If an exact match is not found, it returns the index of the mass spectrum with the closest retention time.
+    /// This function looks up the data point of the main chromatogram with the closest retention time to the given value and returns the index of the scan it was read from.
     ///
     /// # Parameters
-    /// - `&self`: A reference to the current instance of the struct that contains the `parsed_ms_data` field.
-    /// - `clicked_rt: Option<f32>`: The retention time at which the user clicked on the plot, or `None` if no click was detected.
+    /// - `&self`: A reference to the current instance of the struct that contains the `chromatograms` field.
+    /// - `clicked_rt: Option<f64>`: The retention time at which the user clicked on the plot, or `None` if no click was detected.
     ///
     /// # Returns
-    /// - `Option<usize>`: The index of the mass spectrum with the closest retention time to the given value, or `None` if the retention time or index data is missing.
-    fn find_closest_spectrum(&self, clicked_rt: Option<f32>) -> Option<usize> {
-        if let Some(rt) = clicked_rt {
-            if let (Some(retention_times), Some(indices)) = (
-                &self.parsed_ms_data.retention_time,
-                &self.parsed_ms_data.index,
-            ) {
-                match retention_times.binary_search_by(|spectrum| {
-                    spectrum.partial_cmp(&rt).unwrap_or(Ordering::Equal)
-                }) {
-                    Ok(found_index) => {
-                        info!("Exact Rt match found at index: {:?}", found_index);
-                        Some(indices[found_index])
-                    }
-                    Err(found_index) => {
-                        // If the exact RT is not found, return the closest one
-                        info!(
-                            "Closest Rt match not found, using nearest index: {:?}",
-                            found_index
-                        );
-                        if found_index == 0 {
-                            info!("Returning the first index: {:?}", indices.first());
-                            indices.first().copied()
-                        } else if found_index == indices.len() {
-                            info!("Returning the last index: {:?}", indices.last());
-                            indices.last().copied()
-                        } else {
-                            // Compare the two closest values and return the closer one
-                            let prev = &retention_times[found_index - 1];
-                            let next = &retention_times[found_index];
-                            info!(
-                                "Comparing previous: {:?} and next: {:?} for RT: {:?}",
-                                prev, next, rt
+    /// - `Option<usize>`: The index of the mass spectrum with the closest retention time to the given value, or `None` if no chromatogram is plotted.
+    fn find_closest_spectrum(&self, clicked_rt: Option<f64>) -> Option<usize> {
+        let Some(rt) = clicked_rt else {
+            warn!("No close RT match found. Mass spectrum can't be extracted/displayed.");
+            return None;
+        };
+        let index = self
+            .chromatograms
+            .first()
+            .and_then(|chromatogram| chromatogram.closest_scan(rt));
+        if index.is_none() {
+            warn!("Retention time or index data is missing.");
+        }
+        index
+    }
+
+    /// Plots the mass spectra based on the data available in the `parsed_ms_data` object.
+    ///
+    /// This function creates a bar chart of the mass-to-charge (m/z) values and their corresponding intensities for the displayed spectrum and every pinned spectrum.
+    /// Depending on `user_input.spectrum_layout`, the spectra are overlaid in one plot, or stacked in separate plots sharing the m/z axis.
+    /// Profile spectra are drawn as continuous lines and centroid spectra as sticks.
+    /// The spectra are centroided and scaled by `display_spectrum()` only when a spectrum or the display settings change, and spectra without the selected reference peak are listed instead of being drawn.
+    /// A single spectrum is drawn in the line color, several spectra in the colors of `plotting_parameters::trace_color`, each with its own legend entry.
+    /// The `user_input.peak_label_count` most intense peaks of the visible region are labelled with their m/z, at least `user_input.peak_label_spacing` points apart, so the labels follow the zoom.
+    /// If a peptide is typed in `user_input.peptide_input`, its matched fragment ions are highlighted and labelled in the displayed spectrum instead of the m/z labels.
+    /// If `user_input.show_envelopes` is set, the isotope envelopes of the visible region are marked and labelled with their charge state and monoisotopic m/z.
+    /// The envelopes are stored in `spectrum_envelopes` and only detected again when a displayed spectrum, its display settings or the detection parameters change.
+    /// The width of the bars is adjusted based on the zoom level of the plot to provide a better visual representation.
+    /// Clicking a peak of the displayed spectrum opens the elemental composition window for that peak.
+    ///
+    /// # Parameters
+    /// - `&mut self`: A mutable reference to the current instance of the struct that contains the `parsed_ms_data` and `user_input` fields.
+    /// - `ui: &mut egui::Ui`: A mutable reference to the current `egui::Ui` instance, which is used to render the plot.
+    ///
+    /// # Returns
+    /// - `egui::Response`: The response from the `egui_plot::Plot` widget(s), which can be used to handle user interactions with the plot.
+    fn plot_mass_spectrum(&mut self, ui: &mut egui::Ui) -> egui::Response {
+        self.update_displayed_spectra();
+        let spectra = &self.mass_spectra;
+        if spectra.is_empty() {
+            warn!("No mass spectrum data available");
+            return ui.label("No mass spectrum data available");
+        }
+        info!(
+            "Mass spectrum data available. Plotting {} spectra.",
+            spectra.len()
+        );
+
+        let color = |i: usize| {
+            if spectra.len() == 1 {
+                self.user_input.line_color.to_egui()
+            } else {
+                plotting_parameters::trace_color(i)
+            }
+        };
+
+        let mut displayed = Vec::with_capacity(spectra.len());
+        for (i, spectrum) in spectra.iter().enumerate() {
+            match self.displayed_spectra[i].spectrum.as_ref() {
+                Some(spectrum) => displayed.push((i, spectrum)),
+                None => {
+                    warn!("Reference peak not found in {}", spectrum.label);
+                    ui.colored_label(
+                        Color32::LIGHT_RED,
+                        format!(
+                            "Reference peak m/z = {:.4} not found in {}",
+                            self.user_input.reference_mz, spectrum.label
+                        ),
+                    );
+                }
+            }
+        }
+        if displayed.is_empty() {
+            return ui.label("No mass spectrum can be displayed with the selected intensity scale");
+        }
+        if self.user_input.show_envelopes {
+            self.spectrum_envelopes
+                .resize_with(spectra.len(), SpectrumEnvelopes::default);
+            for (i, _) in &displayed {
+                self.spectrum_envelopes[*i].update(
+                    &self.displayed_spectra[*i],
+                    &self.user_input.envelope_parameters,
+                );
+            }
+        }
+        let current = spectra.len() - 1;
+        let annotations = displayed
+            .iter()
+            .find(|(i, _)| *i == current)
+            .map(|(_, spectrum)| self.fragment_annotations(spectrum))
+            .unwrap_or_default();
+
+        let current_spectrum = displayed
+            .iter()
+            .find(|(i, _)| *i == current)
+            .map(|(_, spectrum)| spectrum);
+        let mut clicked_mz = None;
+        let response = match self.user_input.spectrum_layout {
+            SpectrumLayout::Overlay => {
+                let plot_response = self
+                    .spectrum_plot("mass_spectrum")
+                    .width(ui.available_width() * 0.99)
+                    .height(ui.available_height())
+                    .show(ui, |plot_ui| {
+                        for (i, spectrum) in &displayed {
+                            add_spectrum_to_plot(
+                                plot_ui,
+                                spectrum,
+                                color(*i),
+                                self.user_input.line_width,
+                                self.user_input.line_type.to_egui(),
                             );
-                            if (rt - prev).abs() < (next - rt).abs() {
-                                info!("Returning previous index: {:?}", indices[found_index - 1]);
-                                Some(indices[found_index - 1])
+                            if *i == current && !annotations.is_empty() {
+                                add_fragment_annotations(plot_ui, spectrum, &annotations);
                             } else {
-                                info!("Returning next index: {:?}", indices[found_index]);
-                                Some(indices[found_index])
+                                add_peak_labels(
+                                    plot_ui,
+                                    spectrum,
+                                    self.user_input.peak_label_count,
+                                    self.user_input.peak_label_spacing,
+                                    color(*i),
+                                );
+                            }
+                            if self.user_input.show_envelopes {
+                                add_envelope_labels(
+                                    plot_ui,
+                                    &self.spectrum_envelopes[*i],
+                                    self.user_input.peak_label_count,
+                                    self.user_input.peak_label_spacing,
+                                    color(*i),
+                                );
                             }
                         }
+                    });
+                clicked_mz =
+                    current_spectrum.and_then(|spectrum| clicked_peak(&plot_response, spectrum));
+                self.spectrum_bounds = Some(*plot_response.transform.bounds());
+                plot_response.response
+            }
+            SpectrumLayout::Stacked => {
+                let height = (ui.available_height() / displayed.len() as f32).max(150.0);
+                let mut response: Option<egui::Response> = None;
+                for (i, spectrum) in &displayed {
+                    let plot_response = self
+                        .spectrum_plot(&format!("mass_spectrum_{}", i))
+                        .width(ui.available_width() * 0.99)
+                        .height(height)
+                        .link_axis("stacked_mass_spectra", true, false)
+                        .link_cursor("stacked_mass_spectra", true, false)
+                        .show(ui, |plot_ui| {
+                            add_spectrum_to_plot(
+                                plot_ui,
+                                spectrum,
+                                color(*i),
+                                self.user_input.line_width,
+                                self.user_input.line_type.to_egui(),
+                            );
+                            if *i == current && !annotations.is_empty() {
+                                add_fragment_annotations(plot_ui, spectrum, &annotations);
+                            } else {
+                                add_peak_labels(
+                                    plot_ui,
+                                    spectrum,
+                                    self.user_input.peak_label_count,
+                                    self.user_input.peak_label_spacing,
+                                    color(*i),
+                                );
+                            }
+                            if self.user_input.show_envelopes {
+                                add_envelope_labels(
+                                    plot_ui,
+                                    &self.spectrum_envelopes[*i],
+                                    self.user_input.peak_label_count,
+                                    self.user_input.peak_label_spacing,
+                                    color(*i),
+                                );
+                            }
+                        });
+                    if *i == current {
+                        clicked_mz = clicked_peak(&plot_response, spectrum);
                     }
+                    // the axes are linked, so the bounds of any of the plots hold the visible m/z range
+                    self.spectrum_bounds = Some(*plot_response.transform.bounds());
+                    let plot_response = plot_response.response;
+                    response = Some(match response {
+                        Some(response) => response.union(plot_response),
+                        None => plot_response,
+                    });
                 }
-            } else {
-                warn!("Retention time or index data is missing.");
-                None
+                response.expect("at least one spectrum is plotted")
+            }
+        };
+        if let Some(mz) = clicked_mz {
+            self.open_composition_window(mz);
+        }
+        response
+    }
+
+    /// Prepares the displayed copies of the spectra of the spectrum panel.
+    ///
+    /// A copy is only prepared again by `display_spectrum()` when its spectrum is new, is pinned or unpinned, or when the display settings change,
+    /// so the centroiding, filtering and scaling do not run on every frame.
+    fn update_displayed_spectra(&mut self) {
+        let parameters = DisplayParameters::new(&self.user_input);
+        let mut previous = std::mem::take(&mut self.displayed_spectra);
+        let displayed = self
+            .mass_spectra
+            .iter()
+            .enumerate()
+            .map(|(i, spectrum)| {
+                let key = SpectrumKey::new(spectrum, self.is_pinned(i));
+                match previous.iter().position(|displayed| {
+                    displayed.key == key && displayed.parameters == parameters
+                }) {
+                    Some(position) => previous.swap_remove(position),
+                    None => {
+                        debug!("Preparing {} for display", spectrum.label);
+                        DisplayedSpectrum {
+                            spectrum: self.display_spectrum(spectrum),
+                            key,
+                            parameters,
+                        }
+                    }
+                }
+            })
+            .collect();
+        self.displayed_spectra = displayed;
+    }
+
+    /// Returns the displayed copy of the spectrum at a position of `mass_spectra`, as prepared by the last `update_displayed_spectra()`.
+    ///
+    /// # Returns
+    /// - `Option<&parser::Spectrum>`: The displayed spectrum, or `None` if there is no spectrum at that position or its base or reference peak is missing.
+    fn displayed_spectrum(&self, position: usize) -> Option<&parser::Spectrum> {
+        self.displayed_spectra
+            .get(position)
+            .and_then(|displayed| displayed.spectrum.as_ref())
+    }
+
+    /// Prepares a spectrum for display by centroiding it if requested, filtering it and applying the intensity scale selected by the user.
+    ///
+    /// The intensity threshold and the top-N filter only apply to centroid spectra, as removing points from a profile spectrum would distort its peaks. Profile spectra are only cropped.
+    ///
+    /// # Parameters
+    /// - `spectrum: &parser::Spectrum`: The spectrum to be displayed.
+    ///
+    /// # Returns
+    /// - `Option<parser::Spectrum>`: A copy of the spectrum with normalized and optionally log-scaled intensities, or `None` if its base or reference peak is missing.
+    fn display_spectrum(&self, spectrum: &parser::Spectrum) -> Option<parser::Spectrum> {
+        let mut spectrum = spectrum.clone();
+        if spectrum.profile && self.user_input.centroid_profile {
+            (spectrum.mz, spectrum.intensity) = spectrum_processing::centroid(
+                &spectrum.mz,
+                &spectrum.intensity,
+                &self.user_input.centroid_parameters,
+            );
+            spectrum.profile = false;
+            debug!(
+                "Centroided {} into {} peaks",
+                spectrum.label,
+                spectrum.mz.len()
+            );
+        }
+
+        let filter = if spectrum.profile {
+            spectrum_processing::SpectrumFilter {
+                threshold_mode: spectrum_processing::ThresholdMode::Off,
+                top_n: 0,
+                ..self.user_input.spectrum_filter
             }
         } else {
-            warn!("No close RT match found. Mass spectrum can't be extracted/displayed.");
-            None
+            self.user_input.spectrum_filter
+        };
+        (spectrum.mz, spectrum.intensity) =
+            spectrum_processing::filter_peaks(&spectrum.mz, &spectrum.intensity, &filter);
+
+        let mut intensity = spectrum_processing::normalize(
+            &spectrum.mz,
+            &spectrum.intensity,
+            self.user_input.normalization,
+            self.user_input.reference_mz,
+            self.user_input.reference_tolerance,
+        )?;
+        if self.user_input.log_intensity {
+            intensity = spectrum_processing::log_intensities(&intensity);
         }
+        spectrum.intensity = intensity;
+        Some(spectrum)
     }
 
-    /// Plots the mass spectrum based on the data available in the `parsed_ms_data` object.
+    /// Creates a mass spectrum plot with a legend and the intensity axis of the selected intensity scale.
     ///
-    /// This function creates a bar chart plot of the mass-to-charge (m/z) values and their corresponding intensities.
-    /// The width of the bars is adjusted based on the zoom level of the plot to provide a better visual representation.
+    /// For a logarithmic intensity axis, the grid marks are labelled with the intensities they stand for.
+    fn spectrum_plot(&self, id: &str) -> egui_plot::Plot {
+        let plot = egui_plot::Plot::new(id)
+            .legend(egui_plot::Legend::default())
+            .x_axis_label("m/z")
+            .y_axis_label(self.intensity_axis_label());
+        if self.user_input.log_intensity {
+            plot.y_axis_formatter(|mark, _, _| format_log_intensity(mark.value))
+        } else {
+            plot
+        }
+    }
+
+    /// Returns the label of the intensity axis of the mass spectrum, which depends on the intensity scale selected by the user.
+    fn intensity_axis_label(&self) -> String {
+        let unit = match self.user_input.normalization {
+            spectrum_processing::Normalization::Absolute => "Intensity".to_string(),
+            spectrum_processing::Normalization::BasePeak => "Relative intensity (%)".to_string(),
+            spectrum_processing::Normalization::ReferencePeak => format!(
+                "Intensity relative to m/z {:.4} (%)",
+                self.user_input.reference_mz
+            ),
+        };
+        if self.user_input.log_intensity {
+            format!("{} (log scale)", unit)
+        } else {
+            unit
+        }
+    }
+
+    /// Plots the precursor coverage map of the MS2 scans.
+    ///
+    /// This function creates a scatter plot of every MS2 precursor, with the retention time on the x-axis and the precursor m/z on the y-axis.
+    /// The precursors are read lazily the first time the map is shown for the current file and polarity.
+    /// Depending on `user_input.precursor_color`, the points are grouped by charge state (one legend entry per charge) or colored by their log-scaled intensity.
+    /// Clicking a point opens the corresponding MS2 spectrum in the mass spectrum plot.
     ///
     /// # Parameters
     /// - `&mut self`: A mutable reference to the current instance of the struct that contains the `parsed_ms_data` and `user_input` fields.
@@ -425,69 +1369,486 @@ impl MzViewerApp {
     ///
     /// # Returns
     /// - `egui::Response`: The response from the `egui_plot::Plot` widget, which can be used to handle user interactions with the plot.
-    fn plot_mass_spectrum(&mut self, ui: &mut egui::Ui) -> egui::Response {
-        if let Some((mz, intensity)) = &self.parsed_ms_data.mass_spectrum {
-            info!("Mass spectrum data available. Plotting the spectrum.");
-
-            // Create bar chart data
-            let _bars: Vec<egui_plot::Bar> = mz
-                .iter()
-                .zip(intensity.iter())
-                .map(|(&m, &i)| {
-                    egui_plot::Bar::new(m, i.into())
-                        .width(self.user_input.line_width.div(2.0).into()) // Adjust width of bars as needed
-                        .fill(self.user_input.line_color.to_egui()) // Adjust color as needed
-                })
-                .collect();
+    fn plot_precursor_map(&mut self, ui: &mut egui::Ui) -> egui::Response {
+        if self.user_input.file_path.is_some() && self.precursors.is_none() {
+            info!("Reading precursors for the precursor coverage map");
+            match self
+                .parsed_ms_data
+                .get_precursor_map(self.user_input.polarity)
+            {
+                Ok(precursors) => self.precursors = Some(precursors),
+                Err(e) => {
+                    self.report_error(format!("Failed to read the MS2 precursors. {}", e));
+                    // an empty map is not read again until the polarity or file changes
+                    self.precursors = Some(Vec::new());
+                }
+            }
+        }
 
-            let response = egui_plot::Plot::new("mass_spectrum")
-                .width(ui.available_width() * 0.99)
-                .height(ui.available_height())
-                .show(ui, |plot_ui| {
-                    let bounds = plot_ui.plot_bounds();
-                    let zoom_level = (bounds.max()[0] - bounds.min()[0]).abs(); // Calculate zoom level based on plot bounds
-                    debug!("Zoom level calculated: {}", zoom_level);
+        let precursors = match &self.precursors {
+            Some(precursors) if !precursors.is_empty() => precursors,
+            _ => {
+                warn!("No precursor data available");
+                return ui.label("No MS2 precursors available");
+            }
+        };
 
-                    let bar_width = zoom_level * 0.001; // Adjust bar width based on zoom level
-                    let adjusted_bars: Vec<egui_plot::Bar> = mz
+        let plot_response = egui_plot::Plot::new("precursor_map")
+            .width(ui.available_width() * 0.99)
+            .height(ui.available_height() * 0.6)
+            .x_axis_label("Retention time")
+            .y_axis_label("Precursor m/z")
+            .legend(egui_plot::Legend::default())
+            .show(ui, |plot_ui| match self.user_input.precursor_color {
+                PrecursorColor::Charge => {
+                    let mut by_charge: std::collections::BTreeMap<Option<i32>, Vec<[f64; 2]>> =
+                        std::collections::BTreeMap::new();
+                    for precursor in precursors {
+                        by_charge
+                            .entry(precursor.charge)
+                            .or_default()
+                            .push([precursor.retention_time, precursor.mz]);
+                    }
+                    for (charge, points) in by_charge {
+                        let (name, color) = match charge {
+                            Some(z) => (
+                                format!("z = {}", z),
+                                plotting_parameters::trace_color(z.unsigned_abs() as usize),
+                            ),
+                            None => ("z = unknown".to_string(), Color32::GRAY),
+                        };
+                        plot_ui.points(
+                            egui_plot::Points::new(points)
+                                .radius(self.user_input.line_width + 1.5)
+                                .color(color)
+                                .name(name),
+                        );
+                    }
+                }
+                PrecursorColor::Intensity => {
+                    let log_intensities: Vec<f64> = precursors
                         .iter()
-                        .zip(intensity.iter())
-                        .map(|(&m, &i)| {
-                            egui_plot::Bar::new(m, i.into())
-                                .width(bar_width) // Adjust width of bars based on zoom level
-                                .fill(self.user_input.line_color.to_egui()) // Adjust color as needed
-                                .name(format!("m/z = {:.4}", m))
-                        })
+                        .map(|precursor| (precursor.intensity as f64).max(1.0).log10())
                         .collect();
+                    let min = log_intensities
+                        .iter()
+                        .cloned()
+                        .fold(f64::INFINITY, f64::min);
+                    let max = log_intensities
+                        .iter()
+                        .cloned()
+                        .fold(f64::NEG_INFINITY, f64::max);
+                    let range = (max - min).max(f64::EPSILON);
 
-                    plot_ui.bar_chart(egui_plot::BarChart::new(adjusted_bars));
-                })
-                .response;
-            response
-        } else {
-            warn!("No mass spectrum data available");
-            ui.label("No mass spectrum data available")
+                    let mut levels = vec![Vec::new(); PRECURSOR_INTENSITY_LEVELS];
+                    for (precursor, log_intensity) in precursors.iter().zip(log_intensities) {
+                        let level = (((log_intensity - min) / range)
+                            * (PRECURSOR_INTENSITY_LEVELS - 1) as f64)
+                            .round() as usize;
+                        levels[level].push([precursor.retention_time, precursor.mz]);
+                    }
+                    for (level, points) in levels.into_iter().enumerate() {
+                        if points.is_empty() {
+                            continue;
+                        }
+                        let fraction = level as f64 / (PRECURSOR_INTENSITY_LEVELS - 1) as f64;
+                        plot_ui.points(
+                            egui_plot::Points::new(points)
+                                .radius(self.user_input.line_width + 1.5)
+                                .color(plotting_parameters::intensity_color(fraction))
+                                .name(format!("log10 intensity ≈ {:.1}", min + fraction * range)),
+                        );
+                    }
+                }
+            });
+
+        if plot_response.response.clicked() {
+            if let Some(pointer) = plot_response.response.interact_pointer_pos() {
+                match self.find_clicked_precursor(pointer, &plot_response.transform) {
+                    Some(index) => {
+                        info!(
+                            "Precursor clicked, opening MS2 spectrum at index: {}",
+                            index
+                        );
+                        self.show_mass_spectrum(index);
+                    }
+                    None => debug!("No precursor close to the clicked position"),
+                }
+            }
         }
+
+        plot_response.response
     }
 
-    /// Updates the data selection panel in the user interface.
+    /// Finds the MS2 spectrum index of the precursor closest to the clicked position.
     ///
-    /// This function creates a top panel in the UI that contains the following elements:
-    /// - A "File" button that allows the user to select a file to open.
-    /// - A "Display" menu button that allows the user to configure the display options.
-    /// - A light/dark mode toggle button that allows the user to switch between light and dark themes.
+    /// # Parameters
+    /// - `&self`: A reference to the current instance of the struct that contains the `parsed_ms_data` field.
+    /// - `pointer: egui::Pos2`: The screen position of the click.
+    /// - `transform: &egui_plot::PlotTransform`: The transform between screen and plot coordinates of the precursor coverage map.
     ///
-    /// When the "File" button is clicked, the function handles the file selection process, clears the existing plot data and parser data, and updates the user input accordingly.
+    /// # Returns
+    /// - `Option<usize>`: The index of the MS2 spectrum of the closest precursor, or `None` if no precursor lies within `PRECURSOR_CLICK_RADIUS` of the click.
+    fn find_clicked_precursor(
+        &self,
+        pointer: egui::Pos2,
+        transform: &egui_plot::PlotTransform,
+    ) -> Option<usize> {
+        self.precursors
+            .as_ref()?
+            .iter()
+            .map(|precursor| {
+                let position = transform.position_from_point(&egui_plot::PlotPoint::new(
+                    precursor.retention_time,
+                    precursor.mz,
+                ));
+                (precursor.index, position.distance(pointer))
+            })
+            .filter(|(_, distance)| *distance <= PRECURSOR_CLICK_RADIUS)
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
+            .map(|(index, _)| index)
+    }
+
+    /// Adds the precursor coverage map options to the provided `Ui`.
     ///
-    /// When the "Display" menu button is clicked, the function calls the `add_display_options` function to add the display options to the menu.
+    /// This function renders radio buttons that allow the user to color the precursor coverage map by charge state or by intensity.
     ///
-    /// When the light/dark mode toggle button is clicked, the function updates the visuals of the UI based on the user's selection.
+    /// # Parameters
+    ///
+    /// - `ui`: A mutable reference to the `egui::Ui` object, which is used to render the UI elements.
+    fn add_precursor_map_options(&mut self, ui: &mut Ui) {
+        ui.label("Color by");
+        ui.horizontal(|ui| {
+            ui.radio_value(
+                &mut self.user_input.precursor_color,
+                PrecursorColor::Charge,
+                "Charge",
+            );
+            ui.radio_value(
+                &mut self.user_input.precursor_color,
+                PrecursorColor::Intensity,
+                "Intensity",
+            );
+        });
+    }
+
+    /// Plots the binned retention time × m/z heatmap of the MS1 data.
+    ///
+    /// The heatmap is computed lazily the first time it is shown for the current file and polarity, and rendered as a texture with a logarithmic color scale.
+    /// Clicking a region opens the closest MS1 mass spectrum and plots the XIC of the clicked m/z.
     ///
     /// # Parameters
-    /// - `&mut self`: A mutable reference to the current instance of the struct that contains the `plot_data`, `parsed_ms_data`, `user_input`, and other relevant fields.
-    /// - `ctx: &Context`: A reference to the `egui::Context` instance, which is used to update the UI's visuals.
-    fn update_data_selection_panel(&mut self, ctx: &Context) {
-        egui::TopBottomPanel::top("data_selection_panel").show(ctx, |ui| {
+    /// - `&mut self`: A mutable reference to the current instance of the struct that contains the `parsed_ms_data`, `heatmap_texture` and `user_input` fields.
+    /// - `ui: &mut egui::Ui`: A mutable reference to the current `egui::Ui` instance, which is used to render the plot.
+    ///
+    /// # Returns
+    /// - `egui::Response`: The response from the `egui_plot::Plot` widget, which can be used to handle user interactions with the plot.
+    fn plot_heatmap(&mut self, ui: &mut egui::Ui) -> egui::Response {
+        if self.user_input.file_path.is_some() && self.heatmap.is_none() && !self.heatmap_failed {
+            self.compute_heatmap(None, None);
+        }
+
+        let Some(heatmap) = &self.heatmap else {
+            warn!("No heatmap data available");
+            return ui.label("No heatmap data available");
+        };
+
+        let texture = self.heatmap_texture.get_or_insert_with(|| {
+            debug!("Rendering heatmap texture");
+            ui.ctx().load_texture(
+                "heatmap",
+                heatmap_image(heatmap),
+                egui::TextureOptions::NEAREST,
+            )
+        });
+
+        let center = egui_plot::PlotPoint::new(
+            (heatmap.rt_range.0 + heatmap.rt_range.1) / 2.0,
+            (heatmap.mz_range.0 + heatmap.mz_range.1) / 2.0,
+        );
+        let size = egui::vec2(
+            (heatmap.rt_range.1 - heatmap.rt_range.0) as f32,
+            (heatmap.mz_range.1 - heatmap.mz_range.0) as f32,
+        );
+
+        let mut plot_bounds = None;
+        let plot_response = egui_plot::Plot::new("heatmap")
+            .width(ui.available_width() * 0.99)
+            .height(ui.available_height() * 0.6)
+            .x_axis_label("Retention time")
+            .y_axis_label("m/z")
+            .show(ui, |plot_ui| {
+                plot_ui.image(egui_plot::PlotImage::new(texture.id(), center, size));
+                plot_bounds = Some(plot_ui.plot_bounds());
+            });
+        self.heatmap_bounds = plot_bounds;
+
+        if plot_response.response.clicked() {
+            if let Some(pointer) = plot_response.response.interact_pointer_pos() {
+                let point = plot_response.transform.value_from_position(pointer);
+                info!("Heatmap clicked at Rt: {}, m/z: {}", point.x, point.y);
+                self.select_heatmap_region(point.x, point.y);
+            }
+        }
+
+        plot_response.response
+    }
+
+    /// Computes the heatmap of the current file and discards the previously rendered texture.
+    ///
+    /// # Parameters
+    /// - `rt_range: Option<(f64, f64)>`: The retention time range to be binned, or `None` for the full run.
+    /// - `mz_range: Option<(f64, f64)>`: The m/z range to be binned, or `None` for the full run.
+    fn compute_heatmap(&mut self, rt_range: Option<(f64, f64)>, mz_range: Option<(f64, f64)>) {
+        info!(
+            "Computing heatmap for Rt {:?} and m/z {:?}",
+            rt_range, mz_range
+        );
+        self.heatmap_texture = None;
+        match self.parsed_ms_data.get_ms1_heatmap(
+            self.user_input.polarity,
+            HEATMAP_RT_BINS,
+            HEATMAP_MZ_BINS,
+            rt_range,
+            mz_range,
+        ) {
+            Ok(heatmap) => self.heatmap = Some(heatmap),
+            Err(e) => {
+                self.heatmap_failed = true;
+                self.report_error(format!("Failed to compute the heatmap. {}", e));
+            }
+        }
+    }
+
+    /// Opens the mass spectrum and the XIC of a clicked heatmap region.
+    ///
+    /// The MS1 scan closest to the clicked retention time is shown in the mass spectrum plot, and the chromatogram switches to the XIC of the clicked m/z.
+    /// If no mass tolerance has been entered yet, `DEFAULT_MASS_TOLERANCE` is used.
+    ///
+    /// # Parameters
+    /// - `retention_time: f64`: The retention time of the clicked region.
+    /// - `mz: f64`: The m/z of the clicked region.
+    fn select_heatmap_region(&mut self, retention_time: f64, mz: f64) {
+        let Some(heatmap) = &self.heatmap else {
+            return;
+        };
+        if let Some(index) = heatmap.closest_scan(retention_time) {
+            info!("Found closest MS1 scan at index: {}", index);
+            self.show_mass_spectrum(index);
+        }
+
+        self.user_input.plot_type = PlotType::Xic;
+        self.user_input.mass = mz;
+        self.user_input.mass_input = format!("{:.4}", mz);
+        if self.user_input.mass_tolerance <= 0.0 {
+            self.user_input.mass_tolerance = DEFAULT_MASS_TOLERANCE;
+            self.user_input.mass_tolerance_input = DEFAULT_MASS_TOLERANCE.to_string();
+        }
+        self.state_changed = StateChange::Changed;
+    }
+
+    /// Adds the heatmap options to the provided `Ui`.
+    ///
+    /// This function renders buttons that re-bin the heatmap to the currently visible region, for a finer view after zooming, or reset it to the full run.
+    ///
+    /// # Parameters
+    ///
+    /// - `ui`: A mutable reference to the `egui::Ui` object, which is used to render the UI elements.
+    fn add_heatmap_options(&mut self, ui: &mut Ui) {
+        if ui
+            .button("Re-bin visible region")
+            .on_hover_text("Recompute the heatmap at full resolution for the visible region")
+            .clicked()
+        {
+            if let Some(bounds) = self.heatmap_bounds {
+                self.compute_heatmap(
+                    Some((*bounds.range_x().start(), *bounds.range_x().end())),
+                    Some((*bounds.range_y().start(), *bounds.range_y().end())),
+                );
+            }
+            ui.close_menu();
+        }
+        if ui.button("Reset to full run").clicked() {
+            self.compute_heatmap(None, None);
+            ui.close_menu();
+        }
+    }
+
+    /// Retrieves the mass spectrum at the specified index and records it as the selected spectrum.
+    ///
+    /// If the spectrum cannot be retrieved, the error is shown in the status bar and the displayed spectrum is kept.
+    ///
+    /// # Parameters
+    /// - `index: usize`: The index of the spectrum to be shown in the mass spectrum plot.
+    fn show_mass_spectrum(&mut self, index: usize) {
+        match self.parsed_ms_data.get_mass_spectrum_by_index(index) {
+            Ok(spectrum) => {
+                self.set_current_spectrum(spectrum);
+                self.selected_spectrum_index = Some(index);
+            }
+            Err(e) => self.report_error(format!("Failed to read the mass spectrum. {}", e)),
+        }
+    }
+
+    /// Returns the most recently retrieved mass spectrum, which is the last entry of `mass_spectra`.
+    fn current_spectrum(&self) -> Option<&parser::Spectrum> {
+        self.mass_spectra.last()
+    }
+
+    /// Shows a retrieved mass spectrum in the spectrum panel.
+    ///
+    /// The previously retrieved spectrum is replaced unless it is pinned.
+    ///
+    /// # Parameters
+    /// - `spectrum: parser::Spectrum`: The spectrum to be shown.
+    fn set_current_spectrum(&mut self, spectrum: parser::Spectrum) {
+        if !self.current_spectrum_pinned {
+            self.mass_spectra.pop();
+        }
+        // a spectrum read again, e.g. after reopening its file, may differ from the one displayed before
+        let key = SpectrumKey::new(&spectrum, false);
+        self.displayed_spectra
+            .retain(|displayed| displayed.key != key);
+        self.mass_spectra.push(spectrum);
+        self.current_spectrum_pinned = false;
+    }
+
+    /// Pins the most recently retrieved mass spectrum, so that it is kept when another spectrum is retrieved.
+    fn pin_current_spectrum(&mut self) {
+        if let Some(spectrum) = self.mass_spectra.last() {
+            info!("Pinning spectrum {}", spectrum.label);
+            self.current_spectrum_pinned = true;
+        }
+    }
+
+    /// Returns whether the spectrum at a position of `mass_spectra` is pinned.
+    fn is_pinned(&self, position: usize) -> bool {
+        position + 1 < self.mass_spectra.len() || self.current_spectrum_pinned
+    }
+
+    /// Plots the ion mobility data of the current file.
+    ///
+    /// This function renders two plots:
+    /// - The mobilogram of the m/z and mass tolerance entered in the XIC settings, computed lazily after the plot data has been processed.
+    /// - The ion mobility × m/z heatmap of the frame of the spectrum shown in the mass spectrum plot, computed whenever a different spectrum is selected.
+    ///
+    /// # Parameters
+    /// - `&mut self`: A mutable reference to the current instance of the struct that contains the `parsed_ms_data`, `user_input` and `selected_spectrum_index` fields.
+    /// - `ui: &mut egui::Ui`: A mutable reference to the current `egui::Ui` instance, which is used to render the plots.
+    fn plot_ion_mobility(&mut self, ui: &mut egui::Ui) {
+        if self.user_input.file_path.is_none() {
+            ui.label("No ion mobility data available");
+            return;
+        }
+
+        if self.user_input.mass > 0.0 && self.mobilogram.is_none() {
+            match self.parsed_ms_data.get_mobilogram(
+                self.user_input.mass,
+                self.user_input.polarity,
+                self.user_input.mass_tolerance,
+            ) {
+                Ok(mobilogram) => self.mobilogram = Some(mobilogram),
+                Err(e) => {
+                    self.report_error(format!("Failed to read the mobilogram. {}", e));
+                    // an empty mobilogram is not read again until the plot data changes
+                    self.mobilogram = Some(parser::Mobilogram::default());
+                }
+            }
+        }
+
+        ui.label(format!("Mobilogram of m/z = {:.4}", self.user_input.mass));
+        match &self.mobilogram {
+            Some(mobilogram) if !mobilogram.is_empty() => {
+                egui_plot::Plot::new("mobilogram")
+                    .width(ui.available_width() * 0.99)
+                    .height(ui.available_height() * 0.3)
+                    .x_axis_label("Ion mobility")
+                    .show(ui, |plot_ui| {
+                        plot_ui.line(
+                            Line::new(PlotPoints::from(mobilogram.points()))
+                                .width(self.user_input.line_width)
+                                .style(self.user_input.line_type.to_egui())
+                                .color(self.user_input.line_color.to_egui()),
+                        );
+                    });
+            }
+            _ => {
+                ui.label("No ion mobility data found. Enter an m/z in the XIC settings.");
+            }
+        }
+
+        if let Some(index) = self.selected_spectrum_index {
+            if self.mobility_heatmap_index != Some(index) {
+                self.mobility_heatmap_index = Some(index);
+                self.mobility_heatmap_texture = None;
+                match self.parsed_ms_data.get_mobility_heatmap(
+                    index,
+                    MOBILITY_BINS,
+                    HEATMAP_MZ_BINS,
+                ) {
+                    Ok(heatmap) => self.mobility_heatmap = Some(heatmap),
+                    Err(e) => {
+                        self.mobility_heatmap = None;
+                        self.report_error(format!("Failed to compute the mobility heatmap. {}", e));
+                    }
+                }
+            }
+        }
+
+        ui.label("Ion mobility × m/z of the selected frame");
+        let Some(heatmap) = &self.mobility_heatmap else {
+            ui.label("No frame with ion mobility data selected");
+            return;
+        };
+        let texture = self.mobility_heatmap_texture.get_or_insert_with(|| {
+            ui.ctx().load_texture(
+                "mobility_heatmap",
+                intensity_grid_image(
+                    heatmap.mobility_bins,
+                    heatmap.mz_bins,
+                    heatmap.max_intensity(),
+                    |mobility_bin, mz_bin| heatmap.intensity(mobility_bin, mz_bin),
+                ),
+                egui::TextureOptions::NEAREST,
+            )
+        });
+        let center = egui_plot::PlotPoint::new(
+            (heatmap.mobility_range.0 + heatmap.mobility_range.1) / 2.0,
+            (heatmap.mz_range.0 + heatmap.mz_range.1) / 2.0,
+        );
+        let size = egui::vec2(
+            (heatmap.mobility_range.1 - heatmap.mobility_range.0) as f32,
+            (heatmap.mz_range.1 - heatmap.mz_range.0) as f32,
+        );
+        egui_plot::Plot::new("mobility_heatmap")
+            .width(ui.available_width() * 0.99)
+            .height(ui.available_height() * 0.6)
+            .x_axis_label("Ion mobility")
+            .y_axis_label("m/z")
+            .show(ui, |plot_ui| {
+                plot_ui.image(egui_plot::PlotImage::new(texture.id(), center, size));
+            });
+    }
+
+    /// Updates the data selection panel in the user interface.
+    ///
+    /// This function creates a top panel in the UI that contains the following elements:
+    /// - A "File" button that allows the user to select a file to open.
+    /// - A "Display" menu button that allows the user to configure the display options.
+    /// - A "Library" menu button that allows the user to load spectral libraries and open the library search window.
+    /// - A light/dark mode toggle button that allows the user to switch between light and dark themes.
+    ///
+    /// When the "File" button is clicked, the function handles the file selection process, clears the existing plot data and parser data, and updates the user input accordingly.
+    ///
+    /// When the "Display" menu button is clicked, the function calls the `add_display_options` function to add the display options to the menu.
+    ///
+    /// When the light/dark mode toggle button is clicked, the function updates the visuals of the UI based on the user's selection.
+    ///
+    /// # Parameters
+    /// - `&mut self`: A mutable reference to the current instance of the struct that contains the `plot_data`, `parsed_ms_data`, `user_input`, and other relevant fields.
+    /// - `ctx: &Context`: A reference to the `egui::Context` instance, which is used to update the UI's visuals.
+    fn update_data_selection_panel(&mut self, ctx: &Context) {
+        egui::TopBottomPanel::top("data_selection_panel").show(ctx, |ui| {
             ui.horizontal(|ui| {
                 if ui
                     .button("File")
@@ -498,7 +1859,7 @@ impl MzViewerApp {
                     self.reset_state();
                     /*
                     // todo: we should completely clear and get a brand new self
-                    self.plot_data = None; // clears the plot_data if new file is opened
+                    self.plot_data.clear(); // clears the plot_data if new file is opened
                     self.parsed_ms_data = parser::MzData::default(); // clears the parser::MzData struct if new file is opened
                     self.user_input.file_path = None; // clears the file_path if new file is opened
                     */
@@ -513,6 +1874,16 @@ impl MzViewerApp {
                     info!("Display options added.");
                 });
 
+                ui.menu_button("Library", |ui| {
+                    debug!("Library menu button clicked.");
+                    self.add_library_options(ui);
+                });
+
+                ui.menu_button("Export", |ui| {
+                    debug!("Export menu button clicked.");
+                    self.add_export_options(ui);
+                });
+
                 if let Some(new_visuals) = ui
                     .style()
                     .visuals
@@ -527,10 +1898,246 @@ impl MzViewerApp {
         });
     }
 
+    /// Adds the signal-to-noise options to the provided `Ui`.
+    ///
+    /// This function renders the noise measure, the choice between an automatic and a chosen noise region with its window or retention time range,
+    /// and the apex, signal, noise and signal-to-noise ratio of the peak selected with Shift+click.
+    ///
+    /// # Parameters
+    ///
+    /// - `ui`: A mutable reference to the `egui::Ui` object, which is used to render the UI elements.
+    fn add_signal_to_noise_options(&mut self, ui: &mut Ui) {
+        let mut changed = false;
+        ui.label("Shift+click a peak of the chromatogram to compute its signal-to-noise ratio.");
+        ui.horizontal(|ui| {
+            for (method, hover_text) in [
+                (
+                    NoiseMethod::PeakToPeak,
+                    "Highest minus lowest point of the noise region, S/N = 2H/h",
+                ),
+                (
+                    NoiseMethod::Rms,
+                    "Root mean square deviation from the mean of the noise region",
+                ),
+                (
+                    NoiseMethod::Mad,
+                    "Scaled median absolute deviation, robust to spikes in the noise region",
+                ),
+            ] {
+                changed |= ui
+                    .radio_value(
+                        &mut self.user_input.signal_to_noise.method,
+                        method,
+                        method.name(),
+                    )
+                    .on_hover_text(hover_text)
+                    .changed();
+            }
+        });
+
+        let mut automatic = !self.user_input.manual_noise_region;
+        changed |= ui
+            .checkbox(&mut automatic, "Automatic noise region")
+            .on_hover_text("Use the window of median noise of the trace away from the peak")
+            .changed();
+        self.user_input.manual_noise_region = !automatic;
+        if automatic {
+            self.user_input.signal_to_noise.noise_region = None;
+            changed |= ui
+                .add(
+                    egui::Slider::new(&mut self.user_input.signal_to_noise.noise_window, 5..=200)
+                        .logarithmic(true)
+                        .text("Points"),
+                )
+                .changed();
+        } else {
+            ui.horizontal(|ui| {
+                ui.label("Noise from RT");
+                let min_response = ui.add(
+                    egui::TextEdit::singleline(&mut self.user_input.noise_rt_min_input)
+                        .hint_text("Min")
+                        .desired_width(60.0),
+                );
+                ui.label("to");
+                let max_response = ui.add(
+                    egui::TextEdit::singleline(&mut self.user_input.noise_rt_max_input)
+                        .hint_text("Max")
+                        .desired_width(60.0),
+                );
+                if min_response.lost_focus() || max_response.lost_focus() {
+                    changed = true;
+                }
+            });
+            let parsed_region = match (
+                self.user_input.noise_rt_min_input.parse::<f64>(),
+                self.user_input.noise_rt_max_input.parse::<f64>(),
+            ) {
+                (Ok(min), Ok(max)) => Some((min, max)),
+                _ => None,
+            };
+            // a chosen region starts from the automatic one, an incomplete range keeps the previous region and an empty range selects none
+            let automatic_region =
+                self.signal_to_noise
+                    .zip(self.plot_data.first())
+                    .map(|(result, chromatogram)| {
+                        let (first, last) = result.noise_region;
+                        (
+                            chromatogram.retention_time[first],
+                            chromatogram.retention_time[last],
+                        )
+                    });
+            let region = parsed_region
+                .or(self.user_input.signal_to_noise.noise_region)
+                .or(automatic_region);
+            if parsed_region.is_none() {
+                if let Some((min, max)) = region {
+                    self.user_input.noise_rt_min_input = format!("{:.3}", min);
+                    self.user_input.noise_rt_max_input = format!("{:.3}", max);
+                }
+            }
+            self.user_input.signal_to_noise.noise_region = region.filter(|(min, max)| min != max);
+        }
+        if changed {
+            info!(
+                "Signal-to-noise options changed to {}",
+                self.user_input.signal_to_noise
+            );
+            self.update_signal_to_noise();
+        }
+
+        ui.separator();
+        let (Some(result), Some(chromatogram)) = (&self.signal_to_noise, self.plot_data.first())
+        else {
+            if self.user_input.peak_retention_time.is_some()
+                && self.user_input.manual_noise_region
+                && self.user_input.signal_to_noise.noise_region.is_none()
+            {
+                ui.label("No noise region selected.");
+            } else {
+                ui.label("No peak selected.");
+            }
+            return;
+        };
+        let unit = chromatogram.time_unit.symbol();
+        let (first, last) = result.noise_region;
+        egui::Grid::new("signal_to_noise")
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("Apex RT");
+                ui.label(format!(
+                    "{:.3} {}",
+                    chromatogram.retention_time[result.apex], unit
+                ));
+                ui.end_row();
+                ui.label("Signal");
+                ui.label(format!("{:.4e}", result.signal));
+                ui.end_row();
+                ui.label(format!(
+                    "Noise ({})",
+                    self.user_input.signal_to_noise.method.name()
+                ));
+                ui.label(format!("{:.4e}", result.noise));
+                ui.end_row();
+                ui.label("Noise region");
+                ui.label(format!(
+                    "{:.3}-{:.3} {}",
+                    chromatogram.retention_time[first], chromatogram.retention_time[last], unit
+                ));
+                ui.end_row();
+                ui.label("S/N");
+                ui.strong(format!("{:.1}", result.ratio));
+                ui.end_row();
+            });
+        if ui.button("Clear").clicked() {
+            self.user_input.peak_retention_time = None;
+            self.signal_to_noise = None;
+        }
+    }
+
+    /// Adds the baseline options to the provided `Ui`.
+    ///
+    /// This function renders toggles for the baseline overlay and the baseline corrected trace, the estimation method,
+    /// and the parameters of the selected method: the smoothness and asymmetry of asymmetric least squares, or the window of the rolling minimum and SNIP.
+    ///
+    /// # Parameters
+    ///
+    /// - `ui`: A mutable reference to the `egui::Ui` object, which is used to render the UI elements.
+    ///
+    /// # Returns
+    ///
+    /// - `bool`: Whether any option changed, so the chromatogram has to be processed again.
+    fn add_baseline_options(&mut self, ui: &mut Ui) -> bool {
+        let user_input = &mut self.user_input;
+        let mut changed = ui
+            .checkbox(&mut user_input.show_baseline, "Show baseline")
+            .on_hover_text("Draw the estimated baseline on top of the chromatogram")
+            .changed();
+        changed |= ui
+            .checkbox(&mut user_input.subtract_baseline, "Subtract baseline")
+            .on_hover_text("Plot the chromatogram with the estimated baseline subtracted")
+            .changed();
+        ui.separator();
+
+        let parameters = &mut user_input.baseline;
+        for (method, hover_text) in [
+            (
+                BaselineMethod::AsymmetricLeastSquares,
+                "Smooth curve pulled below the peaks, suited to gradient drift",
+            ),
+            (
+                BaselineMethod::RollingMinimum,
+                "Opening of the trace with a moving window, fast and robust",
+            ),
+            (
+                BaselineMethod::Snip,
+                "Iterative clipping of peaks narrower than the window",
+            ),
+        ] {
+            changed |= ui
+                .radio_value(&mut parameters.method, method, method.name())
+                .on_hover_text(hover_text)
+                .changed();
+        }
+        match parameters.method {
+            BaselineMethod::AsymmetricLeastSquares => {
+                let mut exponent = parameters.smoothness.log10();
+                if ui
+                    .add(egui::Slider::new(&mut exponent, 2.0..=9.0).text("Smoothness (log10 λ)"))
+                    .on_hover_text("Larger values give a stiffer baseline")
+                    .changed()
+                {
+                    parameters.smoothness = 10_f64.powf(exponent);
+                    changed = true;
+                }
+                changed |= ui
+                    .add(
+                        egui::Slider::new(&mut parameters.asymmetry, 0.001..=0.1)
+                            .logarithmic(true)
+                            .text("Asymmetry"),
+                    )
+                    .on_hover_text("The weight of points above the baseline, smaller values keep the baseline further below the peaks")
+                    .changed();
+            }
+            BaselineMethod::RollingMinimum | BaselineMethod::Snip => {
+                changed |= ui
+                    .add(
+                        egui::Slider::new(&mut parameters.half_window, 1..=200)
+                            .logarithmic(true)
+                            .text("Points per side"),
+                    )
+                    .on_hover_text("Should be larger than the half width of the widest peak")
+                    .changed();
+            }
+        }
+        changed
+    }
+
     /// Adds the display options to the provided `egui::Ui` instance.
     ///
     /// This function creates a series of menu buttons that allow the user to adjust the following display options:
-    /// - Smoothing level: Adjusts the level of moving average smoothing applied to the plot data.
+    /// - Smoothing: Selects the moving average, Savitzky–Golay or Gaussian filter, its window and the Savitzky–Golay polynomial order.
+    /// - Baseline: Shows the estimated baseline or plots the baseline corrected trace, and selects the estimation method and its parameters.
     /// - Line width: Adjusts the width of the lines in the plot.
     /// - Line color: Allows the user to select the color of the lines in the plot.
     /// - Line style: Allows the user to select the style of the lines in the plot.
@@ -542,13 +2149,53 @@ impl MzViewerApp {
     /// - `ui: &mut Ui`: A mutable reference to the `egui::Ui` instance where the display options will be added.
     fn add_display_options(&mut self, ui: &mut Ui) {
         ui.menu_button("Smoothing", |ui| {
-            let slider = egui::Slider::new(&mut self.user_input.smoothing, 0..=11);
-            let response = ui.add(slider);
-            if response.changed() {
+            let smoothing = &mut self.user_input.smoothing;
+            let mut changed = false;
+            for (method, hover_text) in [
+                (
+                    SmoothingMethod::MovingAverage,
+                    "Unweighted mean of the window",
+                ),
+                (
+                    SmoothingMethod::SavitzkyGolay,
+                    "Polynomial fitted to the window, preserves the height and width of narrow peaks",
+                ),
+                (
+                    SmoothingMethod::Gaussian,
+                    "Mean weighted by a Gaussian kernel",
+                ),
+            ] {
+                changed |= ui
+                    .radio_value(&mut smoothing.method, method, method.name())
+                    .on_hover_text(hover_text)
+                    .changed();
+            }
+            ui.separator();
+            changed |= ui
+                .add(egui::Slider::new(&mut smoothing.half_window, 0..=11).text("Points per side"))
+                .on_hover_text("The number of points on either side of every point included in the window, 0 for no smoothing")
+                .changed();
+            ui.add_enabled_ui(smoothing.method == SmoothingMethod::SavitzkyGolay, |ui| {
+                changed |= ui
+                    .add(
+                        egui::Slider::new(&mut smoothing.polynomial_order, 1..=6)
+                            .text("Polynomial order"),
+                    )
+                    .on_hover_text("The order of the polynomial fitted by the Savitzky–Golay filter")
+                    .changed();
+            });
+            if changed {
+                self.state_changed = StateChange::Changed;
+                info!("Smoothing changed to {}", self.user_input.smoothing);
+            }
+        });
+
+        ui.menu_button("Baseline", |ui| {
+            debug!("Baseline menu button clicked.");
+            if self.add_baseline_options(ui) {
                 self.state_changed = StateChange::Changed;
-                info!("Smoothing level changed to {}", self.user_input.smoothing);
+                info!("Baseline changed to {}", self.user_input.baseline);
             }
-            response.on_hover_text("Adjust the level of moving average smoothing");
         });
 
         ui.menu_button("Line width", |ui| {
@@ -637,6 +2284,16 @@ impl MzViewerApp {
         }
     }
 
+    /// Opens a file passed on the command line, e.g. by a file association or "Open with".
+    ///
+    /// # Parameters
+    ///
+    /// - `path`: A reference to the path of the file.
+    pub fn open_file(&mut self, path: &Path) {
+        info!("Opening file from the command line: {:?}", path);
+        self.update_file_path_and_validity(path);
+    }
+
     /// Updates the file path and validity based on the selected file.
     ///
     /// This function checks the file format and updates the corresponding fields in the struct. If the file format is valid, it opens the file and updates the `parsed_ms_data` field. If the file format is invalid, it sets the `invalid_file` field to `FileValidity::Invalid`.
@@ -647,8 +2304,8 @@ impl MzViewerApp {
     ///
     /// # Errors
     ///
-    /// This function may encounter errors when attempting to open the selected file. These errors will be logged as warning messages.
-    fn update_file_path_and_validity(&mut self, path: &PathBuf) {
+    /// This function may encounter errors when attempting to open the selected file. These errors are shown in the status bar.
+    fn update_file_path_and_validity(&mut self, path: &Path) {
         let file_path_str = path.display().to_string();
         info!("Updating file path and validity for: {}", file_path_str);
 
@@ -656,13 +2313,22 @@ impl MzViewerApp {
             info!("File format is valid.");
             self.invalid_file = FileValidity::Valid;
             self.user_input.file_path = Some(file_path_str.clone());
-            self.parsed_ms_data = parser::MzData::default();
+            self.reset_state();
             match self.parsed_ms_data.open_msfile(path) {
-                Ok(_) => info!("File opened successfully."),
-                Err(e) => warn!("Failed to open file: {}", e),
+                Ok(_) => {
+                    info!("File opened successfully.");
+                    self.status = Some(StatusMessage {
+                        level: StatusLevel::Info,
+                        text: format!("Opened {}", file_path_str),
+                    });
+                }
+                Err(e) => self.report_error(format!("Failed to open the file. {}", e)),
             }
         } else {
-            warn!("Invalid file format.");
+            self.report_warning(format!(
+                "{} is not an {} file. Convert it to {}, e.g. with ProteoWizard msconvert.",
+                file_path_str, FILE_FORMAT, FILE_FORMAT
+            ));
             self.invalid_file = FileValidity::Invalid;
         }
     }
@@ -710,7 +2376,8 @@ impl MzViewerApp {
                             .clicked()
                         {
                             info!("File closed: {}", file_path);
-                            self.plot_data = None;
+                            self.chromatograms.clear();
+                            self.plot_data.clear();
                             self.user_input.file_path = None;
                             self.checkbox_bool = false;
                         }
@@ -724,6 +2391,69 @@ impl MzViewerApp {
         });
     }
 
+    /// Logs an error and shows it in the status bar.
+    ///
+    /// # Parameters
+    ///
+    /// - `message`: The message, which should tell the user how to resolve the problem.
+    fn report_error(&mut self, message: String) {
+        error!("{}", message);
+        self.status = Some(StatusMessage {
+            level: StatusLevel::Error,
+            text: message,
+        });
+    }
+
+    /// Logs a warning and shows it in the status bar, unless an error is already shown.
+    ///
+    /// # Parameters
+    ///
+    /// - `message`: The message, which should tell the user how to resolve the problem.
+    fn report_warning(&mut self, message: String) {
+        warn!("{}", message);
+        if self
+            .status
+            .as_ref()
+            .map_or(true, |status| status.level != StatusLevel::Error)
+        {
+            self.status = Some(StatusMessage {
+                level: StatusLevel::Warning,
+                text: message,
+            });
+        }
+    }
+
+    /// Updates the status bar at the bottom of the user interface.
+    ///
+    /// The status bar shows the last reported message in the color of its severity, with a button to dismiss it.
+    /// It must be added before the central panel, so that the central panel fills the remaining space.
+    ///
+    /// # Parameters
+    ///
+    /// - `ctx`: A reference to the `egui::Context` object, which is used to render the user interface.
+    fn update_status_bar(&mut self, ctx: &Context) {
+        let Some(status) = &self.status else {
+            return;
+        };
+        let mut dismissed = false;
+        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let icon = match status.level {
+                    StatusLevel::Info => "✔",
+                    StatusLevel::Warning => "⚠",
+                    StatusLevel::Error => "❌",
+                };
+                ui.colored_label(status.level.color(), format!("{} {}", icon, status.text));
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    dismissed = ui.small_button("Dismiss").clicked();
+                });
+            });
+        });
+        if dismissed {
+            self.status = None;
+        }
+    }
+
     /// Updates the central panel of the user interface.
     ///
     /// This function is responsible for rendering the main content area of the application, which includes the chromatogram and mass spectrum plots.
@@ -741,8 +2471,19 @@ impl MzViewerApp {
     ///    - Adds a context menu to the chromatogram plot, which allows the user to access the plot properties.
     ///    - Calls the `add_plot_properties()` function to add the plot properties to the context menu.
     /// 4. Adds some vertical space between the chromatogram and mass spectrum plots.
-    /// 5. Renders a `CollapsingHeader` for the mass spectrum plot, which can be expanded or collapsed by the user.
+    /// 5. Renders a collapsed `CollapsingHeader` for the retention time × m/z heatmap of the MS1 data.
+    ///    - Calls the `plot_heatmap()` function to generate the heatmap.
+    ///    - Adds a context menu that allows the user to re-bin the visible region or reset the heatmap to the full run.
+    /// 6. Renders a collapsed `CollapsingHeader` for the ion mobility data, calling the `plot_ion_mobility()` function.
+    /// 7. Renders a collapsed `CollapsingHeader` for the precursor coverage map of the MS2 scans.
+    ///    - Calls the `plot_precursor_map()` function to generate the scatter plot.
+    ///    - Adds a context menu that allows the user to color the precursors by charge or intensity.
+    /// 8. Renders a `CollapsingHeader` for the mass spectrum plot, which can be expanded or collapsed by the user.
     ///    - Calls the `plot_mass_spectrum()` function to generate the mass spectrum plot.
+    ///    - Adds a context menu that allows the user to pin the displayed spectrum, unpin spectra and overlay or stack them.
+    /// 9. Renders a collapsed `CollapsingHeader` for the peptide fragment annotation, calling the `show_peptide_annotation()` function.
+    /// 10. Renders a collapsed `CollapsingHeader` for the mirror plot, calling the `plot_spectrum_comparison()` function.
+    /// 11. Renders a collapsed `CollapsingHeader` for the charge deconvolution, calling the `plot_deconvolution()` function.
     ///
     /// # Errors
     ///
@@ -762,27 +2503,99 @@ impl MzViewerApp {
                             debug!("Adding plot properties.");
                             self.add_plot_properties(ui);
                             ui.separator();
+                            ui.heading("Signal-to-Noise");
+                            ui.separator();
+                            self.add_signal_to_noise_options(ui);
                         });
                         info!("Chromatogram plotted successfully.");
                     });
 
                 ui.add_space(5.0); // Add some space between the plots
 
+                egui::CollapsingHeader::new("Heatmap")
+                    .default_open(false)
+                    .show(ui, |ui| {
+                        debug!("Plotting heatmap.");
+                        let heatmap = self.plot_heatmap(ui);
+                        heatmap.context_menu(|ui| {
+                            ui.heading("Heatmap Properties");
+                            ui.separator();
+                            self.add_heatmap_options(ui);
+                        });
+                    });
+
+                ui.add_space(5.0);
+
+                egui::CollapsingHeader::new("Ion Mobility")
+                    .default_open(false)
+                    .show(ui, |ui| {
+                        debug!("Plotting ion mobility data.");
+                        self.plot_ion_mobility(ui);
+                    });
+
+                ui.add_space(5.0);
+
+                egui::CollapsingHeader::new("Precursor Map")
+                    .default_open(false)
+                    .show(ui, |ui| {
+                        debug!("Plotting precursor coverage map.");
+                        let precursor_map = self.plot_precursor_map(ui);
+                        precursor_map.context_menu(|ui| {
+                            ui.heading("Precursor Map Properties");
+                            ui.separator();
+                            self.add_precursor_map_options(ui);
+                        });
+                    });
+
+                ui.add_space(5.0);
+
                 egui::CollapsingHeader::new("Mass Spectrum")
                     .default_open(true)
                     .show(ui, |ui| {
                         debug!("Plotting mass spectrum.");
-                        self.plot_mass_spectrum(ui);
+                        let mass_spectrum = self.plot_mass_spectrum(ui);
+                        mass_spectrum.context_menu(|ui| {
+                            ui.heading("Mass Spectrum Properties");
+                            ui.separator();
+                            self.add_mass_spectrum_options(ui);
+                        });
                         info!("Mass spectrum plotted successfully.");
                     });
-            });
-        });
-        info!("Central panel updated successfully.");
-    }
 
-    /// Adds the plot properties UI elements to the provided `Ui`.
-    ///
-    /// This function is responsible for rendering the UI elements that allow the user to customize the properties of the plots, such as the polarity and plot type.
+                ui.add_space(5.0);
+
+                egui::CollapsingHeader::new("Peptide Annotation")
+                    .default_open(false)
+                    .show(ui, |ui| {
+                        debug!("Showing peptide annotation.");
+                        self.show_peptide_annotation(ui);
+                    });
+
+                ui.add_space(5.0);
+
+                egui::CollapsingHeader::new("Mirror Plot")
+                    .default_open(false)
+                    .show(ui, |ui| {
+                        debug!("Plotting spectrum comparison.");
+                        self.plot_spectrum_comparison(ui);
+                    });
+
+                ui.add_space(5.0);
+
+                egui::CollapsingHeader::new("Deconvolution")
+                    .default_open(false)
+                    .show(ui, |ui| {
+                        debug!("Plotting deconvolved spectrum.");
+                        self.plot_deconvolution(ui);
+                    });
+            });
+        });
+        info!("Central panel updated successfully.");
+    }
+
+    /// Adds the plot properties UI elements to the provided `Ui`.
+    ///
+    /// This function is responsible for rendering the UI elements that allow the user to customize the properties of the plots, such as the polarity and plot type.
     ///
     /// # Parameters
     ///
@@ -821,6 +2634,9 @@ impl MzViewerApp {
                 .clicked()
             {
                 self.user_input.polarity = ScanPolarity::Positive;
+                self.precursors = None;
+                self.heatmap = None;
+                self.heatmap_failed = false;
                 self.state_changed = StateChange::Changed;
             }
             if ui
@@ -832,6 +2648,9 @@ impl MzViewerApp {
                 .clicked()
             {
                 self.user_input.polarity = ScanPolarity::Negative;
+                self.precursors = None;
+                self.heatmap = None;
+                self.heatmap_failed = false;
                 self.state_changed = StateChange::Changed;
             }
         });
@@ -839,7 +2658,7 @@ impl MzViewerApp {
 
     /// Adds the plot type options UI elements to the provided `Ui`.
     ///
-    /// This function renders the UI elements that allow the user to select the type of plot to display, such as TIC, Base Peak, or XIC. It updates the `user_input.plot_type` and related fields based on the user's selection.
+    /// This function renders the UI elements that allow the user to select the type of plot to display, such as TIC, Base Peak, XIC or DIA. It updates the `user_input.plot_type` and related fields based on the user's selection.
     ///
     /// # Parameters
     ///
@@ -868,12 +2687,20 @@ impl MzViewerApp {
                 self.user_input.plot_type = PlotType::Xic;
                 self.options_window_open = true;
             }
+            if ui
+                .radio_value(&mut self.user_input.plot_type, PlotType::Dia, "DIA")
+                .clicked()
+            {
+                self.user_input.plot_type = PlotType::Dia;
+                self.options_window_open = true;
+            }
         });
     }
 
     /// Updates the XIC (Extracted Ion Chromatogram) settings window.
     ///
     /// This function is responsible for rendering the UI elements that allow the user to configure the settings for the XIC plot, such as the m/z value and mass tolerance.
+    /// For DIA plots the same window is titled "DIA settings", the m/z value is used as the precursor m/z and an additional field collects the fragment m/z values.
     ///
     /// # Parameters
     ///
@@ -891,16 +2718,34 @@ impl MzViewerApp {
     /// 6. It adds a `TextEdit` widget for the user to enter the mass tolerance value in ppm.
     ///    - If the user loses focus on the mass tolerance input field, the function updates the `user_input.mass_tolerance` field with the entered value (or the default value if the input is invalid).
     ///    - It also sets the `state_changed` field to `StateChange::Changed`.
+    /// 7. For XIC plots, it adds two `TextEdit` widgets for an optional ion mobility range.
+    ///    - If the user loses focus on either field, the function updates the `user_input.mobility_range` field, which is `None` unless both bounds are valid and ordered.
+    ///    - It also sets the `state_changed` field to `StateChange::Changed`.
+    /// 8. For DIA plots, it adds a `TextEdit` widget for the comma separated fragment m/z values.
+    ///    - If the user loses focus on the fragment input field, the function updates the `user_input.fragment_mz` field with the parsed values.
+    ///    - It also sets the `state_changed` field to `StateChange::Changed`.
     ///
     /// # Errors
     ///
     /// This function does not return any errors. It handles the rendering of the XIC settings window and the updating of the corresponding fields in the struct.
     fn update_xic_settings_window(&mut self, ctx: &egui::Context) {
         if self.options_window_open {
-            egui::Window::new("XIC settings")
+            let is_dia = self.user_input.plot_type == PlotType::Dia;
+            let title = if is_dia {
+                "DIA settings"
+            } else {
+                "XIC settings"
+            };
+            egui::Window::new(title)
                 .open(&mut self.options_window_open)
                 .show(ctx, |ui| {
-                    ui.label("Enter m/z and mass tolerance values in ppm:");
+                    if is_dia {
+                        ui.label(
+                            "Enter precursor m/z, mass tolerance in ppm and fragment m/z values:",
+                        );
+                    } else {
+                        ui.label("Enter m/z and mass tolerance values in ppm:");
+                    }
                     if ui
                         .add(
                             egui::TextEdit::singleline(&mut self.user_input.mass_input)
@@ -929,10 +2774,2455 @@ impl MzViewerApp {
                             .unwrap_or(self.user_input.mass_tolerance);
                         self.state_changed = StateChange::Changed
                     };
+                    if !is_dia {
+                        ui.label("Optional ion mobility range:");
+                        ui.horizontal(|ui| {
+                            let min_response = ui.add(
+                                egui::TextEdit::singleline(&mut self.user_input.mobility_min_input)
+                                    .hint_text("Min")
+                                    .desired_width(80.0),
+                            );
+                            let max_response = ui.add(
+                                egui::TextEdit::singleline(&mut self.user_input.mobility_max_input)
+                                    .hint_text("Max")
+                                    .desired_width(80.0),
+                            );
+                            if min_response.lost_focus() || max_response.lost_focus() {
+                                self.user_input.mobility_range = match (
+                                    self.user_input.mobility_min_input.parse::<f64>(),
+                                    self.user_input.mobility_max_input.parse::<f64>(),
+                                ) {
+                                    (Ok(min), Ok(max)) if min < max => Some((min, max)),
+                                    _ => None,
+                                };
+                                info!(
+                                    "Ion mobility range set to {:?}",
+                                    self.user_input.mobility_range
+                                );
+                                self.state_changed = StateChange::Changed;
+                            }
+                        });
+                    }
+                    if is_dia
+                        && ui
+                            .add(
+                                egui::TextEdit::singleline(&mut self.user_input.fragment_mz_input)
+                                    .hint_text("Enter fragment m/z values, separated by commas"),
+                            )
+                            .lost_focus()
+                    {
+                        self.user_input.fragment_mz =
+                            parse_mz_list(&self.user_input.fragment_mz_input);
+                        info!(
+                            "Fragment m/z values set to {:?}",
+                            self.user_input.fragment_mz
+                        );
+                        self.state_changed = StateChange::Changed
+                    };
                 });
         }
     }
-}
+
+    /// Adds the mass spectrum options to the provided `Ui`.
+    ///
+    /// This function renders a button that pins the displayed spectrum, radio buttons to overlay or stack the spectra, the intensity scale, centroiding and filter options, sliders for the number and spacing of peak labels, the isotope envelope options, and the list of pinned spectra with a button to unpin each of them.
+    ///
+    /// # Parameters
+    ///
+    /// - `ui`: A mutable reference to the `egui::Ui` object, which is used to render the UI elements.
+    fn add_mass_spectrum_options(&mut self, ui: &mut Ui) {
+        if ui
+            .add_enabled(
+                self.current_spectrum().is_some() && !self.current_spectrum_pinned,
+                egui::Button::new("Pin displayed spectrum"),
+            )
+            .on_hover_text("Keep the spectrum when another one is opened")
+            .clicked()
+        {
+            self.pin_current_spectrum();
+            ui.close_menu();
+        }
+
+        ui.label("Layout");
+        ui.horizontal(|ui| {
+            ui.radio_value(
+                &mut self.user_input.spectrum_layout,
+                SpectrumLayout::Overlay,
+                "Overlay",
+            );
+            ui.radio_value(
+                &mut self.user_input.spectrum_layout,
+                SpectrumLayout::Stacked,
+                "Stacked",
+            );
+        });
+
+        ui.menu_button("Intensity scale", |ui| {
+            self.add_intensity_scale_options(ui);
+        });
+
+        ui.menu_button("Centroiding", |ui| {
+            ui.checkbox(
+                &mut self.user_input.centroid_profile,
+                "Centroid profile spectra",
+            );
+            let parameters = &mut self.user_input.centroid_parameters;
+            ui.add(
+                egui::Slider::new(&mut parameters.relative_threshold, 0.0..=50.0)
+                    .text("Threshold (% of base peak)"),
+            );
+            ui.add(egui::Slider::new(&mut parameters.min_points, 1..=20).text("Minimum points"))
+                .on_hover_text("The minimum number of profile points of a peak");
+        });
+
+        ui.menu_button("Filters", |ui| {
+            self.add_spectrum_filter_options(ui);
+        });
+
+        ui.menu_button("Peak labels", |ui| {
+            ui.add(egui::Slider::new(&mut self.user_input.peak_label_count, 0..=50).text("Peaks"))
+                .on_hover_text("The number of most intense visible peaks to label");
+            ui.add(
+                egui::Slider::new(&mut self.user_input.peak_label_spacing, 0.0..=200.0)
+                    .text("Spacing"),
+            )
+            .on_hover_text("The minimum distance between two labels, in points");
+        });
+
+        ui.menu_button("Isotope envelopes", |ui| {
+            ui.checkbox(&mut self.user_input.show_envelopes, "Label charge states")
+                .on_hover_text("Label isotope envelopes with their charge and monoisotopic m/z");
+            let parameters = &mut self.user_input.envelope_parameters;
+            ui.add(
+                egui::Slider::new(&mut parameters.tolerance, 1.0..=50.0).text("Tolerance (ppm)"),
+            );
+            ui.add(egui::Slider::new(&mut parameters.max_charge, 1..=30).text("Maximum charge"));
+            ui.add(egui::Slider::new(&mut parameters.min_peaks, 2..=10).text("Minimum peaks"))
+                .on_hover_text("The minimum number of isotopic peaks of an envelope");
+        });
+
+        ui.separator();
+        let mut unpinned = None;
+        for (i, spectrum) in self.mass_spectra.iter().enumerate() {
+            if self.is_pinned(i) {
+                ui.horizontal(|ui| {
+                    ui.colored_label(plotting_parameters::trace_color(i), &spectrum.label);
+                    if ui.small_button("Unpin").clicked() {
+                        unpinned = Some(i);
+                    }
+                });
+            }
+        }
+        if let Some(i) = unpinned {
+            info!("Unpinning spectrum {}", self.mass_spectra[i].label);
+            // the displayed spectrum stays visible until another one is opened
+            if i + 1 == self.mass_spectra.len() {
+                self.current_spectrum_pinned = false;
+            } else {
+                self.mass_spectra.remove(i);
+            }
+        }
+    }
+
+    /// Selects a clicked peak of the displayed spectrum and opens the elemental composition window with its candidate formulas.
+    ///
+    /// The sign of the ion charge follows the selected polarity.
+    ///
+    /// # Parameters
+    /// - `mz: f64`: The m/z of the clicked peak.
+    fn open_composition_window(&mut self, mz: f64) {
+        info!("Peak clicked at m/z {:.4}", mz);
+        let charge = self.user_input.composition.charge.abs();
+        self.user_input.composition.charge = match self.user_input.polarity {
+            ScanPolarity::Negative => -charge,
+            _ => charge,
+        };
+        self.composition_mz = Some(mz);
+        self.composition_window_open = true;
+        self.generate_compositions();
+    }
+
+    /// Starts generating the candidate formulas of the peak at `composition_mz`.
+    ///
+    /// Profile spectra are centroided with `user_input.centroid_parameters` first, and the selected m/z is moved to the closest centroid.
+    /// The isotope pattern of every candidate is compared with the centroid peaks of the displayed spectrum.
+    /// The enumeration can take seconds for large m/z values and element ranges, so it runs on a worker thread whose result is
+    /// received by `receive_compositions`. Generating again cancels a running enumeration.
+    fn generate_compositions(&mut self) {
+        self.composition_candidates.clear();
+        self.cancel_compositions();
+        let (Some(mz), Some(spectrum)) = (self.composition_mz, self.current_spectrum()) else {
+            return;
+        };
+        let (peaks, intensities) = if spectrum.profile {
+            spectrum_processing::centroid(
+                &spectrum.mz,
+                &spectrum.intensity,
+                &self.user_input.centroid_parameters,
+            )
+        } else {
+            (spectrum.mz.clone(), spectrum.intensity.clone())
+        };
+        let mz = if spectrum.profile {
+            peaks
+                .iter()
+                .copied()
+                .min_by(|a, b| {
+                    (a - mz)
+                        .abs()
+                        .partial_cmp(&(b - mz).abs())
+                        .unwrap_or(Ordering::Equal)
+                })
+                .unwrap_or(mz)
+        } else {
+            mz
+        };
+        self.composition_mz = Some(mz);
+        let parameters = self.user_input.composition;
+        let (sender, receiver) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let worker_cancel = Arc::clone(&cancel);
+        thread::spawn(move || {
+            let candidates =
+                composition::generate(mz, &peaks, &intensities, &parameters, &worker_cancel);
+            // the receiver is gone if the generation was cancelled
+            let _ = sender.send((mz, candidates));
+        });
+        self.composition_receiver = Some(receiver);
+        self.composition_cancel = Some(cancel);
+    }
+
+    /// Stops the running formula enumeration, if any, and discards its result.
+    fn cancel_compositions(&mut self) {
+        if let Some(cancel) = self.composition_cancel.take() {
+            cancel.store(true, atomic::Ordering::Relaxed);
+        }
+        self.composition_receiver = None;
+    }
+
+    /// Stores the candidate formulas generated by the worker thread started in `generate_compositions`.
+    ///
+    /// While the worker thread is running, a repaint is requested so that the result is shown as soon as it arrives.
+    ///
+    /// # Parameters
+    /// - `ctx: &egui::Context`: A reference to the `egui::Context` object, which is used to request a repaint.
+    fn receive_compositions(&mut self, ctx: &egui::Context) {
+        let Some(receiver) = &self.composition_receiver else {
+            return;
+        };
+        match receiver.try_recv() {
+            Ok((mz, candidates)) => {
+                info!(
+                    "Found {} candidate formulas for m/z {:.4}",
+                    candidates.len(),
+                    mz
+                );
+                self.composition_candidates = candidates;
+                self.composition_receiver = None;
+                self.composition_cancel = None;
+            }
+            Err(mpsc::TryRecvError::Empty) => ctx.request_repaint_after(Duration::from_millis(100)),
+            Err(mpsc::TryRecvError::Disconnected) => {
+                error!("The generation of candidate formulas stopped without a result");
+                self.composition_receiver = None;
+                self.composition_cancel = None;
+            }
+        }
+    }
+
+    /// Updates the elemental composition window.
+    ///
+    /// This function renders the tolerance, ion charge, RDBE range, nitrogen rule and element ranges of the composition generator, a button to
+    /// generate the candidates again with the changed constraints, and the ranked candidate formulas with their m/z, mass error, RDBE and isotope pattern fit.
+    ///
+    /// # Parameters
+    /// - `ctx: &egui::Context`: A reference to the `egui::Context` object, which is used to render the window.
+    fn update_composition_window(&mut self, ctx: &egui::Context) {
+        self.receive_compositions(ctx);
+        if !self.composition_window_open {
+            return;
+        }
+        let mut open = self.composition_window_open;
+        egui::Window::new("Elemental composition")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                let Some(mz) = self.composition_mz else {
+                    ui.label("Click a peak of the mass spectrum to generate its formulas");
+                    return;
+                };
+                ui.heading(format!("m/z {:.4}", mz));
+
+                let parameters = &mut self.user_input.composition;
+                egui::Grid::new("composition_settings")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Tolerance (ppm)");
+                        ui.add(
+                            egui::DragValue::new(&mut parameters.tolerance)
+                                .speed(0.1)
+                                .clamp_range(0.1..=100.0),
+                        );
+                        ui.end_row();
+
+                        ui.label("Charge");
+                        ui.add(egui::DragValue::new(&mut parameters.charge).clamp_range(-5..=5))
+                            .on_hover_text("[M+zH] for positive and [M-zH] for negative charges, 0 for the neutral mass");
+                        ui.end_row();
+
+                        ui.label("RDBE");
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::DragValue::new(&mut parameters.min_rdbe)
+                                    .speed(0.5)
+                                    .clamp_range(-5.0..=parameters.max_rdbe),
+                            );
+                            ui.label("to");
+                            ui.add(
+                                egui::DragValue::new(&mut parameters.max_rdbe)
+                                    .speed(0.5)
+                                    .clamp_range(parameters.min_rdbe..=200.0),
+                            );
+                        });
+                        ui.end_row();
+
+                        ui.label("Nitrogen rule");
+                        ui.checkbox(&mut parameters.nitrogen_rule, "");
+                        ui.end_row();
+
+                        ui.label("Isotope tolerance (ppm)");
+                        ui.add(
+                            egui::DragValue::new(&mut parameters.isotope_tolerance)
+                                .speed(0.5)
+                                .clamp_range(1.0..=200.0),
+                        );
+                        ui.end_row();
+
+                        for (element, range) in composition::ELEMENTS
+                            .iter()
+                            .zip(parameters.ranges.iter_mut())
+                        {
+                            ui.label(element.symbol);
+                            ui.horizontal(|ui| {
+                                ui.add(
+                                    egui::DragValue::new(&mut range.0)
+                                        .clamp_range(0..=range.1.min(element.max_count)),
+                                );
+                                ui.label("to");
+                                ui.add(
+                                    egui::DragValue::new(&mut range.1)
+                                        .clamp_range(range.0..=element.max_count),
+                                );
+                            });
+                            ui.end_row();
+                        }
+                    });
+                let generating = self.composition_receiver.is_some();
+                if ui
+                    .add_enabled(!generating, egui::Button::new("Generate"))
+                    .clicked()
+                {
+                    self.generate_compositions();
+                }
+
+                ui.separator();
+                if generating {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Generating formulas...");
+                    });
+                    return;
+                }
+                if self.composition_candidates.is_empty() {
+                    ui.label("No formula within the constraints");
+                    return;
+                }
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        egui::Grid::new("composition_candidates")
+                            .striped(true)
+                            .show(ui, |ui| {
+                                ui.strong("#");
+                                ui.strong("Formula");
+                                ui.strong("m/z");
+                                ui.strong("Error (ppm)");
+                                ui.strong("RDBE");
+                                ui.strong("Isotope fit");
+                                ui.end_row();
+                                for (rank, candidate) in
+                                    self.composition_candidates.iter().enumerate()
+                                {
+                                    ui.label((rank + 1).to_string());
+                                    ui.label(candidate.formula());
+                                    ui.label(format!("{:.4}", candidate.mz));
+                                    ui.label(format!("{:.2}", candidate.error));
+                                    ui.label(format!("{:.1}", candidate.rdbe));
+                                    ui.label(format!("{:.3}", candidate.isotope_score));
+                                    ui.end_row();
+                                }
+                            });
+                    });
+            });
+        if !open {
+            // closing the window cancels a running enumeration
+            self.cancel_compositions();
+        }
+        self.composition_window_open = open;
+    }
+
+    /// Matches the fragment ions of the peptide typed by the user to the peaks of a spectrum.
+    ///
+    /// # Parameters
+    /// - `spectrum: &parser::Spectrum`: The spectrum, as it is displayed.
+    ///
+    /// # Returns
+    /// - `Vec<peptide::PeakAnnotation>`: The matched fragment ions, or an empty vector if no valid peptide is typed.
+    fn fragment_annotations(&self, spectrum: &parser::Spectrum) -> Vec<peptide::PeakAnnotation> {
+        if self.user_input.peptide_input.trim().is_empty() {
+            return Vec::new();
+        }
+        let parameters = &self.user_input.peptide_annotation;
+        match peptide::Peptide::parse(
+            &self.user_input.peptide_input,
+            &parameters.fixed_modifications,
+        ) {
+            Ok(peptide) => peptide::annotate(
+                &spectrum.mz,
+                &spectrum.intensity,
+                &peptide.fragment_ions(&parameters.ion_types, parameters.max_charge),
+                parameters.tolerance,
+            ),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Renders the peptide fragment annotation of the displayed mass spectrum.
+    ///
+    /// This function renders a field for the peptide sequence, checkboxes for the fixed modifications and the annotated ion types, and values for the
+    /// highest fragment charge and the m/z tolerance. For a valid peptide, it shows the peptide mass, its m/z at the first charge states next to the
+    /// precursor m/z of the displayed spectrum, and a coverage table listing for every residue the N-terminal and C-terminal ions ending there.
+    /// Matched ions are shown in color with the m/z of their peak, unmatched ions in gray with their singly charged m/z.
+    ///
+    /// # Parameters
+    /// - `ui: &mut egui::Ui`: A mutable reference to the current `egui::Ui` instance, which is used to render the controls and the table.
+    fn show_peptide_annotation(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Peptide");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.user_input.peptide_input)
+                    .hint_text("e.g. PEPTM[Oxidation]IDE")
+                    .desired_width(300.0),
+            )
+            .on_hover_text(
+                "Variable modifications by name or mass in brackets after the residue, an N-terminal modification as [Acetyl]-PEPTIDE",
+            );
+        });
+
+        let parameters = &mut self.user_input.peptide_annotation;
+        ui.horizontal(|ui| {
+            ui.label("Fixed modifications");
+            for modification in peptide::MODIFICATIONS.iter() {
+                let mut fixed = parameters
+                    .fixed_modifications
+                    .iter()
+                    .any(|name| name == modification.name);
+                if ui
+                    .checkbox(
+                        &mut fixed,
+                        format!("{} ({})", modification.name, modification.residues),
+                    )
+                    .changed()
+                {
+                    parameters
+                        .fixed_modifications
+                        .retain(|name| name != modification.name);
+                    if fixed {
+                        parameters
+                            .fixed_modifications
+                            .push(modification.name.to_string());
+                    }
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Ions");
+            for ion_type in peptide::IonType::ALL {
+                let mut selected = parameters.ion_types.contains(&ion_type);
+                if ui
+                    .checkbox(&mut selected, ion_type.symbol().to_string())
+                    .changed()
+                {
+                    let ion_types = &parameters.ion_types;
+                    // keep the N-terminal ions first, in the order of the coverage table
+                    parameters.ion_types = peptide::IonType::ALL
+                        .into_iter()
+                        .filter(|t| {
+                            if *t == ion_type {
+                                selected
+                            } else {
+                                ion_types.contains(t)
+                            }
+                        })
+                        .collect();
+                }
+            }
+            ui.add(
+                egui::DragValue::new(&mut parameters.max_charge)
+                    .clamp_range(1..=6)
+                    .prefix("max. charge: "),
+            );
+            ui.add(
+                egui::DragValue::new(&mut parameters.tolerance)
+                    .speed(0.001)
+                    .clamp_range(0.001..=1.0)
+                    .prefix("tolerance: ")
+                    .suffix(" Da"),
+            );
+        });
+
+        if self.user_input.peptide_input.trim().is_empty() {
+            ui.label("Type a peptide sequence to annotate the displayed spectrum");
+            return;
+        }
+        let peptide = match peptide::Peptide::parse(
+            &self.user_input.peptide_input,
+            &self.user_input.peptide_annotation.fixed_modifications,
+        ) {
+            Ok(peptide) => peptide,
+            Err(e) => {
+                ui.colored_label(Color32::LIGHT_RED, e.to_string());
+                return;
+            }
+        };
+
+        ui.label(format!(
+            "Monoisotopic mass: {:.4} Da   [M+H]+ {:.4}   [M+2H]2+ {:.4}   [M+3H]3+ {:.4}",
+            peptide.mass(),
+            peptide.precursor_mz(1),
+            peptide.precursor_mz(2),
+            peptide.precursor_mz(3)
+        ));
+        let Some(spectrum) = self
+            .mass_spectra
+            .len()
+            .checked_sub(1)
+            .and_then(|current| self.displayed_spectrum(current))
+        else {
+            ui.label("No mass spectrum displayed");
+            return;
+        };
+        if let Some(precursor_mz) = spectrum.precursor_mz {
+            ui.label(format!(
+                "Precursor m/z of {}: {:.4}",
+                spectrum.label, precursor_mz
+            ));
+        }
+
+        let annotations = self.fragment_annotations(spectrum);
+        let parameters = &self.user_input.peptide_annotation;
+        let n = peptide.residues.len();
+        let n_terminal: Vec<peptide::IonType> = parameters
+            .ion_types
+            .iter()
+            .copied()
+            .filter(|ion_type| ion_type.is_n_terminal())
+            .collect();
+        let c_terminal: Vec<peptide::IonType> = parameters
+            .ion_types
+            .iter()
+            .copied()
+            .filter(|ion_type| !ion_type.is_n_terminal())
+            .collect();
+        let theoretical = peptide.fragment_ions(&parameters.ion_types, 1);
+        let explained = (1..n)
+            .filter(|&site| {
+                annotations.iter().any(|annotation| {
+                    let ion = annotation.ion;
+                    (ion.ion_type.is_n_terminal() && ion.number == site)
+                        || (!ion.ion_type.is_n_terminal() && ion.number == n - site)
+                })
+            })
+            .count();
+        ui.label(format!(
+            "{} matched ions, {} of {} fragmentation sites explained",
+            annotations.len(),
+            explained,
+            n - 1
+        ));
+
+        let cell = |ui: &mut egui::Ui, ion_type: peptide::IonType, number: usize| {
+            if number == 0 || number >= n {
+                ui.label("");
+                return;
+            }
+            let matched: Vec<&peptide::PeakAnnotation> = annotations
+                .iter()
+                .filter(|annotation| {
+                    annotation.ion.ion_type == ion_type && annotation.ion.number == number
+                })
+                .collect();
+            let color = if ion_type.is_n_terminal() {
+                N_TERMINAL_ION_COLOR
+            } else {
+                C_TERMINAL_ION_COLOR
+            };
+            match matched.first() {
+                Some(annotation) => {
+                    ui.colored_label(
+                        color,
+                        format!(
+                            "{} {:.4}",
+                            annotation.ion.label(),
+                            spectrum.mz[annotation.peak]
+                        ),
+                    )
+                    .on_hover_text(
+                        matched
+                            .iter()
+                            .map(|annotation| annotation.ion.label())
+                            .collect::<Vec<String>>()
+                            .join(", "),
+                    );
+                }
+                None => {
+                    let mz = theoretical
+                        .iter()
+                        .find(|ion| ion.ion_type == ion_type && ion.number == number)
+                        .map(|ion| ion.mz)
+                        .unwrap_or_default();
+                    ui.colored_label(Color32::GRAY, format!("{:.4}", mz));
+                }
+            }
+        };
+        egui::Grid::new("peptide_coverage")
+            .striped(true)
+            .show(ui, |ui| {
+                for ion_type in &n_terminal {
+                    ui.strong(ion_type.symbol().to_string());
+                }
+                ui.strong("#");
+                ui.strong("Residue");
+                ui.strong("#");
+                for ion_type in &c_terminal {
+                    ui.strong(ion_type.symbol().to_string());
+                }
+                ui.end_row();
+                for (position, residue) in peptide.residues.iter().enumerate() {
+                    for &ion_type in &n_terminal {
+                        cell(ui, ion_type, position + 1);
+                    }
+                    ui.label((position + 1).to_string());
+                    ui.label(residue.to_string());
+                    ui.label((n - position).to_string());
+                    for &ion_type in &c_terminal {
+                        cell(ui, ion_type, n - position);
+                    }
+                    ui.end_row();
+                }
+            });
+    }
+
+    /// Renders the charge deconvolution of the displayed mass spectrum.
+    ///
+    /// This function renders fields for a retention time range and a button that replaces the displayed spectrum by the average of the MS1 scans of that range,
+    /// fields for the m/z range and the charge range of the deconvolution, and a button that deconvolves the displayed spectrum into `deconvolved_spectrum`.
+    /// Profile spectra are centroided with `user_input.centroid_parameters` before they are deconvolved.
+    /// The neutral mass spectrum is drawn below the controls, with its most intense peaks labelled.
+    ///
+    /// # Parameters
+    /// - `ui: &mut egui::Ui`: A mutable reference to the current `egui::Ui` instance, which is used to render the controls and the plot.
+    fn plot_deconvolution(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Average scans from RT");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.user_input.average_rt_min_input)
+                    .hint_text("Min")
+                    .desired_width(60.0),
+            );
+            ui.label("to");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.user_input.average_rt_max_input)
+                    .hint_text("Max")
+                    .desired_width(60.0),
+            );
+            let rt_range = match (
+                self.user_input.average_rt_min_input.parse::<f64>(),
+                self.user_input.average_rt_max_input.parse::<f64>(),
+            ) {
+                (Ok(min), Ok(max)) if min <= max => Some((min, max)),
+                _ => None,
+            };
+            if ui
+                .add_enabled(rt_range.is_some(), egui::Button::new("Average"))
+                .on_hover_text("Display the average of the MS1 scans of the retention time range")
+                .clicked()
+            {
+                if let Some(rt_range) = rt_range {
+                    match self.parsed_ms_data.get_averaged_mass_spectrum(
+                        self.user_input.polarity,
+                        rt_range,
+                        DEFAULT_MASS_TOLERANCE,
+                    ) {
+                        Ok(spectrum) => {
+                            self.set_current_spectrum(spectrum);
+                            self.selected_spectrum_index = None;
+                        }
+                        Err(e) => {
+                            self.report_error(format!("Failed to average mass spectra: {}", e))
+                        }
+                    }
+                }
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("m/z range");
+            let min_response = ui.add(
+                egui::TextEdit::singleline(&mut self.user_input.deconvolution_mz_min_input)
+                    .hint_text("Min")
+                    .desired_width(60.0),
+            );
+            let max_response = ui.add(
+                egui::TextEdit::singleline(&mut self.user_input.deconvolution_mz_max_input)
+                    .hint_text("Max")
+                    .desired_width(60.0),
+            );
+            if min_response.lost_focus() || max_response.lost_focus() {
+                self.user_input.deconvolution_parameters.mz_range = match (
+                    self.user_input.deconvolution_mz_min_input.parse::<f64>(),
+                    self.user_input.deconvolution_mz_max_input.parse::<f64>(),
+                ) {
+                    (Ok(min), Ok(max)) if min < max => Some((min, max)),
+                    _ => None,
+                };
+            }
+
+            let parameters = &mut self.user_input.deconvolution_parameters;
+            ui.label("Charge");
+            ui.add(
+                egui::DragValue::new(&mut parameters.charge_range.0)
+                    .clamp_range(1..=parameters.charge_range.1),
+            );
+            ui.label("to");
+            ui.add(
+                egui::DragValue::new(&mut parameters.charge_range.1)
+                    .clamp_range(parameters.charge_range.0..=200),
+            );
+            ui.add(
+                egui::DragValue::new(&mut parameters.tolerance)
+                    .speed(1.0)
+                    .clamp_range(1.0..=500.0)
+                    .suffix(" ppm"),
+            );
+            ui.add(
+                egui::DragValue::new(&mut parameters.min_charge_states)
+                    .clamp_range(2..=20)
+                    .prefix("min. charge states: "),
+            );
+            ui.add(
+                egui::DragValue::new(&mut parameters.mass_bin)
+                    .speed(0.1)
+                    .clamp_range(0.01..=100.0)
+                    .prefix("bin: ")
+                    .suffix(" Da"),
+            );
+
+            if ui
+                .add_enabled(
+                    self.current_spectrum().is_some(),
+                    egui::Button::new("Deconvolve displayed spectrum"),
+                )
+                .clicked()
+            {
+                if let Some(spectrum) = self.current_spectrum() {
+                    let (mz, intensity) = if spectrum.profile {
+                        spectrum_processing::centroid(
+                            &spectrum.mz,
+                            &spectrum.intensity,
+                            &self.user_input.centroid_parameters,
+                        )
+                    } else {
+                        (spectrum.mz.clone(), spectrum.intensity.clone())
+                    };
+                    let (masses, intensities) = deconvolution::deconvolve(
+                        &mz,
+                        &intensity,
+                        &self.user_input.deconvolution_parameters,
+                    );
+                    info!(
+                        "Deconvolved {} into {} neutral masses",
+                        spectrum.label,
+                        masses.len()
+                    );
+                    self.deconvolved_spectrum = Some(parser::Spectrum {
+                        label: format!("{} (neutral mass)", spectrum.label),
+                        file_name: spectrum.file_name.clone(),
+                        ms_level: spectrum.ms_level,
+                        polarity: spectrum.polarity,
+                        mz: masses,
+                        intensity: intensities,
+                        ..Default::default()
+                    });
+                }
+            }
+        });
+
+        let Some(spectrum) = &self.deconvolved_spectrum else {
+            ui.label("No deconvolved spectrum available");
+            return;
+        };
+        if spectrum.mz.is_empty() {
+            ui.label("No charge state series found in the displayed spectrum");
+            return;
+        }
+        egui_plot::Plot::new("deconvolved_spectrum")
+            .legend(egui_plot::Legend::default())
+            .x_axis_label("Neutral mass (Da)")
+            .y_axis_label("Intensity")
+            .width(ui.available_width() * 0.99)
+            .height(ui.available_height().max(300.0))
+            .show(ui, |plot_ui| {
+                add_spectrum_to_plot(
+                    plot_ui,
+                    spectrum,
+                    self.user_input.line_color.to_egui(),
+                    self.user_input.line_width,
+                    self.user_input.line_type.to_egui(),
+                );
+                add_peak_labels(
+                    plot_ui,
+                    spectrum,
+                    self.user_input.peak_label_count,
+                    self.user_input.peak_label_spacing,
+                    self.user_input.line_color.to_egui(),
+                );
+            });
+    }
+
+    /// Adds the display-time filter options of the mass spectra to the provided `Ui`.
+    ///
+    /// This function renders radio buttons and a value for the absolute or relative intensity threshold, the number of peaks kept per m/z window and the window width, and two fields for the m/z range the spectra are cropped to.
+    ///
+    /// # Parameters
+    ///
+    /// - `ui`: A mutable reference to the `egui::Ui` object, which is used to render the UI elements.
+    fn add_spectrum_filter_options(&mut self, ui: &mut Ui) {
+        let filter = &mut self.user_input.spectrum_filter;
+        ui.label("Intensity threshold");
+        ui.horizontal(|ui| {
+            ui.radio_value(
+                &mut filter.threshold_mode,
+                spectrum_processing::ThresholdMode::Off,
+                "Off",
+            );
+            ui.radio_value(
+                &mut filter.threshold_mode,
+                spectrum_processing::ThresholdMode::Absolute,
+                "Absolute",
+            );
+            ui.radio_value(
+                &mut filter.threshold_mode,
+                spectrum_processing::ThresholdMode::Relative,
+                "% of base peak",
+            );
+        });
+        match filter.threshold_mode {
+            spectrum_processing::ThresholdMode::Off => {}
+            spectrum_processing::ThresholdMode::Absolute => {
+                ui.add(
+                    egui::DragValue::new(&mut filter.threshold)
+                        .speed(100.0)
+                        .clamp_range(0.0..=f32::MAX),
+                );
+            }
+            spectrum_processing::ThresholdMode::Relative => {
+                ui.add(egui::Slider::new(&mut filter.threshold, 0.0..=100.0).suffix(" %"));
+            }
+        }
+
+        ui.separator();
+        ui.label("Most intense peaks per m/z window (0 keeps all)");
+        ui.horizontal(|ui| {
+            ui.add(egui::DragValue::new(&mut filter.top_n).clamp_range(0..=1000));
+            ui.add(
+                egui::DragValue::new(&mut filter.window)
+                    .speed(1.0)
+                    .clamp_range(1.0..=10000.0)
+                    .prefix("window: ")
+                    .suffix(" m/z"),
+            );
+        });
+
+        ui.separator();
+        ui.label("m/z range");
+        ui.horizontal(|ui| {
+            let min_response = ui.add(
+                egui::TextEdit::singleline(&mut self.user_input.crop_min_input)
+                    .hint_text("Min")
+                    .desired_width(80.0),
+            );
+            let max_response = ui.add(
+                egui::TextEdit::singleline(&mut self.user_input.crop_max_input)
+                    .hint_text("Max")
+                    .desired_width(80.0),
+            );
+            if min_response.lost_focus() || max_response.lost_focus() {
+                self.user_input.spectrum_filter.mz_range = match (
+                    self.user_input.crop_min_input.parse::<f64>(),
+                    self.user_input.crop_max_input.parse::<f64>(),
+                ) {
+                    (Ok(min), Ok(max)) if min < max => Some((min, max)),
+                    _ => None,
+                };
+                info!(
+                    "Spectrum m/z range set to {:?}",
+                    self.user_input.spectrum_filter.mz_range
+                );
+            }
+        });
+    }
+
+    /// Adds the intensity scale options of the mass spectra to the provided `Ui`.
+    ///
+    /// This function renders radio buttons to show absolute intensities, intensities relative to the base peak or intensities relative to a reference peak, whose m/z and tolerance can be entered, and a checkbox for a logarithmic intensity axis.
+    ///
+    /// # Parameters
+    ///
+    /// - `ui`: A mutable reference to the `egui::Ui` object, which is used to render the UI elements.
+    fn add_intensity_scale_options(&mut self, ui: &mut Ui) {
+        ui.radio_value(
+            &mut self.user_input.normalization,
+            spectrum_processing::Normalization::Absolute,
+            "Absolute",
+        );
+        ui.radio_value(
+            &mut self.user_input.normalization,
+            spectrum_processing::Normalization::BasePeak,
+            "% of base peak",
+        );
+        ui.radio_value(
+            &mut self.user_input.normalization,
+            spectrum_processing::Normalization::ReferencePeak,
+            "% of reference peak",
+        );
+        if self.user_input.normalization == spectrum_processing::Normalization::ReferencePeak {
+            ui.horizontal(|ui| {
+                if ui
+                    .add(
+                        egui::TextEdit::singleline(&mut self.user_input.reference_mz_input)
+                            .hint_text("Reference m/z")
+                            .desired_width(80.0),
+                    )
+                    .lost_focus()
+                {
+                    self.user_input.reference_mz = self
+                        .user_input
+                        .reference_mz_input
+                        .parse()
+                        .unwrap_or(self.user_input.reference_mz);
+                    info!("Reference peak set to {}", self.user_input.reference_mz);
+                }
+                ui.add(
+                    egui::DragValue::new(&mut self.user_input.reference_tolerance)
+                        .speed(0.5)
+                        .clamp_range(0.1..=1000.0)
+                        .suffix(" ppm"),
+                );
+            });
+        }
+        ui.separator();
+        ui.checkbox(
+            &mut self.user_input.log_intensity,
+            "Logarithmic intensity axis",
+        );
+    }
+
+    /// Plots a pinned spectrum against another spectrum in a head-to-tail mirror plot.
+    ///
+    /// # Parameters
+    /// - `&mut self`: A mutable reference to the current instance of the struct that contains the `parsed_ms_data`, `comparison_data` and `user_input` fields.
+    /// - `ui: &mut egui::Ui`: A mutable reference to the current `egui::Ui` instance, which is used to render the plot.
+    ///
+    /// # Functionality
+    /// 1. Renders a button that pins the displayed spectrum, and a combo box to select the pinned spectrum drawn on top. The most recently pinned spectrum is used by default.
+    /// 2. Renders radio buttons to compare the pinned spectrum with the displayed scan, a scan of another mzML file or the selected library hit.
+    ///    - For another file, renders a button to open the file and the index of the scan to be compared, which is read whenever it changes.
+    /// 3. Scores the two spectra with the similarity method and fragment tolerance of the library search settings.
+    /// 4. Calls `plot_mirror()` to render the spectra head-to-tail, with the matched peaks highlighted.
+    fn plot_spectrum_comparison(&mut self, ui: &mut egui::Ui) {
+        let pinned: Vec<usize> = (0..self.mass_spectra.len())
+            .filter(|&i| self.is_pinned(i))
+            .collect();
+        if !self
+            .mirror_reference
+            .is_some_and(|reference| pinned.contains(&reference))
+        {
+            self.mirror_reference = pinned.last().copied();
+        }
+
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(
+                    self.current_spectrum().is_some() && !self.current_spectrum_pinned,
+                    egui::Button::new("Pin displayed spectrum"),
+                )
+                .clicked()
+            {
+                self.pin_current_spectrum();
+                self.mirror_reference = self.mass_spectra.len().checked_sub(1);
+            }
+            let spectra = &self.mass_spectra;
+            egui::ComboBox::from_id_source("mirror_reference")
+                .selected_text(
+                    self.mirror_reference
+                        .map(|reference| spectra[reference].label.as_str())
+                        .unwrap_or("No spectrum pinned"),
+                )
+                .show_ui(ui, |ui| {
+                    for &i in &pinned {
+                        ui.selectable_value(&mut self.mirror_reference, Some(i), &spectra[i].label);
+                    }
+                });
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Compare with");
+            ui.radio_value(
+                &mut self.user_input.mirror_source,
+                MirrorSource::Displayed,
+                "Displayed scan",
+            );
+            ui.radio_value(
+                &mut self.user_input.mirror_source,
+                MirrorSource::File,
+                "Other file",
+            );
+            ui.radio_value(
+                &mut self.user_input.mirror_source,
+                MirrorSource::Library,
+                "Library hit",
+            );
+        });
+
+        if self.user_input.mirror_source == MirrorSource::File {
+            ui.horizontal(|ui| {
+                if ui.button("Open file…").clicked() {
+                    if let Some(path) = rfd::FileDialog::new()
+                        .add_filter(FILE_FORMAT, &[FILE_FORMAT])
+                        .pick_file()
+                    {
+                        self.comparison_data = parser::MzData::default();
+                        self.comparison_spectrum = None;
+                        match self.comparison_data.open_msfile(&path) {
+                            Ok(data) => {
+                                info!("Comparison file opened successfully.");
+                                match data
+                                    .get_mass_spectrum_by_index(self.user_input.comparison_index)
+                                {
+                                    Ok(spectrum) => self.comparison_spectrum = Some(spectrum),
+                                    Err(e) => self.report_error(format!(
+                                        "Failed to read the comparison spectrum. {}",
+                                        e
+                                    )),
+                                }
+                            }
+                            Err(e) => self
+                                .report_error(format!("Failed to open the comparison file. {}", e)),
+                        }
+                    }
+                }
+                ui.label(
+                    self.comparison_data
+                        .file_name
+                        .as_deref()
+                        .unwrap_or("No file opened"),
+                );
+                ui.label("Scan index");
+                if ui
+                    .add(egui::DragValue::new(&mut self.user_input.comparison_index))
+                    .changed()
+                {
+                    self.comparison_spectrum = None;
+                    match self
+                        .comparison_data
+                        .get_mass_spectrum_by_index(self.user_input.comparison_index)
+                    {
+                        Ok(spectrum) => self.comparison_spectrum = Some(spectrum),
+                        Err(e) => self
+                            .report_error(format!("Failed to read the comparison spectrum. {}", e)),
+                    }
+                }
+            });
+        }
+
+        let Some(reference) = self
+            .mirror_reference
+            .map(|reference| &self.mass_spectra[reference])
+        else {
+            ui.label("Pin a spectrum to compare it with another one");
+            return;
+        };
+
+        let comparison = match self.user_input.mirror_source {
+            MirrorSource::Displayed => self.current_spectrum().map(|spectrum| {
+                (
+                    spectrum.label.as_str(),
+                    spectrum.mz.as_slice(),
+                    spectrum.intensity.as_slice(),
+                )
+            }),
+            MirrorSource::File => self.comparison_spectrum.as_ref().map(|spectrum| {
+                (
+                    spectrum.label.as_str(),
+                    spectrum.mz.as_slice(),
+                    spectrum.intensity.as_slice(),
+                )
+            }),
+            MirrorSource::Library => self
+                .selected_library_hit
+                .and_then(|rank| self.library_hits.get(rank))
+                .map(|hit| {
+                    let entry = &self.libraries[hit.library_index].spectra[hit.spectrum_index];
+                    (
+                        entry.name.as_str(),
+                        entry.mz.as_slice(),
+                        entry.intensity.as_slice(),
+                    )
+                }),
+        };
+        let Some((label, mz, intensity)) = comparison else {
+            ui.label("No spectrum to compare with");
+            return;
+        };
+
+        let (score, matches) = library::similarity(
+            (&reference.mz, &reference.intensity),
+            (mz, intensity),
+            self.user_input.library_search.fragment_tolerance,
+            self.user_input.library_search.method,
+        );
+        ui.label(format!(
+            "{:?} similarity: {:.3} ({} matched peaks, tolerance {} Da)",
+            self.user_input.library_search.method,
+            score,
+            matches.len(),
+            self.user_input.library_search.fragment_tolerance
+        ));
+        plot_mirror(
+            ui,
+            "spectrum_mirror",
+            (&reference.label, &reference.mz, &reference.intensity),
+            (label, mz, intensity),
+            &matches,
+        );
+    }
+
+    /// Adds the export options to the provided `Ui`.
+    ///
+    /// This function renders radio buttons to write the chromatogram traces as aligned columns or in long format, and a button that saves the
+    /// displayed chromatogram to a CSV or TSV file chosen by the user. For the mass spectrum, it renders a checkbox to export the spectrum as displayed
+    /// and a button that saves the current spectrum to a CSV, TSV, MSP or MGF file chosen by the user.
+    ///
+    /// # Parameters
+    ///
+    /// - `ui`: A mutable reference to the `egui::Ui` object, which is used to render the UI elements.
+    ///
+    /// # Errors
+    ///
+    /// This function does not return any errors. Files that cannot be written are logged as warnings.
+    fn add_export_options(&mut self, ui: &mut Ui) {
+        ui.label("Chromatogram traces");
+        ui.horizontal(|ui| {
+            ui.radio_value(
+                &mut self.user_input.trace_layout,
+                export::TraceLayout::Aligned,
+                "Aligned columns",
+            )
+            .on_hover_text("One row per retention time, one column per trace");
+            ui.radio_value(
+                &mut self.user_input.trace_layout,
+                export::TraceLayout::Long,
+                "Long format",
+            )
+            .on_hover_text("One row per trace and retention time");
+        });
+        if ui
+            .add_enabled(
+                !self.plot_data.is_empty(),
+                egui::Button::new("Save chromatogram…"),
+            )
+            .clicked()
+        {
+            if let Some(path) = rfd::FileDialog::new()
+                .add_filter("CSV", &["csv"])
+                .add_filter("TSV", &["tsv"])
+                .set_file_name("chromatogram.csv")
+                .save_file()
+            {
+                match self.export_chromatogram(&path) {
+                    Ok(()) => info!("Chromatogram exported to {:?}", path),
+                    Err(e) => self.report_error(format!(
+                        "Failed to export the chromatogram to {}: {}",
+                        path.display(),
+                        e
+                    )),
+                }
+            }
+            ui.close_menu();
+        }
+
+        ui.separator();
+        ui.label("Mass spectrum");
+        ui.checkbox(&mut self.user_input.export_displayed_spectrum, "As displayed")
+            .on_hover_text(
+                "Export the spectrum centroided, filtered and scaled as shown, instead of as read from the file",
+            );
+        if ui
+            .add_enabled(
+                !self.mass_spectra.is_empty(),
+                egui::Button::new("Save spectrum…"),
+            )
+            .clicked()
+        {
+            if let Some(path) = rfd::FileDialog::new()
+                .add_filter("CSV", &["csv"])
+                .add_filter("TSV", &["tsv"])
+                .add_filter("MSP", &["msp"])
+                .add_filter("MGF", &["mgf"])
+                .set_file_name("spectrum.csv")
+                .save_file()
+            {
+                match self.export_spectrum(&path) {
+                    Ok(()) => info!("Mass spectrum exported to {:?}", path),
+                    Err(e) => self.report_error(format!(
+                        "Failed to export the mass spectrum to {}: {}",
+                        path.display(),
+                        e
+                    )),
+                }
+            }
+            ui.close_menu();
+        }
+
+        ui.separator();
+        ui.label("Images");
+        let parameters = &mut self.user_input.image_parameters;
+        egui::Grid::new("image_parameters").show(ui, |ui| {
+            ui.label("Width (in)");
+            ui.add(
+                egui::DragValue::new(&mut parameters.width)
+                    .clamp_range(1.0..=20.0)
+                    .speed(0.1),
+            );
+            ui.end_row();
+            ui.label("Height (in)");
+            ui.add(
+                egui::DragValue::new(&mut parameters.height)
+                    .clamp_range(1.0..=20.0)
+                    .speed(0.1),
+            );
+            ui.end_row();
+            ui.label("Resolution (DPI)");
+            ui.add(
+                egui::DragValue::new(&mut parameters.dpi)
+                    .clamp_range(72..=1200)
+                    .speed(10),
+            )
+            .on_hover_text("Only used for PNG images, SVG images are scalable");
+            ui.end_row();
+        });
+        ui.checkbox(&mut parameters.dark_background, "Dark background")
+            .on_hover_text("Draw light text on a dark background, e.g. for white or yellow lines");
+
+        let chromatogram_figure = if ui
+            .add_enabled(
+                !self.plot_data.is_empty(),
+                egui::Button::new("Save chromatogram image…"),
+            )
+            .clicked()
+        {
+            ui.close_menu();
+            self.chromatogram_figure()
+        } else {
+            None
+        };
+        let spectrum_figure = if ui
+            .add_enabled(
+                !self.mass_spectra.is_empty(),
+                egui::Button::new("Save spectrum image…"),
+            )
+            .clicked()
+        {
+            ui.close_menu();
+            self.spectrum_figure()
+        } else {
+            None
+        };
+        for (figure, file_name) in [
+            (chromatogram_figure, "chromatogram.png"),
+            (spectrum_figure, "spectrum.png"),
+        ] {
+            let Some(figure) = figure else { continue };
+            if let Some(path) = rfd::FileDialog::new()
+                .add_filter("PNG", &["png"])
+                .add_filter("SVG", &["svg"])
+                .set_file_name(file_name)
+                .save_file()
+            {
+                match self.export_image(&figure, &path) {
+                    Ok(()) => info!("Image exported to {:?}", path),
+                    Err(e) => self.report_error(format!(
+                        "Failed to export the image to {}: {}",
+                        path.display(),
+                        e
+                    )),
+                }
+            }
+        }
+    }
+
+    /// Describes the displayed chromatogram as a figure for image export.
+    ///
+    /// The figure shows the smoothed trace and, for DIA plots, the fragment traces with the colors, line width and visible range of the chromatogram plot.
+    /// Shown baselines are drawn as thinner lines in a lighter color.
+    ///
+    /// # Returns
+    /// - `Option<figure::Figure>`: The figure, or `None` if no chromatogram is displayed.
+    fn chromatogram_figure(&self) -> Option<figure::Figure> {
+        let main = self.plot_data.first()?;
+        let user_input = &self.user_input;
+        let name = self.trace_name(0, main);
+        let file_name = user_input
+            .file_path
+            .as_deref()
+            .map(|path| {
+                Path::new(path)
+                    .file_name()
+                    .map_or(path.to_string(), |name| name.to_string_lossy().to_string())
+            })
+            .unwrap_or_default();
+
+        let mut series = Vec::new();
+        for (i, chromatogram) in self.plot_data.iter().enumerate() {
+            series.push(figure::Series {
+                name: Some(self.trace_name(i, chromatogram)),
+                points: chromatogram.points(),
+                color: rgb(self.trace_color(i)),
+                width: user_input.line_width,
+                style: figure::SeriesStyle::Line,
+            });
+            if let Some(baseline) = self.baseline_points(i) {
+                series.push(figure::Series {
+                    name: (i == 0).then(|| "Baseline".to_string()),
+                    points: baseline,
+                    color: rgb(self.trace_color(i).gamma_multiply(0.7)),
+                    width: user_input.line_width / 2.0,
+                    style: figure::SeriesStyle::Line,
+                });
+            }
+        }
+        let (x_range, y_range) = figure_ranges(self.chromatogram_bounds, &series);
+        Some(figure::Figure {
+            title: format!("{} {}", file_name, name).trim().to_string(),
+            x_label: format!("Retention time ({})", main.time_unit.symbol()),
+            y_label: "Intensity".to_string(),
+            x_range,
+            y_range,
+            series,
+            ..Default::default()
+        })
+    }
+
+    /// Describes the displayed mass spectra as a figure for image export.
+    ///
+    /// The displayed and pinned spectra are overlaid with the colors, intensity scale and visible range of the mass spectrum plot, also when the
+    /// spectra are stacked. Profile spectra are drawn as lines and centroid spectra as sticks. The current spectrum carries its fragment ion
+    /// annotations, or else its peak labels.
+    ///
+    /// # Returns
+    /// - `Option<figure::Figure>`: The figure, or `None` if no spectrum can be displayed.
+    fn spectrum_figure(&self) -> Option<figure::Figure> {
+        let spectra = &self.mass_spectra;
+        let current = spectra.len().checked_sub(1)?;
+        let displayed: Vec<(usize, &parser::Spectrum)> = (0..spectra.len())
+            .filter_map(|i| self.displayed_spectrum(i).map(|spectrum| (i, spectrum)))
+            .collect();
+        if displayed.is_empty() {
+            return None;
+        }
+        let color = |i: usize| {
+            if spectra.len() == 1 {
+                rgb(self.user_input.line_color.to_egui())
+            } else {
+                rgb(plotting_parameters::trace_color(i))
+            }
+        };
+
+        let series: Vec<figure::Series> = displayed
+            .iter()
+            .map(|(i, spectrum)| figure::Series {
+                name: Some(spectrum.label.clone()),
+                points: spectrum
+                    .mz
+                    .iter()
+                    .zip(spectrum.intensity.iter())
+                    .map(|(&mz, &intensity)| [mz, intensity as f64])
+                    .collect(),
+                color: color(*i),
+                width: if spectrum.profile {
+                    self.user_input.line_width
+                } else {
+                    1.0
+                },
+                style: if spectrum.profile {
+                    figure::SeriesStyle::Line
+                } else {
+                    figure::SeriesStyle::Sticks
+                },
+            })
+            .collect();
+        let (x_range, y_range) = figure_ranges(self.spectrum_bounds, &series);
+        let mut figure = figure::Figure {
+            title: spectra[current].label.clone(),
+            x_label: "m/z".to_string(),
+            y_label: self.intensity_axis_label(),
+            x_range,
+            y_range,
+            y_tick_format: if self.user_input.log_intensity {
+                Some(format_log_intensity)
+            } else {
+                None
+            },
+            series,
+            ..Default::default()
+        };
+
+        let parameters = &self.user_input.image_parameters;
+        for (i, spectrum) in &displayed {
+            let annotations = if *i == current {
+                self.fragment_annotations(spectrum)
+            } else {
+                Vec::new()
+            };
+            if !annotations.is_empty() {
+                for annotation in &annotations {
+                    let color = if annotation.ion.ion_type.is_n_terminal() {
+                        N_TERMINAL_ION_COLOR
+                    } else {
+                        C_TERMINAL_ION_COLOR
+                    };
+                    let peak = annotation.peak;
+                    let label = annotations
+                        .iter()
+                        .filter(|a| a.peak == peak)
+                        .map(|a| a.ion.label())
+                        .collect::<Vec<String>>()
+                        .join(", ");
+                    let text = format!("{}\n{:.4}", label, spectrum.mz[peak]);
+                    if figure.annotations.iter().any(|a| a.text == text) {
+                        continue;
+                    }
+                    figure.annotations.push(figure::Annotation {
+                        position: [spectrum.mz[peak], spectrum.intensity[peak] as f64],
+                        text,
+                        color: rgb(color),
+                    });
+                }
+                continue;
+            }
+            let min_distance =
+                self.user_input.peak_label_spacing as f64 * figure.x_per_point(parameters);
+            for peak in spectrum_processing::top_peaks(
+                &spectrum.mz,
+                &spectrum.intensity,
+                figure.x_range,
+                self.user_input.peak_label_count,
+                min_distance,
+            ) {
+                figure.annotations.push(figure::Annotation {
+                    position: [spectrum.mz[peak], spectrum.intensity[peak] as f64],
+                    text: format!("{:.4}", spectrum.mz[peak]),
+                    color: color(*i),
+                });
+            }
+        }
+        Some(figure)
+    }
+
+    /// Writes a figure to a PNG or SVG file, chosen from the extension of the file.
+    ///
+    /// The size, resolution and background of the image are taken from `image_parameters`.
+    ///
+    /// # Parameters
+    /// - `figure: &figure::Figure`: The figure to be written.
+    /// - `path: &Path`: The path of the file to be written.
+    ///
+    /// # Returns
+    /// - `Result<()>`: An error if the image cannot be rendered or the file cannot be written.
+    fn export_image(&self, figure: &figure::Figure, path: &Path) -> Result<()> {
+        let parameters = &self.user_input.image_parameters;
+        let is_svg = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("svg"));
+        if is_svg {
+            std::fs::write(path, figure::render_svg(figure, parameters))?;
+        } else {
+            std::fs::write(path, figure::render_png(figure, parameters)?)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the current mass spectrum to a CSV, TSV, MSP or MGF file.
+    ///
+    /// The current spectrum is the last entry of `mass_spectra`. If `export_displayed_spectrum` is set, it is written as displayed, i.e. centroided,
+    /// filtered and scaled with the current display settings. The format is chosen from the extension of the file, and the label, scan, retention time,
+    /// precursor m/z and precursor charge of the spectrum are written along with the peaks.
+    ///
+    /// # Parameters
+    /// - `path: &Path`: The path of the file to be written.
+    ///
+    /// # Returns
+    /// - `Result<()>`: An error if no spectrum is displayed, the spectrum cannot be displayed with the selected intensity scale or the file cannot be written.
+    fn export_spectrum(&self, path: &Path) -> Result<()> {
+        let spectrum = self
+            .mass_spectra
+            .last()
+            .ok_or_else(|| anyhow::anyhow!("No mass spectrum displayed"))?;
+        let spectrum = if self.user_input.export_displayed_spectrum {
+            self.displayed_spectrum(self.mass_spectra.len() - 1)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("Reference peak not found in {}", spectrum.label))?
+        } else {
+            spectrum.clone()
+        };
+
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        export::write_spectrum(
+            &mut writer,
+            &spectrum,
+            export::SpectrumFormat::from_file_name(&path.to_string_lossy()),
+        )
+    }
+
+    /// Writes the displayed chromatogram to a CSV or TSV file.
+    ///
+    /// The file starts with comment lines describing the provenance of the main chromatogram, e.g. its file, plot type, polarity, MS level, m/z, mass tolerance and smoothing.
+    /// Every trace of `chromatograms` is written with its smoothed copy of `plot_data`, so for DIA plots every fragment trace is written as well.
+    /// The delimiter is chosen from the extension of the file, tabs for `.tsv` and `.txt` and commas otherwise.
+    ///
+    /// # Parameters
+    /// - `path: &Path`: The path of the file to be written.
+    ///
+    /// # Returns
+    /// - `Result<()>`: An error if no chromatogram is displayed or the file cannot be written.
+    fn export_chromatogram(&self, path: &Path) -> Result<()> {
+        let main = self
+            .plot_data
+            .first()
+            .ok_or_else(|| anyhow::anyhow!("No chromatogram displayed"))?;
+
+        let mut metadata = main.metadata();
+        if self.user_input.plot_type == PlotType::Dia {
+            for (key, value) in metadata.iter_mut() {
+                if key == "plot type" {
+                    *value = "DIA".to_string();
+                }
+            }
+        }
+
+        let traces: Vec<export::Trace> = self
+            .chromatograms
+            .iter()
+            .zip(self.plot_data.iter())
+            .enumerate()
+            .map(|(i, (raw, smoothed))| export::Trace::new(self.trace_name(i, raw), raw, smoothed))
+            .collect();
+
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        export::write_chromatogram(
+            &mut writer,
+            &metadata,
+            &traces,
+            self.user_input.trace_layout,
+            export::Delimiter::from_file_name(&path.to_string_lossy()),
+        )
+    }
+
+    /// Adds the spectral library options to the provided `Ui`.
+    ///
+    /// This function renders a button to load one or more MSP libraries, the list of loaded libraries with a button to remove each of them, and a button that opens the library search window.
+    ///
+    /// # Parameters
+    ///
+    /// - `ui`: A mutable reference to the `egui::Ui` object, which is used to render the UI elements.
+    ///
+    /// # Errors
+    ///
+    /// This function does not return any errors. Libraries that cannot be read are logged as warnings and skipped.
+    fn add_library_options(&mut self, ui: &mut Ui) {
+        if ui
+            .button("Load MSP library…")
+            .on_hover_text("Load one or more MSP/NIST spectral libraries")
+            .clicked()
+        {
+            if let Some(paths) = rfd::FileDialog::new()
+                .add_filter("MSP library", &[LIBRARY_FORMAT])
+                .pick_files()
+            {
+                for path in paths {
+                    match library::SpectralLibrary::open(&path) {
+                        Ok(spectral_library) => {
+                            info!(
+                                "Loaded library {} with {} entries",
+                                spectral_library.name,
+                                spectral_library.spectra.len()
+                            );
+                            self.libraries.push(spectral_library);
+                        }
+                        Err(e) => self.report_error(format!(
+                            "Failed to load the library {}: {}",
+                            path.display(),
+                            e
+                        )),
+                    }
+                }
+            }
+            ui.close_menu();
+        }
+
+        ui.separator();
+        if self.libraries.is_empty() {
+            ui.label("No library loaded");
+        }
+        let mut removed = None;
+        for (i, spectral_library) in self.libraries.iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label(format!(
+                    "{} ({} entries)",
+                    spectral_library.name,
+                    spectral_library.spectra.len()
+                ));
+                if ui.small_button("Remove").clicked() {
+                    removed = Some(i);
+                }
+            });
+        }
+        if let Some(i) = removed {
+            info!("Removing library {}", self.libraries[i].name);
+            self.libraries.remove(i);
+            // hits refer to libraries by position, so they are no longer valid
+            self.library_hits.clear();
+            self.selected_library_hit = None;
+            self.library_matches = None;
+            self.cancel_library_search();
+        }
+
+        ui.separator();
+        if ui.button("Search…").clicked() {
+            self.library_window_open = true;
+            ui.close_menu();
+        }
+    }
+
+    /// Searches the displayed mass spectrum against the loaded libraries.
+    ///
+    /// The precursor m/z of the spectrum, if any, is used for the precursor tolerance. The best hit is selected for the mirror plot.
+    fn search_displayed_spectrum(&mut self) {
+        let Some(spectrum) = self.current_spectrum() else {
+            warn!("No mass spectrum displayed, nothing to search");
+            return;
+        };
+        self.library_hits = library::search(
+            (&spectrum.mz, &spectrum.intensity),
+            spectrum.precursor_mz,
+            &self.libraries,
+            &self.user_input.library_search,
+        );
+        info!("Library search returned {} hits", self.library_hits.len());
+        self.selected_library_hit = if self.library_hits.is_empty() {
+            None
+        } else {
+            Some(0)
+        };
+    }
+
+    /// Starts searching all MS2 spectra of the opened file against the loaded libraries.
+    ///
+    /// Reading and searching every spectrum can take minutes for large files, so the search runs on a worker thread that opens the file
+    /// again, and its result is received by `receive_library_matches`. Starting the search again cancels a running search.
+    fn search_ms2_spectra(&mut self) {
+        self.cancel_library_search();
+        self.library_matches = None;
+        let Some(path) = self.user_input.file_path.clone() else {
+            warn!("No file opened, no MS2 spectra to search");
+            return;
+        };
+        let polarity = self.user_input.polarity;
+        let libraries = self.libraries.clone();
+        let parameters = self.user_input.library_search.clone();
+        let progress = Arc::new(parser::Progress::default());
+        let worker_progress = Arc::clone(&progress);
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut data = parser::MzData::new();
+            let result = data.open_msfile(&path).and_then(|data| {
+                data.search_ms2_spectra(polarity, &libraries, &parameters, &worker_progress)
+            });
+            // the receiver is gone if the search was cancelled
+            let _ = sender.send(result);
+        });
+        self.library_search_receiver = Some(receiver);
+        self.library_search_progress = Some(progress);
+    }
+
+    /// Stores the best hits of the MS2 spectra searched by the worker thread started in `search_ms2_spectra`.
+    ///
+    /// While the worker thread is running, a repaint is requested so that its progress is shown.
+    ///
+    /// # Parameters
+    /// - `ctx: &egui::Context`: A reference to the `egui::Context` object, which is used to request a repaint.
+    fn receive_library_matches(&mut self, ctx: &egui::Context) {
+        let Some(receiver) = &self.library_search_receiver else {
+            return;
+        };
+        let result = match receiver.try_recv() {
+            Ok(result) => result,
+            Err(mpsc::TryRecvError::Empty) => {
+                ctx.request_repaint_after(Duration::from_millis(100));
+                return;
+            }
+            Err(mpsc::TryRecvError::Disconnected) => {
+                error!("The search of the MS2 spectra stopped without a result");
+                self.library_search_receiver = None;
+                self.library_search_progress = None;
+                return;
+            }
+        };
+        self.library_search_receiver = None;
+        self.library_search_progress = None;
+        match result {
+            Ok(matches) => {
+                info!("Found library hits for {} MS2 spectra", matches.len());
+                self.library_matches = Some(matches);
+            }
+            Err(e) => self.report_error(format!("Failed to search the MS2 spectra. {}", e)),
+        }
+    }
+
+    /// Stops the running search of the MS2 spectra, if any, and discards its result.
+    fn cancel_library_search(&mut self) {
+        if let Some(progress) = self.library_search_progress.take() {
+            progress.cancel();
+        }
+        self.library_search_receiver = None;
+    }
+
+    /// Updates the library search window.
+    ///
+    /// # Parameters
+    ///
+    /// - `ctx`: A reference to the `egui::Context` object, which is used to render the UI elements.
+    ///
+    /// # Functionality
+    ///
+    /// 1. Checks if the `library_window_open` field is `true`, indicating that the library search window should be displayed.
+    /// 2. Renders the search settings: the similarity method, the fragment tolerance in Da, an optional precursor tolerance in ppm and the maximum number of hits.
+    /// 3. Renders buttons to search the displayed spectrum or all MS2 spectra of the file. The search of all MS2 spectra runs on a worker thread,
+    ///    whose progress is shown with a button to cancel it.
+    /// 4. Lists the ranked hits of the displayed spectrum. Clicking a hit selects it for the mirror plot.
+    /// 5. Lists the best hit of every MS2 spectrum after a search of the whole file. Clicking a row opens the spectrum and searches it.
+    /// 6. Renders a mirror plot of the displayed spectrum against the selected hit, with the matched peaks highlighted.
+    ///
+    /// # Errors
+    ///
+    /// This function does not return any errors. Failed searches are logged as errors.
+    fn update_library_window(&mut self, ctx: &egui::Context) {
+        self.receive_library_matches(ctx);
+        if !self.library_window_open {
+            return;
+        }
+        let mut open = self.library_window_open;
+        egui::Window::new("Library search")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                if self.libraries.is_empty() {
+                    ui.colored_label(
+                        Color32::LIGHT_RED,
+                        "No library loaded. Use the Library menu to load an MSP file.",
+                    );
+                }
+
+                let parameters = &mut self.user_input.library_search;
+                egui::Grid::new("library_search_settings")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Similarity");
+                        ui.horizontal(|ui| {
+                            ui.radio_value(
+                                &mut parameters.method,
+                                SimilarityMethod::Cosine,
+                                "Cosine",
+                            );
+                            ui.radio_value(
+                                &mut parameters.method,
+                                SimilarityMethod::Entropy,
+                                "Entropy",
+                            );
+                        });
+                        ui.end_row();
+
+                        ui.label("Fragment tolerance (Da)");
+                        ui.add(
+                            egui::DragValue::new(&mut parameters.fragment_tolerance)
+                                .speed(0.001)
+                                .clamp_range(0.0001..=1.0),
+                        );
+                        ui.end_row();
+
+                        let mut use_precursor = parameters.precursor_tolerance.is_some();
+                        if ui
+                            .checkbox(&mut use_precursor, "Precursor tolerance (ppm)")
+                            .changed()
+                        {
+                            parameters.precursor_tolerance =
+                                use_precursor.then_some(DEFAULT_PRECURSOR_TOLERANCE);
+                        }
+                        if let Some(tolerance) = parameters.precursor_tolerance.as_mut() {
+                            ui.add(
+                                egui::DragValue::new(tolerance)
+                                    .speed(0.5)
+                                    .clamp_range(0.1..=1000.0),
+                            );
+                        }
+                        ui.end_row();
+
+                        ui.label("Maximum hits");
+                        ui.add(egui::DragValue::new(&mut parameters.max_hits).clamp_range(1..=500));
+                        ui.end_row();
+                    });
+
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(
+                            self.current_spectrum().is_some(),
+                            egui::Button::new("Search displayed spectrum"),
+                        )
+                        .clicked()
+                    {
+                        self.search_displayed_spectrum();
+                    }
+                    if ui
+                        .add_enabled(
+                            self.user_input.file_path.is_some()
+                                && self.library_search_progress.is_none(),
+                            egui::Button::new("Search all MS2 spectra"),
+                        )
+                        .clicked()
+                    {
+                        self.search_ms2_spectra();
+                    }
+                });
+                if let Some(progress) = &self.library_search_progress {
+                    let fraction = progress.fraction();
+                    let mut cancelled = false;
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::ProgressBar::new(fraction)
+                                .desired_width(200.0)
+                                .text(format!("Searching MS2 spectra… {:.0} %", fraction * 100.0)),
+                        );
+                        cancelled = ui.button("Cancel").clicked();
+                    });
+                    if cancelled {
+                        info!("Cancelling the search of the MS2 spectra");
+                        self.cancel_library_search();
+                    }
+                }
+                ui.separator();
+
+                if let Some(matches) = &self.library_matches {
+                    ui.label(format!("Best hits of {} MS2 spectra", matches.len()));
+                    let mut opened = None;
+                    egui::ScrollArea::vertical()
+                        .id_source("library_matches")
+                        .max_height(150.0)
+                        .show(ui, |ui| {
+                            egui::Grid::new("library_matches_grid")
+                                .num_columns(5)
+                                .striped(true)
+                                .show(ui, |ui| {
+                                    ui.strong("Scan");
+                                    ui.strong("Rt");
+                                    ui.strong("Precursor m/z");
+                                    ui.strong("Best hit");
+                                    ui.strong("Score");
+                                    ui.end_row();
+                                    for spectrum_match in matches {
+                                        if ui
+                                            .selectable_label(
+                                                self.selected_spectrum_index
+                                                    == Some(spectrum_match.index),
+                                                spectrum_match.index.to_string(),
+                                            )
+                                            .clicked()
+                                        {
+                                            opened = Some(spectrum_match.index);
+                                        }
+                                        ui.label(format!("{:.2}", spectrum_match.retention_time));
+                                        ui.label(
+                                            spectrum_match
+                                                .precursor_mz
+                                                .map(|mz| format!("{:.4}", mz))
+                                                .unwrap_or_default(),
+                                        );
+                                        ui.label(library_entry_name(
+                                            &self.libraries,
+                                            &spectrum_match.hit,
+                                        ));
+                                        ui.label(format!("{:.3}", spectrum_match.hit.score));
+                                        ui.end_row();
+                                    }
+                                });
+                        });
+                    if let Some(index) = opened {
+                        self.show_mass_spectrum(index);
+                        self.search_displayed_spectrum();
+                    }
+                    ui.separator();
+                }
+
+                if self.library_hits.is_empty() {
+                    ui.label("No hits");
+                    return;
+                }
+                egui::ScrollArea::vertical()
+                    .id_source("library_hits")
+                    .max_height(150.0)
+                    .show(ui, |ui| {
+                        egui::Grid::new("library_hits_grid")
+                            .num_columns(5)
+                            .striped(true)
+                            .show(ui, |ui| {
+                                ui.strong("#");
+                                ui.strong("Name");
+                                ui.strong("Library");
+                                ui.strong("Score");
+                                ui.strong("Matched peaks");
+                                ui.end_row();
+                                for (rank, hit) in self.library_hits.iter().enumerate() {
+                                    ui.label((rank + 1).to_string());
+                                    if ui
+                                        .selectable_label(
+                                            self.selected_library_hit == Some(rank),
+                                            library_entry_name(&self.libraries, hit),
+                                        )
+                                        .clicked()
+                                    {
+                                        self.selected_library_hit = Some(rank);
+                                    }
+                                    ui.label(&self.libraries[hit.library_index].name);
+                                    ui.label(format!("{:.3}", hit.score));
+                                    ui.label(hit.matched_peaks.to_string());
+                                    ui.end_row();
+                                }
+                            });
+                    });
+
+                let (Some(hit), Some(spectrum)) = (
+                    self.selected_library_hit
+                        .and_then(|rank| self.library_hits.get(rank)),
+                    self.current_spectrum(),
+                ) else {
+                    return;
+                };
+                let entry = &self.libraries[hit.library_index].spectra[hit.spectrum_index];
+                let (score, matches) = library::similarity(
+                    (&spectrum.mz, &spectrum.intensity),
+                    (&entry.mz, &entry.intensity),
+                    self.user_input.library_search.fragment_tolerance,
+                    self.user_input.library_search.method,
+                );
+                ui.label(format!("{} — score {:.3}", entry.name, score));
+                plot_mirror(
+                    ui,
+                    "library_mirror",
+                    (&spectrum.label, &spectrum.mz, &spectrum.intensity),
+                    (&entry.name, &entry.mz, &entry.intensity),
+                    &matches,
+                );
+            });
+        self.library_window_open = open;
+    }
+}
+
+/// Renders a heatmap into an image with a logarithmic color scale.
+///
+/// Every pixel corresponds to one bin, with the highest m/z in the top row. Empty bins are transparent.
+fn heatmap_image(heatmap: &parser::Heatmap) -> egui::ColorImage {
+    intensity_grid_image(
+        heatmap.rt_bins,
+        heatmap.mz_bins,
+        heatmap.max_intensity(),
+        |rt_bin, mz_bin| heatmap.intensity(rt_bin, mz_bin),
+    )
+}
+
+/// Renders a grid of binned intensities into an image with a logarithmic color scale.
+///
+/// # Parameters
+/// - `columns: usize`: The number of bins along the x-axis.
+/// - `rows: usize`: The number of bins along the y-axis. The highest bin is drawn in the top row.
+/// - `max_intensity: f32`: The highest intensity of the grid, mapped to the end of the color gradient.
+/// - `intensity: impl Fn(usize, usize) -> f32`: Returns the intensity of the bin at the given column and row.
+///
+/// # Returns
+/// - `egui::ColorImage`: The rendered image, in which empty bins are transparent.
+fn intensity_grid_image(
+    columns: usize,
+    rows: usize,
+    max_intensity: f32,
+    intensity: impl Fn(usize, usize) -> f32,
+) -> egui::ColorImage {
+    let log_max = (max_intensity as f64).ln_1p().max(f64::EPSILON);
+    let mut pixels = Vec::with_capacity(columns * rows);
+    for row in (0..rows).rev() {
+        for column in 0..columns {
+            let value = intensity(column, row);
+            if value > 0.0 {
+                pixels.push(plotting_parameters::intensity_color(
+                    (value as f64).ln_1p() / log_max,
+                ));
+            } else {
+                pixels.push(Color32::TRANSPARENT);
+            }
+        }
+    }
+    egui::ColorImage {
+        size: [columns, rows],
+        pixels,
+    }
+}
+
+/// Returns the bar width of a spectrum plot, adjusted to its zoom level.
+fn spectrum_bar_width(bounds: &egui_plot::PlotBounds) -> f64 {
+    let zoom_level = (bounds.max()[0] - bounds.min()[0]).abs(); // Calculate zoom level based on plot bounds
+    debug!("Zoom level calculated: {}", zoom_level);
+    zoom_level * 0.001
+}
+
+/// Adds a labelled spectrum to a plot, named after its label.
+///
+/// Profile spectra are drawn as a continuous line with the given width and style, centroid spectra as sticks whose width follows the zoom level.
+fn add_spectrum_to_plot(
+    plot_ui: &mut egui_plot::PlotUi,
+    spectrum: &parser::Spectrum,
+    color: Color32,
+    line_width: f32,
+    line_style: egui_plot::LineStyle,
+) {
+    if spectrum.profile {
+        let points: Vec<[f64; 2]> = spectrum
+            .mz
+            .iter()
+            .zip(spectrum.intensity.iter())
+            .map(|(&m, &i)| [m, i as f64])
+            .collect();
+        plot_ui.line(
+            Line::new(PlotPoints::from(points))
+                .color(color)
+                .width(line_width)
+                .style(line_style)
+                .name(&spectrum.label),
+        );
+        return;
+    }
+
+    let bar_width = spectrum_bar_width(&plot_ui.plot_bounds());
+    let bars: Vec<egui_plot::Bar> = spectrum
+        .mz
+        .iter()
+        .zip(spectrum.intensity.iter())
+        .map(|(&m, &i)| {
+            egui_plot::Bar::new(m, i.into())
+                .width(bar_width)
+                .name(format!("m/z = {:.4}", m))
+        })
+        .collect();
+    plot_ui.bar_chart(
+        egui_plot::BarChart::new(bars)
+            .color(color)
+            .name(&spectrum.label),
+    );
+}
+
+/// Formats a tick of the logarithmic intensity axis with the intensity it stands for.
+fn format_log_intensity(value: f64) -> String {
+    let intensity = spectrum_processing::inverse_log_intensity(value);
+    if intensity >= 1e4 {
+        format!("{:.0e}", intensity)
+    } else {
+        format!("{:.1}", intensity)
+    }
+}
+
+/// Converts an egui color to the RGB color of a figure.
+fn rgb(color: Color32) -> [u8; 3] {
+    [color.r(), color.g(), color.b()]
+}
+
+/// Returns the axis ranges of an exported figure: the plot bounds at the last frame if available, or else the extent of the data.
+///
+/// # Parameters
+/// - `bounds: Option<egui_plot::PlotBounds>`: The plot bounds at the last frame.
+/// - `series: &[figure::Series]`: The data series of the figure.
+///
+/// # Returns
+/// - `((f64, f64), (f64, f64))`: The x and y ranges. The y range of the data extent starts at zero and leaves room for labels above the highest point.
+fn figure_ranges(
+    bounds: Option<egui_plot::PlotBounds>,
+    series: &[figure::Series],
+) -> ((f64, f64), (f64, f64)) {
+    if let Some(bounds) = bounds.filter(|bounds| bounds.is_valid()) {
+        return (
+            (bounds.min()[0], bounds.max()[0]),
+            (bounds.min()[1], bounds.max()[1]),
+        );
+    }
+    let points = series.iter().flat_map(|series| series.points.iter());
+    let (mut x_min, mut x_max, mut y_max) = (f64::INFINITY, f64::NEG_INFINITY, 0f64);
+    for point in points {
+        x_min = x_min.min(point[0]);
+        x_max = x_max.max(point[0]);
+        y_max = y_max.max(point[1]);
+    }
+    if x_min >= x_max {
+        (x_min, x_max) = (x_min - 1.0, x_min + 1.0);
+    }
+    if !x_min.is_finite() {
+        (x_min, x_max) = (0.0, 1.0);
+    }
+    (
+        (x_min, x_max),
+        (0.0, if y_max > 0.0 { y_max * 1.1 } else { 1.0 }),
+    )
+}
+
+/// Labels the most intense visible peaks of a spectrum with their m/z.
+///
+/// # Parameters
+/// - `plot_ui: &mut egui_plot::PlotUi`: The plot the labels are added to. Its bounds define the visible region.
+/// - `spectrum: &parser::Spectrum`: The spectrum to be labelled.
+/// - `count: usize`: The maximum number of labels.
+/// - `spacing: f32`: The minimum distance (in points) between two labelled peaks.
+/// - `color: Color32`: The color of the labels.
+fn add_peak_labels(
+    plot_ui: &mut egui_plot::PlotUi,
+    spectrum: &parser::Spectrum,
+    count: usize,
+    spacing: f32,
+    color: Color32,
+) {
+    let bounds = plot_ui.plot_bounds();
+    let mz_per_point = plot_ui.transform().dvalue_dpos()[0].abs();
+    for i in spectrum_processing::top_peaks(
+        &spectrum.mz,
+        &spectrum.intensity,
+        (bounds.min()[0], bounds.max()[0]),
+        count,
+        spacing as f64 * mz_per_point,
+    ) {
+        plot_ui.text(
+            egui_plot::Text::new(
+                egui_plot::PlotPoint::new(spectrum.mz[i], spectrum.intensity[i]),
+                egui::RichText::new(format!("{:.4}", spectrum.mz[i])).small(),
+            )
+            .anchor(egui::Align2::CENTER_BOTTOM)
+            .color(color),
+        );
+    }
+}
+
+/// Returns the m/z of the peak of a spectrum selected by a click on its plot.
+///
+/// Among the peaks within `PEAK_CLICK_RADIUS` of the click along the m/z axis, the most intense one is selected, which is the apex for profile spectra.
+///
+/// # Parameters
+/// - `plot_response: &egui_plot::PlotResponse<()>`: The response of the plot showing the spectrum.
+/// - `spectrum: &parser::Spectrum`: The plotted spectrum.
+///
+/// # Returns
+/// - `Option<f64>`: The m/z of the selected peak, or `None` if the plot was not clicked or no peak lies close to the click.
+fn clicked_peak(
+    plot_response: &egui_plot::PlotResponse<()>,
+    spectrum: &parser::Spectrum,
+) -> Option<f64> {
+    if !plot_response.response.clicked() {
+        return None;
+    }
+    let pointer = plot_response.response.interact_pointer_pos()?;
+    let transform = &plot_response.transform;
+    let clicked = transform.value_from_position(pointer).x;
+    let radius = PEAK_CLICK_RADIUS as f64 * transform.dvalue_dpos()[0].abs();
+    let start = spectrum.mz.partition_point(|&mz| mz < clicked - radius);
+    (start..spectrum.mz.len().min(spectrum.intensity.len()))
+        .take_while(|&i| spectrum.mz[i] <= clicked + radius)
+        .max_by(|&a, &b| {
+            spectrum.intensity[a]
+                .partial_cmp(&spectrum.intensity[b])
+                .unwrap_or(Ordering::Equal)
+        })
+        .map(|i| spectrum.mz[i])
+}
+
+/// Marks the noise region and the apex of a peak on the chromatogram plot and labels the apex with its signal-to-noise ratio.
+///
+/// # Parameters
+/// - `plot_ui: &mut egui_plot::PlotUi`: The plot the annotation is added to.
+/// - `chromatogram: &parser::Chromatogram`: The chromatogram the ratio was computed on.
+/// - `result: &SignalToNoise`: The signal-to-noise ratio of the peak.
+/// - `color: Color32`: The color of the chromatogram.
+fn add_signal_to_noise_annotation(
+    plot_ui: &mut egui_plot::PlotUi,
+    chromatogram: &parser::Chromatogram,
+    result: &SignalToNoise,
+    color: Color32,
+) {
+    let (first, last) = result.noise_region;
+    let noise = &chromatogram.intensity[first..=last];
+    let low = noise.iter().cloned().fold(f64::INFINITY, f64::min);
+    let high = noise.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let (start, end) = (
+        chromatogram.retention_time[first],
+        chromatogram.retention_time[last],
+    );
+    plot_ui.polygon(
+        egui_plot::Polygon::new(vec![[start, low], [end, low], [end, high], [start, high]])
+            .fill_color(Color32::GRAY.gamma_multiply(0.3))
+            .stroke(egui::Stroke::new(1.0, Color32::GRAY))
+            .name("Noise region"),
+    );
+
+    let apex = [
+        chromatogram.retention_time[result.apex],
+        chromatogram.intensity[result.apex],
+    ];
+    plot_ui.points(egui_plot::Points::new(vec![apex]).radius(4.0).color(color));
+    plot_ui.text(
+        egui_plot::Text::new(
+            egui_plot::PlotPoint::new(apex[0], apex[1]),
+            egui::RichText::new(format!("S/N = {:.1}", result.ratio)).strong(),
+        )
+        .anchor(egui::Align2::LEFT_BOTTOM)
+        .color(color),
+    );
+}
+
+/// Highlights the peaks matched to peptide fragment ions and labels them with the ion and its m/z.
+///
+/// N-terminal ions are drawn in `N_TERMINAL_ION_COLOR` and C-terminal ions in `C_TERMINAL_ION_COLOR`. Ions matching the same peak share one label.
+///
+/// # Parameters
+/// - `plot_ui: &mut egui_plot::PlotUi`: The plot the annotations are added to.
+/// - `spectrum: &parser::Spectrum`: The annotated spectrum.
+/// - `annotations: &[peptide::PeakAnnotation]`: The matched fragment ions.
+fn add_fragment_annotations(
+    plot_ui: &mut egui_plot::PlotUi,
+    spectrum: &parser::Spectrum,
+    annotations: &[peptide::PeakAnnotation],
+) {
+    let bar_width = spectrum_bar_width(&plot_ui.plot_bounds());
+    let mut peaks: Vec<usize> = annotations.iter().map(|a| a.peak).collect();
+    peaks.sort_unstable();
+    peaks.dedup();
+    for peak in peaks {
+        let ions: Vec<&peptide::PeakAnnotation> =
+            annotations.iter().filter(|a| a.peak == peak).collect();
+        let color = if ions[0].ion.ion_type.is_n_terminal() {
+            N_TERMINAL_ION_COLOR
+        } else {
+            C_TERMINAL_ION_COLOR
+        };
+        let (mz, intensity) = (spectrum.mz[peak], spectrum.intensity[peak] as f64);
+        plot_ui.bar_chart(
+            egui_plot::BarChart::new(vec![egui_plot::Bar::new(mz, intensity).width(bar_width)])
+                .color(color),
+        );
+        let label = ions
+            .iter()
+            .map(|a| a.ion.label())
+            .collect::<Vec<String>>()
+            .join(", ");
+        plot_ui.text(
+            egui_plot::Text::new(
+                egui_plot::PlotPoint::new(mz, intensity),
+                egui::RichText::new(format!("{}\n{:.4}", label, mz)).small(),
+            )
+            .anchor(egui::Align2::CENTER_BOTTOM)
+            .color(color),
+        );
+    }
+}
+
+/// Marks the isotope envelopes of the visible region of a spectrum and labels them with their charge state and monoisotopic m/z.
+///
+/// The labels are placed to the left of the monoisotopic peak at the height of the most intense peak of the envelope, and only the most
+/// intense envelopes are labelled, like the peak labels.
+///
+/// # Parameters
+/// - `plot_ui: &mut egui_plot::PlotUi`: The plot the labels are added to. Its bounds define the visible region.
+/// - `envelopes: &SpectrumEnvelopes`: The envelopes detected in the spectrum by `SpectrumEnvelopes::update`.
+/// - `count: usize`: The maximum number of labelled envelopes.
+/// - `spacing: f32`: The minimum distance (in points) between two labelled envelopes.
+/// - `color: Color32`: The color of the labels.
+fn add_envelope_labels(
+    plot_ui: &mut egui_plot::PlotUi,
+    envelopes: &SpectrumEnvelopes,
+    count: usize,
+    spacing: f32,
+    color: Color32,
+) {
+    let (peaks, envelopes) = (&envelopes.peaks, &envelopes.envelopes);
+    let monoisotopic_mz: Vec<f64> = envelopes.iter().map(|e| e.monoisotopic_mz).collect();
+    let max_intensity: Vec<f32> = envelopes.iter().map(|e| e.max_intensity).collect();
+
+    let bounds = plot_ui.plot_bounds();
+    let mz_per_point = plot_ui.transform().dvalue_dpos()[0].abs();
+    for i in spectrum_processing::top_peaks(
+        &monoisotopic_mz,
+        &max_intensity,
+        (bounds.min()[0], bounds.max()[0]),
+        count,
+        spacing as f64 * mz_per_point,
+    ) {
+        let envelope = &envelopes[i];
+        let points: Vec<[f64; 2]> = envelope.peaks.iter().map(|&peak| peaks[peak]).collect();
+        plot_ui.points(
+            egui_plot::Points::new(points)
+                .shape(egui_plot::MarkerShape::Diamond)
+                .radius(3.0)
+                .color(color),
+        );
+        plot_ui.text(
+            egui_plot::Text::new(
+                egui_plot::PlotPoint::new(envelope.monoisotopic_mz, envelope.max_intensity),
+                egui::RichText::new(format!(
+                    "z = {}\nmono {:.4}",
+                    envelope.charge, envelope.monoisotopic_mz
+                ))
+                .small(),
+            )
+            .anchor(egui::Align2::RIGHT_BOTTOM)
+            .color(color),
+        );
+    }
+}
+
+/// Returns the name of the library entry of a hit.
+fn library_entry_name(libraries: &[library::SpectralLibrary], hit: &library::LibraryHit) -> String {
+    libraries[hit.library_index].spectra[hit.spectrum_index]
+        .name
+        .clone()
+}
+
+/// Plots two spectra head-to-tail in a mirror plot.
+///
+/// Both spectra are scaled to a base peak of 100, the first one pointing up and the second one pointing down.
+/// The matched peaks of both spectra are drawn in color, all other peaks in gray.
+///
+/// # Parameters
+/// - `ui: &mut egui::Ui`: The `egui::Ui` instance used to render the plot.
+/// - `id: &str`: The id of the plot.
+/// - `top: (&str, &[f64], &[f32])`: The name, m/z values and intensities of the spectrum drawn upwards.
+/// - `bottom: (&str, &[f64], &[f32])`: The name, m/z values and intensities of the spectrum drawn downwards.
+/// - `matches: &[(usize, usize)]`: The matched (top, bottom) peak indices.
+///
+/// # Returns
+/// - `egui::Response`: The response from the `egui_plot::Plot` widget.
+fn plot_mirror(
+    ui: &mut egui::Ui,
+    id: &str,
+    top: (&str, &[f64], &[f32]),
+    bottom: (&str, &[f64], &[f32]),
+    matches: &[(usize, usize)],
+) -> egui::Response {
+    let mut top_matched = vec![false; top.1.len()];
+    let mut bottom_matched = vec![false; bottom.1.len()];
+    for &(i, j) in matches {
+        top_matched[i] = true;
+        bottom_matched[j] = true;
+    }
+
+    egui_plot::Plot::new(id)
+        .width(ui.available_width() * 0.99)
+        .height(300.0)
+        .x_axis_label("m/z")
+        .y_axis_label("Relative intensity (%)")
+        .legend(egui_plot::Legend::default())
+        .show(ui, |plot_ui| {
+            let bounds = plot_ui.plot_bounds();
+            let bar_width = (bounds.max()[0] - bounds.min()[0]).abs() * 0.001;
+            for (side, (name, mz, intensity), matched, color) in [
+                (1.0, top, &top_matched, plotting_parameters::trace_color(0)),
+                (
+                    -1.0,
+                    bottom,
+                    &bottom_matched,
+                    plotting_parameters::trace_color(1),
+                ),
+            ] {
+                let base_peak = intensity.iter().cloned().fold(0.0_f32, f32::max);
+                if base_peak <= 0.0 {
+                    continue;
+                }
+                let (matched_bars, unmatched_bars): (Vec<_>, Vec<_>) = mz
+                    .iter()
+                    .zip(intensity.iter())
+                    .zip(matched.iter())
+                    .map(|((&m, &i), &is_matched)| {
+                        (
+                            is_matched,
+                            egui_plot::Bar::new(m, side * 100.0 * (i / base_peak) as f64)
+                                .width(bar_width)
+                                .name(format!("m/z = {:.4}", m)),
+                        )
+                    })
+                    .partition(|(is_matched, _)| *is_matched);
+                plot_ui.bar_chart(
+                    egui_plot::BarChart::new(
+                        unmatched_bars.into_iter().map(|(_, bar)| bar).collect(),
+                    )
+                    .color(Color32::GRAY)
+                    .name(format!("{} (unmatched)", name)),
+                );
+                plot_ui.bar_chart(
+                    egui_plot::BarChart::new(
+                        matched_bars.into_iter().map(|(_, bar)| bar).collect(),
+                    )
+                    .color(color)
+                    .name(format!("{} (matched)", name)),
+                );
+            }
+        })
+        .response
+}
+
+/// Parses a list of m/z values separated by commas, semicolons or whitespace.
+///
+/// Entries that cannot be parsed as a number are skipped and logged as a warning.
+fn parse_mz_list(input: &str) -> Vec<f64> {
+    input
+        .split(|c: char| c == ',' || c == ';' || c.is_whitespace())
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| match entry.parse::<f64>() {
+            Ok(mz) => Some(mz),
+            Err(_) => {
+                warn!("Skipping invalid m/z value: {}", entry);
+                None
+            }
+        })
+        .collect()
+}
+
 impl eframe::App for MzViewerApp {
     /// Updates the application's user interface.
     ///
@@ -945,18 +5235,28 @@ impl eframe::App for MzViewerApp {
     ///
     /// # Functionality
     ///
-    /// 1. Calls the `update_data_selection_panel()` function to update the data selection panel in the user interface.
+    /// 1. Calls the `update_displayed_spectra()` function to prepare the displayed copies of new spectra, and the
+    ///    `update_data_selection_panel()` function to update the data selection panel in the user interface.
     /// 2. Calls the `update_file_information_panel()` function to update the file information panel in the user interface.
+    ///    Then calls the `update_status_bar()` function to show the last error or warning at the bottom of the user interface.
     /// 3. Calls the `update_central_panel()` function to update the central panel in the user interface, which includes the chromatogram and mass spectrum plots.
     /// 4. Calls the `update_xic_settings_window()` function to update the XIC (Extracted Ion Chromatogram) settings window in the user interface, if it is open.
+    /// 5. Calls the `update_library_window()` function to receive the result of a running search of all MS2 spectra and to update the library
+    ///    search window, if it is open.
+    /// 6. Calls the `update_composition_window()` function to receive the candidate formulas generated on the worker thread and to update the
+    ///    elemental composition window, if it is open.
     ///
     /// # Errors
     ///
     /// This method does not return any errors. It calls several other functions that may encounter errors, but those errors are handled within the respective functions
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.update_displayed_spectra();
         self.update_data_selection_panel(ctx);
         self.update_file_information_panel(ctx);
+        self.update_status_bar(ctx);
         self.update_central_panel(ctx);
         self.update_xic_settings_window(ctx);
+        self.update_library_window(ctx);
+        self.update_composition_window(ctx);
     }
 }
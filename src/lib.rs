@@ -0,0 +1,64 @@
+//! The Chromascope library reads mzML mass spectrometry data and processes it independently of the GUI, so pipelines can reuse the
+//! extraction logic of the application.
+//! The GUI and its dependencies are behind the default `gui` feature, so pipelines can depend on the library with `default-features = false`.
+//!
+//! The library consists of eight modules:
+//!
+//! 1. `parser`: Opens mzML files and extracts typed chromatograms (TIC, BPC, XIC, DIA fragment XICs) and mass spectra that carry their provenance, heatmaps and ion mobility data.
+//! 2. `library`: Reads MSP spectral libraries and scores spectra against them.
+//! 3. `spectrum_processing`: Centroids, filters and scales mass spectra and selects the peaks to be labelled.
+//! 4. `deconvolution`: Detects isotope envelopes, assigns their charge states and deconvolves spectra to neutral masses.
+//! 5. `peptide`: Computes peptide fragment ions and annotates them in MS2 spectra.
+//! 6. `composition`: Generates the molecular formulas that explain an accurate mass.
+//! 7. `export`: Writes chromatograms and spectra as delimited text, JSON, MSP or MGF.
+//! 8. `chromatogram_processing`: Smooths chromatograms with a moving average, Savitzky–Golay or Gaussian filter, estimates and subtracts their baseline and computes the signal-to-noise ratio of peaks.
+//!
+//! The Chromascope application (the `egui` GUI and the command-line interface) is built on top of this library.
+//!
+//! # Example
+//!
+//! Extract and smooth the XIC of an m/z, then retrieve the mass spectrum at the apex:
+//!
+//! ```
+//! use chromascope::chromatogram_processing::{SmoothingMethod, SmoothingParameters};
+//! use chromascope::{MzData, ScanPolarity};
+//! use std::path::PathBuf;
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let mut data = MzData::default();
+//! data.open_msfile(&PathBuf::from("test_file/data_dependent_02.mzML"))?;
+//!
+//! // TIC and BPC are extracted with `get_tic` and `get_bpic`
+//! let xic = data.get_xic(810.4, ScanPolarity::Positive, 10.0)?;
+//! let smoothed = xic.smoothed(&SmoothingParameters {
+//!     method: SmoothingMethod::SavitzkyGolay,
+//!     half_window: 3,
+//!     polynomial_order: 2,
+//! });
+//! assert_eq!(xic.len(), smoothed.len());
+//! assert_eq!(smoothed.metadata()[1], ("plot type".to_string(), "XIC".to_string()));
+//!
+//! let tic = data.get_tic(ScanPolarity::Positive)?;
+//! let apex = (0..tic.len())
+//!     .max_by(|&a, &b| tic.intensity[a].total_cmp(&tic.intensity[b]))
+//!     .expect("the file holds MS1 scans");
+//! let index = tic.scan_index[apex];
+//! let spectrum = data.get_mass_spectrum_by_index(index)?;
+//! assert_eq!(spectrum.mz.len(), spectrum.intensity.len());
+//! # Ok(())
+//! # }
+//! ```
+
+#![warn(clippy::all)]
+
+pub mod chromatogram_processing;
+pub mod composition;
+pub mod deconvolution;
+pub mod export;
+pub mod library;
+pub mod parser;
+pub mod peptide;
+pub mod spectrum_processing;
+
+pub use mzdata::spectrum::ScanPolarity;
+pub use parser::{Chromatogram, MzData, ParserError, Spectrum};
@@ -0,0 +1,471 @@
+//! # spectral library loading and searching
+
+//! The `library` module provides functionality for reading local spectral libraries in the MSP (NIST) text format and for searching mass spectra against them.
+
+//! ## Overview
+
+//! A library is loaded with `SpectralLibrary::open`, which parses every entry of an MSP file into a `LibrarySpectrum`.
+//! Spectra are compared with `similarity`, using either the cosine or the spectral entropy similarity, and `search` ranks the entries of one or more libraries against a query spectrum.
+
+//!## Features
+
+//!- **MSP Parsing**: Read the name, precursor m/z, metadata and peak list of every entry.
+//!- **Peak Matching**: Match the peaks of two spectra within an m/z tolerance, each peak being used at most once.
+//!- **Similarity Scoring**: Score spectra with the cosine or the (unweighted) spectral entropy similarity.
+//!- **Library Search**: Rank library entries against a query spectrum, with an optional precursor tolerance for MS2 spectra.
+
+#![warn(clippy::all)]
+
+use anyhow::{anyhow, Result};
+use log::{debug, info, warn};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// Represents a single entry of a spectral library.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct LibrarySpectrum {
+    /// The name of the compound or peptide.
+    pub name: String,
+    /// The precursor m/z of the entry, if it is an MS2 spectrum.
+    pub precursor_mz: Option<f64>,
+    /// All other metadata lines of the entry as (key, value) pairs, in the order of the file.
+    pub metadata: Vec<(String, String)>,
+    /// The m/z values of the peaks.
+    pub mz: Vec<f64>,
+    /// The intensities of the peaks.
+    pub intensity: Vec<f32>,
+}
+
+/// Represents a spectral library loaded from an MSP file.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SpectralLibrary {
+    /// The name of the library, taken from its file name.
+    pub name: String,
+    /// The entries of the library.
+    pub spectra: Vec<LibrarySpectrum>,
+}
+
+/// The similarity measure used to compare two spectra.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SimilarityMethod {
+    #[default]
+    Cosine,
+    Entropy,
+}
+
+/// The parameters of a library search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchParameters {
+    /// The similarity measure used to score the library entries.
+    pub method: SimilarityMethod,
+    /// The m/z tolerance (in Da) used to match fragment peaks.
+    pub fragment_tolerance: f64,
+    /// The precursor m/z tolerance (in ppm). If `None`, entries are not filtered by precursor m/z.
+    pub precursor_tolerance: Option<f64>,
+    /// The minimum score of a reported hit.
+    pub min_score: f64,
+    /// The maximum number of reported hits.
+    pub max_hits: usize,
+}
+
+impl Default for SearchParameters {
+    fn default() -> Self {
+        Self {
+            method: SimilarityMethod::default(),
+            fragment_tolerance: 0.01,
+            precursor_tolerance: None,
+            min_score: 0.0,
+            max_hits: 20,
+        }
+    }
+}
+
+/// Represents a library entry matched by a search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LibraryHit {
+    /// The index of the library in the searched slice of libraries.
+    pub library_index: usize,
+    /// The index of the entry within its library.
+    pub spectrum_index: usize,
+    /// The similarity score between the query and the entry, between `0.0` and `1.0`.
+    pub score: f64,
+    /// The number of matched peaks.
+    pub matched_peaks: usize,
+}
+
+impl SpectralLibrary {
+    /// Opens and parses an MSP library file.
+    ///
+    /// # Parameters
+    /// - `path: &Path` - The path of the MSP file.
+    ///
+    /// # Returns
+    /// - `Result<SpectralLibrary>` - The parsed library, named after the file.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read, or if it contains no entry.
+    pub fn open(path: &Path) -> Result<Self> {
+        info!("Attempting to open MSP library at path: {:?}", path);
+        let file = File::open(path)?;
+        let spectra = parse_msp(BufReader::new(file))?;
+        if spectra.is_empty() {
+            return Err(anyhow!("No library entries found in {:?}", path));
+        }
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.display().to_string());
+        debug!("Loaded {} entries from library {}", spectra.len(), name);
+        Ok(Self { name, spectra })
+    }
+}
+
+/// Parses the entries of an MSP library.
+///
+/// Every entry starts with a `Name:` line, followed by metadata lines (`Key: value`) and the peak list after the `Num Peaks:` line.
+/// Peak lines contain an m/z and an intensity separated by whitespace, optionally followed by an annotation. Several peaks may share a line when separated by `;`.
+///
+/// # Parameters
+/// - `reader: impl BufRead` - The reader of the MSP text.
+///
+/// # Returns
+/// - `Result<Vec<LibrarySpectrum>>` - The parsed entries.
+///
+/// # Errors
+/// Returns an error if the text cannot be read. Malformed peak lines are skipped and logged as a warning.
+pub fn parse_msp(reader: impl BufRead) -> Result<Vec<LibrarySpectrum>> {
+    let mut spectra = Vec::new();
+    let mut current: Option<LibrarySpectrum> = None;
+    let mut in_peaks = false;
+
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            in_peaks = false;
+            continue;
+        }
+
+        if let Some((key, value)) = line.split_once(':') {
+            if !in_peaks || key.trim().eq_ignore_ascii_case("name") {
+                let (key, value) = (key.trim(), value.trim());
+                match key.to_ascii_lowercase().as_str() {
+                    "name" => {
+                        spectra.extend(current.take());
+                        current = Some(LibrarySpectrum {
+                            name: value.to_string(),
+                            ..Default::default()
+                        });
+                        in_peaks = false;
+                    }
+                    "precursormz" | "precursor_mz" | "precursor m/z" => {
+                        if let Some(spectrum) = current.as_mut() {
+                            spectrum.precursor_mz = value.parse().ok();
+                        }
+                    }
+                    "num peaks" | "num_peaks" => in_peaks = true,
+                    _ => {
+                        if let Some(spectrum) = current.as_mut() {
+                            spectrum.metadata.push((key.to_string(), value.to_string()));
+                        }
+                    }
+                }
+                continue;
+            }
+        }
+
+        if in_peaks {
+            if let Some(spectrum) = current.as_mut() {
+                for peak in line.split(';').filter(|peak| !peak.trim().is_empty()) {
+                    let mut fields = peak.split_whitespace();
+                    match (
+                        fields.next().and_then(|mz| mz.parse::<f64>().ok()),
+                        fields.next().and_then(|int| int.parse::<f32>().ok()),
+                    ) {
+                        (Some(mz), Some(intensity)) => {
+                            spectrum.mz.push(mz);
+                            spectrum.intensity.push(intensity);
+                        }
+                        _ => warn!("Skipping malformed peak line: {}", peak),
+                    }
+                }
+            }
+        }
+    }
+    spectra.extend(current);
+    Ok(spectra)
+}
+
+/// Matches the peaks of two spectra within an m/z tolerance.
+///
+/// Candidate pairs are assigned greedily by decreasing intensity product, so every peak is used at most once.
+///
+/// # Parameters
+/// - `query: (&[f64], &[f32])` - The m/z values and intensities of the first spectrum.
+/// - `reference: (&[f64], &[f32])` - The m/z values and intensities of the second spectrum.
+/// - `tolerance: f64` - The m/z tolerance (in Da).
+///
+/// # Returns
+/// - `Vec<(usize, usize)>` - The matched (query, reference) peak indices, ordered by query index.
+pub fn match_peaks(
+    query: (&[f64], &[f32]),
+    reference: (&[f64], &[f32]),
+    tolerance: f64,
+) -> Vec<(usize, usize)> {
+    let mut reference_order: Vec<usize> = (0..reference.0.len()).collect();
+    reference_order.sort_by(|&a, &b| reference.0[a].total_cmp(&reference.0[b]));
+
+    let mut candidates = Vec::new();
+    for (i, &mz) in query.0.iter().enumerate() {
+        let start = reference_order.partition_point(|&j| reference.0[j] < mz - tolerance);
+        for &j in reference_order[start..]
+            .iter()
+            .take_while(|&&j| reference.0[j] <= mz + tolerance)
+        {
+            candidates.push((query.1[i] as f64 * reference.1[j] as f64, i, j));
+        }
+    }
+    candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let mut query_used = vec![false; query.0.len()];
+    let mut reference_used = vec![false; reference.0.len()];
+    let mut matches = Vec::new();
+    for (_, i, j) in candidates {
+        if !query_used[i] && !reference_used[j] {
+            query_used[i] = true;
+            reference_used[j] = true;
+            matches.push((i, j));
+        }
+    }
+    matches.sort();
+    matches
+}
+
+/// Computes the similarity between two spectra.
+///
+/// # Parameters
+/// - `query: (&[f64], &[f32])` - The m/z values and intensities of the first spectrum.
+/// - `reference: (&[f64], &[f32])` - The m/z values and intensities of the second spectrum.
+/// - `tolerance: f64` - The m/z tolerance (in Da) used to match peaks.
+/// - `method: SimilarityMethod` - The similarity measure.
+///
+/// # Returns
+/// - `(f64, Vec<(usize, usize)>)` - The similarity score between `0.0` and `1.0`, and the matched (query, reference) peak indices.
+pub fn similarity(
+    query: (&[f64], &[f32]),
+    reference: (&[f64], &[f32]),
+    tolerance: f64,
+    method: SimilarityMethod,
+) -> (f64, Vec<(usize, usize)>) {
+    let matches = match_peaks(query, reference, tolerance);
+    let score = match method {
+        SimilarityMethod::Cosine => cosine_score(query.1, reference.1, &matches),
+        SimilarityMethod::Entropy => entropy_score(query.1, reference.1, &matches),
+    };
+    (score, matches)
+}
+
+/// Computes the cosine similarity of two spectra from their matched peaks.
+fn cosine_score(query: &[f32], reference: &[f32], matches: &[(usize, usize)]) -> f64 {
+    let norm = |intensities: &[f32]| {
+        intensities
+            .iter()
+            .map(|&i| (i as f64).powi(2))
+            .sum::<f64>()
+            .sqrt()
+    };
+    let denominator = norm(query) * norm(reference);
+    if denominator == 0.0 {
+        return 0.0;
+    }
+    let dot: f64 = matches
+        .iter()
+        .map(|&(i, j)| query[i] as f64 * reference[j] as f64)
+        .sum();
+    (dot / denominator).clamp(0.0, 1.0)
+}
+
+/// Returns the Shannon entropy of a set of intensities normalized to sum to one.
+fn spectral_entropy(intensities: impl Iterator<Item = f64>) -> f64 {
+    intensities.filter(|&p| p > 0.0).map(|p| -p * p.ln()).sum()
+}
+
+/// Computes the unweighted spectral entropy similarity of two spectra from their matched peaks.
+///
+/// Both spectra are normalized to a total intensity of one and merged at half intensity, matched peaks being combined.
+/// The similarity is `1 - (2 * S_merged - S_query - S_reference) / ln(4)`.
+fn entropy_score(query: &[f32], reference: &[f32], matches: &[(usize, usize)]) -> f64 {
+    let query_total: f64 = query.iter().map(|&i| i as f64).sum();
+    let reference_total: f64 = reference.iter().map(|&i| i as f64).sum();
+    if query_total <= 0.0 || reference_total <= 0.0 {
+        return 0.0;
+    }
+    let p = |i: usize| query[i] as f64 / query_total;
+    let q = |j: usize| reference[j] as f64 / reference_total;
+
+    let mut query_matched = vec![false; query.len()];
+    let mut reference_matched = vec![false; reference.len()];
+    let mut merged = Vec::with_capacity(query.len() + reference.len());
+    for &(i, j) in matches {
+        query_matched[i] = true;
+        reference_matched[j] = true;
+        merged.push((p(i) + q(j)) / 2.0);
+    }
+    merged.extend(
+        (0..query.len())
+            .filter(|&i| !query_matched[i])
+            .map(|i| p(i) / 2.0),
+    );
+    merged.extend(
+        (0..reference.len())
+            .filter(|&j| !reference_matched[j])
+            .map(|j| q(j) / 2.0),
+    );
+
+    let query_entropy = spectral_entropy((0..query.len()).map(p));
+    let reference_entropy = spectral_entropy((0..reference.len()).map(q));
+    let merged_entropy = spectral_entropy(merged.into_iter());
+
+    (1.0 - (2.0 * merged_entropy - query_entropy - reference_entropy) / 4.0_f64.ln())
+        .clamp(0.0, 1.0)
+}
+
+/// Searches a query spectrum against one or more spectral libraries.
+///
+/// # Parameters
+/// - `query: (&[f64], &[f32])` - The m/z values and intensities of the query spectrum.
+/// - `precursor_mz: Option<f64>` - The precursor m/z of the query spectrum. Entries are only filtered by precursor if both this and `parameters.precursor_tolerance` are set.
+/// - `libraries: &[SpectralLibrary]` - The libraries to be searched.
+/// - `parameters: &SearchParameters` - The parameters of the search.
+///
+/// # Returns
+/// - `Vec<LibraryHit>` - The hits scoring at least `parameters.min_score`, ranked by decreasing score and truncated to `parameters.max_hits`.
+pub fn search(
+    query: (&[f64], &[f32]),
+    precursor_mz: Option<f64>,
+    libraries: &[SpectralLibrary],
+    parameters: &SearchParameters,
+) -> Vec<LibraryHit> {
+    info!(
+        "Searching a spectrum with {} peaks against {} libraries",
+        query.0.len(),
+        libraries.len()
+    );
+    let mut hits: Vec<LibraryHit> = Vec::new();
+    for (library_index, library) in libraries.iter().enumerate() {
+        for (spectrum_index, entry) in library.spectra.iter().enumerate() {
+            if let (Some(query_precursor), Some(tolerance)) =
+                (precursor_mz, parameters.precursor_tolerance)
+            {
+                match entry.precursor_mz {
+                    Some(entry_precursor)
+                        if (entry_precursor - query_precursor).abs() / query_precursor * 1e6
+                            <= tolerance => {}
+                    _ => continue,
+                }
+            }
+            let (score, matches) = similarity(
+                query,
+                (&entry.mz, &entry.intensity),
+                parameters.fragment_tolerance,
+                parameters.method,
+            );
+            if score >= parameters.min_score && !matches.is_empty() {
+                hits.push(LibraryHit {
+                    library_index,
+                    spectrum_index,
+                    score,
+                    matched_peaks: matches.len(),
+                });
+            }
+        }
+    }
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+    hits.truncate(parameters.max_hits);
+    debug!("Library search returned {} hits", hits.len());
+    hits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MSP: &str = "Name: Caffeine
+PrecursorMZ: 195.0877
+Formula: C8H10N4O2
+Num Peaks: 3
+110.0713 20
+138.0662 100 \"b-ion\"
+195.0877 45
+
+NAME: Theobromine
+PRECURSORMZ: 181.0720
+Num Peaks: 2
+138.0662 100; 181.0720 30
+";
+
+    #[test]
+    fn test_parse_msp() {
+        let spectra = parse_msp(MSP.as_bytes()).unwrap();
+        assert_eq!(spectra.len(), 2);
+        assert_eq!(spectra[0].name, "Caffeine");
+        assert_eq!(spectra[0].precursor_mz, Some(195.0877));
+        assert_eq!(
+            spectra[0].metadata,
+            vec![("Formula".to_string(), "C8H10N4O2".to_string())]
+        );
+        assert_eq!(spectra[0].mz, vec![110.0713, 138.0662, 195.0877]);
+        assert_eq!(spectra[0].intensity, vec![20.0, 100.0, 45.0]);
+        assert_eq!(spectra[1].name, "Theobromine");
+        assert_eq!(spectra[1].mz, vec![138.0662, 181.0720]);
+    }
+
+    #[test]
+    fn test_match_peaks() {
+        let query = (&[100.0, 100.004, 200.0][..], &[10.0, 50.0, 5.0][..]);
+        let reference = (&[100.002, 300.0][..], &[20.0, 1.0][..]);
+        assert_eq!(match_peaks(query, reference, 0.01), vec![(1, 0)]);
+    }
+
+    #[test]
+    fn test_similarity() {
+        let spectrum = (&[100.0, 150.0, 200.0][..], &[10.0, 100.0, 40.0][..]);
+        let disjoint = (&[120.0, 170.0][..], &[10.0, 100.0][..]);
+        for method in [SimilarityMethod::Cosine, SimilarityMethod::Entropy] {
+            let (score, matches) = similarity(spectrum, spectrum, 0.01, method);
+            assert!((score - 1.0).abs() < 1e-9);
+            assert_eq!(matches.len(), 3);
+            let (score, matches) = similarity(spectrum, disjoint, 0.01, method);
+            assert!(score.abs() < 1e-9);
+            assert!(matches.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_search() {
+        let library = SpectralLibrary {
+            name: "test".to_string(),
+            spectra: parse_msp(MSP.as_bytes()).unwrap(),
+        };
+        let query = (&[110.07, 138.066, 195.088][..], &[25.0, 100.0, 40.0][..]);
+
+        let hits = search(
+            query,
+            None,
+            std::slice::from_ref(&library),
+            &SearchParameters::default(),
+        );
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].spectrum_index, 0);
+        assert!(hits[0].score > hits[1].score);
+
+        let parameters = SearchParameters {
+            precursor_tolerance: Some(10.0),
+            ..Default::default()
+        };
+        let hits = search(query, Some(195.0877), &[library], &parameters);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].spectrum_index, 0);
+    }
+}
@@ -1,25 +1,62 @@
 //! Chromascope is a lightweight and easy-to-use GUI application designed to read mzML mass spectrometry data.
 //!
-//! The crate consists of three main modules:
+//! The application is built on the `chromascope` library, which reads and processes the data (see `lib.rs`), and consists of four modules:
 //!
 //! 1. `gui.rs`: This module contains the implementation of the graphical user interface (GUI) using the `egui` library.
-//! 2. `parser.rs`: This module handles the parsing and processing of the mzML data files.
-//! 3. `plotting_parameters.rs`: This module defines the parameters and settings for the data plotting functionality.
+//! 2. `plotting_parameters.rs`: This module defines the parameters and settings for the data plotting functionality.
+//! 3. `figure.rs`: This module renders chromatograms and mass spectra to PNG and SVG images.
+//! 4. `cli.rs`: This module extracts chromatograms from the command line without opening a window.
+//!
+//! When started with arguments, e.g. `chromascope xic --mz 722.43 --ppm 5 file.mzML -o out.csv`, Chromascope runs the command line interface
+//! and exits with its exit code. Without arguments, the GUI is started. A single argument naming an existing file, as passed by a file
+//! association or "Open with", opens that file in the GUI.
+//!
+//! On Windows the binary uses the GUI subsystem, so the command line interface attaches to the console of the shell it was started from
+//! to print its output, help and errors.
 
 #![cfg_attr(target_os = "windows", windows_subsystem = "windows")]
 
+mod cli;
+mod figure;
 mod gui;
-mod parser;
 mod plotting_parameters;
 
 use egui::IconData;
 use gui::*;
 use log::{error, info};
+use std::path::PathBuf;
 use std::process;
 
+/// Attaches the process to the console of its parent, so the command line interface can write to it.
+///
+/// The GUI subsystem starts the process without a console. Without a parent console, e.g. when started from Explorer, nothing happens.
+#[cfg(target_os = "windows")]
+fn attach_parent_console() {
+    /// The process identifier that selects the console of the parent process.
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+    // SAFETY: AttachConsole has no preconditions and only fails if there is no parent console or one is already attached
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
 fn main() {
     env_logger::init();
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let file_to_open = match args.as_slice() {
+        [path] if PathBuf::from(path).is_file() => Some(PathBuf::from(path)),
+        _ => None,
+    };
+    if !args.is_empty() && file_to_open.is_none() {
+        #[cfg(target_os = "windows")]
+        attach_parent_console();
+        process::exit(cli::run(&args));
+    }
+
     // include icon in the compiled binary
     let icon_image = image::load_from_memory(include_bytes!(r"../assets/chromascope_icon.png"))
         .expect("Should be able to open icon PNG file");
@@ -38,11 +75,14 @@ fn main() {
         ..Default::default()
     };
 
-    match eframe::run_native(
-        "Chromascope",
-        native_options,
-        Box::new(|cc| Box::new(MzViewerApp::new(cc))),
-    ) {
+    let app_creator: eframe::AppCreator = Box::new(move |cc| {
+        let mut app = MzViewerApp::new(cc);
+        if let Some(path) = file_to_open {
+            app.open_file(&path);
+        }
+        Box::new(app)
+    });
+    match eframe::run_native("Chromascope", native_options, app_creator) {
         Ok(_) => {
             info!("Application exited succesfully.");
             process::exit(0)
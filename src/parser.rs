@@ -4,47 +4,743 @@
 
 //! ## Overview
 
-//!The main struct in this crate is `MzData`, which encapsulates the data and methods necessary for handling MzML files. The struct includes fields for storing file information, retention times, intensities, mass-to-charge ratios (m/z), and more.
+//!The main struct in this crate is `MzData`, which encapsulates the opened MzML file and the methods for extracting data from it.
+//!Every extraction returns its result as a typed value, e.g. a `Chromatogram`, a `Spectrum`, a `Heatmap` or a `Mobilogram`, and leaves storing it to the caller.
+//!Chromatograms and mass spectra Both carry their provenance, i.e. the file, the type of data, the polarity, the MS level,
+//!the extracted m/z and tolerance and the retention time unit, so that several traces can coexist and be exported with a description of their origin.
 
 //!## Features
 
 //!- **File Handling**: Open and read MzML files.
 //!- **Data Extraction**: Extract BIC, TIC, and XIC based on specified parameters.
-//!- **Data Processing**: Smooth data for better visualization and analysis.
-//!- **Plot Preparation**: Prepare data for plotting with appropriate formatting.
+//!- **DIA/SWATH Extraction**: Extract co-eluting fragment XICs from the MS2 scans whose isolation window contains a given precursor.
+//!- **Precursor Coverage**: Collect the retention time, m/z, charge and intensity of every MS2 precursor.
+//!- **Heatmap**: Bin all MS1 data into a fixed size retention time × m/z intensity grid.
+//!- **Ion Mobility**: Extract mobilograms, mobility × m/z heatmaps of a frame and mobility filtered XICs from ion mobility arrays or per-scan drift times.
+//!- **Data Processing**: Smooth chromatograms with the filters of the `chromatogram_processing` module for better visualization and analysis.
+//!- **Error Reporting**: Opening files and extracting chromatograms fail with a `ParserError` whose message tells the user how to resolve the problem.
 
 #![warn(clippy::all)]
 
+use crate::chromatogram_processing::{
+    self, BaselineParameters, SignalToNoise, SignalToNoiseParameters, SmoothingParameters,
+};
+use crate::library::{self, LibraryHit, SearchParameters, SpectralLibrary};
+use crate::spectrum_processing;
 use anyhow::anyhow;
 use anyhow::Result;
 use log::{debug, error, info, trace, warn};
 use mzdata::io::mzml::MzMLReaderType;
-use mzdata::spectrum::ScanPolarity;
+use mzdata::spectrum::bindata::ArrayRetrievalError;
+use mzdata::spectrum::{
+    IsolationWindow, IsolationWindowState, ScanPolarity, SignalContinuity, SpectrumConversionError,
+};
 use mzdata::{prelude::*, MzMLReader};
 use std::fs::File;
-use std::path::PathBuf;
+use std::io::Read;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
 /// Represents a data structure for storing mass spectrometry data.
 const MS_LEVEL: u8 = 1;
+/// The MS level of the fragment scans used for DIA/SWATH extraction.
+const MS2_LEVEL: u8 = 2;
+/// The number of bytes at the start of a file that are searched for the `<mzML` root element.
+const MZML_HEADER_LENGTH: u64 = 4096;
+
+/// Represents the errors of opening an mzML file and extracting data from it.
+///
+/// Every message ends with a hint on how to resolve the problem, so it can be shown to the user as is.
+#[derive(Debug, thiserror::Error)]
+pub enum ParserError {
+    /// The file cannot be opened or read.
+    #[error("Cannot read {path}: {source}. Check that the file exists and that you are allowed to read it.")]
+    Io {
+        /// The path of the file.
+        path: String,
+        /// The underlying I/O error.
+        #[source]
+        source: std::io::Error,
+    },
+    /// The file is not a well-formed mzML document.
+    #[error("{path} is not a valid mzML file: {detail}. Convert the raw data to mzML again, e.g. with ProteoWizard msconvert.")]
+    MalformedXml {
+        /// The path of the file.
+        path: String,
+        /// A description of the problem.
+        detail: String,
+    },
+    /// A spectrum has no usable m/z or intensity array.
+    #[error("Scan {index} has no usable peak data: {detail}. The file may be truncated or hold only spectrum metadata.")]
+    MissingArrays {
+        /// The index of the spectrum in its file.
+        index: usize,
+        /// A description of the problem.
+        detail: String,
+    },
+    /// The data arrays of a spectrum use an encoding, compression or signal type that cannot be read.
+    #[error("The data of scan {index} cannot be read: {detail}. Convert the file again with peak picking and zlib or no compression (no MS-Numpress).")]
+    UnsupportedEncoding {
+        /// The index of the spectrum in its file.
+        index: usize,
+        /// A description of the problem.
+        detail: String,
+    },
+    /// No file has been opened.
+    #[error("No mzML file is open. Open a file first.")]
+    NoFileOpened,
+    /// No spectrum of the file matches the requested polarity, MS level or precursor.
+    #[error("{file} contains no {description}. Check the polarity and the acquisition method of the file.")]
+    NoMatchingSpectra {
+        /// The name of the file.
+        file: String,
+        /// A description of the spectra that were searched for, e.g. `Negative MS1 scans`.
+        description: String,
+    },
+    /// The operation was cancelled through its `Progress` before it finished.
+    #[error("The operation was cancelled.")]
+    Cancelled,
+}
+
+/// The result of opening an mzML file or extracting data from it.
+pub type ParserResult<T> = std::result::Result<T, ParserError>;
+
+impl ParserError {
+    /// Classifies an error of decoding the data arrays of the spectrum at `index`.
+    fn from_arrays(index: usize, error: ArrayRetrievalError) -> Self {
+        match error {
+            ArrayRetrievalError::NotFound(_) => ParserError::MissingArrays {
+                index,
+                detail: error.to_string(),
+            },
+            ArrayRetrievalError::DecompressionError(_)
+            | ArrayRetrievalError::DataTypeSizeMismatch => ParserError::UnsupportedEncoding {
+                index,
+                detail: error.to_string(),
+            },
+        }
+    }
+
+    /// Classifies an error of centroiding the spectrum at `index`.
+    fn from_conversion(index: usize, error: SpectrumConversionError) -> Self {
+        match error {
+            SpectrumConversionError::ArrayRetrievalError(error) => Self::from_arrays(index, error),
+            SpectrumConversionError::NotCentroided => ParserError::UnsupportedEncoding {
+                index,
+                detail: "the spectrum is in profile mode, but centroided data is required"
+                    .to_string(),
+            },
+            _ => ParserError::MissingArrays {
+                index,
+                detail: error.to_string(),
+            },
+        }
+    }
+
+    /// Creates the error of a file without spectra matching `description`.
+    fn no_matching_spectra(file_name: Option<&str>, description: String) -> Self {
+        ParserError::NoMatchingSpectra {
+            file: file_name.unwrap_or("The file").to_string(),
+            description,
+        }
+    }
+}
+
+/// Represents a single MS2 precursor of a data dependent acquisition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrecursorPoint {
+    /// The index of the MS2 spectrum in the source file.
+    pub index: usize,
+    /// The retention time of the MS2 scan.
+    pub retention_time: f64,
+    /// The m/z of the selected precursor ion.
+    pub mz: f64,
+    /// The charge state of the selected precursor ion, if it was reported.
+    pub charge: Option<i32>,
+    /// The intensity of the selected precursor ion.
+    pub intensity: f32,
+}
+
+/// Represents a binned intensity map of the MS1 data across retention time and m/z.
+///
+/// The size of the grid is fixed by the number of bins, independent of the number of data points in the file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heatmap {
+    /// The retention time range covered by the grid.
+    pub rt_range: (f64, f64),
+    /// The m/z range covered by the grid.
+    pub mz_range: (f64, f64),
+    /// The number of bins along the retention time axis.
+    pub rt_bins: usize,
+    /// The number of bins along the m/z axis.
+    pub mz_bins: usize,
+    /// The summed intensities of every bin, stored row by row with one row per m/z bin.
+    pub intensities: Vec<f32>,
+    /// The retention time and spectrum index of every binned MS1 scan, used to retrieve the mass spectrum of a selected region.
+    pub scans: Vec<(f64, usize)>,
+}
+
+impl Heatmap {
+    /// Returns the summed intensity of the bin at the given retention time and m/z bin indices.
+    pub fn intensity(&self, rt_bin: usize, mz_bin: usize) -> f32 {
+        self.intensities[mz_bin * self.rt_bins + rt_bin]
+    }
+
+    /// Returns the highest summed intensity of the grid.
+    pub fn max_intensity(&self) -> f32 {
+        self.intensities.iter().cloned().fold(0.0, f32::max)
+    }
+
+    /// Returns the index of the binned MS1 scan closest to the provided retention time.
+    pub fn closest_scan(&self, retention_time: f64) -> Option<usize> {
+        self.scans
+            .iter()
+            .min_by(|a, b| {
+                (a.0 - retention_time)
+                    .abs()
+                    .partial_cmp(&(b.0 - retention_time).abs())
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .map(|&(_, index)| index)
+    }
+}
+
+/// Returns the bin of `value` within `range` split into `bins` equally sized bins, or `None` if the value is outside of the range.
+fn bin_index(value: f64, range: (f64, f64), bins: usize) -> Option<usize> {
+    if value < range.0 || value > range.1 || bins == 0 {
+        return None;
+    }
+    let width = (range.1 - range.0).max(f64::EPSILON);
+    Some((((value - range.0) / width) * bins as f64).min((bins - 1) as f64) as usize)
+}
+
+/// Represents a binned intensity map of a single ion mobility frame across ion mobility and m/z.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MobilityHeatmap {
+    /// The index of the spectrum the frame was selected by.
+    pub index: usize,
+    /// The ion mobility range covered by the grid.
+    pub mobility_range: (f64, f64),
+    /// The m/z range covered by the grid.
+    pub mz_range: (f64, f64),
+    /// The number of bins along the ion mobility axis.
+    pub mobility_bins: usize,
+    /// The number of bins along the m/z axis.
+    pub mz_bins: usize,
+    /// The summed intensities of every bin, stored row by row with one row per m/z bin.
+    pub intensities: Vec<f32>,
+}
+
+impl MobilityHeatmap {
+    /// Returns the summed intensity of the bin at the given ion mobility and m/z bin indices.
+    pub fn intensity(&self, mobility_bin: usize, mz_bin: usize) -> f32 {
+        self.intensities[mz_bin * self.mobility_bins + mobility_bin]
+    }
+
+    /// Returns the highest summed intensity of the grid.
+    pub fn max_intensity(&self) -> f32 {
+        self.intensities.iter().cloned().fold(0.0, f32::max)
+    }
+}
+
+/// Represents the ion mobilogram of an m/z, i.e. its intensity summed over all MS1 scans at every ion mobility value.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Mobilogram {
+    /// The name of the file the mobilogram was extracted from, if any.
+    pub file_name: Option<String>,
+    /// The polarity of the scans.
+    pub polarity: ScanPolarity,
+    /// The extracted m/z.
+    pub mz: f64,
+    /// The mass tolerance (in ppm) of the extraction.
+    pub mass_tolerance: f64,
+    /// The ion mobility values, in increasing order.
+    pub mobility: Vec<f64>,
+    /// The summed intensity at every ion mobility value.
+    pub intensity: Vec<f64>,
+}
+
+impl Mobilogram {
+    /// Returns the number of data points.
+    pub fn len(&self) -> usize {
+        self.mobility.len()
+    }
+
+    /// Returns `true` if the mobilogram has no data points, e.g. because the file has no ion mobility dimension.
+    pub fn is_empty(&self) -> bool {
+        self.mobility.is_empty()
+    }
+
+    /// Returns the (ion mobility, intensity) data points for plotting.
+    pub fn points(&self) -> Vec<[f64; 2]> {
+        self.mobility
+            .iter()
+            .zip(self.intensity.iter())
+            .map(|(&mobility, &intensity)| [mobility, intensity])
+            .collect()
+    }
+}
+
+/// Returns `true` if the `<mzML` root element (or `<indexedmzML`) occurs at the start of the file.
+///
+/// Only the element tags are matched, so comments, schema URLs or file names that mention mzML are not mistaken for the root element.
+///
+/// # Errors
+/// If the file cannot be read, the function returns the I/O error.
+fn has_mzml_root(path: &Path) -> std::io::Result<bool> {
+    let mut header = Vec::new();
+    File::open(path)?
+        .take(MZML_HEADER_LENGTH)
+        .read_to_end(&mut header)?;
+    let header = String::from_utf8_lossy(&header);
+    Ok(["<mzML", "<indexedmzML"].iter().any(|tag| {
+        header.match_indices(tag).any(|(start, _)| {
+            header[start + tag.len()..].starts_with(|c: char| c.is_whitespace() || c == '>')
+        })
+    }))
+}
+
+/// Returns the m/z, intensity and ion mobility of every data point of a spectrum.
+///
+/// The ion mobility is read from the ion mobility array of the spectrum if present, otherwise the scan-level ion mobility (e.g. drift time) is assigned to every data point.
+/// Spectra without any ion mobility information yield no data points.
+///
+/// # Errors
+/// If the m/z or intensity arrays of the spectrum cannot be decoded, the function returns a `ParserError`.
+fn mobility_points(spectrum: &mzdata::Spectrum) -> ParserResult<Vec<(f64, f32, f64)>> {
+    let Some(arrays) = spectrum.arrays.as_ref() else {
+        return Ok(Vec::new());
+    };
+    let to_error = |error| ParserError::from_arrays(spectrum.index(), error);
+    let mzs = arrays.mzs().map_err(to_error)?;
+    let intensities = arrays.intensities().map_err(to_error)?;
+
+    if let Ok((mobilities, _)) = arrays.ion_mobility() {
+        return Ok(mzs
+            .iter()
+            .zip(intensities.iter())
+            .zip(mobilities.iter())
+            .map(|((mz, intensity), mobility)| (*mz, *intensity, *mobility as f64))
+            .collect());
+    }
+
+    Ok(match spectrum.ion_mobility() {
+        Some(mobility) => mzs
+            .iter()
+            .zip(intensities.iter())
+            .map(|(mz, intensity)| (*mz, *intensity, mobility))
+            .collect(),
+        None => Vec::new(),
+    })
+}
+
+/// Sums the intensities of data points sharing the same ion mobility value.
+///
+/// # Returns
+/// - `(Vec<f64>, Vec<f64>)` - The ion mobility values in increasing order and their summed intensities.
+fn sum_by_mobility(mut points: Vec<(f64, f64)>) -> (Vec<f64>, Vec<f64>) {
+    points.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+    let mut mobility: Vec<f64> = Vec::new();
+    let mut intensity: Vec<f64> = Vec::new();
+    for (value, point_intensity) in points {
+        match (mobility.last(), intensity.last_mut()) {
+            (Some(last), Some(summed)) if *last == value => *summed += point_intensity,
+            _ => {
+                mobility.push(value);
+                intensity.push(point_intensity);
+            }
+        }
+    }
+    (mobility, intensity)
+}
+
+/// Merges data points of several spectra whose m/z values lie within a tolerance of each other.
+///
+/// # Parameters
+/// - `points: Vec<(f64, f32)>` - The (m/z, intensity) data points of all spectra.
+/// - `tolerance: f64` - The tolerance (in ppm) between the first m/z of a group and the m/z of the points merged into it.
+///
+/// # Returns
+/// - `(Vec<f64>, Vec<f32>)` - The intensity-weighted mean m/z and the summed intensity of every group, ordered by m/z.
+fn merge_points(mut points: Vec<(f64, f32)>, tolerance: f64) -> (Vec<f64>, Vec<f32>) {
+    points.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+    let mut groups: Vec<(f64, f64, f64)> = Vec::new();
+    for (mz, intensity) in points {
+        match groups.last_mut() {
+            Some((first_mz, weighted_mz, total))
+                if mz - *first_mz <= *first_mz * tolerance * 1e-6 =>
+            {
+                *weighted_mz += mz * intensity as f64;
+                *total += intensity as f64;
+            }
+            _ => groups.push((mz, mz * intensity as f64, intensity as f64)),
+        }
+    }
+    groups
+        .into_iter()
+        .map(|(first_mz, weighted_mz, total)| {
+            let mz = if total > 0.0 {
+                weighted_mz / total
+            } else {
+                first_mz
+            };
+            (mz, total as f32)
+        })
+        .unzip()
+}
+
+/// The unit of retention times.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimeUnit {
+    /// Minutes, the unit of the scan start times read by `mzdata`.
+    #[default]
+    Minute,
+    /// Seconds.
+    Second,
+}
+
+impl TimeUnit {
+    /// Returns the symbol of the unit, e.g. `min`.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            TimeUnit::Minute => "min",
+            TimeUnit::Second => "s",
+        }
+    }
+
+    /// Converts a time in this unit to minutes.
+    pub fn to_minutes(&self, time: f64) -> f64 {
+        match self {
+            TimeUnit::Minute => time,
+            TimeUnit::Second => time / 60.0,
+        }
+    }
+
+    /// Converts a time in this unit to seconds.
+    pub fn to_seconds(&self, time: f64) -> f64 {
+        match self {
+            TimeUnit::Minute => time * 60.0,
+            TimeUnit::Second => time,
+        }
+    }
+}
+
+/// The type of a chromatogram.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChromatogramType {
+    /// The total ion current of every scan.
+    #[default]
+    Tic,
+    /// The base peak intensity of every scan.
+    Bpc,
+    /// The intensity of an m/z within a mass tolerance in the MS1 scans.
+    Xic,
+    /// The intensity of a fragment m/z in the MS2 scans whose isolation window contains a precursor (DIA/SWATH).
+    DiaFragment,
+}
+
+/// Represents a chromatogram together with the provenance of its data.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Chromatogram {
+    /// The name of the file the chromatogram was extracted from.
+    pub file_name: Option<String>,
+    /// The type of the chromatogram.
+    pub chromatogram_type: ChromatogramType,
+    /// The polarity of the scans.
+    pub polarity: ScanPolarity,
+    /// The MS level of the scans, or `None` if scans of all MS levels are included.
+    pub ms_level: Option<u8>,
+    /// The extracted m/z of an XIC, or the fragment m/z of a DIA fragment trace.
+    pub mz: Option<f64>,
+    /// The mass tolerance (in ppm) of the extraction.
+    pub mass_tolerance: Option<f64>,
+    /// The precursor m/z of a DIA fragment trace.
+    pub precursor_mz: Option<f64>,
+    /// The ion mobility range of a mobility filtered XIC.
+    pub mobility_range: Option<(f64, f64)>,
+    /// The smoothing applied to the intensities, a half window of 0 for raw data.
+    pub smoothing: SmoothingParameters,
+    /// The baseline estimation whose baseline was subtracted from the intensities, if any.
+    pub baseline_correction: Option<BaselineParameters>,
+    /// The unit of the retention times.
+    pub time_unit: TimeUnit,
+    /// The retention times of the data points, in ascending order.
+    pub retention_time: Vec<f64>,
+    /// The intensities of the data points, in the intensity unit of the file (usually detector counts).
+    pub intensity: Vec<f64>,
+    /// The index of the scan every data point was read from.
+    pub scan_index: Vec<usize>,
+}
+
+impl Chromatogram {
+    /// Creates an empty chromatogram of the given type, file and polarity.
+    fn new(
+        chromatogram_type: ChromatogramType,
+        file_name: Option<&str>,
+        polarity: ScanPolarity,
+    ) -> Self {
+        Self {
+            file_name: file_name.map(String::from),
+            chromatogram_type,
+            polarity,
+            ..Default::default()
+        }
+    }
+
+    /// Appends a data point. A data point at the retention time of the previous one, e.g. another spectrum of the same ion mobility frame
+    /// or another peak within the mass tolerance, is averaged with it.
+    fn push(&mut self, retention_time: f64, intensity: f64, scan_index: usize, count: &mut usize) {
+        match (self.retention_time.last(), self.intensity.last_mut()) {
+            (Some(&last), Some(last_intensity)) if last == retention_time => {
+                *count += 1;
+                *last_intensity += (intensity - *last_intensity) / *count as f64;
+            }
+            _ => {
+                *count = 1;
+                self.retention_time.push(retention_time);
+                self.intensity.push(intensity);
+                self.scan_index.push(scan_index);
+            }
+        }
+    }
+
+    /// Returns the number of data points.
+    pub fn len(&self) -> usize {
+        self.retention_time.len()
+    }
+
+    /// Returns `true` if the chromatogram has no data points.
+    pub fn is_empty(&self) -> bool {
+        self.retention_time.is_empty()
+    }
+
+    /// Returns the name of the chromatogram, e.g. `TIC` or `XIC m/z = 722.4300`.
+    pub fn name(&self) -> String {
+        match self.chromatogram_type {
+            ChromatogramType::Tic => "TIC".to_string(),
+            ChromatogramType::Bpc => "BPC".to_string(),
+            ChromatogramType::Xic => format!("XIC m/z = {:.4}", self.mz.unwrap_or_default()),
+            ChromatogramType::DiaFragment => {
+                format!("MS2 fragment m/z = {:.4}", self.mz.unwrap_or_default())
+            }
+        }
+    }
+
+    /// Returns the (retention time, intensity) data points, e.g. for plotting.
+    pub fn points(&self) -> Vec<[f64; 2]> {
+        self.retention_time
+            .iter()
+            .zip(self.intensity.iter())
+            .map(|(&rt, &intensity)| [rt, intensity])
+            .collect()
+    }
+
+    /// Returns a copy of the chromatogram smoothed with `chromatogram_processing::smooth`, recording the parameters in `smoothing`.
+    pub fn smoothed(&self, parameters: &SmoothingParameters) -> Self {
+        Self {
+            intensity: chromatogram_processing::smooth(&self.intensity, parameters),
+            smoothing: *parameters,
+            ..self.clone()
+        }
+    }
+
+    /// Returns the baseline of the intensities, estimated with `chromatogram_processing::estimate_baseline`.
+    pub fn baseline(&self, parameters: &BaselineParameters) -> Vec<f64> {
+        chromatogram_processing::estimate_baseline(&self.intensity, parameters)
+    }
+
+    /// Returns a copy of the chromatogram with the estimated baseline subtracted, recording the parameters in `baseline_correction`.
+    pub fn baseline_corrected(&self, parameters: &BaselineParameters) -> Self {
+        Self {
+            intensity: chromatogram_processing::subtract_baseline(
+                &self.intensity,
+                &self.baseline(parameters),
+            ),
+            baseline_correction: Some(*parameters),
+            ..self.clone()
+        }
+    }
+
+    /// Returns the signal-to-noise ratio of the peak at a retention time, computed with `chromatogram_processing::signal_to_noise`.
+    pub fn signal_to_noise(
+        &self,
+        retention_time: f64,
+        parameters: &SignalToNoiseParameters,
+    ) -> Option<SignalToNoise> {
+        chromatogram_processing::signal_to_noise(
+            &self.retention_time,
+            &self.intensity,
+            retention_time,
+            parameters,
+        )
+    }
+
+    /// Returns the index of the scan whose data point lies closest to the provided retention time.
+    pub fn closest_scan(&self, retention_time: f64) -> Option<usize> {
+        let position = self
+            .retention_time
+            .partition_point(|&rt| rt < retention_time);
+        let candidates = [position.checked_sub(1), Some(position)];
+        candidates
+            .into_iter()
+            .flatten()
+            .filter(|&i| i < self.len())
+            .min_by(|&a, &b| {
+                (self.retention_time[a] - retention_time)
+                    .abs()
+                    .total_cmp(&(self.retention_time[b] - retention_time).abs())
+            })
+            .map(|i| self.scan_index[i])
+    }
+
+    /// Returns the provenance of the chromatogram as (key, value) pairs, e.g. to describe it in an exported file.
+    ///
+    /// The file, plot type, polarity, MS level, smoothing and retention time unit are always included, the m/z, precursor m/z,
+    /// mass tolerance, ion mobility range and baseline correction only if they apply.
+    pub fn metadata(&self) -> Vec<(String, String)> {
+        let plot_type = match self.chromatogram_type {
+            ChromatogramType::Tic => "TIC",
+            ChromatogramType::Bpc => "BPC",
+            ChromatogramType::Xic => "XIC",
+            ChromatogramType::DiaFragment => "DIA fragment XIC",
+        };
+        let mut metadata = vec![
+            (
+                "file".to_string(),
+                self.file_name.clone().unwrap_or_default(),
+            ),
+            ("plot type".to_string(), plot_type.to_string()),
+            ("polarity".to_string(), format!("{:?}", self.polarity)),
+            (
+                "MS level".to_string(),
+                self.ms_level
+                    .map_or("all".to_string(), |level| level.to_string()),
+            ),
+        ];
+        if let Some(precursor_mz) = self.precursor_mz {
+            metadata.push(("precursor m/z".to_string(), precursor_mz.to_string()));
+        }
+        if let Some(mz) = self.mz {
+            metadata.push(("m/z".to_string(), mz.to_string()));
+        }
+        if let Some(mass_tolerance) = self.mass_tolerance {
+            metadata.push((
+                "mass tolerance (ppm)".to_string(),
+                mass_tolerance.to_string(),
+            ));
+        }
+        if let Some((min, max)) = self.mobility_range {
+            metadata.push(("ion mobility range".to_string(), format!("{}-{}", min, max)));
+        }
+        metadata.push(("smoothing".to_string(), self.smoothing.to_string()));
+        if let Some(baseline_correction) = self.baseline_correction {
+            metadata.push((
+                "baseline correction".to_string(),
+                baseline_correction.to_string(),
+            ));
+        }
+        metadata.push((
+            "retention time unit".to_string(),
+            self.time_unit.symbol().to_string(),
+        ));
+        metadata
+    }
+}
+
+/// Represents a mass spectrum together with the label it is displayed with and the provenance of its data.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Spectrum {
+    /// The label of the spectrum, e.g. its file and scan index.
+    pub label: String,
+    /// The name of the file the spectrum was read from, if any.
+    pub file_name: Option<String>,
+    /// The index of the spectrum in its file, if it was read from one.
+    pub index: Option<usize>,
+    /// The MS level of the spectrum, if it was read from a file.
+    pub ms_level: Option<u8>,
+    /// The polarity of the spectrum.
+    pub polarity: ScanPolarity,
+    /// The retention time of the spectrum, if it was read from a file.
+    pub retention_time: Option<f64>,
+    /// The unit of the retention time.
+    pub time_unit: TimeUnit,
+    /// The precursor m/z of the spectrum, if it is an MS2 spectrum.
+    pub precursor_mz: Option<f64>,
+    /// The precursor charge state of the spectrum, if it is an MS2 spectrum and the charge was reported.
+    pub precursor_charge: Option<i32>,
+    /// The m/z values of the spectrum.
+    pub mz: Vec<f64>,
+    /// The intensities of the spectrum.
+    pub intensity: Vec<f32>,
+    /// Whether the spectrum is profile data, as opposed to centroid data.
+    pub profile: bool,
+}
+
+/// Returns the name of a file without its directory, used to label its spectra.
+fn file_label(file_name: Option<&str>) -> String {
+    file_name
+        .and_then(|name| std::path::Path::new(name).file_name())
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "Unknown file".to_string())
+}
+
+/// Returns the label of a spectrum, made of the name of its file and its index.
+fn spectrum_label(file_name: Option<&str>, index: usize) -> String {
+    format!("{} scan {}", file_label(file_name), index)
+}
+
+/// Represents the best library hit of a single MS2 spectrum.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpectrumMatch {
+    /// The index of the MS2 spectrum.
+    pub index: usize,
+    /// The retention time of the MS2 spectrum.
+    pub retention_time: f64,
+    /// The precursor m/z of the MS2 spectrum, if available.
+    pub precursor_mz: Option<f64>,
+    /// The highest scoring library hit of the spectrum.
+    pub hit: LibraryHit,
+}
+
+/// The progress of an extraction that reads every spectrum of a file, shared with the thread that waits for it.
+#[derive(Debug, Default)]
+pub struct Progress {
+    /// The number of spectra read so far.
+    pub done: AtomicUsize,
+    /// The number of spectra of the file.
+    pub total: AtomicUsize,
+    /// Stops the extraction before the next spectrum when set.
+    pub cancel: AtomicBool,
+}
+
+impl Progress {
+    /// Returns the fraction of the spectra read so far, between 0 and 1.
+    pub fn fraction(&self) -> f32 {
+        let total = self.total.load(Ordering::Relaxed);
+        if total == 0 {
+            0.0
+        } else {
+            self.done.load(Ordering::Relaxed) as f32 / total as f32
+        }
+    }
+
+    /// Requests the extraction to stop.
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns whether the extraction was requested to stop.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+}
 
 /// Represents a data structure for storing mass spectrometry data.
 pub struct MzData {
     /// An optional `String` representing the name of the data file.
     pub file_name: Option<String>,
-    /// An optional vector of `usize`corresponding to the indices.
-    pub index: Option<Vec<usize>>,
-    /// An optional vector of `f32` values representing retention times.
-    pub retention_time: Option<Vec<f32>>,
-    /// An optional vector of `f32` values representing intensity values.
-    pub intensity: Option<Vec<f32>>,
-    /// An optional vector of `f32` values representing m/z (mass-to-charge) ratios.
-    pub mz: Option<Vec<f32>>,
     /// A `Result` containing the `MzMLReaderType<File>`, which represents the parsed mass spectrometry file.
     pub msfile: Result<MzMLReaderType<File>>,
-    /// An optional vector of tuples, each containing two `f64` values for plotting data points.
-    pub plot_data: Option<Vec<[f64; 2]>>,
-    /// An optional tuple containing two vectors: one for mass values (`Vec<f64>`) and one for corresponding intensity values (`Vec<f32>`).
-    pub mass_spectrum: Option<(Vec<f64>, Vec<f32>)>,
 }
 
 /// Provides a default implementation for `MzData`.
@@ -59,20 +755,14 @@ impl core::fmt::Debug for MzData {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("MzData")
             .field("file_name", &self.file_name)
-            .field("retention_time", &self.retention_time)
-            .field("intensity", &self.intensity)
-            .field("mz", &self.mz)
             .field("msfile", &"Result<MzMLReaderType<File>>")
-            .field("plot_data", &self.plot_data)
-            .field("mass_spectrum", &self.mass_spectrum)
             .finish()
     }
 }
 impl MzData {
     /// Creates a new instance of `MzData` with default values.
     ///
-    /// This method initializes all fields of `MzData` to `None`, except for the `msfile` field,
-    /// which is set to an error indicating that the file has not been opened.
+    /// This method initializes the `file_name` field to `None` and the `msfile` field to an error indicating that the file has not been opened.
     ///
     /// # Returns
     ///
@@ -80,42 +770,57 @@ impl MzData {
     pub fn new() -> Self {
         Self {
             file_name: None,
-            index: None,
-            retention_time: None,
-            intensity: None,
-            mz: None,
             msfile: Err(anyhow!("File not opened")),
-            plot_data: None,
-            mass_spectrum: None,
         }
     }
     /// Opens an MzML file at the specified path and sets it as the current file for the `self` object.
     ///
     /// # Arguments
-    /// * `path` - A reference to a `PathBuf` representing the file path of the MzML file to be opened.
+    /// * `path` - The path of the MzML file to be opened, e.g. a `&Path`, `&PathBuf` or `&str`.
     ///
     /// # Returns
-    /// * `Result<&mut Self>` - A result containing either a reference to the `self` object if the file was successfully opened, or an error if the file could not be opened.
+    /// * `ParserResult<&mut Self>` - A result containing either a reference to the `self` object if the file was successfully opened, or an error if the file could not be opened.
     ///
     /// # Errors
     /// This function may return the following errors:
-    /// * `anyhow::Error` - If the MzML file could not be opened for any reason.
+    /// * `ParserError::Io` - If the file does not exist or cannot be read.
+    /// * `ParserError::MalformedXml` - If the file is not an mzML document.
+    /// * `ParserError::NoMatchingSpectra` - If the mzML document holds no spectra.
     ///
     /// # Examples
     /// ```
+    /// use chromascope::parser::MzData;
     /// use std::path::PathBuf;
     ///
-    /// let mut example_struct = Mzdata::default();
-    /// let file_path = PathBuf::from("path/to/your/mzml/file.mzml");
+    /// let mut example_struct = MzData::default();
+    /// let file_path = PathBuf::from("test_file/data_dependent_02.mzML");
     /// example_struct.open_msfile(&file_path).unwrap();
     /// ```
-    pub fn open_msfile(&mut self, path: &PathBuf) -> Result<&mut Self> {
+    pub fn open_msfile(&mut self, path: impl AsRef<Path>) -> ParserResult<&mut Self> {
+        let path = path.as_ref();
         info!("Attempting to open MzML file at path: {:?}", &path);
+        let file_name = path.display().to_string();
 
-        match MzMLReader::open_path(&path) {
+        match MzMLReader::open_path(path) {
             Ok(reader) => {
+                // mzdata skips what it cannot parse, so a document that is not mzML opens as an empty file
+                if reader.spectrum_index.is_empty() {
+                    let error = if has_mzml_root(path).map_err(|source| ParserError::Io {
+                        path: file_name.clone(),
+                        source,
+                    })? {
+                        ParserError::no_matching_spectra(Some(&file_name), "spectra".to_string())
+                    } else {
+                        ParserError::MalformedXml {
+                            path: file_name,
+                            detail: "no <mzML> root element was found".to_string(),
+                        }
+                    };
+                    error!("Failed to open MzML file at path: {:?}: {}", &path, error);
+                    return Err(error);
+                }
                 self.msfile = Ok(reader);
-                self.file_name = Some(path.display().to_string());
+                self.file_name = Some(file_name);
                 debug!("Successfully opened MzML file at path: {:?}", &path);
                 Ok(self)
             }
@@ -124,7 +829,16 @@ impl MzData {
                     "Failed to open MzML file at path: {:?} with error: {:?}",
                     &path, e
                 );
-                Err(anyhow!("Failed to open MzML file: {:?}", e))
+                Err(match e.kind() {
+                    std::io::ErrorKind::InvalidData => ParserError::MalformedXml {
+                        path: file_name,
+                        detail: e.to_string(),
+                    },
+                    _ => ParserError::Io {
+                        path: file_name,
+                        source: e,
+                    },
+                })
             }
         }
     }
@@ -134,55 +848,56 @@ impl MzData {
     /// - `polarity: ScanPolarity` - The polarity of the mass spectrometry scans to be considered.
     ///
     /// # Returns
-    /// - `Result<&mut Self>` - A mutable reference to the current instance of the struct, or an error if the operation fails.
+    /// - `ParserResult<Chromatogram>` - The base peak chromatogram, or an error if the operation fails.
     ///
     /// # Functionality
     /// 1. Logs an informational message about the attempt to read the BPIC.
     /// 2. Matches the `msfile` field, which is a `Result<MsFile, Error>`, and performs the following steps:
-    ///     a. Iterates over the spectra in the `MsFile` and filters them based on the provided `polarity`.
-    ///     b. For each filtered spectrum, extracts the retention time, intensity, m/z, and index, and stores them in separate vectors.
-    ///     c. Assigns the extracted values to the corresponding fields in the current instance of the struct (`retention_time`, `intensity`, `mz`, `index`).
+    ///     a. Iterates over the spectra in the `MsFile` and filters them based on the provided `polarity`. Scans of all MS levels are included.
+    ///     b. For each filtered spectrum, appends the retention time, the intensity of the base peak and the spectrum index to the chromatogram.
     /// 3. Logs a debug message indicating the successful extraction of the BPIC.
-    /// 4. Logs a trace message with the details of the extracted BPIC (retention time, index, m/z, and intensity).
-    /// 5. Returns the mutable reference to the current instance of the struct.
+    /// 4. Returns the chromatogram.
     ///
     /// # Errors
-    /// If there is an error while accessing the `msfile` field, an error message is logged, and the function returns an error.
-    pub fn get_bpic(&mut self, polarity: ScanPolarity) -> Result<&mut Self> {
+    /// If no file is open, an error message is logged, and the function returns `ParserError::NoFileOpened`.
+    /// If the file has no scans of the provided polarity, the function returns `ParserError::NoMatchingSpectra`.
+    pub fn get_bpic(&mut self, polarity: ScanPolarity) -> ParserResult<Chromatogram> {
         info!("Attempting to read BIC of {:?}", &self.file_name);
+        let mut chromatogram =
+            Chromatogram::new(ChromatogramType::Bpc, self.file_name.as_deref(), polarity);
         match &mut self.msfile {
             Ok(reader) => {
-                let (retention_time, intensity, mz, index) = reader
+                let mut count = 0;
+                for spectrum in reader
                     .iter()
                     .filter(|spectrum| spectrum.description.polarity == polarity)
-                    .map(|spectrum| {
-                        let retention_time = spectrum.start_time() as f32;
-                        let intensity = spectrum.peaks().base_peak().intensity;
-                        let mz = spectrum.peaks().base_peak().mz as f32;
-                        let index = spectrum.index();
-                        (retention_time, intensity, mz, index)
-                    })
-                    .fold(
-                        (Vec::new(), Vec::new(), Vec::new(), Vec::new()),
-                        |mut acc, (rt, int, mz, index)| {
-                            acc.0.push(rt);
-                            acc.1.push(int);
-                            acc.2.push(mz);
-                            acc.3.push(index);
-                            acc
-                        },
+                {
+                    chromatogram.push(
+                        spectrum.start_time(),
+                        spectrum.peaks().base_peak().intensity as f64,
+                        spectrum.index(),
+                        &mut count,
                     );
-
-                self.retention_time = Some(retention_time);
-                self.intensity = Some(intensity);
-                self.mz = Some(mz);
-                self.index = Some(index);
+                }
                 debug!("Successfully extracted BIC from: {:?}", &self.file_name);
-                trace!("Successfully extracted the BIC of {:?}. Rt is {:?}, Index is {:?}, Mz is {:?}, Intensity is {:?}, ", &self.file_name, &self.retention_time, &self.index, &self.mz, &self.intensity);
+                trace!(
+                    "Successfully extracted the BIC of {:?}: {:?}",
+                    &self.file_name,
+                    &chromatogram
+                );
+            }
+            Err(e) => {
+                error!("Failed to get BIC due to {:?}", e);
+                return Err(ParserError::NoFileOpened);
             }
-            Err(e) => error!("Failed to get BIC due to {:?}", e),
         }
-        Ok(self)
+        if chromatogram.is_empty() {
+            return Err(ParserError::no_matching_spectra(
+                self.file_name.as_deref(),
+                format!("{:?} scans", polarity),
+            ));
+        }
+        Ok(chromatogram)
     }
     /// Method to read the Total Ion Chromatogram (TIC) from the associated mass spectrometry file.
     ///
@@ -190,52 +905,56 @@ impl MzData {
     /// - `polarity: ScanPolarity` - The polarity of the mass spectrometry scans to be considered.
     ///
     /// # Returns
-    /// - `Result<&mut Self>` - A mutable reference to the current instance of the struct, or an error if the operation fails.
+    /// - `ParserResult<Chromatogram>` - The total ion chromatogram, or an error if the operation fails.
     ///
     /// # Functionality
     /// 1. Logs an informational message about the attempt to read the TIC.
     /// 2. Matches the `msfile` field, which is a `Result<MsFile, Error>`, and performs the following steps:
-    ///     a. Initializes empty vectors for `retention_time`, `intensity`, and `index`.
-    ///     b. Iterates over the spectra in the `MsFile` and filters them based on the provided `polarity`.
-    ///     c. For each filtered spectrum, extracts the retention time, total ion intensity, and index, and appends them to the corresponding vectors.
-    ///     d. Initializes an empty vector for `mz`.
-    ///     e. Assigns the extracted vectors to the corresponding fields in the current instance of the struct (`retention_time`, `intensity`, `mz`, `index`).
+    ///     a. Iterates over the spectra in the `MsFile` and filters them based on the provided `polarity`. Scans of all MS levels are included.
+    ///     b. For each filtered spectrum, appends the retention time, the total ion intensity and the spectrum index to the chromatogram.
     /// 3. Logs a debug message indicating the successful extraction of the TIC.
-    /// 4. Logs a trace message with the details of the extracted TIC (retention time, index, m/z, and intensity).
-    /// 5. Returns the mutable reference to the current instance of the struct.
+    /// 4. Returns the chromatogram.
     ///
     /// # Errors
-    /// If there is an error while accessing the `msfile` field, an error message is logged, and the function returns an error.
-
-    pub fn get_tic(&mut self, polarity: ScanPolarity) -> Result<&mut Self> {
+    /// If no file is open, an error message is logged, and the function returns `ParserError::NoFileOpened`.
+    /// If the file has no scans of the provided polarity, the function returns `ParserError::NoMatchingSpectra`.
+    pub fn get_tic(&mut self, polarity: ScanPolarity) -> ParserResult<Chromatogram> {
         info!("Attempting to read TIC of {:?}", &self.file_name);
+        let mut chromatogram =
+            Chromatogram::new(ChromatogramType::Tic, self.file_name.as_deref(), polarity);
         match &mut self.msfile {
             Ok(reader) => {
-                let mut retention_time = Vec::new();
-                let mut intensity = Vec::new();
-                let mut index = Vec::new();
-
+                let mut count = 0;
                 for spectrum in reader
                     .iter()
                     .filter(|spectrum| spectrum.description.polarity == polarity)
                 {
-                    retention_time.push(spectrum.start_time() as f32);
-                    intensity.push(spectrum.peaks().tic());
-                    index.push(spectrum.index());
+                    chromatogram.push(
+                        spectrum.start_time(),
+                        spectrum.peaks().tic() as f64,
+                        spectrum.index(),
+                        &mut count,
+                    );
                 }
-
-                let mz: Vec<f32> = Vec::new();
-
-                self.retention_time = Some(retention_time);
-                self.intensity = Some(intensity);
-                self.mz = Some(mz);
-                self.index = Some(index);
                 debug!("Successfully extracted TIC from: {:?}", &self.file_name);
-                trace!("Successfully extracted the BIC of {:?}. Rt is {:?}, Index is {:?}, Mz is {:?}, Intensity is {:?}, ", &self.file_name, &self.retention_time, &self.index, &self.mz, &self.intensity);
+                trace!(
+                    "Successfully extracted the TIC of {:?}: {:?}",
+                    &self.file_name,
+                    &chromatogram
+                );
+            }
+            Err(e) => {
+                error!("Failed to get TIC due to {:?}", e);
+                return Err(ParserError::NoFileOpened);
             }
-            Err(e) => error!("Failed to get TIC due to {:?}", e),
         }
-        Ok(self)
+        if chromatogram.is_empty() {
+            return Err(ParserError::no_matching_spectra(
+                self.file_name.as_deref(),
+                format!("{:?} scans", polarity),
+            ));
+        }
+        Ok(chromatogram)
     }
     /// Method to read the Extracted Ion Chromatogram (XIC) for the specified mass and polarity from the associated mass spectrometry file.
     ///
@@ -245,280 +964,1024 @@ impl MzData {
     /// - `mass_tolerance: f64` - The mass tolerance (in parts per million) to be used for peak extraction.
     ///
     /// # Returns
-    /// - `Result<&mut Self>` - A mutable reference to the current instance of the struct, or an error if the operation fails.
+    /// - `ParserResult<Chromatogram>` - The extracted ion chromatogram, or an error if the operation fails.
     ///
     /// # Functionality
     /// 1. Logs an informational message about the attempt to read the XIC.
-    /// 2. Initializes empty vectors for `retention_time`, `intensity`, `index`, and `mz` in the current instance of the struct.
-    /// 3. Matches the `msfile` field, which is a `Result<MsFile, Error>`, and performs the following steps:
+    /// 2. Matches the `msfile` field, which is a `Result<MsFile, Error>`, and performs the following steps:
     ///     a. Iterates over the spectra in the `MsFile`.
     ///     b. For each spectrum, checks if the MS level is the expected level and the polarity matches the provided one.
     ///     c. If the conditions are met, the spectrum is cloned and converted to a centroided spectrum.
     ///     d. The centroided spectrum is then used to extract the peaks that match the provided mass and mass tolerance.
-    ///     e. For each extracted peak, the retention time, intensity, and index are appended to the corresponding vectors in the current instance of the struct.
-    /// 4. If the `index` vector was populated, it is sorted to ensure the data is in the correct order.
-    /// 5. Logs a debug message indicating the successful extraction of the XIC.
-    /// 6. Logs a trace message with the details of the extracted XIC (retention time, index, m/z, and intensity).
-    /// 7. If no matching peaks were found, a warning message is logged.
-    /// 8. Returns the mutable reference to the current instance of the struct.
+    ///     e. For each extracted peak, the retention time, intensity and spectrum index are appended to the chromatogram. Peaks of the same scan are averaged.
+    /// 3. Logs a debug message indicating the successful extraction of the XIC.
+    /// 4. If no matching peaks were found, a warning message is logged.
+    /// 5. Returns the chromatogram. Scans without a matching peak have no data point.
     ///
     /// # Errors
-    /// If there is an error while accessing the `msfile` field or converting the spectrum to a centroided spectrum, an error message is logged, and the function returns an error.
+    /// If no file is open, an error message is logged, and the function returns `ParserError::NoFileOpened`.
+    /// If the file has no MS1 scans of the provided polarity, the function returns `ParserError::NoMatchingSpectra`.
+    /// If a spectrum cannot be converted to a centroided spectrum, the function returns `ParserError::MissingArrays` or `ParserError::UnsupportedEncoding`.
 
     pub fn get_xic(
         &mut self,
         mass: f64,
         polarity: ScanPolarity,
         mass_tolerance: f64,
-    ) -> Result<&mut Self> {
+    ) -> ParserResult<Chromatogram> {
         info!("Attempting to read XIC of {:?}", &self.file_name);
-
-        self.retention_time = Some(Vec::new());
-        self.intensity = Some(Vec::new());
-        self.index = Some(Vec::new()); // if the self.index is cleared, when triple clicked one cannot extract the mass spectrum
-        self.mz = Some(Vec::new());
+        let mut chromatogram = Chromatogram {
+            ms_level: Some(MS_LEVEL),
+            mz: Some(mass),
+            mass_tolerance: Some(mass_tolerance),
+            ..Chromatogram::new(ChromatogramType::Xic, self.file_name.as_deref(), polarity)
+        };
 
         match &mut self.msfile {
             Ok(reader) => {
+                let mut count = 0;
+                let mut matching_scans = 0;
                 for spectrum in reader.iter() {
                     if spectrum.description.ms_level == MS_LEVEL
                         && spectrum.description.polarity == polarity
                     {
-                        let centroided = spectrum.clone().into_centroid()?;
+                        matching_scans += 1;
+                        let centroided = spectrum
+                            .clone()
+                            .into_centroid()
+                            .map_err(|e| ParserError::from_conversion(spectrum.index(), e))?;
                         let extracted_centroided = centroided
                             .peaks
                             .all_peaks_for(mass, Tolerance::PPM(mass_tolerance));
 
                         for peak in extracted_centroided {
-                            if let Some(rt) = &mut self.retention_time {
-                                rt.push(
-                                    spectrum.description.acquisition.scans[0].start_time as f32,
-                                );
-                            };
-                            if let Some(intensity) = &mut self.intensity {
-                                intensity.push(peak.intensity);
-                            };
-                            if let Some(index) = &mut self.index {
-                                index.push(peak.index as usize);
-                            };
+                            chromatogram.push(
+                                spectrum.start_time(),
+                                peak.intensity as f64,
+                                spectrum.index(),
+                                &mut count,
+                            );
                         }
                     }
                 }
-                if let Some(index) = &mut self.index {
-                    index.sort()
-                }; // self.index was unordered in case of XIC
 
                 debug!("Successfully extracted XIC from: {:?}", &self.file_name);
-                trace!("Successfully extracted the XIC of {:?}. Rt is {:?}, Index is {:?}, Mz is {:?}, Intensity is {:?}, ", &self.file_name, &self.retention_time, &self.index, &self.mz, &self.intensity);
+                trace!(
+                    "Successfully extracted the XIC of {:?}: {:?}",
+                    &self.file_name,
+                    &chromatogram
+                );
 
-                if self.retention_time.is_none() {
+                if matching_scans == 0 {
+                    return Err(ParserError::no_matching_spectra(
+                        self.file_name.as_deref(),
+                        format!("{:?} MS1 scans", polarity),
+                    ));
+                }
+                if chromatogram.is_empty() {
                     warn!("No matching peaks found");
                 }
             }
-            Err(e) => error!("Failed to get XIC due to {:?}", e),
+            Err(e) => {
+                error!("Failed to get XIC due to {:?}", e);
+                return Err(ParserError::NoFileOpened);
+            }
         }
-        Ok(self)
+        Ok(chromatogram)
     }
 
-    /// Prepares the data for plotting by processing the retention times and intensities.
+    /// Method to extract the fragment ion chromatograms of a precursor from DIA/SWATH data.
+    ///
+    /// # Parameters
+    /// - `precursor_mz: f64` - The precursor m/z used to select the MS2 scans.
+    /// - `fragment_mzs: &[f64]` - The fragment m/z values to be extracted.
+    /// - `polarity: ScanPolarity` - The polarity of the mass spectrometry scans to be considered.
+    /// - `mass_tolerance: f64` - The mass tolerance (in parts per million) to be used for fragment extraction.
     ///
     /// # Returns
-    /// - `Result<Vec<[f64; 2]>>` - A vector of data points, where each data point is an array of two `f64` values representing the retention time and the average intensity, or an error if the operation fails.
+    /// - `ParserResult<Vec<Chromatogram>>` - One chromatogram per fragment m/z, in the order of `fragment_mzs`, or an error if the operation fails.
     ///
     /// # Functionality
-    /// 1. Logs an informational message about the start of the data preparation for plotting.
-    /// 2. Initializes an empty vector `data` to store the prepared data points.
-    /// 3. Initializes variables `temp_rt` (to store the current retention time) and `temp_intensity_collector` (to store the intensities for the current retention time).
-    /// 4. Checks if the `retention_time` and `intensity` fields in the current instance of the struct are not `None`.
-    /// 5. If the fields are not `None`, the function performs the following steps:
-    ///     a. Logs a trace message with the number of retention times and intensities being processed.
-    ///     b. Iterates over the retention times and intensities, and for each unique retention time:
-    ///         i. Calculates the average intensity for the current retention time and adds a data point (retention time, average intensity) to the `data` vector.
-    ///         ii. Clears the `temp_intensity_collector` and updates the `temp_rt` variable.
-    ///     c. After the loop, if there are any remaining intensities, the function adds a final data point to the `data` vector.
-    /// 6. If the `retention_time` or `intensity` fields are `None`, the function logs a warning message.
-    /// 7. Logs a debug message with the number of data points prepared for plotting.
-    /// 8. Returns the `data` vector.
+    /// 1. Logs an informational message about the attempt to read the fragment XICs.
+    /// 2. Initializes an empty chromatogram for every fragment m/z.
+    /// 3. Matches the `msfile` field, which is a `Result<MsFile, Error>`, and performs the following steps:
+    ///     a. Iterates over the spectra in the `MsFile`.
+    ///     b. For each spectrum, checks if it is an MS2 scan of the provided polarity whose isolation window contains the precursor m/z.
+    ///     c. If the conditions are met, the spectrum is cloned and converted to a centroided spectrum.
+    ///     d. For every fragment, the intensities of the peaks within the mass tolerance are summed and appended to the fragment trace together with the retention time. Scans without a matching peak contribute a zero intensity, so all traces share the same retention times.
+    /// 4. Returns the chromatograms.
     ///
     /// # Errors
-    /// The function does not return any errors, but it may log warning messages if the required data is missing.
-
-    pub fn prepare_for_plot(&self) -> Result<Vec<[f64; 2]>> {
+    /// If no file is open, an error message is logged, and the function returns `ParserError::NoFileOpened`.
+    /// If no MS2 scan isolated the precursor, the function returns `ParserError::NoMatchingSpectra`.
+    /// If a spectrum cannot be converted to a centroided spectrum, the function returns `ParserError::MissingArrays` or `ParserError::UnsupportedEncoding`.
+    pub fn get_dia_fragment_xics(
+        &mut self,
+        precursor_mz: f64,
+        fragment_mzs: &[f64],
+        polarity: ScanPolarity,
+        mass_tolerance: f64,
+    ) -> ParserResult<Vec<Chromatogram>> {
         info!(
-            "Starting to prepare data for plotting {:?}",
-            &self.file_name
+            "Attempting to read fragment XICs of precursor {} from {:?}",
+            precursor_mz, &self.file_name
         );
 
-        let mut data = Vec::new();
-        let mut temp_rt = 0.0;
-        let mut temp_intensity_collector = Vec::new();
-
-        if let (Some(retention_times), Some(intensities)) = (&self.retention_time, &self.intensity)
-        {
-            trace!(
-                "Processing {} retention times and intensities",
-                retention_times.len()
-            );
-
-            for (idx, &rt) in retention_times.iter().enumerate() {
-                if rt != temp_rt && !temp_intensity_collector.is_empty() {
-                    data.push([
-                        temp_rt as f64,
-                        temp_intensity_collector.iter().sum::<f64>()
-                            / temp_intensity_collector.len() as f64,
-                    ]);
-                    trace!("Added data point for RT: {}", temp_rt);
-                    temp_intensity_collector.clear();
-                    temp_rt = rt;
+        let mut traces: Vec<Chromatogram> = fragment_mzs
+            .iter()
+            .map(|&fragment_mz| Chromatogram {
+                ms_level: Some(MS2_LEVEL),
+                mz: Some(fragment_mz),
+                mass_tolerance: Some(mass_tolerance),
+                precursor_mz: Some(precursor_mz),
+                ..Chromatogram::new(
+                    ChromatogramType::DiaFragment,
+                    self.file_name.as_deref(),
+                    polarity,
+                )
+            })
+            .collect();
+
+        match &mut self.msfile {
+            Ok(reader) => {
+                let mut matching_scans = 0;
+                for spectrum in reader.iter() {
+                    let isolates_precursor = spectrum
+                        .precursor()
+                        .map(|precursor| {
+                            isolation_window_contains(&precursor.isolation_window, precursor_mz)
+                        })
+                        .unwrap_or(false);
+
+                    if spectrum.description.ms_level == MS2_LEVEL
+                        && spectrum.description.polarity == polarity
+                        && isolates_precursor
+                    {
+                        matching_scans += 1;
+                        let retention_time = spectrum.start_time();
+                        let centroided = spectrum
+                            .clone()
+                            .into_centroid()
+                            .map_err(|e| ParserError::from_conversion(spectrum.index(), e))?;
+
+                        for trace in traces.iter_mut() {
+                            let intensity: f64 = centroided
+                                .peaks
+                                .all_peaks_for(
+                                    trace.mz.unwrap_or_default(),
+                                    Tolerance::PPM(mass_tolerance),
+                                )
+                                .iter()
+                                .map(|peak| peak.intensity as f64)
+                                .sum();
+                            trace.retention_time.push(retention_time);
+                            trace.intensity.push(intensity);
+                            trace.scan_index.push(spectrum.index());
+                        }
+                    }
+                }
+
+                debug!(
+                    "Successfully extracted {} fragment XICs from {} MS2 scans of: {:?}",
+                    traces.len(),
+                    matching_scans,
+                    &self.file_name
+                );
+                trace!("Fragment XICs of {:?}: {:?}", &self.file_name, &traces);
+
+                if matching_scans == 0 {
+                    warn!(
+                        "No MS2 scan with an isolation window containing {} found",
+                        precursor_mz
+                    );
+                    return Err(ParserError::no_matching_spectra(
+                        self.file_name.as_deref(),
+                        format!(
+                            "{:?} MS2 scans isolating m/z {} (DIA/SWATH data is required)",
+                            polarity, precursor_mz
+                        ),
+                    ));
                 }
-                temp_intensity_collector.push(intensities[idx].into());
             }
-            // The second if statement after the loop is needed to process the remaining intensities.
-            if !temp_intensity_collector.is_empty() {
-                data.push([
-                    temp_rt as f64,
-                    temp_intensity_collector.iter().sum::<f64>()
-                        / temp_intensity_collector.len() as f64,
-                ]);
-                trace!("Added final data point for RT: {}", temp_rt);
+            Err(e) => {
+                error!("Failed to get fragment XICs due to {:?}", e);
+                return Err(ParserError::NoFileOpened);
             }
-        } else {
-            warn!("Retention times or intensities are missing");
         }
 
-        debug!(
-            "Prepared {} data points for plotting {:?}",
-            data.len(),
-            &self.file_name
-        );
-
-        Ok(data)
+        Ok(traces)
     }
 
-    /// Method to smooth the provided data using a moving average filter.
+    /// Method to collect the precursors of all MS2 scans from the associated mass spectrometry file.
     ///
     /// # Parameters
-    /// - `data: Result<Vec<[f64; 2]>>` - The data to be smoothed, represented as a vector of arrays with two `f64` values (x and y).
-    /// - `window_size: u8` - The size of the smoothing window.
+    /// - `polarity: ScanPolarity` - The polarity of the mass spectrometry scans to be considered.
     ///
     /// # Returns
-    /// - `Result<&mut Self>` - A mutable reference to the current instance of the struct, with the smoothed data stored in the `plot_data` field, or an error if the operation fails.
+    /// - `ParserResult<Vec<PrecursorPoint>>` - The precursors of all MS2 scans in the order of the file, or an error if the operation fails.
     ///
     /// # Functionality
-    /// 1. Logs an informational message about the start of the data smoothing process with the specified window size.
-    /// 2. Unwraps the `data` parameter, which is a `Result<Vec<[f64; 2]>>`.
-    /// 3. Logs a debug message with the number of data points received for smoothing.
-    /// 4. Initializes an empty vector `smoothed_data` to store the smoothed data points.
-    /// 5. Iterates over the input data points:
-    ///     a. If the current index is less than the window size or greater than or equal to the length of the data minus the window size, the original data point is added to the `smoothed_data` vector.
-    ///     b. Otherwise, the function calculates the average of the data points within the smoothing window (the current point and the `window_size` points before and after it) and adds the smoothed data point (original x-value, average y-value) to the `smoothed_data` vector.
-    /// 6. Assigns the `smoothed_data` vector to the `plot_data` field in the current instance of the struct.
-    /// 7. Logs a debug message indicating that the data smoothing is complete.
-    /// 8. Returns the mutable reference to the current instance of the struct.
+    /// 1. Logs an informational message about the attempt to read the precursors.
+    /// 2. Matches the `msfile` field, which is a `Result<MsFile, Error>`, and performs the following steps:
+    ///     a. Iterates over the spectra in the `MsFile` and filters them based on the MS level and the provided `polarity`.
+    ///     b. For each selected ion of every filtered spectrum, stores the spectrum index, retention time, precursor m/z, charge and intensity as a `PrecursorPoint`.
+    /// 3. If no precursor was found, a warning message is logged.
+    /// 4. Returns the collected precursors.
     ///
     /// # Errors
-    /// If there is an error unwrapping the `data` parameter, the function returns the error.
-    pub fn smooth_data(
+    /// If no file is open, an error message is logged, and the function returns `ParserError::NoFileOpened`.
+    pub fn get_precursor_map(
         &mut self,
-        data: Result<Vec<[f64; 2]>>,
-        window_size: u8,
-    ) -> Result<&mut Self> {
-        info!("Starting data smoothing with window size: {}", window_size);
-
-        let data = data?;
-        debug!("Received {} data points for smoothing", data.len());
-
-        let mut smoothed_data = Vec::new();
-        let window_size_usize = window_size as usize;
-
-        for i in 0..data.len() {
-            if i < window_size_usize || i >= data.len() - window_size_usize {
-                // Not enough data to smooth, keep original
-                smoothed_data.push(data[i]);
-                trace!("Keeping original data point at index {}", i);
-            } else {
-                // Calculate the average for the smoothing window
-                let sum: f64 = data[i - window_size_usize..=i + window_size_usize]
+        polarity: ScanPolarity,
+    ) -> ParserResult<Vec<PrecursorPoint>> {
+        info!("Attempting to read MS2 precursors of {:?}", &self.file_name);
+        match &mut self.msfile {
+            Ok(reader) => {
+                let precursors: Vec<PrecursorPoint> = reader
                     .iter()
-                    .map(|point| point[1])
-                    .sum();
-                let average = sum / (f64::from(window_size) * 2.0_f64 + 1.0_f64);
-                smoothed_data.push([data[i][0], average]);
-                trace!("Smoothed data point at index {}: {}", i, average);
+                    .filter(|spectrum| {
+                        spectrum.description.ms_level == MS2_LEVEL
+                            && spectrum.description.polarity == polarity
+                    })
+                    .flat_map(|spectrum| {
+                        let index = spectrum.index();
+                        let retention_time = spectrum.start_time();
+                        spectrum
+                            .precursor()
+                            .map(|precursor| {
+                                precursor
+                                    .ions
+                                    .iter()
+                                    .map(|ion| PrecursorPoint {
+                                        index,
+                                        retention_time,
+                                        mz: ion.mz,
+                                        charge: ion.charge,
+                                        intensity: ion.intensity,
+                                    })
+                                    .collect::<Vec<_>>()
+                            })
+                            .unwrap_or_default()
+                    })
+                    .collect();
+
+                debug!(
+                    "Successfully extracted {} precursors from: {:?}",
+                    precursors.len(),
+                    &self.file_name
+                );
+                if precursors.is_empty() {
+                    warn!("No MS2 precursors found");
+                }
+                Ok(precursors)
+            }
+            Err(e) => {
+                error!("Failed to get MS2 precursors due to {:?}", e);
+                Err(ParserError::NoFileOpened)
             }
         }
-
-        self.plot_data = Some(smoothed_data);
-        debug!("Data smoothing complete",);
-
-        Ok(self)
     }
 
-    /// Method to retrieve the mass spectrum for the specified index from the associated mass spectrometry file.
+    /// Method to bin the MS1 data of the associated mass spectrometry file into a retention time × m/z intensity map.
     ///
     /// # Parameters
-    /// - `index: usize` - The index of the mass spectrum to be retrieved.
+    /// - `polarity: ScanPolarity` - The polarity of the mass spectrometry scans to be considered.
+    /// - `rt_bins: usize` - The number of bins along the retention time axis.
+    /// - `mz_bins: usize` - The number of bins along the m/z axis.
+    /// - `rt_range: Option<(f64, f64)>` - The retention time range to be binned. If `None`, the full retention time range of the file is used.
+    /// - `mz_range: Option<(f64, f64)>` - The m/z range to be binned. If `None`, the full m/z range of the file is used.
+    ///
+    /// # Returns
+    /// - `ParserResult<Heatmap>` - The binned intensity map, or an error if the operation fails.
     ///
     /// # Functionality
-    /// 1. Logs an informational message about the start of the mass spectrum retrieval process for the specified index.
+    /// 1. Logs an informational message about the attempt to build the heatmap.
     /// 2. Matches the `msfile` field, which is a `Result<MsFile, Error>`, and performs the following steps:
-    ///     a. Attempts to get the spectrum at the specified index using the `get_spectrum_by_index` method of the `MsFile`.
-    ///     b. If a spectrum is found, the function extracts the m/z values and intensities from the spectrum's arrays.
-    ///     c. If the extraction of m/z values and intensities is successful, the function stores the data in the `mass_spectrum` field of the current instance of the struct.
-    /// 3. If no spectrum is found at the specified index, a warning message is logged.
-    /// 4. If there is an error while accessing the `msfile` field or retrieving the spectrum, an error message is logged.
-    /// 5. Logs a debug message indicating that the mass spectrum retrieval process is complete.
-    ///
-    /// # Notes
-    /// This function does not return any value. It directly modifies the `mass_spectrum` field of the current instance of the struct.
-    pub fn get_mass_spectrum_by_index(&mut self, index: usize) {
-        info!("Starting to get mass spectrum at index: {:?}", &index);
-
+    ///     a. If a range is missing, iterates once over the MS1 spectra of the provided `polarity` to find the retention time and m/z extent of the data.
+    ///     b. Iterates over the MS1 spectra again and adds the intensity of every data point within the ranges to its bin. Only one spectrum is held in memory at a time, so the memory use is bounded by the size of the grid.
+    /// 3. Returns the resulting `Heatmap`.
+    ///
+    /// # Errors
+    /// If no file is open, an error message is logged, and the function returns `ParserError::NoFileOpened`.
+    /// If the m/z or intensity arrays of a spectrum cannot be decoded, the function returns a `ParserError` describing the problem.
+    /// If the file has no MS1 scans of the provided polarity, or the retention time or m/z range is empty (e.g. a single scan or scans without peaks),
+    /// the function returns `ParserError::NoMatchingSpectra`.
+    pub fn get_ms1_heatmap(
+        &mut self,
+        polarity: ScanPolarity,
+        rt_bins: usize,
+        mz_bins: usize,
+        rt_range: Option<(f64, f64)>,
+        mz_range: Option<(f64, f64)>,
+    ) -> ParserResult<Heatmap> {
+        info!(
+            "Attempting to build a {}x{} heatmap of {:?}",
+            rt_bins, mz_bins, &self.file_name
+        );
         match &mut self.msfile {
             Ok(reader) => {
-                if let Some(spec) = reader.get_spectrum_by_index(index) {
-                    let arrays = spec.arrays.as_ref();
-                    if let Some(arrays) = arrays {
-                        let peaks = arrays.mzs().map(|mzs| mzs.to_vec());
-                        let intensities = arrays.intensities().map(|ints| ints.to_vec());
-                        if peaks.is_ok() && intensities.is_ok() {
-                            self.mass_spectrum =
-                                Some((peaks.clone().unwrap(), intensities.clone().unwrap()));
-                            debug!(
-                                "Successfully retrieved mass spectrum at index: {:?} with {} peaks and {} intensities",
-                                index,
-                                peaks.unwrap().len(),
-                                intensities.unwrap().len()
-                            );
+                let (rt_range, mz_range) = match (rt_range, mz_range) {
+                    (Some(rt_range), Some(mz_range)) => (rt_range, mz_range),
+                    _ => {
+                        let mut rt_extent = (f64::INFINITY, f64::NEG_INFINITY);
+                        let mut mz_extent = (f64::INFINITY, f64::NEG_INFINITY);
+                        let mut matched = false;
+                        for spectrum in reader.iter().filter(|spectrum| {
+                            spectrum.description.ms_level == MS_LEVEL
+                                && spectrum.description.polarity == polarity
+                        }) {
+                            matched = true;
+                            let rt = spectrum.start_time();
+                            rt_extent = (rt_extent.0.min(rt), rt_extent.1.max(rt));
+                            if let Some(arrays) = spectrum.arrays.as_ref() {
+                                let mzs = arrays.mzs().map_err(|error| {
+                                    ParserError::from_arrays(spectrum.index(), error)
+                                })?;
+                                if let (Some(first), Some(last)) = (mzs.first(), mzs.last()) {
+                                    mz_extent = (mz_extent.0.min(*first), mz_extent.1.max(*last));
+                                }
+                            }
+                        }
+                        if !matched {
+                            return Err(ParserError::no_matching_spectra(
+                                self.file_name.as_deref(),
+                                format!("{:?} MS1 scans", polarity),
+                            ));
+                        }
+                        (rt_range.unwrap_or(rt_extent), mz_range.unwrap_or(mz_extent))
+                    }
+                };
+                // a single scan, scans without peaks or a collapsed zoom leave no range to bin
+                let is_valid = |range: (f64, f64)| {
+                    range.0.is_finite() && range.1.is_finite() && range.0 < range.1
+                };
+                if !is_valid(rt_range) || !is_valid(mz_range) {
+                    warn!(
+                        "Cannot bin retention times {:?} and m/z {:?}",
+                        rt_range, mz_range
+                    );
+                    return Err(ParserError::no_matching_spectra(
+                        self.file_name.as_deref(),
+                        format!(
+                            "{:?} MS1 scans spanning a range of retention times and m/z values",
+                            polarity
+                        ),
+                    ));
+                }
+                debug!(
+                    "Heatmap covers retention times {:?} and m/z {:?}",
+                    rt_range, mz_range
+                );
+
+                let mut intensities = vec![0.0_f32; rt_bins * mz_bins];
+                let mut scans = Vec::new();
+                for spectrum in reader.iter().filter(|spectrum| {
+                    spectrum.description.ms_level == MS_LEVEL
+                        && spectrum.description.polarity == polarity
+                }) {
+                    let Some(rt_bin) = bin_index(spectrum.start_time(), rt_range, rt_bins) else {
+                        continue;
+                    };
+                    scans.push((spectrum.start_time(), spectrum.index()));
+                    if let Some(arrays) = spectrum.arrays.as_ref() {
+                        let to_error = |error| ParserError::from_arrays(spectrum.index(), error);
+                        let mzs = arrays.mzs().map_err(to_error)?;
+                        let spectrum_intensities = arrays.intensities().map_err(to_error)?;
+                        for (mz, intensity) in mzs.iter().zip(spectrum_intensities.iter()) {
+                            if let Some(mz_bin) = bin_index(*mz, mz_range, mz_bins) {
+                                intensities[mz_bin * rt_bins + rt_bin] += intensity;
+                            }
                         }
-                    } else {
-                        warn!("No spectrum found at index: {:?}", index);
                     }
                 }
+
+                debug!(
+                    "Successfully binned {} MS1 scans of: {:?}",
+                    scans.len(),
+                    &self.file_name
+                );
+                Ok(Heatmap {
+                    rt_range,
+                    mz_range,
+                    rt_bins,
+                    mz_bins,
+                    intensities,
+                    scans,
+                })
+            }
+            Err(e) => {
+                error!("Failed to build heatmap due to {:?}", e);
+                Err(ParserError::NoFileOpened)
             }
-            Err(e) => error!("Failed to get mass spectrum at {:?} due to {:?}", &index, e),
         }
-
-        debug!("Finished getting mass spectrum at index: {:?}", &index);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::path::PathBuf;
-    //use approx::assert_relative_eq;
-    const TEST_FILE: &str = r"test_file\data_dependent_02.mzML"; //thermo example file converted to mzML (only Rt 10-12min)
+    /// Method to read the Extracted Ion Chromatogram (XIC) for the specified mass, restricted to an ion mobility range.
+    ///
+    /// # Parameters
+    /// - `mass: f64` - The mass value to be extracted.
+    /// - `polarity: ScanPolarity` - The polarity of the mass spectrometry scans to be considered.
+    /// - `mass_tolerance: f64` - The mass tolerance (in parts per million) to be used for peak extraction.
+    /// - `mobility_range: (f64, f64)` - The lower and upper ion mobility bounds of the data points to be included.
+    ///
+    /// # Returns
+    /// - `ParserResult<Chromatogram>` - The extracted ion chromatogram, with one data point per MS1 scan, or an error if the operation fails.
+    ///
+    /// # Functionality
+    /// 1. Logs an informational message about the attempt to read the XIC.
+    /// 2. Matches the `msfile` field, which is a `Result<MsFile, Error>`, and performs the following steps:
+    ///     a. Iterates over the MS1 spectra of the provided polarity.
+    ///     b. Sums the intensities of the data points within the mass tolerance and the ion mobility range. Unlike `get_xic`, the raw data points are used because centroiding drops the ion mobility dimension.
+    ///     c. Spectra of the same frame (i.e. with the same retention time) are summed into a single data point.
+    /// 3. Logs a debug message indicating the successful extraction of the XIC.
+    /// 4. Returns the chromatogram.
+    ///
+    /// # Errors
+    /// If no file is open, an error message is logged, and the function returns `ParserError::NoFileOpened`.
+    /// If the file has no MS1 scans of the provided polarity, the function returns `ParserError::NoMatchingSpectra`.
+    /// If the arrays of a spectrum cannot be decoded, the function returns `ParserError::MissingArrays` or `ParserError::UnsupportedEncoding`.
+    pub fn get_mobility_filtered_xic(
+        &mut self,
+        mass: f64,
+        polarity: ScanPolarity,
+        mass_tolerance: f64,
+        mobility_range: (f64, f64),
+    ) -> ParserResult<Chromatogram> {
+        info!(
+            "Attempting to read XIC of {:?} within ion mobility range {:?}",
+            &self.file_name, mobility_range
+        );
+
+        let mut chromatogram = Chromatogram {
+            ms_level: Some(MS_LEVEL),
+            mz: Some(mass),
+            mass_tolerance: Some(mass_tolerance),
+            mobility_range: Some(mobility_range),
+            ..Chromatogram::new(ChromatogramType::Xic, self.file_name.as_deref(), polarity)
+        };
+
+        match &mut self.msfile {
+            Ok(reader) => {
+                for spectrum in reader.iter().filter(|spectrum| {
+                    spectrum.description.ms_level == MS_LEVEL
+                        && spectrum.description.polarity == polarity
+                }) {
+                    let summed: f64 = mobility_points(&spectrum)?
+                        .into_iter()
+                        .filter(|(mz, _, mobility)| {
+                            Tolerance::PPM(mass_tolerance).test(*mz, mass)
+                                && mobility_range.0 <= *mobility
+                                && *mobility <= mobility_range.1
+                        })
+                        .map(|(_, intensity, _)| intensity as f64)
+                        .sum();
+
+                    let rt = spectrum.start_time();
+                    if chromatogram.retention_time.last() == Some(&rt) {
+                        if let Some(last) = chromatogram.intensity.last_mut() {
+                            *last += summed;
+                        }
+                    } else {
+                        chromatogram.retention_time.push(rt);
+                        chromatogram.intensity.push(summed);
+                        chromatogram.scan_index.push(spectrum.index());
+                    }
+                }
+                debug!(
+                    "Successfully extracted mobility filtered XIC from: {:?}",
+                    &self.file_name
+                );
+            }
+            Err(e) => {
+                error!("Failed to get mobility filtered XIC due to {:?}", e);
+                return Err(ParserError::NoFileOpened);
+            }
+        }
+        if chromatogram.is_empty() {
+            return Err(ParserError::no_matching_spectra(
+                self.file_name.as_deref(),
+                format!("{:?} MS1 scans", polarity),
+            ));
+        }
+
+        trace!(
+            "Successfully extracted the mobility filtered XIC of {:?}: {:?}",
+            &self.file_name,
+            &chromatogram
+        );
+        Ok(chromatogram)
+    }
+
+    /// Method to read the ion mobilogram of the specified mass.
+    ///
+    /// # Parameters
+    /// - `mass: f64` - The mass value to be extracted.
+    /// - `polarity: ScanPolarity` - The polarity of the mass spectrometry scans to be considered.
+    /// - `mass_tolerance: f64` - The mass tolerance (in parts per million) to be used for peak extraction.
+    ///
+    /// # Returns
+    /// - `ParserResult<Mobilogram>` - The mobilogram of the mass, or an error if the operation fails.
+    ///
+    /// # Functionality
+    /// 1. Logs an informational message about the attempt to read the mobilogram.
+    /// 2. Matches the `msfile` field, which is a `Result<MsFile, Error>`, and performs the following steps:
+    ///     a. Iterates over the MS1 spectra of the provided polarity.
+    ///     b. Collects the ion mobility and intensity of every data point within the mass tolerance.
+    ///     c. Sums the intensities of data points sharing the same ion mobility.
+    /// 3. If the file has no ion mobility information, a warning message is logged and the mobilogram is empty.
+    /// 4. Returns the mobilogram.
+    ///
+    /// # Errors
+    /// If no file is open, an error message is logged, and the function returns `ParserError::NoFileOpened`.
+    /// If the arrays of a spectrum cannot be decoded, the function returns a `ParserError` describing the problem.
+    pub fn get_mobilogram(
+        &mut self,
+        mass: f64,
+        polarity: ScanPolarity,
+        mass_tolerance: f64,
+    ) -> ParserResult<Mobilogram> {
+        info!(
+            "Attempting to read mobilogram of {} from {:?}",
+            mass, &self.file_name
+        );
+        match &mut self.msfile {
+            Ok(reader) => {
+                let mut points = Vec::new();
+                for spectrum in reader.iter().filter(|spectrum| {
+                    spectrum.description.ms_level == MS_LEVEL
+                        && spectrum.description.polarity == polarity
+                }) {
+                    points.extend(
+                        mobility_points(&spectrum)?
+                            .into_iter()
+                            .filter(|(mz, _, _)| Tolerance::PPM(mass_tolerance).test(*mz, mass))
+                            .map(|(_, intensity, mobility)| (mobility, intensity as f64)),
+                    );
+                }
+                let (mobility, intensity) = sum_by_mobility(points);
+                let mobilogram = Mobilogram {
+                    file_name: self.file_name.clone(),
+                    polarity,
+                    mz: mass,
+                    mass_tolerance,
+                    mobility,
+                    intensity,
+                };
+                debug!(
+                    "Successfully extracted mobilogram with {} points from: {:?}",
+                    mobilogram.len(),
+                    &self.file_name
+                );
+                if mobilogram.is_empty() {
+                    warn!("No ion mobility data found for {}", mass);
+                }
+                Ok(mobilogram)
+            }
+            Err(e) => {
+                error!("Failed to get mobilogram due to {:?}", e);
+                Err(ParserError::NoFileOpened)
+            }
+        }
+    }
+
+    /// Method to bin a single ion mobility frame into an ion mobility × m/z intensity map.
+    ///
+    /// # Parameters
+    /// - `index: usize` - The index of a spectrum of the frame.
+    /// - `mobility_bins: usize` - The number of bins along the ion mobility axis.
+    /// - `mz_bins: usize` - The number of bins along the m/z axis.
+    ///
+    /// # Returns
+    /// - `ParserResult<MobilityHeatmap>` - The binned intensity map of the frame, or an error if the operation fails.
+    ///
+    /// # Functionality
+    /// 1. Logs an informational message about the attempt to build the mobility heatmap.
+    /// 2. Matches the `msfile` field, which is a `Result<MsFile, Error>`, and performs the following steps:
+    ///     a. Retrieves the spectrum at the specified index.
+    ///     b. If the spectrum carries an ion mobility array, the frame consists of this spectrum only. Otherwise, the neighbouring spectra with the same retention time (i.e. the mobility scans of the same frame) are added.
+    ///     c. Bins the data points of the frame over their full ion mobility and m/z extent.
+    /// 3. Returns the resulting `MobilityHeatmap`.
+    ///
+    /// # Errors
+    /// If no file is open, an error message is logged, and the function returns `ParserError::NoFileOpened`.
+    /// If the file has no spectrum at the index, or the frame has no ion mobility information, the function returns `ParserError::NoMatchingSpectra`.
+    /// If the arrays of a spectrum cannot be decoded, the function returns a `ParserError` describing the problem.
+    pub fn get_mobility_heatmap(
+        &mut self,
+        index: usize,
+        mobility_bins: usize,
+        mz_bins: usize,
+    ) -> ParserResult<MobilityHeatmap> {
+        info!(
+            "Attempting to build mobility heatmap of frame at index: {}",
+            index
+        );
+        match &mut self.msfile {
+            Ok(reader) => {
+                let Some(spectrum) = reader.get_spectrum_by_index(index) else {
+                    warn!("No spectrum found at index: {:?}", index);
+                    return Err(ParserError::no_matching_spectra(
+                        self.file_name.as_deref(),
+                        format!("scan with index {}", index),
+                    ));
+                };
+                let mut points = mobility_points(&spectrum)?;
+
+                let has_mobility_array = spectrum
+                    .arrays
+                    .as_ref()
+                    .map(|arrays| arrays.ion_mobility().is_ok())
+                    .unwrap_or(false);
+                if !has_mobility_array && spectrum.ion_mobility().is_some() {
+                    let frame_time = spectrum.start_time();
+                    for step in [-1_isize, 1] {
+                        let mut neighbour = index as isize + step;
+                        while neighbour >= 0 {
+                            match reader.get_spectrum_by_index(neighbour as usize) {
+                                Some(scan) if scan.start_time() == frame_time => {
+                                    points.extend(mobility_points(&scan)?);
+                                    neighbour += step;
+                                }
+                                _ => break,
+                            }
+                        }
+                    }
+                }
+
+                if points.is_empty() {
+                    warn!("No ion mobility data found for frame at index: {}", index);
+                    return Err(ParserError::no_matching_spectra(
+                        self.file_name.as_deref(),
+                        format!("ion mobility data in the frame of scan {}", index),
+                    ));
+                }
+
+                let extent = |values: &mut dyn Iterator<Item = f64>| {
+                    values.fold((f64::INFINITY, f64::NEG_INFINITY), |acc, value| {
+                        (acc.0.min(value), acc.1.max(value))
+                    })
+                };
+                let mobility_range = extent(&mut points.iter().map(|point| point.2));
+                let mz_range = extent(&mut points.iter().map(|point| point.0));
+
+                let mut intensities = vec![0.0_f32; mobility_bins * mz_bins];
+                for (mz, intensity, mobility) in points {
+                    if let (Some(mobility_bin), Some(mz_bin)) = (
+                        bin_index(mobility, mobility_range, mobility_bins),
+                        bin_index(mz, mz_range, mz_bins),
+                    ) {
+                        intensities[mz_bin * mobility_bins + mobility_bin] += intensity;
+                    }
+                }
+
+                debug!(
+                    "Successfully built mobility heatmap of frame at index: {}",
+                    index
+                );
+                Ok(MobilityHeatmap {
+                    index,
+                    mobility_range,
+                    mz_range,
+                    mobility_bins,
+                    mz_bins,
+                    intensities,
+                })
+            }
+            Err(e) => {
+                error!("Failed to build mobility heatmap due to {:?}", e);
+                Err(ParserError::NoFileOpened)
+            }
+        }
+    }
+
+    /// Method to search every MS2 spectrum of the associated mass spectrometry file against spectral libraries.
+    ///
+    /// # Parameters
+    /// - `polarity: ScanPolarity` - The polarity of the mass spectrometry scans to be considered.
+    /// - `libraries: &[SpectralLibrary]` - The libraries to be searched.
+    /// - `parameters: &SearchParameters` - The parameters of the search. The precursor m/z of every spectrum is used for the precursor tolerance.
+    /// - `progress: &Progress` - Receives the number of spectra of the file and of those read so far, and stops the search when it is cancelled.
+    ///
+    /// # Returns
+    /// - `ParserResult<Vec<SpectrumMatch>>` - The best hit of every MS2 spectrum with a hit, ordered by spectrum index, or an error if the operation fails.
+    ///
+    /// # Functionality
+    /// 1. Logs an informational message about the attempt to search the MS2 spectra.
+    /// 2. Matches the `msfile` field, which is a `Result<MsFile, Error>`, and performs the following steps:
+    ///     a. Iterates over the spectra in the `MsFile`, records each of them in `progress` and filters them based on the MS level and the provided `polarity`.
+    ///     b. Searches the peaks of every filtered spectrum with `library::search` and keeps its highest scoring hit as a `SpectrumMatch`. Only one spectrum is held in memory at a time.
+    /// 3. Returns the matches.
+    ///
+    /// # Errors
+    /// If no file is open, an error message is logged, and the function returns `ParserError::NoFileOpened`.
+    /// If the m/z or intensity arrays of a spectrum cannot be decoded, the function returns a `ParserError` describing the problem.
+    /// If `progress` is cancelled, the function returns `ParserError::Cancelled`.
+    pub fn search_ms2_spectra(
+        &mut self,
+        polarity: ScanPolarity,
+        libraries: &[SpectralLibrary],
+        parameters: &SearchParameters,
+        progress: &Progress,
+    ) -> ParserResult<Vec<SpectrumMatch>> {
+        info!(
+            "Attempting to search the MS2 spectra of {:?} against {} libraries",
+            &self.file_name,
+            libraries.len()
+        );
+        match &mut self.msfile {
+            Ok(reader) => {
+                progress.total.store(reader.len(), Ordering::Relaxed);
+                let mut matches = Vec::new();
+                for spectrum in reader.iter() {
+                    if progress.is_cancelled() {
+                        info!(
+                            "Cancelled the search of the MS2 spectra of {:?}",
+                            &self.file_name
+                        );
+                        return Err(ParserError::Cancelled);
+                    }
+                    progress.done.fetch_add(1, Ordering::Relaxed);
+                    if spectrum.description.ms_level != MS2_LEVEL
+                        || spectrum.description.polarity != polarity
+                    {
+                        continue;
+                    }
+                    let Some(arrays) = spectrum.arrays.as_ref() else {
+                        continue;
+                    };
+                    let to_error = |error| ParserError::from_arrays(spectrum.index(), error);
+                    let mzs = arrays.mzs().map_err(to_error)?;
+                    let intensities = arrays.intensities().map_err(to_error)?;
+                    let precursor_mz = spectrum
+                        .precursor()
+                        .and_then(|precursor| precursor.ions.first())
+                        .map(|ion| ion.mz);
+                    let best_hit = library::search(
+                        (&mzs, &intensities),
+                        precursor_mz,
+                        libraries,
+                        &SearchParameters {
+                            max_hits: 1,
+                            ..parameters.clone()
+                        },
+                    )
+                    .into_iter()
+                    .next();
+                    if let Some(hit) = best_hit {
+                        matches.push(SpectrumMatch {
+                            index: spectrum.index(),
+                            retention_time: spectrum.start_time(),
+                            precursor_mz,
+                            hit,
+                        });
+                    }
+                }
+                debug!(
+                    "Found library hits for {} MS2 spectra of {:?}",
+                    matches.len(),
+                    &self.file_name
+                );
+                Ok(matches)
+            }
+            Err(e) => {
+                error!("Failed to search MS2 spectra due to {:?}", e);
+                Err(ParserError::NoFileOpened)
+            }
+        }
+    }
+
+    /// Method to retrieve the mass spectrum for the specified index from the associated mass spectrometry file.
+    ///
+    /// # Parameters
+    /// - `index: usize` - The index of the mass spectrum to be retrieved.
+    ///
+    /// # Returns
+    /// - `ParserResult<Spectrum>` - The mass spectrum, or an error if the spectrum cannot be retrieved.
+    ///
+    /// # Functionality
+    /// 1. Logs an informational message about the start of the mass spectrum retrieval process for the specified index.
+    /// 2. Matches the `msfile` field, which is a `Result<MsFile, Error>`, and performs the following steps:
+    ///     a. Attempts to get the spectrum at the specified index using the `get_spectrum_by_index` method of the `MsFile`.
+    ///     b. Extracts the m/z values and intensities from the spectrum's arrays.
+    ///     c. Labels the spectrum with the file name and index, and determines whether it is profile data from its signal continuity (or from its point spacing if the file does not say).
+    /// 3. Returns the spectrum with its retention time, precursor m/z and precursor charge.
+    ///
+    /// # Errors
+    /// If no file is open, an error message is logged, and the function returns `ParserError::NoFileOpened`.
+    /// If the file has no spectrum at the index, the function returns `ParserError::NoMatchingSpectra`.
+    /// If the spectrum has no data arrays or they cannot be decoded, the function returns `ParserError::MissingArrays` or `ParserError::UnsupportedEncoding`.
+    pub fn get_mass_spectrum_by_index(&mut self, index: usize) -> ParserResult<Spectrum> {
+        info!("Starting to get mass spectrum at index: {:?}", &index);
+
+        let reader = match &mut self.msfile {
+            Ok(reader) => reader,
+            Err(e) => {
+                error!("Failed to get mass spectrum at {:?} due to {:?}", &index, e);
+                return Err(ParserError::NoFileOpened);
+            }
+        };
+        let Some(spec) = reader.get_spectrum_by_index(index) else {
+            warn!("No spectrum found at index: {:?}", index);
+            return Err(ParserError::no_matching_spectra(
+                self.file_name.as_deref(),
+                format!("scan with index {}", index),
+            ));
+        };
+        let Some(arrays) = spec.arrays.as_ref() else {
+            return Err(ParserError::MissingArrays {
+                index,
+                detail: "the spectrum has no data arrays".to_string(),
+            });
+        };
+        let to_error = |error| ParserError::from_arrays(index, error);
+        let peaks = arrays.mzs().map_err(to_error)?.to_vec();
+        let intensities = arrays.intensities().map_err(to_error)?.to_vec();
+        let profile = match spec.signal_continuity() {
+            SignalContinuity::Profile => true,
+            SignalContinuity::Centroid => false,
+            _ => spectrum_processing::looks_like_profile(&peaks),
+        };
+        let precursor_ion = spec
+            .precursor()
+            .and_then(|precursor| precursor.ions.first());
+        debug!(
+            "Successfully retrieved mass spectrum at index: {:?} with {} peaks and {} intensities",
+            index,
+            peaks.len(),
+            intensities.len()
+        );
+        Ok(Spectrum {
+            label: spectrum_label(self.file_name.as_deref(), index),
+            file_name: self.file_name.clone(),
+            index: Some(index),
+            ms_level: Some(spec.ms_level()),
+            polarity: spec.polarity(),
+            retention_time: Some(spec.start_time()),
+            time_unit: TimeUnit::Minute,
+            precursor_mz: precursor_ion.map(|ion| ion.mz),
+            precursor_charge: precursor_ion.and_then(|ion| ion.charge),
+            mz: peaks,
+            intensity: intensities,
+            profile,
+        })
+    }
+
+    /// Method to average the MS1 spectra of a retention time range into a single mass spectrum.
+    ///
+    /// # Parameters
+    /// - `polarity: ScanPolarity` - The polarity of the mass spectrometry scans to be averaged.
+    /// - `rt_range: (f64, f64)` - The lower and upper retention time bounds of the scans to be averaged.
+    /// - `mass_tolerance: f64` - The tolerance (in parts per million) within which data points of different scans are merged.
+    ///
+    /// # Returns
+    /// - `ParserResult<Spectrum>` - The averaged spectrum, labelled with its file and retention time range, or an error if the operation fails.
+    ///
+    /// # Functionality
+    /// 1. Logs an informational message about the attempt to average the spectra.
+    /// 2. Matches the `msfile` field, which is a `Result<MsFile, Error>`, and performs the following steps:
+    ///     a. Iterates over the MS1 spectra of the provided polarity within the retention time range and collects their data points.
+    ///     b. Merges the data points within the mass tolerance and divides their summed intensity by the number of scans.
+    /// 3. Returns the averaged spectrum.
+    ///
+    /// # Errors
+    /// If no file is open, an error message is logged, and the function returns `ParserError::NoFileOpened`.
+    /// If the arrays of a spectrum cannot be decoded, the function returns a `ParserError` describing the problem.
+    /// If no MS1 spectrum lies within the retention time range, the function returns `ParserError::NoMatchingSpectra`.
+    pub fn get_averaged_mass_spectrum(
+        &mut self,
+        polarity: ScanPolarity,
+        rt_range: (f64, f64),
+        mass_tolerance: f64,
+    ) -> ParserResult<Spectrum> {
+        info!(
+            "Attempting to average the MS1 spectra between {:?} of {:?}",
+            rt_range, &self.file_name
+        );
+        match &mut self.msfile {
+            Ok(reader) => {
+                let mut points: Vec<(f64, f32)> = Vec::new();
+                let mut scans = 0_usize;
+                let mut profile = None;
+                for spectrum in reader.iter().filter(|spectrum| {
+                    spectrum.description.ms_level == MS_LEVEL
+                        && spectrum.description.polarity == polarity
+                        && rt_range.0 <= spectrum.start_time()
+                        && spectrum.start_time() <= rt_range.1
+                }) {
+                    scans += 1;
+                    if let Some(arrays) = spectrum.arrays.as_ref() {
+                        let to_error = |error| ParserError::from_arrays(spectrum.index(), error);
+                        let mzs = arrays.mzs().map_err(to_error)?;
+                        let intensities = arrays.intensities().map_err(to_error)?;
+                        if profile.is_none() {
+                            profile = Some(match spectrum.signal_continuity() {
+                                SignalContinuity::Profile => true,
+                                SignalContinuity::Centroid => false,
+                                _ => spectrum_processing::looks_like_profile(&mzs),
+                            });
+                        }
+                        points.extend(mzs.iter().copied().zip(intensities.iter().copied()));
+                    }
+                }
+                if scans == 0 {
+                    return Err(ParserError::no_matching_spectra(
+                        self.file_name.as_deref(),
+                        format!(
+                            "{:?} MS1 scans between {:.2} and {:.2} min",
+                            polarity, rt_range.0, rt_range.1
+                        ),
+                    ));
+                }
+
+                let (mz, mut intensity) = merge_points(points, mass_tolerance);
+                for value in intensity.iter_mut() {
+                    *value /= scans as f32;
+                }
+                debug!(
+                    "Successfully averaged {} MS1 scans into {} data points",
+                    scans,
+                    mz.len()
+                );
+                Ok(Spectrum {
+                    label: format!(
+                        "{} RT {:.2}-{:.2} ({} scans)",
+                        file_label(self.file_name.as_deref()),
+                        rt_range.0,
+                        rt_range.1,
+                        scans
+                    ),
+                    file_name: self.file_name.clone(),
+                    index: None,
+                    ms_level: Some(MS_LEVEL),
+                    polarity,
+                    retention_time: Some((rt_range.0 + rt_range.1) / 2.0),
+                    time_unit: TimeUnit::Minute,
+                    precursor_mz: None,
+                    precursor_charge: None,
+                    mz,
+                    intensity,
+                    profile: profile.unwrap_or(false),
+                })
+            }
+            Err(e) => {
+                error!("Failed to average the mass spectra due to {:?}", e);
+                Err(ParserError::NoFileOpened)
+            }
+        }
+    }
+}
+
+/// Checks whether an isolation window contains the provided m/z.
+///
+/// `IsolationWindow::contains` from `mzdata` compares the point against the upper bound in the wrong direction,
+/// and windows that were only given as offsets still need to be resolved around the target m/z.
+///
+/// # Parameters
+/// - `window: &IsolationWindow` - The isolation window of the precursor.
+/// - `mz: f64` - The m/z value to be checked.
+///
+/// # Returns
+/// - `bool` - `true` if the m/z falls within the bounds of the window, `false` otherwise or if the window is empty.
+fn isolation_window_contains(window: &IsolationWindow, mz: f64) -> bool {
+    if window.is_empty() {
+        return false;
+    }
+    let (lower_bound, upper_bound) = match window.flags {
+        IsolationWindowState::Offset => (
+            window.target - window.lower_bound,
+            window.target + window.upper_bound,
+        ),
+        _ => (window.lower_bound, window.upper_bound),
+    };
+    (lower_bound as f64) <= mz && mz <= (upper_bound as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    //use approx::assert_relative_eq;
+    const TEST_FILE: &str = r"test_file\data_dependent_02.mzML"; //thermo example file converted to mzML (only Rt 10-12min)
+    const DIA_TEST_FILE: &str = r"test_file\dia_ms2.mzML"; //synthetic file with two MS1 scans, each followed by MS2 scans of the isolation windows 487.5-512.5 and 512.5-537.5
+
+    /// Opens a test file relative to the crate root.
+    fn open_test_file(file: &str) -> MzData {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push(file);
+
+        // Normalize the path to account for different separators
+        let normalized_d = PathBuf::from(d.to_str().unwrap().replace('\\', "/"));
+
+        let mut mzdata = MzData::new();
+        mzdata.open_msfile(&normalized_d).unwrap();
+        mzdata
+    }
 
     #[test]
     fn test_new() {
         let mzdata = MzData::new();
-        assert!(mzdata.retention_time.is_none());
-        assert!(mzdata.intensity.is_none());
-        assert!(mzdata.mz.is_none());
         assert!(mzdata.msfile.is_err());
-        assert!(mzdata.plot_data.is_none());
-        assert!(mzdata.mass_spectrum.is_none());
+        assert!(mzdata.file_name.is_none());
     }
 
     #[test]
@@ -527,7 +1990,7 @@ mod tests {
         d.push(TEST_FILE);
 
         // Normalize the path to account for different separators
-        let normalized_d = PathBuf::from(d.to_str().unwrap().replace("\\", "/"));
+        let normalized_d = PathBuf::from(d.to_str().unwrap().replace('\\', "/"));
 
         let mut mzdata = MzData::new();
         let result = mzdata.open_msfile(&normalized_d);
@@ -541,16 +2004,93 @@ mod tests {
         d.push(TEST_FILE);
 
         // Normalize the path to account for different separators
-        let normalized_d = PathBuf::from(d.to_str().unwrap().replace("\\", "/"));
+        let normalized_d = PathBuf::from(d.to_str().unwrap().replace('\\', "/"));
 
         let mut mzdata = MzData::new();
 
         mzdata.open_msfile(&normalized_d).unwrap();
 
-        let result = mzdata.get_xic(722.43, ScanPolarity::Positive, 1000.0);
-        assert!(result.is_ok());
-        assert!(!mzdata.retention_time.is_none());
-        assert!(!mzdata.intensity.is_none());
+        let xic = mzdata
+            .get_xic(722.43, ScanPolarity::Positive, 1000.0)
+            .unwrap();
+        assert_eq!(xic.chromatogram_type, ChromatogramType::Xic);
+        assert!(xic
+            .file_name
+            .as_ref()
+            .unwrap()
+            .ends_with("data_dependent_02.mzML"));
+        assert_eq!(xic.ms_level, Some(MS_LEVEL));
+        assert_eq!(xic.mz, Some(722.43));
+        assert_eq!(xic.mass_tolerance, Some(1000.0));
+        assert_eq!(xic.retention_time.len(), xic.intensity.len());
+        assert_eq!(xic.retention_time.len(), xic.scan_index.len());
+        assert!(xic.retention_time.windows(2).all(|rt| rt[0] < rt[1]));
+        assert_eq!(xic.name(), "XIC m/z = 722.4300");
+
+        assert!(matches!(
+            MzData::new().get_xic(722.43, ScanPolarity::Positive, 10.0),
+            Err(ParserError::NoFileOpened)
+        ));
+    }
+
+    #[test]
+    fn test_open_msfile_errors() {
+        let mut mzdata = MzData::new();
+        let missing = PathBuf::from("test_file/missing.mzML");
+        assert!(matches!(
+            mzdata.open_msfile(&missing),
+            Err(ParserError::Io { .. })
+        ));
+
+        let path = std::env::temp_dir().join("chromascope_malformed.mzML");
+        std::fs::write(&path, "this is not an XML document").unwrap();
+        let result = mzdata.open_msfile(&path);
+        std::fs::remove_file(&path).unwrap();
+        let error = result.unwrap_err();
+        assert!(matches!(error, ParserError::MalformedXml { .. }));
+        assert!(error.to_string().contains("msconvert"));
+        assert!(mzdata.msfile.is_err());
+    }
+
+    #[test]
+    fn test_has_mzml_root() {
+        let path = std::env::temp_dir().join("chromascope_header.xml");
+        let check = |content: &str| {
+            std::fs::write(&path, content).unwrap();
+            has_mzml_root(&path).unwrap()
+        };
+        assert!(check(
+            "<?xml version=\"1.0\"?>\n<indexedmzML xmlns=\"http://psi.hupo.org/ms/mzml\">"
+        ));
+        assert!(check("<?xml version=\"1.0\"?>\n<mzML>"));
+        // mentions of mzML outside of the root element tag
+        assert!(!check(
+            "<?xml version=\"1.0\"?>\n<!-- converted to mzML -->\n<run/>"
+        ));
+        assert!(!check(
+            "<data xmlns=\"http://psi.hupo.org/ms/mzml\" file=\"run.mzML\"><mzMLish/></data>"
+        ));
+        std::fs::remove_file(&path).unwrap();
+        assert!(has_mzml_root(&path).is_err());
+    }
+
+    #[test]
+    fn test_parser_error_classification() {
+        assert!(matches!(
+            ParserError::from_conversion(3, SpectrumConversionError::NotCentroided),
+            ParserError::UnsupportedEncoding { index: 3, .. }
+        ));
+        assert!(matches!(
+            ParserError::from_conversion(3, SpectrumConversionError::NoPeakData),
+            ParserError::MissingArrays { index: 3, .. }
+        ));
+        assert!(matches!(
+            ParserError::from_arrays(
+                5,
+                ArrayRetrievalError::DecompressionError("numpress".to_string())
+            ),
+            ParserError::UnsupportedEncoding { index: 5, .. }
+        ));
     }
     #[test]
     fn test_get_tic() {
@@ -558,29 +2098,513 @@ mod tests {
         d.push(TEST_FILE);
 
         // Normalize the path to account for different separators
-        let normalized_d = PathBuf::from(d.to_str().unwrap().replace("\\", "/"));
+        let normalized_d = PathBuf::from(d.to_str().unwrap().replace('\\', "/"));
 
         let mut mzdata = MzData::new();
 
         mzdata.open_msfile(&normalized_d).unwrap();
 
-        let result = mzdata.get_tic(ScanPolarity::Positive);
-        assert!(result.is_ok());
-        assert!(!mzdata.retention_time.is_none());
-        assert!(!mzdata.intensity.is_none());
-        assert!(mzdata.mz.is_some());
+        let tic = mzdata.get_tic(ScanPolarity::Positive).unwrap();
+        assert_eq!(tic.len(), 53);
+        assert_eq!(tic.ms_level, None);
+        assert_eq!(tic.scan_index.len(), 53);
+        assert!(tic.intensity.iter().all(|&intensity| intensity > 0.0));
+
+        // the scans can be looked up from a retention time
+        let rt = tic.retention_time[10];
+        assert_eq!(tic.closest_scan(rt + 1e-6), Some(tic.scan_index[10]));
+        assert_eq!(tic.closest_scan(-1.0), Some(tic.scan_index[0]));
+        assert_eq!(tic.closest_scan(1e6), tic.scan_index.last().copied());
+
+        // several chromatograms can coexist
+        let bpc = mzdata.get_bpic(ScanPolarity::Positive).unwrap();
+        assert_eq!(bpc.retention_time, tic.retention_time);
+        assert!(bpc
+            .intensity
+            .iter()
+            .zip(tic.intensity.iter())
+            .all(|(bpc, tic)| bpc <= tic));
+
+        assert!(matches!(
+            mzdata.get_tic(ScanPolarity::Negative),
+            Err(ParserError::NoMatchingSpectra { .. })
+        ));
+    }
+
+    #[test]
+    fn test_chromatogram() {
+        let chromatogram = Chromatogram {
+            file_name: Some("run.mzML".to_string()),
+            chromatogram_type: ChromatogramType::Xic,
+            polarity: ScanPolarity::Positive,
+            ms_level: Some(1),
+            mz: Some(445.12),
+            mass_tolerance: Some(10.0),
+            retention_time: vec![1.0, 2.0, 3.0, 4.0, 5.0],
+            intensity: vec![1.0, 4.0, 1.0, 4.0, 1.0],
+            scan_index: vec![0, 2, 4, 6, 8],
+            ..Default::default()
+        };
+        assert_eq!(
+            chromatogram.points(),
+            vec![[1.0, 1.0], [2.0, 4.0], [3.0, 1.0], [4.0, 4.0], [5.0, 1.0]]
+        );
+
+        let parameters = SmoothingParameters::moving_average(1);
+        let smoothed = chromatogram.smoothed(&parameters);
+        assert_eq!(smoothed.smoothing, parameters);
+        assert_eq!(smoothed.retention_time, chromatogram.retention_time);
+        assert_eq!(smoothed.intensity, vec![2.5, 2.0, 3.0, 2.0, 2.5]);
+
+        assert_eq!(chromatogram.closest_scan(2.4), Some(2));
+        assert_eq!(chromatogram.closest_scan(2.6), Some(4));
+        assert_eq!(Chromatogram::default().closest_scan(1.0), None);
+
+        let metadata = smoothed.metadata();
+        let value = |key: &str| {
+            metadata
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.as_str())
+        };
+        assert_eq!(value("file"), Some("run.mzML"));
+        assert_eq!(value("plot type"), Some("XIC"));
+        assert_eq!(value("polarity"), Some("Positive"));
+        assert_eq!(value("MS level"), Some("1"));
+        assert_eq!(value("m/z"), Some("445.12"));
+        assert_eq!(value("mass tolerance (ppm)"), Some("10"));
+        assert_eq!(value("smoothing"), Some("moving average, 1 point per side"));
+        assert_eq!(value("retention time unit"), Some("min"));
+        assert_eq!(value("precursor m/z"), None);
+        assert_eq!(value("baseline correction"), None);
+
+        let parameters = BaselineParameters {
+            method: chromatogram_processing::BaselineMethod::RollingMinimum,
+            half_window: 1,
+            ..Default::default()
+        };
+        let corrected = chromatogram.baseline_corrected(&parameters);
+        assert_eq!(corrected.baseline_correction, Some(parameters));
+        assert_eq!(corrected.intensity.len(), chromatogram.len());
+        assert!(corrected
+            .metadata()
+            .contains(&("baseline correction".to_string(), parameters.to_string())));
+
+        // the trace is too short for an automatic noise region, the chosen one holds 1, 4 and 1
+        assert!(chromatogram
+            .signal_to_noise(1.0, &SignalToNoiseParameters::default())
+            .is_none());
+        let parameters = SignalToNoiseParameters {
+            noise_region: Some((2.5, 5.0)),
+            ..Default::default()
+        };
+        let result = chromatogram.signal_to_noise(1.0, &parameters).unwrap();
+        assert_eq!(result.apex, 1);
+        assert_eq!(result.noise_region, (2, 4));
+        assert!((result.ratio - 2.0_f64.sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_chromatogram_push() {
+        let mut chromatogram = Chromatogram::default();
+        let mut count = 0;
+        chromatogram.push(1.0, 2.0, 0, &mut count);
+        chromatogram.push(1.0, 4.0, 0, &mut count);
+        chromatogram.push(1.0, 6.0, 0, &mut count);
+        chromatogram.push(2.0, 1.0, 1, &mut count);
+        assert_eq!(chromatogram.retention_time, vec![1.0, 2.0]);
+        assert_eq!(chromatogram.intensity, vec![4.0, 1.0]);
+        assert_eq!(chromatogram.scan_index, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_get_dia_fragment_xics() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push(TEST_FILE);
+
+        // Normalize the path to account for different separators
+        let normalized_d = PathBuf::from(d.to_str().unwrap().replace('\\', "/"));
+
+        let mut mzdata = MzData::new();
+
+        mzdata.open_msfile(&normalized_d).unwrap();
+
+        // the test file only contains MS1 scans, so no scan isolates the precursor
+        let result =
+            mzdata.get_dia_fragment_xics(722.43, &[300.1, 400.2], ScanPolarity::Positive, 10.0);
+        assert!(matches!(
+            result,
+            Err(ParserError::NoMatchingSpectra { ref description, .. }) if description.contains("MS2")
+        ));
+
+        // only the MS2 scans 1 and 4 isolate m/z 500.25, and a fragment missing from a scan has a zero intensity
+        let mut mzdata = open_test_file(DIA_TEST_FILE);
+        let traces = mzdata
+            .get_dia_fragment_xics(500.25, &[200.1, 300.2, 400.3], ScanPolarity::Positive, 10.0)
+            .unwrap();
+        assert_eq!(traces.len(), 3);
+        for trace in &traces {
+            assert_eq!(trace.scan_index, vec![1, 4]);
+            assert_eq!(trace.retention_time, vec![1.01, 2.01]);
+            assert_eq!(trace.precursor_mz, Some(500.25));
+        }
+        assert_eq!(traces[0].intensity, vec![10.0, 40.0]);
+        assert_eq!(traces[1].intensity, vec![20.0, 0.0]);
+        assert_eq!(traces[2].intensity, vec![30.0, 60.0]);
+
+        let traces = mzdata
+            .get_dia_fragment_xics(520.0, &[350.0], ScanPolarity::Positive, 10.0)
+            .unwrap();
+        assert_eq!(traces[0].scan_index, vec![2, 5]);
+        assert_eq!(traces[0].intensity, vec![15.0, 25.0]);
+
+        // no isolation window covers m/z 600 and no scan has a negative polarity
+        assert!(mzdata
+            .get_dia_fragment_xics(600.0, &[200.1], ScanPolarity::Positive, 10.0)
+            .is_err());
+        assert!(mzdata
+            .get_dia_fragment_xics(500.25, &[200.1], ScanPolarity::Negative, 10.0)
+            .is_err());
     }
 
     #[test]
-    fn test_smooth_data() {
+    fn test_get_precursor_map() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push(TEST_FILE);
+
+        // Normalize the path to account for different separators
+        let normalized_d = PathBuf::from(d.to_str().unwrap().replace('\\', "/"));
+
         let mut mzdata = MzData::new();
-        let data = vec![[1.0, 1.0], [2.0, 2.0], [3.0, 3.0], [4.0, 4.0], [5.0, 5.0]];
 
-        let result = mzdata.smooth_data(Ok(data), 1);
-        assert!(result.is_ok());
+        mzdata.open_msfile(&normalized_d).unwrap();
+
+        // the test file only contains MS1 scans, so no precursor is expected
+        let precursors = mzdata.get_precursor_map(ScanPolarity::Positive).unwrap();
+        assert!(precursors.is_empty());
+
+        let mut mzdata = open_test_file(DIA_TEST_FILE);
+        let precursors = mzdata.get_precursor_map(ScanPolarity::Positive).unwrap();
+        assert_eq!(
+            precursors,
+            vec![
+                PrecursorPoint {
+                    index: 1,
+                    retention_time: 1.01,
+                    mz: 500.25,
+                    charge: Some(2),
+                    intensity: 2000.0,
+                },
+                PrecursorPoint {
+                    index: 2,
+                    retention_time: 1.02,
+                    mz: 520.0,
+                    charge: Some(1),
+                    intensity: 500.0,
+                },
+                PrecursorPoint {
+                    index: 4,
+                    retention_time: 2.01,
+                    mz: 500.25,
+                    charge: Some(2),
+                    intensity: 1800.0,
+                },
+                PrecursorPoint {
+                    index: 5,
+                    retention_time: 2.02,
+                    mz: 520.0,
+                    charge: Some(1),
+                    intensity: 700.0,
+                },
+            ]
+        );
+        assert!(mzdata
+            .get_precursor_map(ScanPolarity::Negative)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_search_ms2_spectra() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push(TEST_FILE);
+
+        // Normalize the path to account for different separators
+        let normalized_d = PathBuf::from(d.to_str().unwrap().replace('\\', "/"));
+
+        let mut mzdata = MzData::new();
+
+        mzdata.open_msfile(&normalized_d).unwrap();
+
+        let library = SpectralLibrary {
+            name: "test".to_string(),
+            spectra: vec![library::LibrarySpectrum {
+                name: "entry".to_string(),
+                mz: vec![100.0, 200.0],
+                intensity: vec![50.0, 100.0],
+                ..Default::default()
+            }],
+        };
+
+        // the test file only contains MS1 scans, so no MS2 spectrum is searched
+        let progress = Progress::default();
+        let matches = mzdata
+            .search_ms2_spectra(
+                ScanPolarity::Positive,
+                std::slice::from_ref(&library),
+                &Default::default(),
+                &progress,
+            )
+            .unwrap();
+        assert!(matches.is_empty());
+        assert_eq!(progress.total.load(Ordering::Relaxed), 53);
+        assert_eq!(progress.done.load(Ordering::Relaxed), 53);
+        assert_eq!(progress.fraction(), 1.0);
+
+        let progress = Progress::default();
+        progress.cancel();
+        assert!(matches!(
+            mzdata.search_ms2_spectra(
+                ScanPolarity::Positive,
+                &[library],
+                &Default::default(),
+                &progress,
+            ),
+            Err(ParserError::Cancelled)
+        ));
+
+        let spectrum = mzdata.get_mass_spectrum_by_index(0).unwrap();
+        assert!(spectrum.precursor_mz.is_none());
+
+        // every MS2 scan is matched with the entry of its precursor, the identical scans 1 and 2 with a perfect score
+        let library = SpectralLibrary {
+            name: "test".to_string(),
+            spectra: vec![
+                library::LibrarySpectrum {
+                    name: "precursor 500".to_string(),
+                    precursor_mz: Some(500.25),
+                    mz: vec![200.1, 300.2, 400.3],
+                    intensity: vec![10.0, 20.0, 30.0],
+                    ..Default::default()
+                },
+                library::LibrarySpectrum {
+                    name: "precursor 520".to_string(),
+                    precursor_mz: Some(520.0),
+                    mz: vec![200.1, 350.0],
+                    intensity: vec![5.0, 15.0],
+                    ..Default::default()
+                },
+            ],
+        };
+        let parameters = SearchParameters {
+            precursor_tolerance: Some(10.0),
+            ..Default::default()
+        };
+        let mut mzdata = open_test_file(DIA_TEST_FILE);
+        let progress = Progress::default();
+        let matches = mzdata
+            .search_ms2_spectra(ScanPolarity::Positive, &[library], &parameters, &progress)
+            .unwrap();
+        assert_eq!(progress.done.load(Ordering::Relaxed), 6);
+        let summary: Vec<(usize, Option<f64>, usize, usize)> = matches
+            .iter()
+            .map(|spectrum_match| {
+                (
+                    spectrum_match.index,
+                    spectrum_match.precursor_mz,
+                    spectrum_match.hit.spectrum_index,
+                    spectrum_match.hit.matched_peaks,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (1, Some(500.25), 0, 3),
+                (2, Some(520.0), 1, 2),
+                (4, Some(500.25), 0, 2),
+                (5, Some(520.0), 1, 1),
+            ]
+        );
+        assert!((matches[0].hit.score - 1.0).abs() < 1e-6);
+        assert!((matches[1].hit.score - 1.0).abs() < 1e-6);
+        assert!(matches[2].hit.score < matches[0].hit.score);
+        assert_eq!(matches[2].retention_time, 2.01);
+
+        let spectrum = mzdata.get_mass_spectrum_by_index(1).unwrap();
+        assert_eq!(spectrum.ms_level, Some(2));
+        assert_eq!(spectrum.precursor_mz, Some(500.25));
+        assert_eq!(spectrum.precursor_charge, Some(2));
+    }
+
+    #[test]
+    fn test_mass_spectra() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push(TEST_FILE);
+
+        // Normalize the path to account for different separators
+        let normalized_d = PathBuf::from(d.to_str().unwrap().replace('\\', "/"));
+
+        let mut mzdata = MzData::new();
+
+        mzdata.open_msfile(&normalized_d).unwrap();
+
+        let spectrum = mzdata.get_mass_spectrum_by_index(1).unwrap();
+        assert_eq!(spectrum.index, Some(1));
+        assert_eq!(spectrum.ms_level, Some(1));
+        assert!(!spectrum.profile);
+        assert_eq!(spectrum.label, "data_dependent_02.mzML scan 1");
+        assert_eq!(spectrum.mz.len(), spectrum.intensity.len());
+        assert!(!spectrum.mz.is_empty());
+
+        // failures are reported instead of leaving the displayed spectrum unchanged without notice
+        assert!(matches!(
+            mzdata.get_mass_spectrum_by_index(10_000),
+            Err(ParserError::NoMatchingSpectra { .. })
+        ));
+        assert!(matches!(
+            MzData::new().get_mass_spectrum_by_index(0),
+            Err(ParserError::NoFileOpened)
+        ));
+    }
+
+    #[test]
+    fn test_get_averaged_mass_spectrum() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push(TEST_FILE);
+
+        // Normalize the path to account for different separators
+        let normalized_d = PathBuf::from(d.to_str().unwrap().replace('\\', "/"));
+
+        let mut mzdata = MzData::new();
+
+        mzdata.open_msfile(&normalized_d).unwrap();
+
+        // a single scan is averaged into itself
+        let scan = mzdata.get_mass_spectrum_by_index(0).unwrap();
+        let rt = scan.retention_time.unwrap();
+        let averaged = mzdata
+            .get_averaged_mass_spectrum(ScanPolarity::Positive, (rt, rt), 0.0)
+            .unwrap();
+        assert!(averaged.label.ends_with("(1 scans)"));
+        assert_eq!(averaged.index, None);
+        let averaged_total: f32 = averaged.intensity.iter().sum();
+        let scan_total: f32 = scan.intensity.iter().sum();
+        assert!((averaged_total - scan_total).abs() <= scan_total * 1e-4);
+
+        let averaged = mzdata
+            .get_averaged_mass_spectrum(ScanPolarity::Positive, (0.0, f64::MAX), 10.0)
+            .unwrap();
+        assert!(averaged.label.ends_with("(53 scans)"));
+
+        assert!(matches!(
+            mzdata.get_averaged_mass_spectrum(ScanPolarity::Negative, (0.0, f64::MAX), 10.0),
+            Err(ParserError::NoMatchingSpectra { .. })
+        ));
+    }
+
+    #[test]
+    fn test_get_ms1_heatmap() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push(TEST_FILE);
+
+        // Normalize the path to account for different separators
+        let normalized_d = PathBuf::from(d.to_str().unwrap().replace('\\', "/"));
+
+        let mut mzdata = MzData::new();
+
+        mzdata.open_msfile(&normalized_d).unwrap();
+
+        let heatmap = mzdata
+            .get_ms1_heatmap(ScanPolarity::Positive, 20, 30, None, None)
+            .unwrap();
+        assert_eq!(heatmap.intensities.len(), 20 * 30);
+        assert_eq!(heatmap.scans.len(), 53);
+        assert!(heatmap.max_intensity() > 0.0);
+        assert!(heatmap.rt_range.0 < heatmap.rt_range.1);
+
+        // no scans of the polarity, or a range without width, leave nothing to bin
+        assert!(matches!(
+            mzdata.get_ms1_heatmap(ScanPolarity::Negative, 20, 30, None, None),
+            Err(ParserError::NoMatchingSpectra { .. })
+        ));
+        assert!(matches!(
+            mzdata.get_ms1_heatmap(
+                ScanPolarity::Positive,
+                20,
+                30,
+                Some((1.0, 1.0)),
+                Some((100.0, 200.0))
+            ),
+            Err(ParserError::NoMatchingSpectra { .. })
+        ));
+    }
+
+    #[test]
+    fn test_get_mobilogram() {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push(TEST_FILE);
+
+        // Normalize the path to account for different separators
+        let normalized_d = PathBuf::from(d.to_str().unwrap().replace('\\', "/"));
+
+        let mut mzdata = MzData::new();
+
+        mzdata.open_msfile(&normalized_d).unwrap();
+
+        // the test file has no ion mobility dimension
+        let mobilogram = mzdata
+            .get_mobilogram(722.43, ScanPolarity::Positive, 1000.0)
+            .unwrap();
+        assert!(mobilogram.is_empty());
+        assert_eq!(mobilogram.mz, 722.43);
+
+        assert!(matches!(
+            mzdata.get_mobility_heatmap(0, 10, 10),
+            Err(ParserError::NoMatchingSpectra { ref description, .. }) if description.contains("ion mobility")
+        ));
+        assert!(matches!(
+            mzdata.get_mobility_heatmap(10_000, 10, 10),
+            Err(ParserError::NoMatchingSpectra { ref description, .. }) if description.contains("10000")
+        ));
+
+        let xic = mzdata
+            .get_mobility_filtered_xic(722.43, ScanPolarity::Positive, 1000.0, (0.0, 2.0))
+            .unwrap();
+        assert_eq!(xic.len(), 53);
+        assert_eq!(xic.mobility_range, Some((0.0, 2.0)));
+        assert!(xic.intensity.iter().all(|&i| i == 0.0));
+    }
+
+    #[test]
+    fn test_sum_by_mobility() {
+        let points = vec![(0.9, 1.0), (0.8, 2.0), (0.9, 3.0), (1.1, 4.0)];
+        assert_eq!(
+            sum_by_mobility(points),
+            (vec![0.8, 0.9, 1.1], vec![2.0, 4.0, 4.0])
+        );
+    }
+
+    #[test]
+    fn test_bin_index() {
+        assert_eq!(bin_index(0.0, (0.0, 10.0), 10), Some(0));
+        assert_eq!(bin_index(5.0, (0.0, 10.0), 10), Some(5));
+        assert_eq!(bin_index(10.0, (0.0, 10.0), 10), Some(9));
+        assert_eq!(bin_index(10.5, (0.0, 10.0), 10), None);
+        assert_eq!(bin_index(1.0, (1.0, 1.0), 4), Some(0));
+    }
+
+    #[test]
+    fn test_isolation_window_contains() {
+        let window = IsolationWindow::new(500.0, 487.5, 512.5, IsolationWindowState::Complete);
+        assert!(isolation_window_contains(&window, 500.0));
+        assert!(isolation_window_contains(&window, 487.5));
+        assert!(!isolation_window_contains(&window, 520.0));
+
+        let offset_window = IsolationWindow::new(500.0, 12.5, 12.5, IsolationWindowState::Offset);
+        assert!(isolation_window_contains(&offset_window, 510.0));
+        assert!(!isolation_window_contains(&offset_window, 480.0));
 
-        let smoothed = mzdata.plot_data.unwrap();
-        assert_eq!(smoothed.len(), 5);
-        //assert_relative_eq!(smoothed[2][1], 3.0);
+        assert!(!isolation_window_contains(&IsolationWindow::default(), 0.0));
     }
 }
@@ -0,0 +1,406 @@
+//! # peptide fragment ion annotation
+
+//! The `peptide` module provides functionality for computing the fragment ions of a modified peptide and matching them to the peaks of an MS2 spectrum.
+
+//! ## Overview
+
+//! A peptide is typed as a sequence of one-letter amino acid codes. Variable modifications are written in brackets after the modified residue,
+//! either by name or by mass shift (e.g. `PEPTM[Oxidation]IDE` or `PEPS[+79.9663]IDE`), and an N-terminal modification is written in brackets
+//! before the sequence (e.g. `[Acetyl]-PEPTIDE`). Fixed modifications are applied to every residue they target.
+
+//!## Features
+
+//!- **Sequence Parsing**: Read peptide sequences with fixed and variable modifications.
+//!- **Fragment Ions**: Compute the m/z of the a, b, c, y and z ions of a peptide at several charge states.
+//!- **Annotation**: Match the fragment ions to the peaks of a spectrum within a tolerance.
+
+#![warn(clippy::all)]
+
+use crate::deconvolution::PROTON_MASS;
+use anyhow::{anyhow, Result};
+
+/// The monoisotopic mass of water, in Da.
+const WATER_MASS: f64 = 18.010565;
+/// The monoisotopic mass of carbon monoxide, lost by a ions with respect to b ions, in Da.
+const CO_MASS: f64 = 27.994915;
+/// The monoisotopic mass of ammonia, gained by c ions with respect to b ions, in Da.
+const AMMONIA_MASS: f64 = 17.026549;
+/// The mass difference between y ions and z• ions, in Da.
+const Z_ION_OFFSET: f64 = 16.018724;
+
+/// Returns the monoisotopic residue mass of an amino acid, or `None` for an unknown one-letter code.
+fn residue_mass(residue: char) -> Option<f64> {
+    Some(match residue {
+        'G' => 57.021464,
+        'A' => 71.037114,
+        'S' => 87.032028,
+        'P' => 97.052764,
+        'V' => 99.068414,
+        'T' => 101.047679,
+        'C' => 103.009185,
+        'L' | 'I' => 113.084064,
+        'N' => 114.042927,
+        'D' => 115.026943,
+        'Q' => 128.058578,
+        'K' => 128.094963,
+        'E' => 129.042593,
+        'M' => 131.040485,
+        'H' => 137.058912,
+        'F' => 147.068414,
+        'R' => 156.101111,
+        'Y' => 163.063329,
+        'W' => 186.079313,
+        _ => return None,
+    })
+}
+
+/// Represents a common modification that can be referred to by name.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Modification {
+    /// The name of the modification, as written in sequences.
+    pub name: &'static str,
+    /// The monoisotopic mass shift of the modification, in Da.
+    pub mass: f64,
+    /// The residues targeted by the modification when it is used as a fixed modification.
+    pub residues: &'static str,
+}
+
+/// The modifications that can be referred to by name.
+pub const MODIFICATIONS: [Modification; 5] = [
+    Modification {
+        name: "Carbamidomethyl",
+        mass: 57.021464,
+        residues: "C",
+    },
+    Modification {
+        name: "Oxidation",
+        mass: 15.994915,
+        residues: "M",
+    },
+    Modification {
+        name: "Phospho",
+        mass: 79.966331,
+        residues: "STY",
+    },
+    Modification {
+        name: "Deamidated",
+        mass: 0.984016,
+        residues: "NQ",
+    },
+    Modification {
+        name: "Acetyl",
+        mass: 42.010565,
+        residues: "K",
+    },
+];
+
+/// Returns the mass shift of a modification written in brackets, either its name or a signed mass.
+fn modification_mass(modification: &str) -> Result<f64> {
+    if let Some(known) = MODIFICATIONS
+        .iter()
+        .find(|known| known.name.eq_ignore_ascii_case(modification))
+    {
+        return Ok(known.mass);
+    }
+    modification
+        .parse::<f64>()
+        .map_err(|_| anyhow!("Unknown modification: {}", modification))
+}
+
+/// The type of a peptide fragment ion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IonType {
+    A,
+    B,
+    C,
+    Y,
+    Z,
+}
+
+impl IonType {
+    /// All ion types, N-terminal ions first.
+    pub const ALL: [IonType; 5] = [IonType::A, IonType::B, IonType::C, IonType::Y, IonType::Z];
+
+    /// Returns the letter of the ion type.
+    pub fn symbol(&self) -> char {
+        match self {
+            IonType::A => 'a',
+            IonType::B => 'b',
+            IonType::C => 'c',
+            IonType::Y => 'y',
+            IonType::Z => 'z',
+        }
+    }
+
+    /// Returns whether the ion contains the N-terminus of the peptide.
+    pub fn is_n_terminal(&self) -> bool {
+        matches!(self, IonType::A | IonType::B | IonType::C)
+    }
+}
+
+/// Represents a fragment ion of a peptide.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FragmentIon {
+    /// The type of the ion.
+    pub ion_type: IonType,
+    /// The number of residues of the ion.
+    pub number: usize,
+    /// The charge state of the ion.
+    pub charge: u32,
+    /// The m/z of the ion.
+    pub mz: f64,
+}
+
+impl FragmentIon {
+    /// Returns the label of the ion, e.g. `b3` or `y5++`.
+    pub fn label(&self) -> String {
+        let charge = if self.charge > 1 {
+            "+".repeat(self.charge as usize)
+        } else {
+            String::new()
+        };
+        format!("{}{}{}", self.ion_type.symbol(), self.number, charge)
+    }
+}
+
+/// Represents a parsed peptide with its modifications.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Peptide {
+    /// The one-letter codes of the residues.
+    pub residues: Vec<char>,
+    /// The masses of the residues, including their modifications.
+    pub masses: Vec<f64>,
+    /// The mass shift of the N-terminal modification.
+    pub n_terminal_mass: f64,
+}
+
+impl Peptide {
+    /// Parses a peptide sequence with modifications.
+    ///
+    /// # Parameters
+    /// - `sequence: &str` - The sequence, with variable modifications in brackets after the residues and an optional N-terminal modification in brackets followed by `-`.
+    /// - `fixed_modifications: &[String]` - The names of the fixed modifications, applied to all residues they target.
+    ///
+    /// # Returns
+    /// - `Result<Peptide>` - The parsed peptide.
+    ///
+    /// # Errors
+    /// Returns an error if the sequence is empty, contains an unknown residue or modification, or has unbalanced brackets.
+    pub fn parse(sequence: &str, fixed_modifications: &[String]) -> Result<Self> {
+        let mut peptide = Peptide::default();
+        let mut rest = sequence.trim();
+        if let Some(stripped) = rest.strip_prefix('[') {
+            let end = stripped
+                .find("]-")
+                .ok_or_else(|| anyhow!("The N-terminal modification must be followed by ']-'"))?;
+            peptide.n_terminal_mass = modification_mass(&stripped[..end])?;
+            rest = &stripped[end + 2..];
+        }
+
+        let mut chars = rest.chars();
+        while let Some(c) = chars.next() {
+            if c.is_whitespace() {
+                continue;
+            }
+            if c == '[' {
+                let remainder = chars.as_str();
+                let end = remainder
+                    .find(']')
+                    .ok_or_else(|| anyhow!("Unbalanced brackets in {}", sequence))?;
+                let mass = modification_mass(&remainder[..end])?;
+                chars = remainder[end + 1..].chars();
+                *peptide
+                    .masses
+                    .last_mut()
+                    .ok_or_else(|| anyhow!("A modification must follow a residue"))? += mass;
+                continue;
+            }
+            let residue = c.to_ascii_uppercase();
+            let mut mass = residue_mass(residue)
+                .ok_or_else(|| anyhow!("Unknown residue '{}' in {}", c, sequence))?;
+            for known in MODIFICATIONS.iter().filter(|known| {
+                known.residues.contains(residue)
+                    && fixed_modifications.iter().any(|name| name == known.name)
+            }) {
+                mass += known.mass;
+            }
+            peptide.residues.push(residue);
+            peptide.masses.push(mass);
+        }
+        if peptide.residues.is_empty() {
+            return Err(anyhow!("The peptide sequence is empty"));
+        }
+        Ok(peptide)
+    }
+
+    /// Returns the monoisotopic neutral mass of the peptide.
+    pub fn mass(&self) -> f64 {
+        self.n_terminal_mass + self.masses.iter().sum::<f64>() + WATER_MASS
+    }
+
+    /// Returns the m/z of the peptide at a charge state.
+    pub fn precursor_mz(&self, charge: u32) -> f64 {
+        (self.mass() + charge as f64 * PROTON_MASS) / charge as f64
+    }
+
+    /// Computes the fragment ions of the peptide.
+    ///
+    /// # Parameters
+    /// - `ion_types: &[IonType]` - The types of the ions to be computed.
+    /// - `max_charge: u32` - The highest charge state of the ions.
+    ///
+    /// # Returns
+    /// - `Vec<FragmentIon>` - The ions of every type, number (1 to the length of the peptide - 1) and charge state from 1 to `max_charge`.
+    pub fn fragment_ions(&self, ion_types: &[IonType], max_charge: u32) -> Vec<FragmentIon> {
+        let n = self.masses.len();
+        let mut ions = Vec::new();
+        for number in 1..n {
+            let prefix = self.n_terminal_mass + self.masses[..number].iter().sum::<f64>();
+            let suffix: f64 = self.masses[n - number..].iter().sum();
+            for &ion_type in ion_types {
+                // the neutral mass of the fragment
+                let mass = match ion_type {
+                    IonType::A => prefix - CO_MASS,
+                    IonType::B => prefix,
+                    IonType::C => prefix + AMMONIA_MASS,
+                    IonType::Y => suffix + WATER_MASS,
+                    IonType::Z => suffix + WATER_MASS - Z_ION_OFFSET,
+                };
+                for charge in 1..=max_charge {
+                    ions.push(FragmentIon {
+                        ion_type,
+                        number,
+                        charge,
+                        mz: (mass + charge as f64 * PROTON_MASS) / charge as f64,
+                    });
+                }
+            }
+        }
+        ions
+    }
+}
+
+/// The parameters of the fragment ion annotation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnnotationParameters {
+    /// The types of the ions that are annotated.
+    pub ion_types: Vec<IonType>,
+    /// The highest charge state of the fragment ions.
+    pub max_charge: u32,
+    /// The m/z tolerance (in Da) between a fragment ion and a peak.
+    pub tolerance: f64,
+    /// The names of the fixed modifications.
+    pub fixed_modifications: Vec<String>,
+}
+
+impl Default for AnnotationParameters {
+    fn default() -> Self {
+        Self {
+            ion_types: vec![IonType::B, IonType::Y],
+            max_charge: 2,
+            tolerance: 0.02,
+            fixed_modifications: vec!["Carbamidomethyl".to_string()],
+        }
+    }
+}
+
+/// Represents a peak matched to a fragment ion.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PeakAnnotation {
+    /// The fragment ion.
+    pub ion: FragmentIon,
+    /// The index of the matched peak.
+    pub peak: usize,
+}
+
+/// Matches fragment ions to the peaks of a spectrum.
+///
+/// Every ion is matched to the most intense peak within the tolerance, if any. A peak can be matched by several ions.
+///
+/// # Parameters
+/// - `mz: &[f64]` - The sorted m/z values of the spectrum.
+/// - `intensity: &[f32]` - The intensities of the spectrum.
+/// - `ions: &[FragmentIon]` - The fragment ions to be matched.
+/// - `tolerance: f64` - The m/z tolerance (in Da).
+///
+/// # Returns
+/// - `Vec<PeakAnnotation>` - The matched ions, in the order of `ions`.
+pub fn annotate(
+    mz: &[f64],
+    intensity: &[f32],
+    ions: &[FragmentIon],
+    tolerance: f64,
+) -> Vec<PeakAnnotation> {
+    ions.iter()
+        .filter_map(|ion| {
+            let start = mz.partition_point(|&m| m < ion.mz - tolerance);
+            (start..mz.len().min(intensity.len()))
+                .take_while(|&i| mz[i] <= ion.mz + tolerance)
+                .max_by(|&a, &b| {
+                    intensity[a]
+                        .partial_cmp(&intensity[b])
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                })
+                .map(|peak| PeakAnnotation { ion: *ion, peak })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_peptide() {
+        let peptide = Peptide::parse("PEPTIDE", &[]).unwrap();
+        assert_eq!(peptide.residues.len(), 7);
+        assert!((peptide.mass() - 799.359964).abs() < 1e-4);
+
+        let fixed = vec!["Carbamidomethyl".to_string()];
+        let peptide = Peptide::parse("[Acetyl]-PEPCM[Oxidation]S[+79.966331]", &fixed).unwrap();
+        assert_eq!(peptide.n_terminal_mass, 42.010565);
+        assert!((peptide.masses[3] - 160.030649).abs() < 1e-6);
+        assert!((peptide.masses[4] - 147.0354).abs() < 1e-4);
+        assert!((peptide.masses[5] - 166.998359).abs() < 1e-6);
+
+        assert!(Peptide::parse("", &[]).is_err());
+        assert!(Peptide::parse("PEPXIDE", &[]).is_err());
+        assert!(Peptide::parse("PEPT[Unknown]IDE", &[]).is_err());
+        assert!(Peptide::parse("[Oxidation]PEPTIDE", &[]).is_err());
+        assert!(Peptide::parse("PEPTM[Oxidation", &[]).is_err());
+    }
+
+    #[test]
+    fn test_fragment_ions() {
+        let peptide = Peptide::parse("PEPTIDE", &[]).unwrap();
+        let ions = peptide.fragment_ions(&[IonType::B, IonType::Y], 2);
+        assert_eq!(ions.len(), 6 * 2 * 2);
+
+        let b2 = ions
+            .iter()
+            .find(|ion| ion.ion_type == IonType::B && ion.number == 2 && ion.charge == 1)
+            .unwrap();
+        assert!((b2.mz - 227.102633).abs() < 1e-4);
+        assert_eq!(b2.label(), "b2");
+        let y1 = ions
+            .iter()
+            .find(|ion| ion.ion_type == IonType::Y && ion.number == 1 && ion.charge == 2)
+            .unwrap();
+        assert!((y1.mz - (148.060434 + PROTON_MASS) / 2.0).abs() < 1e-4);
+        assert_eq!(y1.label(), "y1++");
+    }
+
+    #[test]
+    fn test_annotate() {
+        let peptide = Peptide::parse("PEPTIDE", &[]).unwrap();
+        let ions = peptide.fragment_ions(&[IonType::B, IonType::Y], 1);
+        let mz = vec![148.06, 227.10, 227.11, 500.0];
+        let intensity = vec![10.0, 5.0, 20.0, 100.0];
+        let annotations = annotate(&mz, &intensity, &ions, 0.02);
+        assert_eq!(annotations.len(), 2);
+        assert_eq!(annotations[0].ion.label(), "y1");
+        assert_eq!(annotations[0].peak, 0);
+        assert_eq!(annotations[1].ion.label(), "b2");
+        assert_eq!(annotations[1].peak, 2);
+    }
+}
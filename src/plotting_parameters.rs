@@ -36,13 +36,43 @@
 //! - `Xic`
 //! - `Bpc`
 //! - `Tic` (default)
+//! - `Dia`
 //!
 //! The `PlotType` enum derives the `PartialEq`, `Debug`, and `Default` traits, allowing for comparison, debugging output, and default instantiation.
 //!
+//! ### `PrecursorColor`
+//!
+//! An enumeration representing how the points of the precursor coverage map are colored. The available modes are:
+//!
+//! - `Charge` (default)
+//! - `Intensity`
+//!
+//! ### `SpectrumLayout`
+//!
+//! An enumeration representing how several mass spectra are shown in the spectrum panel. The available layouts are:
+//!
+//! - `Overlay` (default): all spectra in one plot.
+//! - `Stacked`: one plot per spectrum, sharing the m/z axis.
+//!
+//! ### `MirrorSource`
+//!
+//! An enumeration representing the spectrum compared with the pinned spectrum in the mirror plot. The available sources are:
+//!
+//! - `Displayed` (default): the spectrum shown in the mass spectrum plot.
+//! - `File`: a scan of another mzML file.
+//! - `Library`: the library hit selected in the library search window.
+//!
+//! ## Functions
+//!
+//! - `trace_color`: Returns a color from a fixed palette, used to tell overlaid traces apart.
+//! - `intensity_color`: Maps a relative intensity between `0.0` and `1.0` onto a blue-to-red color gradient.
+//!
 //! ## Constants
 //!
 //! - `DASHED_LINE_LENGTH`: A constant defining the length of dashed lines, set to `10.0`.
 //! - `DOTTED_LINE_SPACING`: A constant defining the spacing between dotted lines, set to `5.0`.
+//! - `TRACE_PALETTE`: The colors cycled through by `trace_color`.
+//! - `INTENSITY_GRADIENT`: The color stops interpolated by `intensity_color`.
 //!
 //! ## Usage
 //!
@@ -103,4 +133,66 @@ pub enum PlotType {
     Bpc,
     #[default]
     Tic,
+    Dia,
+}
+
+const TRACE_PALETTE: [egui::ecolor::Color32; 8] = [
+    egui::ecolor::Color32::from_rgb(31, 119, 180),
+    egui::ecolor::Color32::from_rgb(255, 127, 14),
+    egui::ecolor::Color32::from_rgb(44, 160, 44),
+    egui::ecolor::Color32::from_rgb(148, 103, 189),
+    egui::ecolor::Color32::from_rgb(140, 86, 75),
+    egui::ecolor::Color32::from_rgb(227, 119, 194),
+    egui::ecolor::Color32::from_rgb(188, 189, 34),
+    egui::ecolor::Color32::from_rgb(23, 190, 207),
+];
+
+/// Returns the color of the overlaid trace at `index`, cycling through `TRACE_PALETTE`.
+pub fn trace_color(index: usize) -> egui::ecolor::Color32 {
+    TRACE_PALETTE[index % TRACE_PALETTE.len()]
+}
+
+#[derive(PartialEq, Debug, Default, Clone, Copy)]
+pub enum PrecursorColor {
+    #[default]
+    Charge,
+    Intensity,
+}
+
+#[derive(PartialEq, Debug, Default, Clone, Copy)]
+pub enum SpectrumLayout {
+    #[default]
+    Overlay,
+    Stacked,
+}
+
+#[derive(PartialEq, Debug, Default, Clone, Copy)]
+pub enum MirrorSource {
+    #[default]
+    Displayed,
+    File,
+    Library,
+}
+
+const INTENSITY_GRADIENT: [egui::ecolor::Color32; 5] = [
+    egui::ecolor::Color32::from_rgb(48, 18, 59),
+    egui::ecolor::Color32::from_rgb(40, 130, 230),
+    egui::ecolor::Color32::from_rgb(60, 200, 110),
+    egui::ecolor::Color32::from_rgb(250, 200, 40),
+    egui::ecolor::Color32::from_rgb(200, 30, 20),
+];
+
+/// Maps a relative intensity between `0.0` and `1.0` onto `INTENSITY_GRADIENT`. Values outside the range are clamped.
+pub fn intensity_color(fraction: f64) -> egui::ecolor::Color32 {
+    let position = fraction.clamp(0.0, 1.0) * (INTENSITY_GRADIENT.len() - 1) as f64;
+    let lower = position.floor() as usize;
+    let upper = (lower + 1).min(INTENSITY_GRADIENT.len() - 1);
+    let t = position - lower as f64;
+    let (from, to) = (INTENSITY_GRADIENT[lower], INTENSITY_GRADIENT[upper]);
+    let channel = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+    egui::ecolor::Color32::from_rgb(
+        channel(from.r(), to.r()),
+        channel(from.g(), to.g()),
+        channel(from.b(), to.b()),
+    )
 }
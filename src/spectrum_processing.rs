@@ -0,0 +1,475 @@
+//! # display-time processing of mass spectra
+
+//! The `spectrum_processing` module provides functions that prepare mass spectra for display, independent of how they were read.
+
+//! ## Overview
+
+//! The functions operate on the m/z and intensity arrays of a single spectrum and return indices or new arrays, leaving the input untouched.
+
+//!## Features
+
+//!- **Peak Labelling**: Select the most intense peaks of a visible m/z range, keeping a minimum distance between labelled peaks.
+//!- **Representation**: Recognize profile spectra whose representation is not annotated in the file.
+//!- **Centroiding**: Pick the peaks of profile spectra, with a Gaussian fit of the apex for the centroid m/z.
+//!- **Filtering**: Remove peaks below an absolute or relative intensity threshold, keep the most intense peaks of every m/z window and crop the m/z range.
+//!- **Normalization**: Scale intensities to percent of the base peak or of a chosen reference peak, or to a logarithmic scale.
+
+#![warn(clippy::all)]
+
+use std::cmp::Ordering;
+
+/// The median spacing between consecutive points (in ppm of their m/z) below which a spectrum is considered to be profile data.
+const PROFILE_SPACING_PPM: f64 = 50.0;
+
+/// The parameters of the peak picking of profile spectra.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CentroidParameters {
+    /// The minimum apex intensity of a peak, in percent of the base peak.
+    pub relative_threshold: f32,
+    /// The minimum number of profile points with non-zero intensity that make up a peak.
+    pub min_points: usize,
+}
+
+impl Default for CentroidParameters {
+    fn default() -> Self {
+        Self {
+            relative_threshold: 1.0,
+            min_points: 3,
+        }
+    }
+}
+
+/// How the intensity threshold of a `SpectrumFilter` is interpreted.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ThresholdMode {
+    /// No peak is removed.
+    #[default]
+    Off,
+    /// Peaks below the threshold intensity are removed.
+    Absolute,
+    /// Peaks below the threshold, in percent of the base peak, are removed.
+    Relative,
+}
+
+/// The display-time filters of a spectrum.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpectrumFilter {
+    /// How `threshold` is interpreted.
+    pub threshold_mode: ThresholdMode,
+    /// The intensity threshold, absolute or in percent of the base peak.
+    pub threshold: f32,
+    /// The number of most intense peaks kept in every m/z window. All peaks are kept if `0`.
+    pub top_n: usize,
+    /// The width of the m/z windows of `top_n`.
+    pub window: f64,
+    /// The m/z range the spectrum is cropped to. The spectrum is not cropped if `None`.
+    pub mz_range: Option<(f64, f64)>,
+}
+
+impl Default for SpectrumFilter {
+    fn default() -> Self {
+        Self {
+            threshold_mode: ThresholdMode::default(),
+            threshold: 0.0,
+            top_n: 0,
+            window: 100.0,
+            mz_range: None,
+        }
+    }
+}
+
+/// The intensity scale of a displayed spectrum.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Normalization {
+    /// The intensities as recorded.
+    #[default]
+    Absolute,
+    /// The intensities in percent of the most intense peak.
+    BasePeak,
+    /// The intensities in percent of the most intense peak within the tolerance of a reference m/z.
+    ReferencePeak,
+}
+
+/// Selects the most intense peaks of an m/z range to be labelled.
+///
+/// Peaks are visited by decreasing intensity, and a peak is skipped if it lies closer than `min_distance` to a peak that was already selected.
+///
+/// # Parameters
+/// - `mz: &[f64]` - The m/z values of the spectrum.
+/// - `intensity: &[f32]` - The intensities of the spectrum.
+/// - `mz_range: (f64, f64)` - The m/z range whose peaks are considered, usually the visible region of the plot.
+/// - `count: usize` - The maximum number of selected peaks.
+/// - `min_distance: f64` - The minimum m/z distance between two selected peaks.
+///
+/// # Returns
+/// - `Vec<usize>` - The indices of the selected peaks, ordered by decreasing intensity.
+pub fn top_peaks(
+    mz: &[f64],
+    intensity: &[f32],
+    mz_range: (f64, f64),
+    count: usize,
+    min_distance: f64,
+) -> Vec<usize> {
+    if count == 0 {
+        return Vec::new();
+    }
+    let mut candidates: Vec<usize> = (0..mz.len().min(intensity.len()))
+        .filter(|&i| mz_range.0 <= mz[i] && mz[i] <= mz_range.1 && intensity[i] > 0.0)
+        .collect();
+    candidates.sort_by(|&a, &b| {
+        intensity[b]
+            .partial_cmp(&intensity[a])
+            .unwrap_or(Ordering::Equal)
+    });
+
+    let mut selected: Vec<usize> = Vec::with_capacity(count);
+    for i in candidates {
+        if selected
+            .iter()
+            .all(|&j| (mz[i] - mz[j]).abs() >= min_distance)
+        {
+            selected.push(i);
+            if selected.len() == count {
+                break;
+            }
+        }
+    }
+    selected
+}
+
+/// Guesses whether a spectrum is profile data from the spacing of its m/z values.
+///
+/// Profile spectra sample the signal every few ppm, while the peaks of centroid spectra are usually much further apart.
+///
+/// # Parameters
+/// - `mz: &[f64]` - The sorted m/z values of the spectrum.
+///
+/// # Returns
+/// - `bool` - `true` if the median spacing between consecutive points is below `PROFILE_SPACING_PPM`.
+pub fn looks_like_profile(mz: &[f64]) -> bool {
+    let mut spacings: Vec<f64> = mz
+        .windows(2)
+        .filter(|pair| pair[0] > 0.0)
+        .map(|pair| (pair[1] - pair[0]) / pair[0] * 1e6)
+        .collect();
+    if spacings.is_empty() {
+        return false;
+    }
+    let middle = spacings.len() / 2;
+    let (_, median, _) =
+        spacings.select_nth_unstable_by(middle, |a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    *median < PROFILE_SPACING_PPM
+}
+
+/// Picks the peaks of a profile spectrum.
+///
+/// Every local maximum is a peak candidate. A peak extends on both sides of its apex as long as the intensity keeps decreasing and stays above zero.
+/// Its m/z is the vertex of a Gaussian fitted to the apex and its two neighbours, and its intensity is the apex intensity.
+///
+/// # Parameters
+/// - `mz: &[f64]` - The sorted m/z values of the profile spectrum.
+/// - `intensity: &[f32]` - The intensities of the profile spectrum.
+/// - `parameters: &CentroidParameters` - The intensity threshold and minimum width of a peak.
+///
+/// # Returns
+/// - `(Vec<f64>, Vec<f32>)` - The m/z values and intensities of the picked peaks.
+pub fn centroid(
+    mz: &[f64],
+    intensity: &[f32],
+    parameters: &CentroidParameters,
+) -> (Vec<f64>, Vec<f32>) {
+    let n = mz.len().min(intensity.len());
+    let base_peak = intensity[..n].iter().cloned().fold(0.0_f32, f32::max);
+    let threshold = base_peak * parameters.relative_threshold / 100.0;
+    let (mut peak_mz, mut peak_intensity) = (Vec::new(), Vec::new());
+
+    for apex in 1..n.saturating_sub(1) {
+        let (left, top, right) = (intensity[apex - 1], intensity[apex], intensity[apex + 1]);
+        // plateaus are attributed to their first point
+        if top <= 0.0 || top < threshold || left >= top || right > top {
+            continue;
+        }
+
+        let mut start = apex;
+        while start > 0 && intensity[start - 1] > 0.0 && intensity[start - 1] <= intensity[start] {
+            start -= 1;
+        }
+        let mut end = apex;
+        while end + 1 < n && intensity[end + 1] > 0.0 && intensity[end + 1] <= intensity[end] {
+            end += 1;
+        }
+        if end - start + 1 < parameters.min_points {
+            continue;
+        }
+
+        let mut apex_mz = mz[apex];
+        if left > 0.0 && right > 0.0 {
+            let (l, c, r) = ((left as f64).ln(), (top as f64).ln(), (right as f64).ln());
+            let denominator = l - 2.0 * c + r;
+            if denominator < 0.0 {
+                // offset of the vertex in points, assuming locally even spacing
+                let offset = 0.5 * (l - r) / denominator;
+                let spacing = if offset < 0.0 {
+                    mz[apex] - mz[apex - 1]
+                } else {
+                    mz[apex + 1] - mz[apex]
+                };
+                apex_mz += offset.clamp(-0.5, 0.5) * spacing;
+            }
+        }
+        peak_mz.push(apex_mz);
+        peak_intensity.push(top);
+    }
+    (peak_mz, peak_intensity)
+}
+
+/// Filters the peaks of a spectrum.
+///
+/// The spectrum is first cropped to `filter.mz_range`, then peaks below the intensity threshold are removed, and finally only the `filter.top_n` most intense peaks of every `filter.window` wide m/z window are kept.
+/// The relative threshold refers to the base peak of the cropped spectrum.
+///
+/// # Parameters
+/// - `mz: &[f64]` - The m/z values of the spectrum.
+/// - `intensity: &[f32]` - The intensities of the spectrum.
+/// - `filter: &SpectrumFilter` - The filters to be applied.
+///
+/// # Returns
+/// - `(Vec<f64>, Vec<f32>)` - The m/z values and intensities of the remaining peaks, in their original order.
+pub fn filter_peaks(
+    mz: &[f64],
+    intensity: &[f32],
+    filter: &SpectrumFilter,
+) -> (Vec<f64>, Vec<f32>) {
+    let mut kept: Vec<usize> = (0..mz.len().min(intensity.len()))
+        .filter(|&i| {
+            filter
+                .mz_range
+                .map_or(true, |(min, max)| min <= mz[i] && mz[i] <= max)
+        })
+        .collect();
+
+    let threshold = match filter.threshold_mode {
+        ThresholdMode::Off => None,
+        ThresholdMode::Absolute => Some(filter.threshold),
+        ThresholdMode::Relative => {
+            let base_peak = kept.iter().map(|&i| intensity[i]).fold(0.0_f32, f32::max);
+            Some(base_peak * filter.threshold / 100.0)
+        }
+    };
+    if let Some(threshold) = threshold {
+        kept.retain(|&i| intensity[i] >= threshold);
+    }
+
+    if filter.top_n > 0 && filter.window > 0.0 {
+        let mut windows: std::collections::BTreeMap<i64, Vec<usize>> =
+            std::collections::BTreeMap::new();
+        for &i in &kept {
+            windows
+                .entry((mz[i] / filter.window).floor() as i64)
+                .or_default()
+                .push(i);
+        }
+        kept = windows
+            .into_values()
+            .flat_map(|mut peaks| {
+                peaks.sort_by(|&a, &b| {
+                    intensity[b]
+                        .partial_cmp(&intensity[a])
+                        .unwrap_or(Ordering::Equal)
+                });
+                peaks.truncate(filter.top_n);
+                peaks
+            })
+            .collect();
+        kept.sort_unstable();
+    }
+
+    (
+        kept.iter().map(|&i| mz[i]).collect(),
+        kept.iter().map(|&i| intensity[i]).collect(),
+    )
+}
+
+/// Scales the intensities of a spectrum.
+///
+/// # Parameters
+/// - `mz: &[f64]` - The m/z values of the spectrum.
+/// - `intensity: &[f32]` - The intensities of the spectrum.
+/// - `normalization: Normalization` - The intensity scale.
+/// - `reference_mz: f64` - The m/z of the reference peak, only used for `Normalization::ReferencePeak`.
+/// - `tolerance: f64` - The tolerance (in ppm) around `reference_mz`, only used for `Normalization::ReferencePeak`.
+///
+/// # Returns
+/// - `Option<Vec<f32>>` - The scaled intensities, or `None` if the base or reference peak is missing or has no intensity.
+pub fn normalize(
+    mz: &[f64],
+    intensity: &[f32],
+    normalization: Normalization,
+    reference_mz: f64,
+    tolerance: f64,
+) -> Option<Vec<f32>> {
+    let reference = match normalization {
+        Normalization::Absolute => return Some(intensity.to_vec()),
+        Normalization::BasePeak => intensity.iter().cloned().fold(0.0_f32, f32::max),
+        Normalization::ReferencePeak => {
+            let max_error = reference_mz * tolerance * 1e-6;
+            mz.iter()
+                .zip(intensity.iter())
+                .filter(|(&m, _)| (m - reference_mz).abs() <= max_error)
+                .map(|(_, &i)| i)
+                .fold(0.0_f32, f32::max)
+        }
+    };
+    if reference <= 0.0 {
+        return None;
+    }
+    Some(intensity.iter().map(|&i| 100.0 * i / reference).collect())
+}
+
+/// Converts intensities to `log10(1 + intensity)`, so that zero intensities stay at zero.
+pub fn log_intensities(intensity: &[f32]) -> Vec<f32> {
+    intensity
+        .iter()
+        .map(|&i| i.max(0.0).ln_1p() / std::f32::consts::LN_10)
+        .collect()
+}
+
+/// Converts a value of `log_intensities` back to an intensity.
+pub fn inverse_log_intensity(value: f64) -> f64 {
+    10_f64.powf(value) - 1.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_top_peaks() {
+        let mz = [100.0, 100.5, 101.0, 150.0, 200.0];
+        let intensity = [50.0, 100.0, 20.0, 10.0, 80.0];
+
+        assert_eq!(
+            top_peaks(&mz, &intensity, (0.0, 1000.0), 3, 0.0),
+            vec![1, 4, 0]
+        );
+        // peaks within 1 m/z of the base peak are skipped
+        assert_eq!(
+            top_peaks(&mz, &intensity, (0.0, 1000.0), 3, 1.0),
+            vec![1, 4, 3]
+        );
+        // only the visible range is labelled
+        assert_eq!(
+            top_peaks(&mz, &intensity, (99.0, 160.0), 2, 1.0),
+            vec![1, 3]
+        );
+        assert!(top_peaks(&mz, &intensity, (0.0, 1000.0), 0, 1.0).is_empty());
+    }
+
+    #[test]
+    fn test_looks_like_profile() {
+        let profile: Vec<f64> = (0..100).map(|i| 500.0 + i as f64 * 0.001).collect();
+        assert!(looks_like_profile(&profile));
+        assert!(!looks_like_profile(&[100.0, 150.2, 200.1, 201.1]));
+        assert!(!looks_like_profile(&[]));
+    }
+
+    #[test]
+    fn test_centroid() {
+        // two Gaussian peaks centred at 500.0104 and 501.0 on a 0.002 m/z grid
+        let mz: Vec<f64> = (0..1000).map(|i| 499.5 + i as f64 * 0.002).collect();
+        let gaussian = |m: f64, center: f64, height: f32| {
+            height * (-((m - center) / 0.004).powi(2) / 2.0).exp() as f32
+        };
+        let intensity: Vec<f32> = mz
+            .iter()
+            .map(|&m| gaussian(m, 500.0104, 1000.0) + gaussian(m, 501.0, 5.0))
+            .collect();
+
+        let (peak_mz, peak_intensity) = centroid(&mz, &intensity, &CentroidParameters::default());
+        assert_eq!(peak_mz.len(), 1);
+        assert!((peak_mz[0] - 500.0104).abs() < 1e-4);
+        assert!(peak_intensity[0] > 900.0);
+
+        let parameters = CentroidParameters {
+            relative_threshold: 0.1,
+            min_points: 3,
+        };
+        let (peak_mz, _) = centroid(&mz, &intensity, &parameters);
+        assert_eq!(peak_mz.len(), 2);
+        assert!((peak_mz[1] - 501.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_filter_peaks() {
+        let mz = [50.0, 110.0, 120.0, 130.0, 150.0, 210.0];
+        let intensity = [1000.0, 5.0, 40.0, 30.0, 20.0, 10.0];
+
+        let (filtered_mz, _) = filter_peaks(&mz, &intensity, &SpectrumFilter::default());
+        assert_eq!(filtered_mz, mz.to_vec());
+
+        let filter = SpectrumFilter {
+            threshold_mode: ThresholdMode::Absolute,
+            threshold: 15.0,
+            ..Default::default()
+        };
+        assert_eq!(
+            filter_peaks(&mz, &intensity, &filter).0,
+            vec![50.0, 120.0, 130.0, 150.0]
+        );
+
+        // the relative threshold refers to the base peak of the cropped range
+        let filter = SpectrumFilter {
+            threshold_mode: ThresholdMode::Relative,
+            threshold: 60.0,
+            mz_range: Some((100.0, 300.0)),
+            ..Default::default()
+        };
+        assert_eq!(filter_peaks(&mz, &intensity, &filter).0, vec![120.0, 130.0]);
+
+        let filter = SpectrumFilter {
+            top_n: 2,
+            window: 100.0,
+            ..Default::default()
+        };
+        let (filtered_mz, filtered_intensity) = filter_peaks(&mz, &intensity, &filter);
+        assert_eq!(filtered_mz, vec![50.0, 120.0, 130.0, 210.0]);
+        assert_eq!(filtered_intensity, vec![1000.0, 40.0, 30.0, 10.0]);
+    }
+
+    #[test]
+    fn test_normalize() {
+        let mz = [100.0, 200.0, 300.0];
+        let intensity = [50.0, 200.0, 25.0];
+
+        assert_eq!(
+            normalize(&mz, &intensity, Normalization::Absolute, 0.0, 0.0),
+            Some(intensity.to_vec())
+        );
+        assert_eq!(
+            normalize(&mz, &intensity, Normalization::BasePeak, 0.0, 0.0),
+            Some(vec![25.0, 100.0, 12.5])
+        );
+        assert_eq!(
+            normalize(
+                &mz,
+                &intensity,
+                Normalization::ReferencePeak,
+                100.0005,
+                10.0
+            ),
+            Some(vec![100.0, 400.0, 50.0])
+        );
+        assert_eq!(
+            normalize(&mz, &intensity, Normalization::ReferencePeak, 150.0, 10.0),
+            None
+        );
+    }
+
+    #[test]
+    fn test_log_intensities() {
+        let log = log_intensities(&[0.0, 9.0, 999.0]);
+        assert_eq!(log[0], 0.0);
+        assert!((log[1] - 1.0).abs() < 1e-6);
+        assert!((inverse_log_intensity(log[2] as f64) - 999.0).abs() < 1e-2);
+    }
+}